@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+
+/// The number of tiles in a tile set atlas: one per exit mask.
+pub const TILE_ATLAS_LEN: usize = 16;
+
+/// The layout of a texture atlas packing the 16 tiles of one tile set into
+/// a 4x4 grid, row by row in exit mask order: `ZERO`, `N`, `E`, `NE` on the
+/// top row down to `NESW` in the bottom right. The indices match
+/// [`brain_engine_core::Map::iterate_tiles_atlas`], so one image per tile
+/// set replaces the 16 separate files:
+///
+/// ```no_run
+/// # use bevy::prelude::*;
+/// # use brain_engine_bevy::tile_atlas_layout;
+/// # use brain_engine_core::{Map, TileGeneratorDefault};
+/// # fn setup(
+/// #     mut commands: Commands,
+/// #     asset_server: Res<AssetServer>,
+/// #     mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+/// #     map: Res<Map<TileGeneratorDefault>>,
+/// # ) {
+/// let layout = layouts.add(tile_atlas_layout(UVec2::splat(64)));
+/// for (position, tile_set, index) in map.iterate_tiles_atlas() {
+///     let image = asset_server.load(format!("{tile_set}-atlas.png"));
+///     commands.spawn(Sprite::from_atlas_image(
+///         image,
+///         TextureAtlas { layout: layout.clone(), index },
+///     ));
+/// }
+/// # }
+/// ```
+pub fn tile_atlas_layout(tile_size: UVec2) -> TextureAtlasLayout {
+    TextureAtlasLayout::from_grid(tile_size, 4, 4, None, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use brain_engine_core::MapTile;
+
+    #[test]
+    fn tiles_are_packed_in_exit_mask_order() {
+        let layout = tile_atlas_layout(UVec2::splat(16));
+
+        assert_eq!(layout.len(), TILE_ATLAS_LEN);
+        assert_eq!(layout.size, UVec2::splat(64));
+        assert_eq!(
+            layout.textures[MapTile::NS as usize],
+            URect::new(16, 16, 32, 32)
+        );
+        assert_eq!(
+            layout.textures[MapTile::NESW as usize],
+            URect::new(48, 48, 64, 64)
+        );
+    }
+}
@@ -0,0 +1,260 @@
+use crate::plugin::{
+    SharedTextureResolver, TilePosition, TileSpawned, TileTextures, follow_screen,
+    spawn_tile_sprite,
+};
+
+use bevy::prelude::*;
+use brain_engine_core::{ChunkedMap, FileNameResolver, Screen, TextureResolver, TileGenerator};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Marks an entity, usually the player, whose [`TilePosition`] keeps the
+/// chunks around it loaded.
+#[derive(Component, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChunkViewer;
+
+/// How many chunks around each [`ChunkViewer`] stay loaded: every chunk at
+/// most `radius` chunks away along both axes.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkRadius(pub u32);
+
+/// The chunks whose tile sprites are spawned, with the sprite entities.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct LoadedChunks(pub HashMap<IVec2, Vec<Entity>>);
+
+/// Sent after the tile sprites of a chunk have been spawned.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkLoaded {
+    pub chunk: IVec2,
+}
+
+/// Sent after the tile sprites of a chunk have been despawned. The tiles
+/// stay in the [`ChunkedMap`], so the chunk looks the same when it loads
+/// again.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkUnloaded {
+    pub chunk: IVec2,
+}
+
+/// Streams an unbounded [`ChunkedMap`]: tile sprites are spawned as chunks
+/// come within the [`ChunkRadius`] of a [`ChunkViewer`] and despawned when
+/// they leave it, so only the tiles near the player exist as entities.
+///
+/// Chunks are generated the first time they load. The [`Screen`] resource
+/// is sized to one chunk with chunk `(0, 0)` in the middle; move the camera
+/// to follow the viewer. Tile sprites follow [`Screen`] changes as with
+/// [`crate::BrainEnginePlugin`], which this plugin replaces.
+pub struct ChunkStreamingPlugin<G> {
+    chunk_size: usize,
+    tile_size: f32,
+    radius: u32,
+    generator: Box<dyn Fn() -> G + Send + Sync>,
+    texture_resolver: SharedTextureResolver,
+}
+
+impl<G: TileGenerator> ChunkStreamingPlugin<G> {
+    /// * `chunk_size` - The number of tiles along each side of a chunk.
+    /// * `tile_size` - The size in pixels of a single tile.
+    /// * `generator` - Builds the tile generator used for the map.
+    pub fn new(
+        chunk_size: usize,
+        tile_size: f32,
+        generator: impl Fn() -> G + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            chunk_size,
+            tile_size,
+            radius: 1,
+            generator: Box::new(generator),
+            texture_resolver: Arc::new(FileNameResolver),
+        }
+    }
+
+    /// Keeps chunks up to `radius` chunks from a viewer loaded, 1 by default.
+    pub fn with_radius(mut self, radius: u32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// Loads each tile sprite from the asset path `resolver` returns instead
+    /// of the bundled file names.
+    pub fn with_texture_resolver(
+        mut self,
+        resolver: impl TextureResolver<Texture = String> + Send + Sync + 'static,
+    ) -> Self {
+        self.texture_resolver = Arc::new(resolver);
+        self
+    }
+}
+
+impl<G: TileGenerator + Send + Sync + 'static> Plugin for ChunkStreamingPlugin<G> {
+    fn build(&self, app: &mut App) {
+        let map = ChunkedMap::new(self.chunk_size, (self.generator)());
+        let screen = Screen::new(UVec2::splat(self.chunk_size as u32), self.tile_size);
+
+        app.insert_resource(map)
+            .insert_resource(screen)
+            .insert_resource(TileTextures(self.texture_resolver.clone()))
+            .insert_resource(ChunkRadius(self.radius))
+            .init_resource::<LoadedChunks>()
+            .add_event::<TileSpawned>()
+            .add_event::<ChunkLoaded>()
+            .add_event::<ChunkUnloaded>()
+            .add_systems(Update, stream_chunks::<G>)
+            .add_systems(Update, follow_screen.run_if(resource_changed::<Screen>));
+    }
+}
+
+fn stream_chunks<G: TileGenerator + Send + Sync + 'static>(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut map: ResMut<ChunkedMap<G>>,
+    screen: Res<Screen>,
+    textures: Res<TileTextures>,
+    radius: Res<ChunkRadius>,
+    viewers: Query<&TilePosition, With<ChunkViewer>>,
+    mut loaded: ResMut<LoadedChunks>,
+    mut tile_spawned: EventWriter<TileSpawned>,
+    mut chunk_loaded: EventWriter<ChunkLoaded>,
+    mut chunk_unloaded: EventWriter<ChunkUnloaded>,
+) {
+    let radius = radius.0 as i32;
+    let centers: Vec<IVec2> = viewers
+        .iter()
+        .map(|position| map.chunk_of(position.0))
+        .collect();
+    let wanted: HashSet<IVec2> = centers
+        .iter()
+        .flat_map(|center| {
+            (-radius..=radius)
+                .flat_map(move |x| (-radius..=radius).map(move |y| *center + IVec2::new(x, y)))
+        })
+        .collect();
+
+    let mut unloading: Vec<IVec2> = loaded
+        .0
+        .keys()
+        .filter(|chunk| !wanted.contains(chunk))
+        .copied()
+        .collect();
+    unloading.sort_by_key(|chunk| (chunk.y, chunk.x));
+    for chunk in unloading {
+        for entity in loaded.0.remove(&chunk).unwrap_or_default() {
+            commands.entity(entity).despawn();
+        }
+        chunk_unloaded.write(ChunkUnloaded { chunk });
+    }
+
+    // Nearest chunks first, so the ground under a viewer appears first.
+    let distance = |chunk: IVec2| {
+        centers
+            .iter()
+            .map(|center| (chunk - *center).abs().element_sum())
+            .min()
+            .unwrap_or_default()
+    };
+    let mut loading: Vec<IVec2> = wanted
+        .into_iter()
+        .filter(|chunk| !loaded.0.contains_key(chunk))
+        .collect();
+    loading.sort_by_key(|chunk| (distance(*chunk), chunk.y, chunk.x));
+    for chunk in loading {
+        map.ensure_chunk(chunk);
+        let entities = map
+            .chunk_positions(chunk)
+            .map(|position| {
+                let tile = map.tiles[&position];
+                let entity = spawn_tile_sprite(
+                    &mut commands,
+                    &asset_server,
+                    &screen,
+                    textures.0.as_ref(),
+                    position,
+                    tile,
+                );
+                tile_spawned.write(TileSpawned {
+                    entity,
+                    position,
+                    tile,
+                });
+                entity
+            })
+            .collect();
+        loaded.0.insert(chunk, entities);
+        chunk_loaded.write(ChunkLoaded { chunk });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin::MapTileSprite;
+    use bevy::asset::AssetPlugin;
+    use brain_engine_core::TileGeneratorDefault;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Image>()
+            .add_plugins(ChunkStreamingPlugin::new(4, 16.0, || {
+                TileGeneratorDefault::with_seed(1)
+            }));
+        app
+    }
+
+    #[test]
+    fn chunks_load_around_viewers() {
+        let mut app = app();
+        app.world_mut()
+            .spawn((ChunkViewer, TilePosition(IVec2::new(1, 1))));
+        app.update();
+
+        let world = app.world_mut();
+        let sprites = world.query::<&MapTileSprite>().iter(world).count();
+        assert_eq!(sprites, 9 * 16);
+        assert_eq!(world.resource::<Events<ChunkLoaded>>().len(), 9);
+        assert!(
+            world
+                .resource::<LoadedChunks>()
+                .0
+                .contains_key(&IVec2::new(-1, -1))
+        );
+    }
+
+    #[test]
+    fn chunks_unload_when_viewers_move_away() {
+        let mut app = app();
+        let viewer = app
+            .world_mut()
+            .spawn((ChunkViewer, TilePosition(IVec2::new(1, 1))))
+            .id();
+        app.update();
+        let first_tiles: Vec<Entity> =
+            app.world().resource::<LoadedChunks>().0[&IVec2::ZERO].clone();
+
+        app.world_mut().get_mut::<TilePosition>(viewer).unwrap().0 = IVec2::new(41, 1);
+        app.update();
+
+        let world = app.world_mut();
+        let sprites = world.query::<&MapTileSprite>().iter(world).count();
+        assert_eq!(sprites, 9 * 16);
+        assert_eq!(world.resource::<Events<ChunkUnloaded>>().len(), 9);
+        assert!(
+            first_tiles
+                .iter()
+                .all(|entity| world.get_entity(*entity).is_err())
+        );
+        let loaded = world.resource::<Events<ChunkLoaded>>();
+        assert!(
+            loaded
+                .iter_current_update_events()
+                .any(|event| event.chunk == IVec2::new(10, 0))
+        );
+        // The tiles of unloaded chunks are kept.
+        assert!(
+            world
+                .resource::<ChunkedMap<TileGeneratorDefault>>()
+                .is_chunk_generated(IVec2::ZERO)
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use crate::plugin::TilePosition;
+
+use bevy::prelude::*;
+use brain_engine_core::{CheckpointError, Checkpoints, FastTravel, Map, Screen, TileGenerator};
+
+/// Asks to move `entity` from the checkpoint it stands on to `destination`.
+#[derive(Event, Debug, Clone)]
+pub struct FastTravelRequest {
+    pub entity: Entity,
+    pub destination: String,
+}
+
+/// Sent after an entity has been teleported, so games can animate the jump.
+#[derive(Event, Debug, Clone)]
+pub struct FastTravelled {
+    pub entity: Entity,
+    pub travel: FastTravel,
+}
+
+/// Sent when a [`FastTravelRequest`] is rejected.
+#[derive(Event, Debug, Clone)]
+pub struct FastTravelFailed {
+    pub entity: Entity,
+    pub error: CheckpointError,
+}
+
+pub(crate) fn handle_fast_travel<G: TileGenerator + Send + Sync + 'static>(
+    mut requests: EventReader<FastTravelRequest>,
+    checkpoints: Res<Checkpoints>,
+    map: Res<Map<G>>,
+    screen: Res<Screen>,
+    mut query: Query<(&mut TilePosition, &mut Transform)>,
+    mut travelled: EventWriter<FastTravelled>,
+    mut failed: EventWriter<FastTravelFailed>,
+) {
+    for request in requests.read() {
+        let Ok((mut tile_position, mut transform)) = query.get_mut(request.entity) else {
+            continue;
+        };
+
+        let result = checkpoints
+            .at(tile_position.0)
+            .ok_or(CheckpointError::NoCheckpointAt(tile_position.0))
+            .and_then(|origin| {
+                checkpoints.fast_travel(origin, &request.destination, &map.exploration)
+            });
+
+        match result {
+            Ok(travel) => {
+                tile_position.0 = travel.to;
+                let destination = screen.pixel_position(travel.to);
+                transform.translation = destination.with_z(transform.translation.z);
+                travelled.write(FastTravelled {
+                    entity: request.entity,
+                    travel,
+                });
+            }
+            Err(error) => {
+                failed.write(FastTravelFailed {
+                    entity: request.entity,
+                    error,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,20 @@
+//! Brain Engine Bevy - Bevy integration for the map generation library
+//!
+//! Provides a plugin that sets up the map and screen resources and spawns the
+//! tile sprites, so games don't have to copy the setup systems themselves.
+
+pub mod atlas;
+pub mod chunk_streaming;
+pub mod fast_travel;
+pub mod movement;
+pub mod pathfinding;
+pub mod plugin;
+
+pub use atlas::{TILE_ATLAS_LEN, tile_atlas_layout};
+pub use chunk_streaming::{
+    ChunkLoaded, ChunkRadius, ChunkStreamingPlugin, ChunkUnloaded, ChunkViewer, LoadedChunks,
+};
+pub use fast_travel::{FastTravelFailed, FastTravelRequest, FastTravelled};
+pub use movement::{FootstepEvent, MovementSpeed, TileMove};
+pub use pathfinding::{PathReady, PathRequest, PathfindingBudget};
+pub use plugin::{BrainEnginePlugin, MapSetup, MapTileSprite, TilePosition, TileSpawned};
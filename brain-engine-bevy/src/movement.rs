@@ -0,0 +1,149 @@
+use bevy::prelude::*;
+use brain_engine_core::{Map, MovementSpeeds, Screen, TileGenerator, TileSet};
+
+/// Base speed, in pixels per second, of an entity moving between tiles. The
+/// [`MovementSpeeds`] modifier of the tile being entered is applied on top.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct MovementSpeed(pub f32);
+
+/// Animates an entity's [`Transform`] towards the centre of `destination`.
+///
+/// Games validate the move (e.g. with [`Map::can_move`]), update the
+/// [`crate::TilePosition`] and insert this component; it is removed again once the
+/// entity arrives.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileMove {
+    pub destination: IVec2,
+}
+
+/// Sent when an entity finishes a [`TileMove`] and steps onto a tile, so games
+/// can play footstep sounds or spawn effects per tile set.
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FootstepEvent {
+    pub entity: Entity,
+    pub position: IVec2,
+    pub tile_set: TileSet,
+}
+
+pub(crate) fn move_entities<G: TileGenerator + Send + Sync + 'static>(
+    time: Res<Time>,
+    map: Res<Map<G>>,
+    screen: Res<Screen>,
+    speeds: Res<MovementSpeeds>,
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Transform, &TileMove, Option<&MovementSpeed>)>,
+    mut footsteps: EventWriter<FootstepEvent>,
+) {
+    for (entity, mut transform, tile_move, movement_speed) in &mut query {
+        let tile_set = map
+            .tiles
+            .get(&tile_move.destination)
+            .map(|tile| tile.tile_set);
+        let base_speed = movement_speed.map_or(screen.tile_size(), |speed| speed.0);
+        let speed = tile_set.map_or(base_speed, |tile_set| speeds.speed(base_speed, tile_set));
+        let step = speed * time.delta_secs();
+
+        let destination = screen
+            .pixel_position(tile_move.destination)
+            .with_z(transform.translation.z);
+        let offset = destination - transform.translation;
+        if offset.length() > step {
+            transform.translation += offset.normalize() * step;
+            continue;
+        }
+
+        transform.translation = destination;
+        commands.entity(entity).remove::<TileMove>();
+        if let Some(tile_set) = tile_set {
+            footsteps.write(FootstepEvent {
+                entity,
+                position: tile_move.destination,
+                tile_set,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin::{BrainEnginePlugin, TilePosition};
+    use bevy::asset::AssetPlugin;
+    use bevy::time::TimeUpdateStrategy;
+    use brain_engine_core::{MapTile, Tile, TileGrid};
+    use std::time::Duration;
+
+    /// Rooms in column 0, corridors everywhere else.
+    struct StripeGenerator;
+
+    impl TileGenerator for StripeGenerator {
+        fn tile_at(&self, _tiles: &TileGrid, location: IVec2) -> Tile {
+            let tile_set = if location.x == 0 {
+                TileSet::Room
+            } else {
+                TileSet::Corridor
+            };
+            Tile::new(tile_set, MapTile::NESW)
+        }
+    }
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Image>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                100,
+            )))
+            .add_plugins(BrainEnginePlugin::new(3, 10.0, || StripeGenerator));
+        app.update();
+        app
+    }
+
+    /// Moves an entity one tile and returns the number of updates it took.
+    fn updates_to_arrive(app: &mut App, from: IVec2, to: IVec2) -> usize {
+        let screen = app.world().resource::<Screen>().clone();
+        let entity = app
+            .world_mut()
+            .spawn((
+                TilePosition(to),
+                Transform::from_translation(screen.pixel_position(from)),
+                MovementSpeed(10.0),
+                TileMove { destination: to },
+            ))
+            .id();
+        let mut updates = 0;
+        while app.world().get::<TileMove>(entity).is_some() {
+            app.update();
+            updates += 1;
+            assert!(updates < 100, "entity never arrived");
+        }
+        assert_eq!(
+            app.world().get::<Transform>(entity).unwrap().translation,
+            screen.pixel_position(to)
+        );
+        updates
+    }
+
+    #[test]
+    fn corridors_are_crossed_faster_than_rooms() {
+        let mut app = app();
+
+        let into_room = updates_to_arrive(&mut app, IVec2::new(1, 1), IVec2::new(0, 1));
+        let into_corridor = updates_to_arrive(&mut app, IVec2::new(1, 1), IVec2::new(2, 1));
+
+        assert!(into_corridor < into_room);
+    }
+
+    #[test]
+    fn entering_a_tile_sends_a_footstep() {
+        let mut app = app();
+
+        updates_to_arrive(&mut app, IVec2::new(1, 0), IVec2::new(0, 0));
+
+        let events = app.world().resource::<Events<FootstepEvent>>();
+        let footsteps: Vec<_> = events.iter_current_update_events().collect();
+        assert_eq!(footsteps.len(), 1);
+        assert_eq!(footsteps[0].position, IVec2::new(0, 0));
+        assert_eq!(footsteps[0].tile_set, TileSet::Room);
+    }
+}
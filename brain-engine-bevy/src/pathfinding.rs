@@ -0,0 +1,241 @@
+use crate::plugin::TilePosition;
+
+use bevy::prelude::*;
+use brain_engine_core::{IncrementalPathfinder, Map, PathfindingStatus, TileGenerator};
+
+/// Maximum number of tiles expanded per frame across all outstanding
+/// [`PathRequest`]s.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathfindingBudget {
+    pub nodes_per_frame: usize,
+}
+
+impl Default for PathfindingBudget {
+    fn default() -> Self {
+        Self {
+            nodes_per_frame: 2000,
+        }
+    }
+}
+
+/// Asks for a path from the entity's [`TilePosition`] to `destination`.
+///
+/// The search is spread over as many frames as the [`PathfindingBudget`]
+/// requires. When it finishes the component is removed and a [`PathReady`]
+/// event is sent. Changing `destination`, or moving the entity to another
+/// tile, restarts the search; removing the component cancels it.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathRequest {
+    pub destination: IVec2,
+}
+
+/// Sent when a [`PathRequest`] has been resolved.
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub struct PathReady {
+    pub entity: Entity,
+    pub destination: IVec2,
+    /// The path from the entity's tile to `destination`, or `None` when the
+    /// destination is unreachable.
+    pub path: Option<Vec<IVec2>>,
+}
+
+/// Search state of an outstanding [`PathRequest`].
+#[derive(Component)]
+pub(crate) struct PathSearch(IncrementalPathfinder);
+
+pub(crate) fn drive_path_requests<G: TileGenerator + Send + Sync + 'static>(
+    map: Res<Map<G>>,
+    budget: Res<PathfindingBudget>,
+    mut commands: Commands,
+    mut query: Query<(Entity, &TilePosition, &PathRequest, Option<&mut PathSearch>)>,
+    mut ready: EventWriter<PathReady>,
+) {
+    let mut remaining_budget = budget.nodes_per_frame;
+    let mut remaining_requests = query.iter().len();
+    for (entity, tile_position, request, search) in &mut query {
+        // Share what is left of the budget evenly, so that no request starves.
+        let share = (remaining_budget / remaining_requests.max(1)).max(1);
+        remaining_requests -= 1;
+
+        let mut step = |pathfinder: &mut IncrementalPathfinder| {
+            let expanded_before = pathfinder.expanded_nodes();
+            let status = pathfinder.step(&map, share);
+            remaining_budget =
+                remaining_budget.saturating_sub(pathfinder.expanded_nodes() - expanded_before);
+            status
+        };
+        let status = match search {
+            Some(mut search)
+                if search.0.from() == tile_position.0 && search.0.to() == request.destination =>
+            {
+                step(&mut search.0)
+            }
+            _ => {
+                let mut pathfinder =
+                    IncrementalPathfinder::new(&map, tile_position.0, request.destination);
+                let status = step(&mut pathfinder);
+                if status == PathfindingStatus::InProgress {
+                    commands.entity(entity).insert(PathSearch(pathfinder));
+                }
+                status
+            }
+        };
+
+        let path = match status {
+            PathfindingStatus::InProgress => continue,
+            PathfindingStatus::Found(path) => Some(path),
+            PathfindingStatus::NotFound => None,
+        };
+        commands
+            .entity(entity)
+            .remove::<(PathRequest, PathSearch)>();
+        ready.write(PathReady {
+            entity,
+            destination: request.destination,
+            path,
+        });
+    }
+}
+
+/// Drops the search state of [`PathRequest`]s removed before they finished.
+pub(crate) fn cancel_path_searches(
+    mut removed: RemovedComponents<PathRequest>,
+    searches: Query<(), (With<PathSearch>, Without<PathRequest>)>,
+    mut commands: Commands,
+) {
+    for entity in removed.read() {
+        if searches.contains(entity) {
+            commands.entity(entity).remove::<PathSearch>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin::BrainEnginePlugin;
+    use bevy::asset::AssetPlugin;
+    use brain_engine_core::{MapTile, Tile, TileGrid, TileSet};
+
+    struct OpenGenerator;
+
+    impl TileGenerator for OpenGenerator {
+        fn tile_at(&self, _tiles: &TileGrid, _location: IVec2) -> Tile {
+            Tile::new(TileSet::Room, MapTile::NESW)
+        }
+    }
+
+    #[test]
+    fn path_requests_are_spread_across_frames() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Image>()
+            .add_plugins(BrainEnginePlugin::new(16, 8.0, || OpenGenerator))
+            .insert_resource(PathfindingBudget { nodes_per_frame: 4 });
+        app.update();
+
+        let seekers: Vec<Entity> = (0..2)
+            .map(|_| {
+                app.world_mut()
+                    .spawn((
+                        TilePosition(IVec2::new(0, 0)),
+                        PathRequest {
+                            destination: IVec2::new(15, 15),
+                        },
+                    ))
+                    .id()
+            })
+            .collect();
+
+        let mut frames = 0;
+        let mut paths = Vec::new();
+        while paths.len() < seekers.len() {
+            app.update();
+            frames += 1;
+            assert!(frames < 100, "path requests never finished");
+            let events = app.world().resource::<Events<PathReady>>();
+            paths.extend(events.iter_current_update_events().cloned());
+        }
+
+        // Each search expands at least 30 tiles at 2 nodes per frame.
+        assert!(frames > 10);
+        for ready in paths {
+            assert!(seekers.contains(&ready.entity));
+            assert_eq!(ready.path.unwrap().len(), 31);
+            assert!(app.world().get::<PathRequest>(ready.entity).is_none());
+        }
+    }
+
+    #[test]
+    fn moving_restarts_and_removing_cancels_a_search() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Image>()
+            .add_plugins(BrainEnginePlugin::new(16, 8.0, || OpenGenerator))
+            .insert_resource(PathfindingBudget { nodes_per_frame: 4 });
+        app.update();
+
+        let seeker = app
+            .world_mut()
+            .spawn((
+                TilePosition(IVec2::new(0, 0)),
+                PathRequest {
+                    destination: IVec2::new(15, 15),
+                },
+            ))
+            .id();
+        app.update();
+        app.world_mut()
+            .entity_mut(seeker)
+            .insert(TilePosition(IVec2::new(10, 10)));
+
+        let mut frames = 0;
+        let ready = loop {
+            app.update();
+            frames += 1;
+            assert!(frames < 100, "path request never finished");
+            let events = app.world().resource::<Events<PathReady>>();
+            if let Some(ready) = events.iter_current_update_events().next() {
+                break ready.clone();
+            }
+        };
+        let path = ready.path.unwrap();
+        assert_eq!(path.first(), Some(&IVec2::new(10, 10)));
+        assert_eq!(path.len(), 11);
+
+        app.world_mut().entity_mut(seeker).insert(PathRequest {
+            destination: IVec2::new(0, 0),
+        });
+        app.update();
+        assert!(app.world().get::<PathSearch>(seeker).is_some());
+        app.world_mut().entity_mut(seeker).remove::<PathRequest>();
+        app.update();
+        assert!(app.world().get::<PathSearch>(seeker).is_none());
+    }
+
+    #[test]
+    fn unreachable_destinations_report_no_path() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Image>()
+            .add_plugins(BrainEnginePlugin::new(4, 8.0, || OpenGenerator));
+        app.update();
+
+        let seeker = app
+            .world_mut()
+            .spawn((
+                TilePosition(IVec2::new(0, 0)),
+                PathRequest {
+                    destination: IVec2::new(9, 9),
+                },
+            ))
+            .id();
+        app.update();
+
+        let events = app.world().resource::<Events<PathReady>>();
+        let ready: Vec<_> = events.iter_current_update_events().collect();
+        assert_eq!(ready.len(), 1);
+        assert_eq!(ready[0].entity, seeker);
+        assert_eq!(ready[0].path, None);
+    }
+}
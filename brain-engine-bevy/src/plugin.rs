@@ -0,0 +1,334 @@
+use crate::fast_travel::{FastTravelFailed, FastTravelRequest, FastTravelled, handle_fast_travel};
+use crate::movement::{FootstepEvent, TileMove, move_entities};
+use crate::pathfinding::{PathReady, PathfindingBudget, cancel_path_searches, drive_path_requests};
+
+use bevy::prelude::*;
+use brain_engine_core::{
+    Checkpoints, FileNameResolver, Map, MovementSpeeds, Screen, TextureResolver, Tile,
+    TileGenerator,
+};
+use std::sync::Arc;
+
+/// Position of an entity on the map grid.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TilePosition(pub IVec2);
+
+/// Marks a sprite entity that renders one map tile.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapTileSprite {
+    pub position: IVec2,
+    pub tile: Tile,
+}
+
+/// Startup systems that spawn the map tiles. Order systems `.after(MapSetup)`
+/// to run once the tile sprites exist.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MapSetup;
+
+/// Sent once for every tile sprite spawned by [`BrainEnginePlugin`].
+#[derive(Event, Debug, Clone, Copy)]
+pub struct TileSpawned {
+    pub entity: Entity,
+    pub position: IVec2,
+    pub tile: Tile,
+}
+
+/// Resolves tiles to the asset paths of their textures.
+pub(crate) type SharedTextureResolver = Arc<dyn TextureResolver<Texture = String> + Send + Sync>;
+
+/// The texture resolver the tile sprites are spawned with.
+#[derive(Resource)]
+pub(crate) struct TileTextures(pub(crate) SharedTextureResolver);
+
+/// Generates a [`Map`], inserts it alongside a matching [`Screen`], and spawns
+/// one sprite per tile at startup.
+///
+/// Animates [`crate::TileMove`]s at the speeds in the [`MovementSpeeds`]
+/// resource, sending a [`FootstepEvent`] for every tile entered, and resolves
+/// [`crate::PathRequest`]s within the [`PathfindingBudget`].
+///
+/// Also resolves [`FastTravelRequest`]s against the [`Checkpoints`]
+/// resource, between checkpoints explored in the map's fog of war, which
+/// the game updates with [`Map::mark_visible_from`].
+///
+/// Whenever the [`Screen`] resource changes, e.g. when the camera scrolls or
+/// zooms, tile sprites and [`TilePosition`] entities are scaled by
+/// [`Screen::zoom`] and, unless mid-[`TileMove`], moved to their new pixel
+/// positions.
+///
+/// Tile textures are loaded through the [`AssetServer`], by default from the
+/// file names produced by [`FileNameResolver`]; see
+/// [`BrainEnginePlugin::with_texture_resolver`]. The plugin does not spawn a
+/// camera.
+pub struct BrainEnginePlugin<G> {
+    grid_size: usize,
+    tile_size: f32,
+    generator: Box<dyn Fn() -> G + Send + Sync>,
+    texture_resolver: SharedTextureResolver,
+}
+
+impl<G: TileGenerator> BrainEnginePlugin<G> {
+    /// * `grid_size` - The number of tiles along each side of the map.
+    /// * `tile_size` - The size in pixels of a single tile.
+    /// * `generator` - Builds the tile generator used for the map.
+    pub fn new(
+        grid_size: usize,
+        tile_size: f32,
+        generator: impl Fn() -> G + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            grid_size,
+            tile_size,
+            generator: Box::new(generator),
+            texture_resolver: Arc::new(FileNameResolver),
+        }
+    }
+
+    /// Loads each tile sprite from the asset path `resolver` returns instead
+    /// of the bundled file names.
+    pub fn with_texture_resolver(
+        mut self,
+        resolver: impl TextureResolver<Texture = String> + Send + Sync + 'static,
+    ) -> Self {
+        self.texture_resolver = Arc::new(resolver);
+        self
+    }
+}
+
+impl<G: TileGenerator + Send + Sync + 'static> Plugin for BrainEnginePlugin<G> {
+    fn build(&self, app: &mut App) {
+        let map = Map::new(self.grid_size, (self.generator)());
+        let screen = Screen::new(UVec2::new(map.x as u32, map.y as u32), self.tile_size);
+
+        app.insert_resource(map)
+            .insert_resource(screen)
+            .insert_resource(TileTextures(self.texture_resolver.clone()))
+            .init_resource::<Checkpoints>()
+            .init_resource::<MovementSpeeds>()
+            .init_resource::<PathfindingBudget>()
+            .add_event::<TileSpawned>()
+            .add_event::<FootstepEvent>()
+            .add_event::<PathReady>()
+            .add_event::<FastTravelRequest>()
+            .add_event::<FastTravelled>()
+            .add_event::<FastTravelFailed>()
+            .add_systems(Startup, spawn_map_tiles::<G>.in_set(MapSetup))
+            .add_systems(
+                Update,
+                (
+                    move_entities::<G>,
+                    (cancel_path_searches, drive_path_requests::<G>).chain(),
+                ),
+            )
+            .add_systems(Update, follow_screen.run_if(resource_changed::<Screen>))
+            .add_systems(Update, handle_fast_travel::<G>);
+    }
+}
+
+fn spawn_map_tiles<G: TileGenerator + Send + Sync + 'static>(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    map: Res<Map<G>>,
+    screen: Res<Screen>,
+    textures: Res<TileTextures>,
+    mut tile_spawned: EventWriter<TileSpawned>,
+) {
+    for position in map.positions() {
+        let tile = map.tiles[&position];
+        let entity = spawn_tile_sprite(
+            &mut commands,
+            &asset_server,
+            &screen,
+            textures.0.as_ref(),
+            position,
+            tile,
+        );
+        tile_spawned.write(TileSpawned {
+            entity,
+            position,
+            tile,
+        });
+    }
+}
+
+/// Spawns the sprite for the tile at `position`, placed and scaled for
+/// `screen`.
+pub(crate) fn spawn_tile_sprite(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    screen: &Screen,
+    textures: &(dyn TextureResolver<Texture = String> + Send + Sync),
+    position: IVec2,
+    tile: Tile,
+) -> Entity {
+    commands
+        .spawn((
+            MapTileSprite { position, tile },
+            Sprite::from_image(asset_server.load(textures.texture(position, tile))),
+            Transform::from_translation(screen.pixel_position(position))
+                .with_scale(Vec3::splat(screen.zoom())),
+        ))
+        .id()
+}
+
+pub(crate) fn follow_screen(
+    screen: Res<Screen>,
+    mut tiles: Query<(&MapTileSprite, &mut Transform)>,
+    mut entities: Query<(&TilePosition, &mut Transform, Has<TileMove>), Without<MapTileSprite>>,
+) {
+    let scale = Vec3::splat(screen.zoom());
+    let positions = tiles
+        .iter_mut()
+        .map(|(tile, transform)| (tile.position, transform, false))
+        .chain(
+            entities
+                .iter_mut()
+                .map(|(position, transform, moving)| (position.0, transform, moving)),
+        );
+    for (position, mut transform, moving) in positions {
+        // Moving entities head for the new position on their own.
+        if !moving {
+            transform.translation = screen
+                .pixel_position(position)
+                .with_z(transform.translation.z);
+        }
+        transform.scale = scale;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::asset::AssetPlugin;
+    use brain_engine_core::{BoxedTileGenerator, DynMap, TileGeneratorDefault};
+
+    #[test]
+    fn plugin_spawns_one_sprite_per_tile() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Image>()
+            .add_plugins(BrainEnginePlugin::new(3, 16.0, || {
+                TileGeneratorDefault::with_seed(1)
+            }));
+        app.update();
+
+        let world = app.world_mut();
+        let sprites = world.query::<&MapTileSprite>().iter(world).count();
+        assert_eq!(sprites, 9);
+        assert_eq!(world.resource::<Events<TileSpawned>>().len(), 9);
+        assert_eq!(world.resource::<Screen>().dimensions(), UVec2::new(3, 3));
+    }
+
+    #[test]
+    fn sprites_load_the_textures_the_resolver_picks() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Image>()
+            .add_plugins(
+                BrainEnginePlugin::new(2, 16.0, || TileGeneratorDefault::with_seed(1))
+                    .with_texture_resolver(|position: IVec2, tile: Tile| {
+                        format!("tiles/{}/{}-{}.png", tile.tile_set, position.x, position.y)
+                    }),
+            );
+        app.update();
+
+        let world = app.world_mut();
+        let mut query = world.query::<(&MapTileSprite, &Sprite)>();
+        assert_eq!(query.iter(world).count(), 4);
+        for (tile_sprite, sprite) in query.iter(world) {
+            let expected = format!(
+                "tiles/{}/{}-{}.png",
+                tile_sprite.tile.tile_set, tile_sprite.position.x, tile_sprite.position.y
+            );
+            assert_eq!(sprite.image.path().unwrap().to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn plugin_accepts_a_generator_chosen_at_runtime() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Image>()
+            .add_plugins(BrainEnginePlugin::new(2, 16.0, || {
+                Box::new(TileGeneratorDefault::with_seed(1)) as BoxedTileGenerator
+            }));
+        app.update();
+
+        assert_eq!(app.world().resource::<DynMap>().tiles.len(), 4);
+    }
+
+    #[test]
+    fn fast_travel_teleports_between_visited_checkpoints() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Image>()
+            .add_plugins(BrainEnginePlugin::new(3, 16.0, || {
+                TileGeneratorDefault::with_seed(1)
+            }));
+
+        let world = app.world_mut();
+        let map = world.resource::<Map<TileGeneratorDefault>>();
+        let mut checkpoints = Checkpoints::new();
+        checkpoints
+            .register(map, "shrine", IVec2::new(0, 0))
+            .unwrap();
+        checkpoints
+            .register(map, "tower", IVec2::new(2, 2))
+            .unwrap();
+        world.insert_resource(checkpoints);
+        world
+            .resource_mut::<Map<TileGeneratorDefault>>()
+            .mark_visible([IVec2::new(0, 0), IVec2::new(2, 2)]);
+        let traveller = world
+            .spawn((TilePosition(IVec2::new(0, 0)), Transform::default()))
+            .id();
+        app.update();
+
+        app.world_mut().send_event(FastTravelRequest {
+            entity: traveller,
+            destination: "tower".to_string(),
+        });
+        app.update();
+
+        let world = app.world();
+        assert_eq!(
+            world.get::<TilePosition>(traveller),
+            Some(&TilePosition(IVec2::new(2, 2)))
+        );
+        assert_eq!(world.resource::<Events<FastTravelled>>().len(), 1);
+        assert!(world.resource::<Events<FastTravelFailed>>().is_empty());
+    }
+
+    #[test]
+    fn sprites_follow_the_screen_zoom() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .init_asset::<Image>()
+            .add_plugins(BrainEnginePlugin::new(3, 16.0, || {
+                TileGeneratorDefault::with_seed(1)
+            }));
+        let player = app
+            .world_mut()
+            .spawn((
+                TilePosition(IVec2::new(2, 2)),
+                Transform::from_xyz(0.0, 0.0, 1.0),
+            ))
+            .id();
+        app.update();
+
+        app.world_mut().resource_mut::<Screen>().set_zoom(2.0);
+        app.update();
+
+        let world = app.world_mut();
+        let mut query = world.query::<(&MapTileSprite, &Transform)>();
+        for (tile_sprite, transform) in query.iter(world) {
+            let expected = (tile_sprite.position - IVec2::ONE).as_vec2() * 32.0;
+            assert_eq!(transform.translation, expected.extend(0.0));
+            assert_eq!(transform.scale, Vec3::splat(2.0));
+        }
+        assert_eq!(
+            world.get::<Transform>(player).unwrap().translation,
+            Vec3::new(32.0, 32.0, 1.0)
+        );
+    }
+}
@@ -1,4 +1,8 @@
+use bevy::input::mouse::MouseWheel;
 use bevy::prelude::*;
+use brain_engine_bevy::{
+    BrainEnginePlugin, FootstepEvent, MapSetup, MovementSpeed, TileMove, TilePosition,
+};
 use brain_engine_core::{Map, Screen, TileGeneratorDefault};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Component)]
@@ -16,49 +20,38 @@ struct AnimationTimer(Timer);
 #[derive(Component)]
 struct Player;
 
-#[derive(Component)]
-struct TilePosition(IVec2);
-
-#[derive(Component)]
-struct Move {
-    destination: Vec3,
-}
+/// The zoom the screen is easing towards after mouse-wheel scrolls.
+#[derive(Resource)]
+struct ZoomTarget(f32);
 
 const TILE_SIZE: f32 = 64.0;
 const GRID_SIZE: usize = 5;
 const PLAYER_SPEED: f32 = 100.0;
+const ZOOM_STEP: f32 = 1.1;
+const ZOOM_RATE: f32 = 12.0;
 
 fn main() -> AppExit {
     App::new()
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
-                title: format!("{}x{} Grid", GRID_SIZE, GRID_SIZE).into(),
+                title: format!("{}x{} Grid", GRID_SIZE, GRID_SIZE),
                 resolution: (GRID_SIZE as f32 * TILE_SIZE, GRID_SIZE as f32 * TILE_SIZE).into(),
                 resizable: false,
                 ..default()
             }),
             ..default()
         }))
-        .add_systems(Startup, (setup_map, setup_player).chain())
-        .add_systems(Update, (start_move, animate_move, animate_sprite))
+        .add_plugins(BrainEnginePlugin::new(GRID_SIZE, TILE_SIZE, || {
+            TileGeneratorDefault::with_probabilities(0.5, 0.5)
+        }))
+        .insert_resource(ZoomTarget(1.0))
+        .add_systems(Startup, (setup_camera, setup_player.after(MapSetup)))
+        .add_systems(Update, (start_move, finish_move, animate_sprite, zoom))
         .run()
 }
 
-fn setup_map(mut commands: Commands, asset_server: ResMut<AssetServer>) {
+fn setup_camera(mut commands: Commands) {
     commands.spawn(Camera2d);
-
-    let tile_generator = TileGeneratorDefault::with_probabilities(0.5, 0.5);
-    let map = Map::new(GRID_SIZE, tile_generator);
-    let screen = Screen::new(UVec2::new(map.x as u32, map.y as u32), TILE_SIZE);
-    for (position, texture_file_name) in map.iterate_tiles() {
-        let tile_texture = asset_server.load(texture_file_name);
-        commands.spawn((
-            Sprite::from_image(tile_texture.clone()),
-            Transform::from_translation(screen.pixel_position(position)),
-        ));
-    }
-    commands.insert_resource(map);
-    commands.insert_resource(screen);
 }
 
 fn setup_player(
@@ -89,6 +82,7 @@ fn setup_player(
             },
         ),
         Transform::from_translation(start_position),
+        MovementSpeed(PLAYER_SPEED),
         PlayerAnimationState::Idle,
         AnimationTimer(Timer::from_seconds(0.15, TimerMode::Repeating)),
     ));
@@ -97,9 +91,8 @@ fn setup_player(
 fn start_move(
     keyboard_input: Res<ButtonInput<KeyCode>>,
     map: Res<Map<TileGeneratorDefault>>,
-    screen: Res<Screen>,
     mut commands: Commands,
-    mut query: Query<(Entity, &mut TilePosition, &mut PlayerAnimationState), Without<Move>>,
+    mut query: Query<(Entity, &mut TilePosition, &mut PlayerAnimationState), Without<TileMove>>,
 ) {
     for (entity, mut tile_position, mut animation_state) in query.iter_mut() {
         // Only process input when Idle (not currently moving)
@@ -130,34 +123,22 @@ fn start_move(
                 // Set animation state
                 *animation_state = new_animation_state;
 
-                // Calculate destination pixel position
-                let destination = screen.pixel_position(target);
-
-                // Add Move component to start animation
-                commands.entity(entity).insert(Move { destination });
+                // Add TileMove component so the plugin animates the move
+                commands.entity(entity).insert(TileMove {
+                    destination: target,
+                });
             }
         }
     }
 }
 
-fn animate_move(
-    time: Res<Time>,
-    mut commands: Commands,
-    mut query: Query<(Entity, &mut Transform, &mut PlayerAnimationState, &Move)>,
+fn finish_move(
+    mut footsteps: EventReader<FootstepEvent>,
+    mut query: Query<&mut PlayerAnimationState>,
 ) {
-    for (entity, mut transform, mut animation_state, move_component) in query.iter_mut() {
-        let step = PLAYER_SPEED * time.delta_secs();
-        let direction = move_component.destination - transform.translation;
-        let distance = direction.length();
-
-        if distance <= step {
-            // Snap to destination
-            transform.translation = move_component.destination;
+    for footstep in footsteps.read() {
+        if let Ok(mut animation_state) = query.get_mut(footstep.entity) {
             *animation_state = PlayerAnimationState::Idle;
-            commands.entity(entity).remove::<Move>();
-        } else {
-            // Move toward destination
-            transform.translation += direction.normalize() * step;
         }
     }
 }
@@ -169,18 +150,33 @@ fn animate_sprite(
     for (mut sprite, mut timer, state) in query.iter_mut() {
         timer.0.tick(time.delta());
 
-        if timer.0.just_finished() {
-            if let Some(atlas) = &mut sprite.texture_atlas {
-                let step = (atlas.index + 1) % 4; // step will be 1-4
-                let animation_start_frame = match state {
-                    PlayerAnimationState::Idle => 0,
-                    PlayerAnimationState::WalkSouth => 4,
-                    PlayerAnimationState::WalkWest => 8,
-                    PlayerAnimationState::WalkNorth => 12,
-                    PlayerAnimationState::WalkEast => 16,
-                };
-                atlas.index = animation_start_frame + step;
-            }
+        if timer.0.just_finished()
+            && let Some(atlas) = &mut sprite.texture_atlas
+        {
+            let step = (atlas.index + 1) % 4; // step will be 1-4
+            let animation_start_frame = match state {
+                PlayerAnimationState::Idle => 0,
+                PlayerAnimationState::WalkSouth => 4,
+                PlayerAnimationState::WalkWest => 8,
+                PlayerAnimationState::WalkNorth => 12,
+                PlayerAnimationState::WalkEast => 16,
+            };
+            atlas.index = animation_start_frame + step;
         }
     }
 }
+
+fn zoom(
+    time: Res<Time>,
+    mut wheel: EventReader<MouseWheel>,
+    mut target: ResMut<ZoomTarget>,
+    mut screen: ResMut<Screen>,
+) {
+    for event in wheel.read() {
+        target.0 = (target.0 * ZOOM_STEP.powf(event.y)).clamp(Screen::MIN_ZOOM, Screen::MAX_ZOOM);
+    }
+    // Only touch the screen while zooming, so sprites aren't moved every frame.
+    if screen.zoom() != target.0 {
+        screen.zoom_towards(target.0, ZOOM_RATE, time.delta_secs());
+    }
+}
@@ -0,0 +1,508 @@
+//! Generates and inspects brain-engine maps from the command line, for
+//! scripts and CI.
+//!
+//! ```shell
+//! brain-engine-cli generate --size 16 --seed 7 --output level.map
+//! brain-engine-cli render level.map --format png --output level.png
+//! brain-engine-cli stats level.map
+//! brain-engine-cli path level.map --from 0,0 --to 15,15
+//! ```
+//!
+//! Maps are read and written as map files, see [`MapFile`]; `-` reads the
+//! map from standard input. Without `--output`, results go to standard
+//! output.
+
+use bevy::math::IVec2;
+use brain_engine_core::{
+    DynMap, GeneratorConfig, GeneratorConfigError, GeneratorType, Map, MapFile, MapFileError,
+    RenderStyle, TileExtensions, TileGeneratorDefault,
+};
+use clap::{Arg, ArgMatches, value_parser};
+use serde_json::json;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+/// Largest map side length `generate` accepts.
+const MAX_MAP_SIZE: usize = 1024;
+
+#[derive(Debug)]
+enum Command {
+    Generate {
+        size: usize,
+        seed: Option<u64>,
+        generator: GeneratorKind,
+        config: Option<PathBuf>,
+        format: MapFormat,
+        output: Option<PathBuf>,
+    },
+    Render {
+        map: PathBuf,
+        format: RenderFormat,
+        output: Option<PathBuf>,
+    },
+    Stats {
+        map: PathBuf,
+    },
+    Path {
+        map: PathBuf,
+        from: IVec2,
+        to: IVec2,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GeneratorKind {
+    Default,
+    ClusteredRooms,
+    OpenField,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MapFormat {
+    /// A map file the other subcommands read.
+    Map,
+    /// Box-drawing characters, one per tile.
+    Ascii,
+    /// Every tile with its tile set, biome and exits.
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RenderFormat {
+    Ascii,
+    Svg,
+    Png,
+}
+
+fn cli() -> clap::Command {
+    let map = || {
+        Arg::new("map")
+            .required(true)
+            .value_parser(value_parser!(PathBuf))
+            .help("The map file, or - for standard input")
+    };
+    let output = || {
+        Arg::new("output")
+            .long("output")
+            .short('o')
+            .value_parser(value_parser!(PathBuf))
+            .help("Where to write the result instead of standard output")
+    };
+    let position = |name: &'static str, help: &'static str| {
+        Arg::new(name)
+            .long(name)
+            .required(true)
+            .value_parser(parse_position)
+            .help(help)
+    };
+    clap::Command::new("brain-engine-cli")
+        .about("Generate and inspect brain-engine maps")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .subcommand(
+            clap::Command::new("generate")
+                .about("Generate a map")
+                .arg(
+                    Arg::new("size")
+                        .long("size")
+                        .value_parser(value_parser!(usize))
+                        .default_value("16")
+                        .help("Side length in tiles"),
+                )
+                .arg(
+                    Arg::new("seed")
+                        .long("seed")
+                        .value_parser(value_parser!(u64))
+                        .help("Seed for a reproducible map; random when left out"),
+                )
+                .arg(
+                    Arg::new("generator")
+                        .long("generator")
+                        .value_parser(["default", "clustered-rooms", "open-field"])
+                        .default_value("default"),
+                )
+                .arg(
+                    Arg::new("config")
+                        .long("config")
+                        .value_parser(value_parser!(PathBuf))
+                        .conflicts_with_all(["size", "seed", "generator"])
+                        .help(
+                            "A generator config in JSON, TOML or RON, by file extension, \
+                             instead of the size, seed and generator",
+                        ),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_parser(["map", "ascii", "json"])
+                        .default_value("map")
+                        .help("map writes a map file the other subcommands read"),
+                )
+                .arg(output()),
+        )
+        .subcommand(
+            clap::Command::new("render")
+                .about("Draw a map")
+                .arg(map())
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_parser(["ascii", "svg", "png"])
+                        .default_value("ascii"),
+                )
+                .arg(output()),
+        )
+        .subcommand(
+            clap::Command::new("stats")
+                .about("Measure a map: tile counts, connectivity, dead ends and rooms, as JSON")
+                .arg(map()),
+        )
+        .subcommand(
+            clap::Command::new("path")
+                .about(
+                    "Find the cheapest path between two tiles, as JSON. \
+                     Exits with status 1 when there is none",
+                )
+                .arg(map())
+                .arg(position("from", "The start tile as x,y"))
+                .arg(position("to", "The goal tile as x,y")),
+        )
+}
+
+/// The [`Command`] for arguments that [`cli`] accepted.
+fn command(matches: &ArgMatches) -> Command {
+    let (name, arguments) = matches.subcommand().expect("a subcommand is required");
+    let path = |id: &str| arguments.get_one::<PathBuf>(id).cloned();
+    let choice = |id: &str| arguments.get_one::<String>(id).unwrap().as_str();
+    let position = |id: &str| *arguments.get_one::<IVec2>(id).unwrap();
+    match name {
+        "generate" => Command::Generate {
+            size: *arguments.get_one("size").unwrap(),
+            seed: arguments.get_one("seed").copied(),
+            generator: match choice("generator") {
+                "clustered-rooms" => GeneratorKind::ClusteredRooms,
+                "open-field" => GeneratorKind::OpenField,
+                _ => GeneratorKind::Default,
+            },
+            config: path("config"),
+            format: match choice("format") {
+                "ascii" => MapFormat::Ascii,
+                "json" => MapFormat::Json,
+                _ => MapFormat::Map,
+            },
+            output: path("output"),
+        },
+        "render" => Command::Render {
+            map: path("map").unwrap(),
+            format: match choice("format") {
+                "svg" => RenderFormat::Svg,
+                "png" => RenderFormat::Png,
+                _ => RenderFormat::Ascii,
+            },
+            output: path("output"),
+        },
+        "stats" => Command::Stats {
+            map: path("map").unwrap(),
+        },
+        _ => Command::Path {
+            map: path("map").unwrap(),
+            from: position("from"),
+            to: position("to"),
+        },
+    }
+}
+
+#[derive(Debug)]
+enum CliError {
+    Io { path: PathBuf, error: io::Error },
+    MapFile { path: PathBuf, error: MapFileError },
+    InvalidConfig { path: PathBuf, reason: String },
+    Generation(GeneratorConfigError),
+    TooLarge(usize),
+    OutsideMap(IVec2),
+    Render(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Io { path, error } => write!(f, "{}: {error}", path.display()),
+            CliError::MapFile { path, error } => write!(f, "{}: {error}", path.display()),
+            CliError::InvalidConfig { path, reason } => write!(f, "{}: {reason}", path.display()),
+            CliError::Generation(error) => write!(f, "{error}"),
+            CliError::TooLarge(size) => {
+                write!(f, "map size must be at most {MAX_MAP_SIZE}, got {size}")
+            }
+            CliError::OutsideMap(position) => write!(f, "{position} is outside the map"),
+            CliError::Render(reason) => write!(f, "rendering failed: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// What a subcommand produced, and whether it counts as a success.
+struct Output {
+    bytes: Vec<u8>,
+    success: bool,
+}
+
+impl Output {
+    fn text(text: String) -> Self {
+        Self {
+            bytes: text.into_bytes(),
+            success: true,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let command = command(&cli().get_matches());
+    let output_path = match &command {
+        Command::Generate { output, .. } | Command::Render { output, .. } => output.clone(),
+        Command::Stats { .. } | Command::Path { .. } => None,
+    };
+    let result = run(command).and_then(|output| {
+        write_output(output_path.as_deref(), &output.bytes)?;
+        Ok(output.success)
+    });
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(error) => {
+            eprintln!("brain-engine-cli: {error}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn run(command: Command) -> Result<Output, CliError> {
+    match command {
+        Command::Generate {
+            size,
+            seed,
+            generator,
+            config,
+            format,
+            ..
+        } => {
+            let config = match config {
+                Some(path) => read_config(&path)?,
+                None => generator_config(size, seed, generator),
+            };
+            if config.size > MAX_MAP_SIZE {
+                return Err(CliError::TooLarge(config.size));
+            }
+            let map = DynMap::from_config(&config).map_err(CliError::Generation)?;
+            Ok(Output::text(match format {
+                MapFormat::Map => MapFile::from_map(&map, TileExtensions::new()).write(),
+                MapFormat::Ascii => map.render_ascii(),
+                MapFormat::Json => format!("{:#}\n", map.to_json()),
+            }))
+        }
+        Command::Render { map, format, .. } => {
+            let map = read_map(&map)?;
+            let style = RenderStyle::default();
+            match format {
+                RenderFormat::Ascii => Ok(Output::text(map.render_ascii())),
+                RenderFormat::Svg => Ok(Output::text(map.render_svg(&style))),
+                RenderFormat::Png => Ok(Output {
+                    bytes: map
+                        .render_png(&style)
+                        .map_err(|error| CliError::Render(error.to_string()))?,
+                    success: true,
+                }),
+            }
+        }
+        Command::Stats { map } => {
+            let stats = read_map(&map)?.stats();
+            let mut report = serde_json::to_value(&stats).expect("stats always serialize");
+            report["connectivity"] = json!(stats.connectivity());
+            Ok(Output::text(format!("{report:#}\n")))
+        }
+        Command::Path { map, from, to } => {
+            let map = read_map(&map)?;
+            if let Some(position) = [from, to]
+                .into_iter()
+                .find(|position| !map.in_bounds(*position))
+            {
+                return Err(CliError::OutsideMap(position));
+            }
+            let report = map.path_report(from, to);
+            let success = report.is_some();
+            let report = report.unwrap_or_else(|| json!({ "reachable": false }));
+            Ok(Output {
+                bytes: format!("{report}\n").into_bytes(),
+                success,
+            })
+        }
+    }
+}
+
+fn generator_config(size: usize, seed: Option<u64>, generator: GeneratorKind) -> GeneratorConfig {
+    let generator = match generator {
+        GeneratorKind::Default => GeneratorType::Default {
+            seed,
+            tile_exit_probability: 0.35,
+            room_probability: 0.35,
+        },
+        GeneratorKind::ClusteredRooms => GeneratorType::ClusteredRooms {
+            seed: seed.unwrap_or_else(rand::random),
+            min_room_size: 3,
+            max_room_size: 6,
+        },
+        GeneratorKind::OpenField => GeneratorType::OpenField,
+    };
+    GeneratorConfig {
+        size,
+        generator,
+        passes: Vec::new(),
+    }
+}
+
+fn read_config(path: &Path) -> Result<GeneratorConfig, CliError> {
+    let text = read_text(path)?;
+    let parsed = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("toml") => GeneratorConfig::from_toml(&text).map_err(|error| error.to_string()),
+        Some("ron") => GeneratorConfig::from_ron(&text).map_err(|error| error.to_string()),
+        _ => serde_json::from_str(&text).map_err(|error| error.to_string()),
+    };
+    parsed.map_err(|reason| CliError::InvalidConfig {
+        path: path.to_path_buf(),
+        reason,
+    })
+}
+
+fn read_map(path: &Path) -> Result<Map<TileGeneratorDefault>, CliError> {
+    let file = MapFile::parse(&read_text(path)?).map_err(|error| CliError::MapFile {
+        path: path.to_path_buf(),
+        error,
+    })?;
+    Ok(file.into_map(TileGeneratorDefault::new()).0)
+}
+
+/// Reads `path`, or standard input for `-`.
+fn read_text(path: &Path) -> Result<String, CliError> {
+    let io_error = |error| CliError::Io {
+        path: path.to_path_buf(),
+        error,
+    };
+    if path == Path::new("-") {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text).map_err(io_error)?;
+        Ok(text)
+    } else {
+        fs::read_to_string(path).map_err(io_error)
+    }
+}
+
+/// Writes `bytes` to `path`, or standard output without one.
+fn write_output(path: Option<&Path>, bytes: &[u8]) -> Result<(), CliError> {
+    let result = match path {
+        Some(path) => fs::write(path, bytes),
+        None => io::stdout().write_all(bytes),
+    };
+    result.map_err(|error| CliError::Io {
+        path: path.unwrap_or(Path::new("-")).to_path_buf(),
+        error,
+    })
+}
+
+/// Parses a position written as `x,y`.
+fn parse_position(text: &str) -> Result<IVec2, String> {
+    let invalid = || format!("expected a position as x,y, got {text:?}");
+    let (x, y) = text.split_once(',').ok_or_else(invalid)?;
+    let x = x.trim().parse().map_err(|_| invalid())?;
+    let y = y.trim().parse().map_err(|_| invalid())?;
+    Ok(IVec2::new(x, y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn run_arguments(arguments: &[&str]) -> Result<Output, CliError> {
+        let matches = cli()
+            .try_get_matches_from(["brain-engine-cli"].iter().chain(arguments))
+            .unwrap();
+        run(command(&matches))
+    }
+
+    fn text(output: Output) -> String {
+        String::from_utf8(output.bytes).unwrap()
+    }
+
+    /// Writes a generated map file to a fresh temporary path.
+    fn map_file(name: &str, arguments: &[&str]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "brain-engine-cli-{name}-{}.map",
+            std::process::id()
+        ));
+        let map = text(run_arguments(&[&["generate"][..], arguments].concat()).unwrap());
+        fs::write(&path, map).unwrap();
+        path
+    }
+
+    #[test]
+    fn seeded_generation_is_reproducible() {
+        let arguments = [
+            "generate", "--size", "8", "--seed", "3", "--format", "ascii",
+        ];
+        let first = text(run_arguments(&arguments).unwrap());
+
+        assert_eq!(first, text(run_arguments(&arguments).unwrap()));
+        assert_eq!(first.lines().count(), 8);
+        assert!(
+            cli()
+                .try_get_matches_from([
+                    "brain-engine-cli",
+                    "generate",
+                    "--config",
+                    "a.json",
+                    "--seed",
+                    "1"
+                ])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn generated_map_files_render_like_the_map() {
+        let path = map_file("render", &["--size", "6", "--seed", "5"]);
+        let ascii = text(
+            run_arguments(&[
+                "generate", "--size", "6", "--seed", "5", "--format", "ascii",
+            ])
+            .unwrap(),
+        );
+
+        let rendered = run_arguments(&["render", path.to_str().unwrap()]).unwrap();
+        let png = run_arguments(&["render", path.to_str().unwrap(), "--format", "png"]).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(text(rendered), ascii);
+        assert!(png.bytes.starts_with(b"\x89PNG"));
+    }
+
+    #[test]
+    fn stats_and_paths_read_map_files() {
+        let path = map_file("open", &["--size", "5", "--generator", "open-field"]);
+        let file = path.to_str().unwrap();
+
+        let stats: Value =
+            serde_json::from_slice(&run_arguments(&["stats", file]).unwrap().bytes).unwrap();
+        let found = run_arguments(&["path", file, "--from", "0,0", "--to", "4,4"]).unwrap();
+        let outside = run_arguments(&["path", file, "--from", "0,0", "--to", "5,5"]);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(stats["connectivity"], 1.0);
+        assert!(found.success);
+        let found: Value = serde_json::from_slice(&found.bytes).unwrap();
+        assert_eq!(found["steps"], 8);
+        assert!(matches!(outside, Err(CliError::OutsideMap(_))));
+    }
+}
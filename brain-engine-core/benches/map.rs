@@ -0,0 +1,44 @@
+//! Generation and movement benchmarks, run with `cargo bench -p brain-engine-core`.
+
+use bevy::prelude::*;
+use brain_engine_core::{Map, OpenFieldGenerator, TileGeneratorDefault};
+use criterion::{BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+
+const SIZES: [usize; 3] = [32, 64, 128];
+
+fn generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("generation");
+    for size in SIZES {
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, &size| {
+            b.iter(|| Map::new(size, TileGeneratorDefault::with_seed(1)))
+        });
+    }
+    group.finish();
+}
+
+fn can_move(c: &mut Criterion) {
+    let map = Map::new(64, TileGeneratorDefault::with_seed(1));
+    c.bench_function("can_move", |b| {
+        b.iter(|| {
+            (0..64)
+                .flat_map(|x| (0..63).map(move |y| IVec2::new(x, y)))
+                .filter(|&position| map.can_move(position, black_box(position + IVec2::Y)))
+                .count()
+        })
+    });
+}
+
+fn find_path(c: &mut Criterion) {
+    let mut group = c.benchmark_group("find_path");
+    for size in SIZES {
+        let map = Map::new(size, OpenFieldGenerator);
+        let corner = IVec2::splat(size as i32 - 1);
+        group.bench_with_input(BenchmarkId::from_parameter(size), &map, |b, map| {
+            b.iter(|| map.find_path(IVec2::ZERO, black_box(corner)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, generation, can_move, find_path);
+criterion_main!(benches);
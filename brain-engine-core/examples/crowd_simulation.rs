@@ -0,0 +1,200 @@
+//! Headless crowd simulation stress scenario.
+//!
+//! Spawns hundreds of actors on a large generated map and moves them towards
+//! shared points of interest one tile per tick, blocking on occupied tiles.
+//! Every tick is timed against a frame budget and the run fails if the budget
+//! is blown, so the scenario doubles as a performance regression check:
+//!
+//! ```shell
+//! cargo run --release -p brain-engine-core --example crowd_simulation
+//! ```
+//!
+//! Optional arguments: `[actors] [map size] [ticks]`.
+
+use bevy::prelude::*;
+use brain_engine_core::prelude::*;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+const SEED: u64 = 1234;
+const POINTS_OF_INTEREST: usize = 24;
+/// Ticks an actor waits behind an occupied tile before picking a new goal.
+const PATIENCE: u32 = 3;
+/// Path searches allowed per tick; further actors wait for the next tick so a
+/// wave of arrivals cannot stall a single frame.
+const MAX_REPLANS_PER_TICK: usize = 8;
+
+/// Per-tick budget, matching one frame at 60 FPS. Debug builds get a much
+/// looser budget so the scenario stays runnable without `--release`.
+fn tick_budget() -> Duration {
+    if cfg!(debug_assertions) {
+        Duration::from_millis(250)
+    } else {
+        Duration::from_micros(16_667)
+    }
+}
+
+/// Caches whole paths by `(from, to)`, shared between actors.
+#[derive(Default)]
+struct PathCache {
+    paths: HashMap<(IVec2, IVec2), Rc<Vec<IVec2>>>,
+    hits: usize,
+    misses: usize,
+}
+
+impl PathCache {
+    fn path(
+        &mut self,
+        map: &Map<TileGeneratorDefault>,
+        from: IVec2,
+        to: IVec2,
+    ) -> Option<Rc<Vec<IVec2>>> {
+        if let Some(path) = self.paths.get(&(from, to)) {
+            self.hits += 1;
+            return Some(path.clone());
+        }
+        self.misses += 1;
+        let path = Rc::new(map.find_path(from, to)?);
+        self.paths.insert((from, to), path.clone());
+        Some(path)
+    }
+}
+
+struct Actor {
+    position: IVec2,
+    path: Rc<Vec<IVec2>>,
+    /// Index into `path` of the tile the actor currently stands on.
+    step: usize,
+    blocked_ticks: u32,
+}
+
+fn main() {
+    let mut arguments = std::env::args().skip(1).map(|argument| {
+        argument
+            .parse::<usize>()
+            .expect("arguments must be positive integers")
+    });
+    let actor_count = arguments.next().unwrap_or(400);
+    let map_size = arguments.next().unwrap_or(128);
+    let ticks = arguments.next().unwrap_or(300);
+
+    let started = Instant::now();
+    let mut map = Map::new(
+        map_size,
+        TileGeneratorDefault::with_seed_and_probabilities(SEED, 0.65, 0.35),
+    );
+
+    // Keep the crowd inside the largest connected area so every actor can
+    // reach every point of interest.
+    let mut seen = HashSet::new();
+    let mut largest = HashSet::new();
+    for position in map.positions() {
+        if seen.contains(&position) {
+            continue;
+        }
+        let area = map.reachable_from(position);
+        seen.extend(area.iter().copied());
+        if area.len() > largest.len() {
+            largest = area;
+        }
+    }
+    println!(
+        "generated {map_size}x{map_size} map and found its areas in {:?}",
+        started.elapsed()
+    );
+    let mut walkable: Vec<IVec2> = largest.into_iter().collect();
+    walkable.sort_by_key(|position| (position.y, position.x));
+    assert!(
+        walkable.len() > actor_count,
+        "largest area has {} tiles, too small for {actor_count} actors",
+        walkable.len()
+    );
+
+    let mut rng = StdRng::seed_from_u64(SEED);
+    let points_of_interest: Vec<IVec2> = (0..POINTS_OF_INTEREST)
+        .map(|_| walkable[rng.random_range(0..walkable.len())])
+        .collect();
+
+    // Actors are kept apart through the map's occupancy, identified by their
+    // index. Occupants don't block path searches, so cached paths stay
+    // valid; actors wait behind each other instead.
+    let mut cache = PathCache::default();
+    let mut actors = Vec::with_capacity(actor_count);
+    while actors.len() < actor_count {
+        let position = walkable[rng.random_range(0..walkable.len())];
+        if map.is_occupied(position) {
+            continue;
+        }
+        map.try_occupy(position, actors.len() as u64).unwrap();
+        actors.push(Actor {
+            position,
+            path: Rc::new(vec![position]),
+            step: 0,
+            blocked_ticks: 0,
+        });
+    }
+
+    let budget = tick_budget();
+    let mut tick_times = Vec::with_capacity(ticks);
+    let mut moves = 0usize;
+    let mut arrivals = 0usize;
+    for _ in 0..ticks {
+        let tick_started = Instant::now();
+        let mut replans = 0;
+        for (id, actor) in actors.iter_mut().enumerate() {
+            let arrived = actor.step + 1 >= actor.path.len();
+            if arrived || actor.blocked_ticks >= PATIENCE {
+                if replans == MAX_REPLANS_PER_TICK {
+                    continue;
+                }
+                replans += 1;
+                if arrived {
+                    arrivals += 1;
+                }
+                let goal = points_of_interest[rng.random_range(0..points_of_interest.len())];
+                actor.path = cache
+                    .path(&map, actor.position, goal)
+                    .expect("points of interest share the actor's area");
+                actor.step = 0;
+                actor.blocked_ticks = 0;
+                continue;
+            }
+
+            let next = actor.path[actor.step + 1];
+            debug_assert!(map.can_move(actor.position, next));
+            if map.try_occupy(next, id as u64).is_err() {
+                actor.blocked_ticks += 1;
+                continue;
+            }
+            map.vacate(actor.position);
+            actor.position = next;
+            actor.step += 1;
+            actor.blocked_ticks = 0;
+            moves += 1;
+        }
+        tick_times.push(tick_started.elapsed());
+    }
+
+    assert_eq!(
+        map.occupancy.iter().count(),
+        actor_count,
+        "two actors share a tile"
+    );
+
+    let total: Duration = tick_times.iter().sum();
+    let worst = tick_times.iter().max().copied().unwrap_or_default();
+    let average = total / ticks.max(1) as u32;
+    println!(
+        "{actor_count} actors, {ticks} ticks: {moves} moves, {arrivals} arrivals, \
+         path cache {} hits / {} misses",
+        cache.hits, cache.misses
+    );
+    println!("tick time: average {average:?}, worst {worst:?}, budget {budget:?}");
+
+    assert!(
+        worst <= budget,
+        "worst tick took {worst:?}, over the {budget:?} budget"
+    );
+}
@@ -0,0 +1,154 @@
+//! Generator gallery: a contact sheet of thumbnails across generators,
+//! parameters and seeds.
+//!
+//! Every row is one generator configuration and every column one seed, each
+//! thumbnail captioned with what produced it, so the effect of a parameter
+//! can be seen at a glance:
+//!
+//! ```shell
+//! cargo run --release -p brain-engine-core --example gallery
+//! ```
+//!
+//! Optional arguments: `[output file] [map size] [seeds]`. The sheet is an
+//! SVG document, built from [`Map::render_svg`] thumbnails.
+
+use bevy::prelude::*;
+use brain_engine_core::prelude::*;
+use brain_engine_core::{ClusteredRoomGenerator, RenderStyle};
+use std::fmt::Write;
+
+const THUMBNAIL_TILE_SIZE: f32 = 6.0;
+const MARGIN: f32 = 16.0;
+/// Height reserved under each thumbnail for its caption.
+const CAPTION_HEIGHT: f32 = 28.0;
+/// Width reserved left of each row for the row label.
+const LABEL_WIDTH: f32 = 220.0;
+
+/// One row of the sheet: a label and a way to render a map for a seed.
+struct Row {
+    label: String,
+    render: Box<dyn Fn(usize, u64) -> Thumbnail>,
+}
+
+struct Thumbnail {
+    svg: String,
+    caption: String,
+}
+
+fn thumbnail<G: TileGenerator>(map: Map<G>) -> Thumbnail {
+    let style = RenderStyle {
+        tile_size: THUMBNAIL_TILE_SIZE,
+        wall_thickness: 1.0,
+        ..RenderStyle::default()
+    };
+    let stats = map.stats();
+    Thumbnail {
+        svg: map.render_svg(&style),
+        caption: format!(
+            "{} rooms, {} dead ends, {:.0}% connected",
+            stats.rooms,
+            stats.dead_ends,
+            stats.connectivity() * 100.0
+        ),
+    }
+}
+
+fn rows() -> Vec<Row> {
+    let mut rows = Vec::new();
+    for tile_exit_probability in [0.2, 0.35, 0.5, 0.8] {
+        rows.push(Row {
+            label: format!("default, exits {tile_exit_probability}"),
+            render: Box::new(move |size, seed| {
+                let generator = TileGeneratorDefault::builder()
+                    .seed(seed)
+                    .tile_exit_probability(tile_exit_probability)
+                    .build();
+                thumbnail(Map::new(size, generator))
+            }),
+        });
+    }
+    for room_probability in [0.1, 0.6] {
+        rows.push(Row {
+            label: format!("default, rooms {room_probability}"),
+            render: Box::new(move |size, seed| {
+                let generator = TileGeneratorDefault::builder()
+                    .seed(seed)
+                    .room_probability(room_probability)
+                    .build();
+                thumbnail(Map::new(size, generator))
+            }),
+        });
+    }
+    for (min, max) in [(2, 4), (4, 8)] {
+        rows.push(Row {
+            label: format!("clustered rooms {min}-{max}"),
+            render: Box::new(move |size, seed| {
+                let generator = ClusteredRoomGenerator::new(seed)
+                    .with_room_size(min, max)
+                    .with_bounds(UVec2::splat(size as u32));
+                thumbnail(Map::new(size, generator))
+            }),
+        });
+    }
+    rows
+}
+
+fn main() {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+    let output = arguments.first().map_or("gallery.svg", String::as_str);
+    let size: usize = arguments
+        .get(1)
+        .map_or(24, |value| value.parse().expect("map size"));
+    let seeds: u64 = arguments
+        .get(2)
+        .map_or(4, |value| value.parse().expect("seed count"));
+
+    let rows = rows();
+    let cell = Vec2::new(
+        size as f32 * THUMBNAIL_TILE_SIZE + MARGIN,
+        size as f32 * THUMBNAIL_TILE_SIZE + CAPTION_HEIGHT + MARGIN,
+    );
+    let width = LABEL_WIDTH + seeds as f32 * cell.x + MARGIN;
+    let height = rows.len() as f32 * cell.y + MARGIN;
+
+    let mut sheet = String::new();
+    // Writing into a String cannot fail.
+    let _ = writeln!(
+        sheet,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-family="sans-serif">"#
+    );
+    let _ = writeln!(
+        sheet,
+        r#"<rect width="{width}" height="{height}" fill="white"/>"#
+    );
+    for (row_index, row) in rows.iter().enumerate() {
+        let top = MARGIN + row_index as f32 * cell.y;
+        let _ = writeln!(
+            sheet,
+            r#"<text x="{MARGIN}" y="{}" font-size="14">{}</text>"#,
+            top + cell.y / 2.0,
+            row.label
+        );
+        for seed in 0..seeds {
+            let left = LABEL_WIDTH + seed as f32 * cell.x;
+            let thumbnail = (row.render)(size, seed);
+            let _ = writeln!(sheet, r#"<g transform="translate({left} {top})">"#);
+            sheet.push_str(&thumbnail.svg);
+            let _ = writeln!(
+                sheet,
+                r#"<text y="{}" font-size="9">seed {seed}: {}</text>"#,
+                size as f32 * THUMBNAIL_TILE_SIZE + 12.0,
+                thumbnail.caption
+            );
+            let _ = writeln!(sheet, "</g>");
+        }
+    }
+    let _ = writeln!(sheet, "</svg>");
+
+    std::fs::write(output, sheet).expect("write the contact sheet");
+    println!(
+        "Wrote {} thumbnails ({} rows x {seeds} seeds) to {output}",
+        rows.len() as u64 * seeds,
+        rows.len()
+    );
+}
@@ -0,0 +1,115 @@
+use crate::map::Map;
+use crate::map_tile::Tile;
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+
+/// Offsets of the eight surrounding tiles, clockwise from north (+y):
+/// N, NE, E, SE, S, SW, W, NW.
+pub const NEIGHBOR8_OFFSETS: [IVec2; 8] = [
+    IVec2::new(0, 1),
+    IVec2::new(1, 1),
+    IVec2::new(1, 0),
+    IVec2::new(1, -1),
+    IVec2::new(0, -1),
+    IVec2::new(-1, -1),
+    IVec2::new(-1, 0),
+    IVec2::new(-1, 1),
+];
+
+/// Returns true when `a` and `b` share an edge.
+pub fn is_orthogonally_adjacent(a: IVec2, b: IVec2) -> bool {
+    (a - b).abs().element_sum() == 1
+}
+
+/// Returns true when `a` and `b` only share a corner.
+pub fn is_diagonally_adjacent(a: IVec2, b: IVec2) -> bool {
+    (a - b).abs() == IVec2::ONE
+}
+
+/// Returns true when `b` is one of the eight tiles around `a`.
+pub fn is_adjacent8(a: IVec2, b: IVec2) -> bool {
+    a != b && (a - b).abs().max_element() == 1
+}
+
+impl<G: TileGenerator> Map<G> {
+    /// Returns the on-map tiles around `position` in [`NEIGHBOR8_OFFSETS`]
+    /// order, ignoring exits and walls.
+    ///
+    /// This is for rendering and awareness checks; use [`Map::neighbors`]
+    /// for movement.
+    pub fn neighbors8(&self, position: IVec2) -> impl Iterator<Item = IVec2> + '_ {
+        NEIGHBOR8_OFFSETS
+            .into_iter()
+            .map(move |offset| position + offset)
+            .filter(|neighbor| self.in_bounds(*neighbor))
+    }
+
+    /// Returns a bit per surrounding tile, bit `i` standing for
+    /// `NEIGHBOR8_OFFSETS[i]`, set when that tile is on the map and matches
+    /// `predicate`. Autotilers index their corner pieces with it.
+    pub fn neighbor8_mask(&self, position: IVec2, predicate: impl Fn(&Tile) -> bool) -> u8 {
+        NEIGHBOR8_OFFSETS
+            .iter()
+            .enumerate()
+            .filter(|(_, offset)| {
+                self.tiles
+                    .get(&(position + **offset))
+                    .is_some_and(&predicate)
+            })
+            .fold(0, |mask, (bit, _)| mask | 1 << bit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_tile::TileSet;
+    use crate::open_field::OpenFieldGenerator;
+    use crate::testing::GridBuilder;
+
+    /// Closed rooms in the left column, closed corridors elsewhere.
+    fn column_map() -> Map<OpenFieldGenerator> {
+        GridBuilder::new("▪  \n▪  \n▪  ").build(OpenFieldGenerator)
+    }
+
+    #[test]
+    fn adjacency_predicates_distinguish_edges_and_corners() {
+        let center = IVec2::new(4, 4);
+
+        assert!(is_orthogonally_adjacent(center, IVec2::new(4, 5)));
+        assert!(!is_orthogonally_adjacent(center, IVec2::new(5, 5)));
+        assert!(is_diagonally_adjacent(center, IVec2::new(3, 5)));
+        assert!(!is_diagonally_adjacent(center, IVec2::new(4, 3)));
+        assert!(is_adjacent8(center, IVec2::new(3, 3)));
+        assert!(is_adjacent8(center, IVec2::new(5, 4)));
+        assert!(!is_adjacent8(center, center));
+        assert!(!is_adjacent8(center, IVec2::new(6, 4)));
+    }
+
+    #[test]
+    fn neighbors8_ignore_walls_but_stay_on_the_map() {
+        let map = column_map();
+
+        assert_eq!(map.neighbors8(IVec2::new(1, 1)).count(), 8);
+        assert_eq!(
+            map.neighbors8(IVec2::new(0, 0)).collect::<Vec<_>>(),
+            vec![IVec2::new(0, 1), IVec2::new(1, 1), IVec2::new(1, 0)]
+        );
+        assert_eq!(map.neighbors(IVec2::new(1, 1)).count(), 0);
+    }
+
+    #[test]
+    fn neighbor8_mask_marks_matching_tiles() {
+        let map = column_map();
+        let is_room = |tile: &Tile| tile.tile_set == TileSet::Room;
+
+        // West side of the center tile: SW, W and NW.
+        assert_eq!(map.neighbor8_mask(IVec2::new(1, 1), is_room), 0b1110_0000);
+        // Off-map tiles never match.
+        assert_eq!(
+            map.neighbor8_mask(IVec2::new(0, 0), |_: &Tile| true),
+            0b0000_0111
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use crate::map::Map;
+use crate::map_tile::Direction;
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+
+/// A player or NPC walking a map one tile at a time, for headless
+/// simulations and servers that don't run Bevy's ECS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Agent {
+    position: IVec2,
+    facing: Direction,
+    history: Vec<IVec2>,
+}
+
+impl Agent {
+    /// Places an agent at `start`, facing north.
+    pub fn new(start: IVec2) -> Self {
+        Self {
+            position: start,
+            facing: Direction::North,
+            history: vec![start],
+        }
+    }
+
+    pub fn with_facing(mut self, facing: Direction) -> Self {
+        self.facing = facing;
+        self
+    }
+
+    pub fn position(&self) -> IVec2 {
+        self.position
+    }
+
+    pub fn facing(&self) -> Direction {
+        self.facing
+    }
+
+    /// Every tile the agent has stood on, oldest first, starting with its
+    /// start tile.
+    pub fn history(&self) -> &[IVec2] {
+        &self.history
+    }
+
+    /// Turns towards `direction` without moving.
+    pub fn face(&mut self, direction: Direction) {
+        self.facing = direction;
+    }
+
+    /// Turns towards `direction` and steps one tile that way when
+    /// [`Map::can_move`] allows it and no occupant blocks the tile (see
+    /// [`Map::is_blocked_by_occupant`]). Returns false, without moving, when
+    /// the way is blocked.
+    pub fn try_move<G: TileGenerator>(&mut self, map: &Map<G>, direction: Direction) -> bool {
+        self.facing = direction;
+        let target = map.adjacent(self.position, direction);
+        if !map.can_move(self.position, target) || map.is_blocked_by_occupant(target) {
+            return false;
+        }
+        self.position = target;
+        self.history.push(target);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::open_field::OpenFieldGenerator;
+
+    #[test]
+    fn agents_move_through_exits_only() {
+        let mut map = Map::new(3, OpenFieldGenerator);
+        map.place_obstacle(IVec2::new(1, 1));
+        let mut agent = Agent::new(IVec2::new(0, 1)).with_facing(Direction::South);
+
+        assert!(!agent.try_move(&map, Direction::East));
+        assert_eq!(agent.facing(), Direction::East);
+        assert!(agent.try_move(&map, Direction::North));
+        assert!(agent.try_move(&map, Direction::East));
+        assert!(!agent.try_move(&map, Direction::North));
+
+        assert_eq!(agent.position(), IVec2::new(1, 2));
+        assert_eq!(agent.facing(), Direction::North);
+        assert_eq!(
+            agent.history(),
+            [IVec2::new(0, 1), IVec2::new(0, 2), IVec2::new(1, 2)]
+        );
+    }
+
+    #[test]
+    fn agents_wrap_on_toroidal_maps() {
+        let map = Map::new_toroidal(3, OpenFieldGenerator);
+        let mut agent = Agent::new(IVec2::new(0, 0));
+
+        assert!(agent.try_move(&map, Direction::West));
+
+        assert_eq!(agent.position(), IVec2::new(2, 0));
+    }
+}
@@ -0,0 +1,169 @@
+use crate::map::Map;
+use crate::map_file::MapFile;
+use crate::map_tile::{Direction, MapTile, Tile, TileSet};
+use crate::prefab::{Prefab, PrefabError};
+use crate::tile_generator::TileGenerator;
+use crate::topology::Topology;
+
+use bevy::prelude::*;
+
+/// Corridor glyphs indexed by exit bits (N = 1, E = 2, S = 4, W = 8): light
+/// box-drawing lines towards each exit, a space for a closed tile.
+pub const CORRIDOR_GLYPHS: [char; 16] = [
+    ' ', '╵', '╶', '└', '╷', '│', '┌', '├', '╴', '┘', '─', '┴', '┐', '┤', '┬', '┼',
+];
+
+/// Room glyphs indexed like [`CORRIDOR_GLYPHS`], drawn with heavy lines.
+pub const ROOM_GLYPHS: [char; 16] = [
+    '▪', '╹', '╺', '┗', '╻', '┃', '┏', '┣', '╸', '┛', '━', '┻', '┓', '┫', '┳', '╋',
+];
+
+/// Glyph for walkable [`TileSet::Field`] tiles, whatever their exits.
+pub const FIELD_GLYPH: char = '.';
+
+/// Glyph for field tiles without exits, i.e. obstacles.
+pub const OBSTACLE_GLYPH: char = '#';
+
+/// The character [`Map::render_ascii`] draws `tile` with.
+pub fn glyph(tile: Tile) -> char {
+    let glyphs = match tile.tile_set {
+        TileSet::Room => &ROOM_GLYPHS,
+        TileSet::Corridor => &CORRIDOR_GLYPHS,
+        TileSet::Field if tile.map_tile == MapTile::ZERO => return OBSTACLE_GLYPH,
+        TileSet::Field => return FIELD_GLYPH,
+    };
+    glyphs[tile.map_tile as usize]
+}
+
+impl<G: TileGenerator> Map<G> {
+    /// Draws the map one character per tile, see [`glyph`]. The top line is
+    /// the northernmost row, so the text matches the in-game orientation.
+    /// Missing tiles are drawn as `?`.
+    pub fn render_ascii(&self) -> String {
+        let mut text = String::with_capacity((self.x + 1) * self.y * 3);
+        for y in (0..self.y as i32).rev() {
+            for x in 0..self.x as i32 {
+                let character = self
+                    .tiles
+                    .get(&IVec2::new(x, y))
+                    .map_or('?', |tile| glyph(*tile));
+                text.push(character);
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Parses the glyphs [`Map::render_ascii`] draws, the northernmost row
+    /// first, for fixture maps written inline in tests or config files:
+    /// the box-drawing characters of [`CORRIDOR_GLYPHS`] and
+    /// [`ROOM_GLYPHS`], [`FIELD_GLYPH`], [`OBSTACLE_GLYPH`] and `?` for a
+    /// missing tile. Field tiles are open towards every neighboring tile
+    /// but obstacles and tiles without an exit back, as with [`Prefab`].
+    ///
+    /// The map is as wide as the longest line and as tall as the number of
+    /// lines, so `render_ascii` output parses back to the same map, biomes
+    /// aside. Like [`crate::MapFile::into_map`], `generator` is only used
+    /// for later regeneration.
+    pub fn from_ascii(text: &str, generator: G) -> Result<Self, PrefabError> {
+        let prefab: Prefab = text.parse()?;
+        let mut tiles = prefab.tiles().clone();
+        let open_fields: Vec<IVec2> = tiles
+            .iter()
+            .filter(|(_, tile)| tile.tile_set == TileSet::Field)
+            .map(|(position, _)| *position)
+            .collect();
+        for position in open_fields {
+            for direction in Direction::all() {
+                if !tiles.contains_key(&(position + direction.offset())) {
+                    let tile = tiles.get_mut(&position).unwrap();
+                    tile.map_tile = tile.map_tile.without(direction);
+                }
+            }
+        }
+        let file = MapFile {
+            x: text
+                .lines()
+                .map(|line| line.chars().count())
+                .max()
+                .unwrap_or(0),
+            y: text.lines().count(),
+            topology: Topology::Bounded,
+            tiles,
+            extensions: Default::default(),
+        };
+        Ok(file.into_map(generator).0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile_grid::TileGrid;
+
+    /// A room on the left joined to a corridor on the right.
+    struct Pair;
+
+    impl TileGenerator for Pair {
+        fn tile_at(&self, _tiles: &TileGrid, location: IVec2) -> Tile {
+            match (location.x, location.y) {
+                (0, 0) => Tile::new(TileSet::Room, MapTile::NE),
+                (1, 0) => Tile::new(TileSet::Corridor, MapTile::W),
+                (0, 1) => Tile::new(TileSet::Room, MapTile::S),
+                _ => Tile::new(TileSet::Corridor, MapTile::ZERO),
+            }
+        }
+    }
+
+    #[test]
+    fn glyphs_follow_exit_bits() {
+        assert_eq!(glyph(Tile::new(TileSet::Corridor, MapTile::NESW)), '┼');
+        assert_eq!(glyph(Tile::new(TileSet::Corridor, MapTile::ES)), '┌');
+        assert_eq!(glyph(Tile::new(TileSet::Room, MapTile::NS)), '┃');
+        assert_eq!(glyph(Tile::new(TileSet::Room, MapTile::ZERO)), '▪');
+        assert_eq!(glyph(Tile::new(TileSet::Field, MapTile::NE)), FIELD_GLYPH);
+        assert_eq!(
+            glyph(Tile::new(TileSet::Field, MapTile::ZERO)),
+            OBSTACLE_GLYPH
+        );
+    }
+
+    #[test]
+    fn north_is_drawn_at_the_top() {
+        let mut map = Map::new(2, Pair);
+
+        assert_eq!(map.render_ascii(), "╻ \n┗╴\n");
+
+        map.tiles.remove(&IVec2::new(1, 1));
+        assert_eq!(map.render_ascii(), "╻?\n┗╴\n");
+    }
+
+    #[test]
+    fn rendered_maps_parse_back() {
+        let map = Map::new(2, Pair);
+        let parsed = Map::from_ascii(&map.render_ascii(), Pair).unwrap();
+
+        assert_eq!(parsed.tiles, map.tiles);
+        assert_eq!((parsed.x, parsed.y), (2, 2));
+        let cave = Map::from_ascii("#.??\n..#?", Pair).unwrap();
+        assert_eq!((cave.x, cave.y), (4, 2));
+        // Open fields only lead to the tiles around them.
+        assert_eq!(cave.tiles[&IVec2::new(1, 1)].map_tile, MapTile::S);
+        assert_eq!(cave.tiles[&IVec2::ZERO].map_tile, MapTile::E);
+        assert_eq!(cave.tiles.len(), 5);
+    }
+
+    #[test]
+    fn unknown_glyphs_are_rejected() {
+        let error = Map::from_ascii("┌┐\n└x", Pair).err();
+
+        assert_eq!(
+            error,
+            Some(PrefabError::InvalidGlyph {
+                line: 2,
+                column: 2,
+                character: 'x',
+            })
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use crate::diff::{decode_tile, encode_tile};
+use crate::exploration::Exploration;
+use crate::map::Map;
+use crate::map_events::MapSubscribers;
+use crate::movement::MovementCosts;
+use crate::occupancy::Occupancy;
+use crate::share_code::{fnv1a, read_varint, write_varint};
+use crate::tile_generator::TileGenerator;
+use crate::tile_grid::TileGrid;
+use crate::topology::Topology;
+
+use bevy::prelude::*;
+use itertools::iproduct;
+use std::fmt;
+
+/// Version written after the magic bytes of every binary map.
+pub const BINARY_MAP_VERSION: u8 = 1;
+
+const MAGIC: &[u8; 4] = b"BEMB";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BinaryMapError {
+    /// The bytes don't start with the binary map magic.
+    MissingHeader,
+    /// Written by a newer, unsupported format version.
+    UnsupportedVersion(u8),
+    /// The bytes end before the last tile.
+    Truncated,
+    /// A header field or the tile byte at this position is invalid.
+    Invalid(String),
+    /// More bytes follow the last tile.
+    TrailingBytes,
+}
+
+impl fmt::Display for BinaryMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinaryMapError::MissingHeader => write!(f, "not a binary map"),
+            BinaryMapError::UnsupportedVersion(version) => {
+                write!(f, "unsupported binary map version {version}")
+            }
+            BinaryMapError::Truncated => write!(f, "binary map is truncated"),
+            BinaryMapError::Invalid(reason) => write!(f, "invalid binary map: {reason}"),
+            BinaryMapError::TrailingBytes => write!(f, "unexpected bytes after the last tile"),
+        }
+    }
+}
+
+impl std::error::Error for BinaryMapError {}
+
+impl<G: TileGenerator> Map<G> {
+    /// Compact encoding for save games: the magic `BEMB`, the
+    /// [`BINARY_MAP_VERSION`] byte, a topology byte (0 bounded, 1 toroidal),
+    /// the varint width and height, then one byte per tile in x-then-y
+    /// order, the same byte [`crate::MapDiff::to_bytes`] uses: exits in the
+    /// low four bits plus tile set flags, 0 for a missing tile. Tiles outside
+    /// the [`crate::Biome::Dungeon`] take a second byte for their biome. A
+    /// 256x256 dungeon takes just over 64 KiB.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(MAGIC.len() + 6 + self.x * self.y);
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(BINARY_MAP_VERSION);
+        bytes.push(match self.topology {
+            Topology::Bounded => 0,
+            Topology::Toroidal => 1,
+        });
+        write_varint(&mut bytes, self.x as u64);
+        write_varint(&mut bytes, self.y as u64);
+        for (x, y) in iproduct!(0..self.x as i32, 0..self.y as i32) {
+            encode_tile(&mut bytes, self.tiles.get(&IVec2::new(x, y)).copied());
+        }
+        bytes
+    }
+
+    /// A 64-bit hash of the topology, dimensions and tiles, the same on
+    /// every platform and whatever order the tiles were inserted in. Use it
+    /// to deduplicate maps, to check that a client's map matches the
+    /// server's, or as a map ID in bug reports, e.g. formatted as `{:016x}`.
+    ///
+    /// The hash covers [`Map::to_bytes`], so it only changes along with
+    /// [`BINARY_MAP_VERSION`]. The generator, fog of war and movement costs
+    /// are not part of it.
+    pub fn content_hash(&self) -> u64 {
+        fnv1a(&self.to_bytes())
+    }
+
+    /// Reads [`Map::to_bytes`] output. Like [`crate::MapFile::into_map`],
+    /// `generator` is only used for later regeneration.
+    pub fn from_bytes(bytes: &[u8], generator: G) -> Result<Self, BinaryMapError> {
+        let mut bytes = bytes
+            .strip_prefix(MAGIC)
+            .ok_or(BinaryMapError::MissingHeader)?;
+        let (&version, rest) = bytes.split_first().ok_or(BinaryMapError::Truncated)?;
+        if version != BINARY_MAP_VERSION {
+            return Err(BinaryMapError::UnsupportedVersion(version));
+        }
+        let (&topology, rest) = rest.split_first().ok_or(BinaryMapError::Truncated)?;
+        bytes = rest;
+        let topology = match topology {
+            0 => Topology::Bounded,
+            1 => Topology::Toroidal,
+            _ => return Err(BinaryMapError::Invalid(format!("topology {topology}"))),
+        };
+        let mut dimension = || {
+            let value = read_varint(&mut bytes).ok_or(BinaryMapError::Truncated)?;
+            usize::try_from(value)
+                .ok()
+                .filter(|value| *value <= i32::MAX as usize)
+                .ok_or_else(|| BinaryMapError::Invalid(format!("dimension {value}")))
+        };
+        let (x, y) = (dimension()?, dimension()?);
+        // Every tile takes at least one byte.
+        let tile_bytes = x.checked_mul(y).ok_or(BinaryMapError::Truncated)?;
+        if bytes.len() < tile_bytes {
+            return Err(BinaryMapError::Truncated);
+        }
+
+        let mut tiles = TileGrid::with_size(UVec2::new(x as u32, y as u32));
+        for (x, y) in iproduct!(0..x as i32, 0..y as i32) {
+            let position = IVec2::new(x, y);
+            if bytes.is_empty() {
+                return Err(BinaryMapError::Truncated);
+            }
+            match decode_tile(&mut bytes) {
+                Some(Some(tile)) => {
+                    tiles.insert(position, tile);
+                }
+                Some(None) => {}
+                None => return Err(BinaryMapError::Invalid(format!("tile at {position}"))),
+            }
+        }
+        if !bytes.is_empty() {
+            return Err(BinaryMapError::TrailingBytes);
+        }
+        Ok(Map {
+            size: x,
+            x,
+            y,
+            tiles,
+            generator,
+            topology,
+            movement_costs: MovementCosts::default(),
+            exploration: Exploration::default(),
+            occupancy: Occupancy::default(),
+            subscribers: MapSubscribers::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_tile::Biome;
+    use crate::open_field::OpenFieldGenerator;
+    use crate::tile_generator::TileGeneratorDefault;
+
+    #[test]
+    fn maps_round_trip_in_one_byte_per_tile() {
+        let mut map = Map::new_toroidal(256, TileGeneratorDefault::with_seed(21));
+        map.tiles.remove(&IVec2::new(4, 9));
+
+        let bytes = map.to_bytes();
+
+        assert_eq!(bytes.len(), 4 + 2 + 2 + 2 + 256 * 256);
+        map.tiles.get_mut(&IVec2::new(7, 7)).unwrap().biome = Biome::Water;
+        let bytes = map.to_bytes();
+        assert_eq!(bytes.len(), 4 + 2 + 2 + 2 + 256 * 256 + 1);
+        let loaded = Map::from_bytes(&bytes, TileGeneratorDefault::new()).unwrap();
+        assert_eq!((loaded.x, loaded.y), (256, 256));
+        assert_eq!(loaded.topology, Topology::Toroidal);
+        assert_eq!(loaded.tiles, map.tiles);
+    }
+
+    #[test]
+    fn malformed_bytes_are_rejected() {
+        let map = Map::new(3, TileGeneratorDefault::with_seed(2));
+        let bytes = map.to_bytes();
+        let load = |bytes: &[u8]| Map::from_bytes(bytes, TileGeneratorDefault::new()).err();
+
+        assert_eq!(load(b"BEMA"), Some(BinaryMapError::MissingHeader));
+        assert_eq!(
+            load(&[MAGIC.as_slice(), &[9]].concat()),
+            Some(BinaryMapError::UnsupportedVersion(9))
+        );
+        assert_eq!(
+            load(&bytes[..bytes.len() - 1]),
+            Some(BinaryMapError::Truncated)
+        );
+        assert_eq!(
+            load(&[bytes.as_slice(), &[0]].concat()),
+            Some(BinaryMapError::TrailingBytes)
+        );
+        let mut corrupt = bytes.clone();
+        *corrupt.last_mut().unwrap() = 0x80;
+        assert_eq!(
+            load(&corrupt),
+            Some(BinaryMapError::Invalid("tile at [2, 2]".to_string()))
+        );
+    }
+
+    #[test]
+    fn content_hashes_follow_the_tiles() {
+        let map = Map::new(16, TileGeneratorDefault::with_seed(12));
+        let mut copy = Map::from_bytes(&map.to_bytes(), TileGeneratorDefault::new()).unwrap();
+
+        assert_eq!(copy.content_hash(), map.content_hash());
+        // Pinned so a platform- or hash-order-dependent change fails here.
+        assert_eq!(
+            Map::new(4, OpenFieldGenerator).content_hash(),
+            0xb4df_03a6_ff4f_fb3e
+        );
+
+        copy.place_obstacle(IVec2::new(3, 3));
+        assert_ne!(copy.content_hash(), map.content_hash());
+        let toroidal = Map::new_toroidal(16, TileGeneratorDefault::with_seed(12));
+        assert_ne!(toroidal.content_hash(), map.content_hash());
+    }
+}
@@ -0,0 +1,94 @@
+//! Assigning [`Biome`]s while a map is generated.
+//!
+//! ```
+//! use bevy::prelude::*;
+//! use brain_engine_core::{Biome, BiomeGenerator, Map, OpenFieldGenerator};
+//!
+//! // A lake in the middle of a grassy overworld.
+//! let generator = BiomeGenerator::new(OpenFieldGenerator, |position: IVec2, _| {
+//!     if position.distance_squared(IVec2::splat(8)) < 9 {
+//!         Biome::Water
+//!     } else {
+//!         Biome::Grass
+//!     }
+//! });
+//! let map = Map::new(16, generator);
+//! assert_eq!(map.tiles[&IVec2::splat(8)].biome, Biome::Water);
+//! ```
+
+use crate::generation_context::GenerationContext;
+use crate::map_tile::{Biome, Tile};
+use crate::tile_generator::TileGenerator;
+use crate::tile_grid::TileGrid;
+
+use bevy::prelude::*;
+
+/// Decides the biome of each generated tile. Closures taking
+/// `(IVec2, Tile)` are assigners too.
+pub trait BiomeAssigner {
+    fn biome(&self, position: IVec2, tile: Tile) -> Biome;
+}
+
+impl<F: Fn(IVec2, Tile) -> Biome> BiomeAssigner for F {
+    fn biome(&self, position: IVec2, tile: Tile) -> Biome {
+        self(position, tile)
+    }
+}
+
+/// Wraps a generator and sets the biome of every tile it generates, using
+/// the position and the generated tile.
+#[derive(Resource)]
+pub struct BiomeGenerator<G, B> {
+    generator: G,
+    assigner: B,
+}
+
+impl<G: TileGenerator, B: BiomeAssigner> BiomeGenerator<G, B> {
+    pub fn new(generator: G, assigner: B) -> Self {
+        Self {
+            generator,
+            assigner,
+        }
+    }
+}
+
+impl<G: TileGenerator, B: BiomeAssigner> TileGenerator for BiomeGenerator<G, B> {
+    fn tile_at(&self, tiles: &TileGrid, location: IVec2) -> Tile {
+        self.generate(&GenerationContext::new(tiles, location))
+    }
+
+    fn tile_at_within(&self, tiles: &TileGrid, location: IVec2, bounds: IRect) -> Tile {
+        self.generate(&GenerationContext::new(tiles, location).within(bounds))
+    }
+
+    fn generate(&self, context: &GenerationContext) -> Tile {
+        let tile = self.generator.generate(context);
+        tile.with_biome(self.assigner.biome(context.location(), tile))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Map;
+    use crate::map_tile::TileSet;
+    use crate::tile_generator::TileGeneratorDefault;
+
+    #[test]
+    fn biomes_follow_the_assigner_and_keep_the_tiles() {
+        let caves = |_: IVec2, tile: Tile| match tile.tile_set {
+            TileSet::Room => Biome::Cave,
+            _ => Biome::Dungeon,
+        };
+        let plain = Map::new(10, TileGeneratorDefault::with_seed(6));
+        let map = Map::new(
+            10,
+            BiomeGenerator::new(TileGeneratorDefault::with_seed(6), caves),
+        );
+
+        for (position, tile) in &map.tiles {
+            assert_eq!(tile.with_biome(Biome::Dungeon), plain.tiles[position]);
+            assert_eq!(tile.biome == Biome::Cave, tile.tile_set == TileSet::Room);
+        }
+    }
+}
@@ -0,0 +1,346 @@
+//! Editor brushes that stamp many tiles in one stroke.
+//!
+//! Every stroke keeps the map consistent: exits between painted tiles follow
+//! the brush, and exits towards untouched tiles match what those tiles
+//! already have, so neighbors are never modified. Each stroke returns the
+//! [`MapDiff`] that undoes it, a single undo entry however many tiles
+//! changed.
+
+use crate::diff::MapDiff;
+use crate::map::Map;
+use crate::map_tile::{Direction, MapTile, Tile, TileSet};
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use itertools::iproduct;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+impl<G: TileGenerator> Map<G> {
+    /// Paints every tile in `rect` (both corners inclusive, clipped to the
+    /// map) with the tile set and biome of `template`. Neighboring painted
+    /// tiles are joined along each axis the template has an exit on, so
+    /// `MapTile::NESW` paints an open area and `MapTile::EW` east-west rows.
+    pub fn paint_rect(&mut self, rect: IRect, template: Tile) -> MapDiff {
+        let positions = self.clipped_positions(rect);
+        self.paint_tiles(positions, template, |_, direction| {
+            joins(template.map_tile, direction)
+        })
+    }
+
+    /// Paints a four-connected line from `from` to `to` with `tile_set`,
+    /// each tile joined to the next, like a corridor. Parts of the line off
+    /// the map are skipped.
+    pub fn paint_line(&mut self, from: IVec2, to: IVec2, tile_set: TileSet) -> MapDiff {
+        let line = line_positions(from, to);
+        let order: HashMap<IVec2, usize> = line
+            .iter()
+            .enumerate()
+            .map(|(index, position)| (*position, index))
+            .collect();
+        let positions = line
+            .into_iter()
+            .filter(|position| self.in_bounds(*position))
+            .collect();
+        self.paint_tiles(
+            positions,
+            Tile::new(tile_set, MapTile::ZERO),
+            |position, direction| {
+                let index = order[&position];
+                order
+                    .get(&(position + direction.offset()))
+                    .is_some_and(|other| other.abs_diff(index) == 1)
+            },
+        )
+    }
+
+    /// Fills `rect` with `tile`, e.g. flooding part of a level with water.
+    /// The same as [`Map::paint_rect`], named to sit with the other fills.
+    pub fn fill_region(&mut self, rect: IRect, tile: Tile) -> MapDiff {
+        self.paint_rect(rect, tile)
+    }
+
+    /// Bucket fill: repaints the area of tiles connected to `start` that
+    /// share its tile set, painted and joined like [`Map::paint_rect`]
+    /// paints them.
+    pub fn bucket_fill(&mut self, start: IVec2, template: Tile) -> MapDiff {
+        let Some(tile_set) = self.tiles.get(&start).map(|tile| tile.tile_set) else {
+            return self.empty_diff();
+        };
+        let area = self.connected_area(start, |tile| tile.tile_set == tile_set);
+        self.paint_tiles(area.into_iter().collect(), template, |_, direction| {
+            joins(template.map_tile, direction)
+        })
+    }
+
+    /// Changes the tile set of `start` and of every tile connected to it
+    /// through tiles matching `predicate` to `tile_set`, e.g. turning a
+    /// whole corridor network into rooms. Exits and biomes are kept, so the
+    /// layout stays the same. Nothing changes when `start` does not match.
+    pub fn flood_convert(
+        &mut self,
+        start: IVec2,
+        predicate: impl Fn(&Tile) -> bool,
+        tile_set: TileSet,
+    ) -> MapDiff {
+        if !self.tiles.get(&start).is_some_and(&predicate) {
+            return self.empty_diff();
+        }
+        let mut area: Vec<IVec2> = self.connected_area(start, predicate).into_iter().collect();
+        area.sort_by_key(|position| (position.x, position.y));
+        let mut undo = self.empty_diff();
+        for position in area {
+            let tile = self.tiles.get_mut(&position).unwrap();
+            if tile.tile_set != tile_set {
+                undo.changes.push((position, Some(*tile)));
+                tile.tile_set = tile_set;
+            }
+        }
+        self.publish_undo(&undo);
+        undo
+    }
+
+    /// The tiles reachable from `start` through [`Map::neighbors`] without
+    /// leaving tiles that match `predicate`, `start` included.
+    fn connected_area(&self, start: IVec2, predicate: impl Fn(&Tile) -> bool) -> HashSet<IVec2> {
+        let mut area = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+        while let Some(current) = queue.pop_front() {
+            for neighbor in self.neighbors(current) {
+                if predicate(&self.tiles[&neighbor]) && area.insert(neighbor) {
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        area
+    }
+
+    /// The positions in `rect`, both corners inclusive, that are on the map.
+    fn clipped_positions(&self, rect: IRect) -> Vec<IVec2> {
+        let min = rect.min.max(IVec2::ZERO);
+        let max = rect.max.min(IVec2::new(self.x as i32, self.y as i32) - 1);
+        iproduct!(min.x..=max.x, min.y..=max.y)
+            .map(|(x, y)| IVec2::new(x, y))
+            .collect()
+    }
+
+    fn empty_diff(&self) -> MapDiff {
+        MapDiff {
+            x: self.x,
+            y: self.y,
+            changes: Vec::new(),
+        }
+    }
+
+    /// Replaces the tiles at `positions` with tiles of the tile set and biome
+    /// of `template`, opening an exit between two painted tiles when `joined`
+    /// says so and towards an untouched tile when it has an exit back.
+    /// Returns the undo diff.
+    fn paint_tiles(
+        &mut self,
+        mut positions: Vec<IVec2>,
+        template: Tile,
+        joined: impl Fn(IVec2, Direction) -> bool,
+    ) -> MapDiff {
+        positions.sort_by_key(|position| (position.x, position.y));
+        let painted: HashSet<IVec2> = positions.iter().copied().collect();
+        let tiles: Vec<(IVec2, Tile)> = positions
+            .iter()
+            .map(|&position| {
+                let map_tile = Direction::all()
+                    .into_iter()
+                    .filter(|&direction| {
+                        let neighbor = self.adjacent(position, direction);
+                        if painted.contains(&neighbor) {
+                            joined(position, direction)
+                        } else {
+                            self.in_bounds(neighbor)
+                                && self.tiles.get(&neighbor).is_some_and(|tile| {
+                                    tile.map_tile.contains(direction.opposite())
+                                })
+                        }
+                    })
+                    .fold(MapTile::ZERO, MapTile::with);
+                let tile = Tile::new(template.tile_set, map_tile).with_biome(template.biome);
+                (position, tile)
+            })
+            .collect();
+
+        let mut undo = self.empty_diff();
+        for (position, tile) in tiles {
+            let previous = self.tiles.insert(position, tile);
+            if previous != Some(tile) {
+                undo.changes.push((position, previous));
+            }
+        }
+        self.publish_undo(&undo);
+        undo
+    }
+}
+
+/// Whether a template joins painted neighbors across `direction`'s axis.
+fn joins(template: MapTile, direction: Direction) -> bool {
+    template.contains(direction) || template.contains(direction.opposite())
+}
+
+/// The tiles a line between the centers of `from` and `to` passes through,
+/// stepping one axis at a time.
+fn line_positions(from: IVec2, to: IVec2) -> Vec<IVec2> {
+    let delta = to - from;
+    let steps = delta.abs();
+    let sign = delta.signum();
+    let mut current = from;
+    let mut line = vec![current];
+    let (mut x_taken, mut y_taken) = (0, 0);
+    while x_taken < steps.x || y_taken < steps.y {
+        let next_x = (1 + 2 * x_taken) * steps.y;
+        let next_y = (1 + 2 * y_taken) * steps.x;
+        if y_taken == steps.y || (x_taken < steps.x && next_x <= next_y) {
+            x_taken += 1;
+            current.x += sign.x;
+        } else {
+            y_taken += 1;
+            current.y += sign.y;
+        }
+        line.push(current);
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_tile::Biome;
+    use crate::tile_generator::TileGeneratorDefault;
+
+    fn closed_map(size: usize) -> Map<TileGeneratorDefault> {
+        let generator = TileGeneratorDefault::builder()
+            .tile_exit_probability(0.0)
+            .room_probability(0.0)
+            .build();
+        Map::new(size, generator)
+    }
+
+    #[test]
+    fn rects_paint_open_areas_and_undo_in_one_step() {
+        let mut map = closed_map(6);
+        let before = map.tiles.clone();
+
+        let undo = map.paint_rect(
+            IRect::new(1, 1, 3, 2),
+            Tile::new(TileSet::Room, MapTile::NESW),
+        );
+
+        assert_eq!(undo.len(), 6);
+        assert_eq!(
+            map.tiles[&IVec2::new(1, 1)],
+            Tile::new(TileSet::Room, MapTile::NE)
+        );
+        assert_eq!(
+            map.tiles[&IVec2::new(2, 2)],
+            Tile::new(TileSet::Room, MapTile::ESW)
+        );
+        assert_eq!(map.validate(), Ok(()));
+
+        map.apply(&undo);
+        assert_eq!(map.tiles, before);
+    }
+
+    #[test]
+    fn painted_tiles_keep_connections_to_their_surroundings() {
+        let mut map = closed_map(5);
+        map.paint_line(IVec2::new(0, 2), IVec2::new(4, 2), TileSet::Corridor);
+
+        map.paint_rect(
+            IRect::new(2, 1, 2, 3),
+            Tile::new(TileSet::Room, MapTile::NS),
+        );
+
+        assert_eq!(map.tiles[&IVec2::new(2, 2)].map_tile, MapTile::NESW);
+        assert_eq!(map.tiles[&IVec2::new(2, 1)].map_tile, MapTile::N);
+        assert_eq!(map.validate(), Ok(()));
+    }
+
+    #[test]
+    fn lines_join_consecutive_tiles() {
+        let mut map = closed_map(6);
+
+        map.paint_line(IVec2::new(0, 0), IVec2::new(3, 2), TileSet::Corridor);
+
+        assert_eq!(
+            map.find_path(IVec2::new(0, 0), IVec2::new(3, 2))
+                .map(|path| path.len()),
+            Some(6)
+        );
+        assert_eq!(map.dead_ends(), vec![IVec2::new(0, 0), IVec2::new(3, 2)]);
+        assert_eq!(map.validate(), Ok(()));
+    }
+
+    #[test]
+    fn bucket_fill_repaints_the_connected_area() {
+        let mut map = closed_map(6);
+        map.paint_rect(
+            IRect::new(0, 0, 2, 2),
+            Tile::new(TileSet::Corridor, MapTile::NESW),
+        );
+
+        let undo = map.bucket_fill(IVec2::new(1, 1), Tile::new(TileSet::Room, MapTile::NESW));
+
+        assert_eq!(undo.len(), 9);
+        assert_eq!(map.tiles[&IVec2::new(2, 2)].tile_set, TileSet::Room);
+        assert_eq!(map.tiles[&IVec2::new(3, 3)].tile_set, TileSet::Corridor);
+        assert_eq!(map.validate(), Ok(()));
+    }
+
+    #[test]
+    fn painted_rects_take_the_biome_of_the_template() {
+        let mut map = closed_map(5);
+        map.paint_line(IVec2::new(0, 2), IVec2::new(4, 2), TileSet::Corridor);
+
+        let undo = map.paint_rect(
+            IRect::new(1, 1, 2, 3),
+            Tile::new(TileSet::Room, MapTile::NESW).with_biome(Biome::Water),
+        );
+
+        assert_eq!(undo.len(), 6);
+        assert_eq!(
+            map.tiles[&IVec2::new(1, 2)],
+            Tile::new(TileSet::Room, MapTile::NESW).with_biome(Biome::Water)
+        );
+        assert_eq!(map.tiles[&IVec2::new(0, 2)].biome, Biome::Dungeon);
+        assert_eq!(map.validate(), Ok(()));
+
+        let mut filled = closed_map(5);
+        filled.paint_line(IVec2::new(0, 2), IVec2::new(4, 2), TileSet::Corridor);
+        filled.fill_region(
+            IRect::new(1, 1, 2, 3),
+            Tile::new(TileSet::Room, MapTile::NESW).with_biome(Biome::Water),
+        );
+        assert_eq!(filled.tiles, map.tiles);
+    }
+
+    #[test]
+    fn flood_convert_follows_exits_and_keeps_them() {
+        let mut map = closed_map(5);
+        map.paint_line(IVec2::new(0, 0), IVec2::new(4, 0), TileSet::Corridor);
+        map.paint_line(IVec2::new(0, 2), IVec2::new(4, 2), TileSet::Corridor);
+        let before = map.tiles.clone();
+        let is_corridor = |tile: &Tile| tile.tile_set == TileSet::Corridor;
+
+        let undo = map.flood_convert(IVec2::new(2, 0), is_corridor, TileSet::Room);
+
+        assert_eq!(undo.len(), 5);
+        for x in 0..5 {
+            let position = IVec2::new(x, 0);
+            assert_eq!(map.tiles[&position].tile_set, TileSet::Room);
+            assert_eq!(map.tiles[&position].map_tile, before[&position].map_tile);
+        }
+        assert_eq!(map.tiles[&IVec2::new(2, 2)].tile_set, TileSet::Corridor);
+        assert!(
+            map.flood_convert(IVec2::new(2, 0), is_corridor, TileSet::Room)
+                .is_empty()
+        );
+
+        map.apply(&undo);
+        assert_eq!(map.tiles, before);
+    }
+}
@@ -0,0 +1,251 @@
+use crate::exploration::Exploration;
+use crate::map::Map;
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The outcome of a successful fast travel between two checkpoints.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FastTravel {
+    pub from_name: String,
+    pub from: IVec2,
+    pub to_name: String,
+    pub to: IVec2,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckpointError {
+    /// The position is not on the map.
+    OutOfBounds(IVec2),
+    /// No checkpoint is registered under this name.
+    UnknownCheckpoint(String),
+    /// No checkpoint is registered on this tile.
+    NoCheckpointAt(IVec2),
+    /// The checkpoint exists but its tile has not been explored yet.
+    NotVisited(String),
+    /// Origin and destination are the same checkpoint.
+    SameCheckpoint(String),
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::OutOfBounds(position) => {
+                write!(f, "position {position} is outside the map")
+            }
+            CheckpointError::UnknownCheckpoint(name) => write!(f, "unknown checkpoint '{name}'"),
+            CheckpointError::NoCheckpointAt(position) => {
+                write!(f, "no checkpoint at {position}")
+            }
+            CheckpointError::NotVisited(name) => {
+                write!(f, "checkpoint '{name}' has not been visited")
+            }
+            CheckpointError::SameCheckpoint(name) => {
+                write!(f, "already at checkpoint '{name}'")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {}
+
+/// Named checkpoint tiles (shrines, waypoints) forming a fast-travel graph.
+///
+/// Every checkpoint whose tile has been explored is connected to every other
+/// explored checkpoint; unexplored checkpoints have no edges at all.
+#[derive(Debug, Clone, Default, Resource)]
+pub struct Checkpoints {
+    checkpoints: BTreeMap<String, IVec2>,
+}
+
+impl Checkpoints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a checkpoint on a map tile, replacing any checkpoint that
+    /// already uses the same name.
+    pub fn register<G: TileGenerator>(
+        &mut self,
+        map: &Map<G>,
+        name: impl Into<String>,
+        position: IVec2,
+    ) -> Result<(), CheckpointError> {
+        if !map.in_bounds(position) {
+            return Err(CheckpointError::OutOfBounds(position));
+        }
+        self.checkpoints.insert(name.into(), position);
+        Ok(())
+    }
+
+    /// Removes a checkpoint, returning its position.
+    pub fn remove(&mut self, name: &str) -> Option<IVec2> {
+        self.checkpoints.remove(name)
+    }
+
+    pub fn position(&self, name: &str) -> Option<IVec2> {
+        self.checkpoints.get(name).copied()
+    }
+
+    /// Returns the name of the checkpoint on `position`, if any.
+    pub fn at(&self, position: IVec2) -> Option<&str> {
+        self.checkpoints
+            .iter()
+            .find(|(_, checkpoint)| **checkpoint == position)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Iterates all checkpoints in name order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, IVec2)> + '_ {
+        self.checkpoints
+            .iter()
+            .map(|(name, position)| (name.as_str(), *position))
+    }
+
+    pub fn len(&self) -> usize {
+        self.checkpoints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.checkpoints.is_empty()
+    }
+
+    /// Lists the checkpoints reachable by fast travel from `from`, in name
+    /// order. Empty when `from` is unknown or has not been visited.
+    pub fn destinations(&self, from: &str, exploration: &Exploration) -> Vec<&str> {
+        if self.visited_position(from, exploration).is_err() {
+            return Vec::new();
+        }
+        self.checkpoints
+            .iter()
+            .filter(|(name, position)| name.as_str() != from && exploration.is_explored(**position))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Validates a fast travel between two checkpoints. Both ends must exist
+    /// and have been visited, i.e. their tiles are explored in the map's fog
+    /// of war, [`Map::exploration`].
+    pub fn fast_travel(
+        &self,
+        from: &str,
+        to: &str,
+        exploration: &Exploration,
+    ) -> Result<FastTravel, CheckpointError> {
+        let from_position = self.visited_position(from, exploration)?;
+        let to_position = self.visited_position(to, exploration)?;
+        if from == to {
+            return Err(CheckpointError::SameCheckpoint(from.to_string()));
+        }
+
+        Ok(FastTravel {
+            from_name: from.to_string(),
+            from: from_position,
+            to_name: to.to_string(),
+            to: to_position,
+        })
+    }
+
+    fn visited_position(
+        &self,
+        name: &str,
+        exploration: &Exploration,
+    ) -> Result<IVec2, CheckpointError> {
+        let position = self
+            .position(name)
+            .ok_or_else(|| CheckpointError::UnknownCheckpoint(name.to_string()))?;
+        if !exploration.is_explored(position) {
+            return Err(CheckpointError::NotVisited(name.to_string()));
+        }
+        Ok(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::open_field::OpenFieldGenerator;
+
+    fn checkpoints() -> Checkpoints {
+        let map = Map::new(5, OpenFieldGenerator);
+        let mut checkpoints = Checkpoints::new();
+        checkpoints
+            .register(&map, "shrine", IVec2::new(0, 0))
+            .unwrap();
+        checkpoints
+            .register(&map, "tower", IVec2::new(4, 4))
+            .unwrap();
+        checkpoints
+            .register(&map, "well", IVec2::new(2, 2))
+            .unwrap();
+        checkpoints
+    }
+
+    /// The fog of war of a map on which `positions` have been seen.
+    fn explored(positions: &[IVec2]) -> Exploration {
+        let mut map = Map::new(5, OpenFieldGenerator);
+        map.mark_visible(positions.iter().copied());
+        map.exploration
+    }
+
+    #[test]
+    fn register_rejects_positions_off_the_map() {
+        let map = Map::new(2, OpenFieldGenerator);
+        let mut checkpoints = Checkpoints::new();
+
+        assert_eq!(
+            checkpoints.register(&map, "void", IVec2::new(2, 0)),
+            Err(CheckpointError::OutOfBounds(IVec2::new(2, 0)))
+        );
+        assert!(checkpoints.is_empty());
+    }
+
+    #[test]
+    fn fast_travel_between_visited_checkpoints() {
+        let checkpoints = checkpoints();
+        let explored = explored(&[IVec2::new(0, 0), IVec2::new(4, 4)]);
+
+        let travel = checkpoints
+            .fast_travel("shrine", "tower", &explored)
+            .unwrap();
+        assert_eq!(travel.from, IVec2::new(0, 0));
+        assert_eq!(travel.to, IVec2::new(4, 4));
+        assert_eq!(travel.to_name, "tower");
+    }
+
+    #[test]
+    fn fast_travel_requires_both_ends_visited() {
+        let checkpoints = checkpoints();
+        let explored = explored(&[IVec2::new(0, 0)]);
+
+        assert_eq!(
+            checkpoints.fast_travel("shrine", "well", &explored),
+            Err(CheckpointError::NotVisited("well".to_string()))
+        );
+        assert_eq!(
+            checkpoints.fast_travel("well", "shrine", &explored),
+            Err(CheckpointError::NotVisited("well".to_string()))
+        );
+        assert_eq!(
+            checkpoints.fast_travel("shrine", "castle", &explored),
+            Err(CheckpointError::UnknownCheckpoint("castle".to_string()))
+        );
+        assert_eq!(
+            checkpoints.fast_travel("shrine", "shrine", &explored),
+            Err(CheckpointError::SameCheckpoint("shrine".to_string()))
+        );
+    }
+
+    #[test]
+    fn destinations_only_lists_visited_checkpoints() {
+        let checkpoints = checkpoints();
+        let explored = explored(&[IVec2::new(0, 0), IVec2::new(2, 2)]);
+
+        assert_eq!(checkpoints.destinations("shrine", &explored), vec!["well"]);
+        assert!(checkpoints.destinations("tower", &explored).is_empty());
+        assert_eq!(checkpoints.at(IVec2::new(2, 2)), Some("well"));
+        assert_eq!(checkpoints.at(IVec2::new(1, 2)), None);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::generation_context::GenerationContext;
+use crate::map_tile::{Direction, Tile};
+use crate::tile_generator::TileGenerator;
+use crate::tile_grid::TileGrid;
+
+use bevy::prelude::*;
+use itertools::iproduct;
+use std::collections::HashSet;
+
+/// An unbounded map that generates fixed-size square chunks on demand.
+///
+/// All generated tiles live in one shared tile table, so when a chunk is
+/// generated the [`TileGenerator`] sees the tiles of any neighbouring chunk
+/// that already exists. Generators that honour neighbour exits (such as
+/// [`crate::TileGeneratorDefault`]) therefore stitch chunk seams without
+/// any extra work.
+#[derive(Resource)]
+pub struct ChunkedMap<G: TileGenerator> {
+    chunk_size: usize,
+    pub tiles: TileGrid,
+    generated_chunks: HashSet<IVec2>,
+    pub generator: G,
+}
+
+impl<G: TileGenerator> ChunkedMap<G> {
+    /// Creates an empty map. No tiles exist until a chunk is requested.
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn new(chunk_size: usize, generator: G) -> Self {
+        assert!(chunk_size > 0, "chunk size must be at least one tile");
+        Self {
+            chunk_size,
+            tiles: TileGrid::new(),
+            generated_chunks: HashSet::new(),
+            generator,
+        }
+    }
+
+    /// Returns the side length of a chunk, in tiles.
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Returns the chunk coordinate containing a tile position.
+    pub fn chunk_of(&self, position: IVec2) -> IVec2 {
+        position.div_euclid(IVec2::splat(self.chunk_size as i32))
+    }
+
+    /// Returns the tile position of the south-west corner of a chunk.
+    pub fn chunk_origin(&self, chunk: IVec2) -> IVec2 {
+        chunk * self.chunk_size as i32
+    }
+
+    /// Iterates the tile positions belonging to a chunk.
+    pub fn chunk_positions(&self, chunk: IVec2) -> impl Iterator<Item = IVec2> + use<G> {
+        let origin = self.chunk_origin(chunk);
+        let size = self.chunk_size as i32;
+        iproduct!(0..size, 0..size).map(move |(x, y)| origin + IVec2::new(x, y))
+    }
+
+    pub fn is_chunk_generated(&self, chunk: IVec2) -> bool {
+        self.generated_chunks.contains(&chunk)
+    }
+
+    /// Iterates the coordinates of every chunk generated so far.
+    pub fn generated_chunks(&self) -> impl Iterator<Item = IVec2> + '_ {
+        self.generated_chunks.iter().copied()
+    }
+
+    /// Generates `chunk` if it does not exist yet.
+    ///
+    /// Returns `true` when the chunk was generated by this call.
+    pub fn ensure_chunk(&mut self, chunk: IVec2) -> bool {
+        if !self.generated_chunks.insert(chunk) {
+            return false;
+        }
+        let positions: Vec<IVec2> = self.chunk_positions(chunk).collect();
+        for (generated, position) in positions.iter().copied().enumerate() {
+            let context = GenerationContext::new(&self.tiles, position)
+                .with_progress(generated, positions.len());
+            let tile = self.generator.generate(&context);
+            self.tiles.insert(position, tile);
+        }
+        true
+    }
+
+    /// Generates every chunk within `radius` chunks of the chunk containing
+    /// `position`, nearest chunks first.
+    ///
+    /// Returns the chunks that were generated by this call.
+    pub fn ensure_around(&mut self, position: IVec2, radius: u32) -> Vec<IVec2> {
+        let center = self.chunk_of(position);
+        let radius = radius as i32;
+        let mut chunks: Vec<IVec2> = iproduct!(-radius..=radius, -radius..=radius)
+            .map(|(x, y)| center + IVec2::new(x, y))
+            .collect();
+        chunks.sort_by_key(|chunk| ((*chunk - center).abs().element_sum(), chunk.y, chunk.x));
+        chunks
+            .into_iter()
+            .filter(|chunk| self.ensure_chunk(*chunk))
+            .collect()
+    }
+
+    /// Returns the tile at `position`, if its chunk has been generated.
+    pub fn tile(&self, position: IVec2) -> Option<&Tile> {
+        self.tiles.get(&position)
+    }
+
+    /// Same rules as [`crate::Map::can_move`], but without map bounds; both
+    /// tiles must already be generated.
+    pub fn can_move(&self, from: IVec2, to: IVec2) -> bool {
+        let Some(direction) = Direction::from_offset(to - from) else {
+            return false;
+        };
+        let (Some(from_tile), Some(to_tile)) = (self.tiles.get(&from), self.tiles.get(&to)) else {
+            return false;
+        };
+
+        from_tile.map_tile.contains(direction) && to_tile.map_tile.contains(direction.opposite())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile_generator::TileGeneratorDefault;
+
+    #[test]
+    fn chunk_of_handles_negative_positions() {
+        let map = ChunkedMap::new(8, TileGeneratorDefault::with_seed(1));
+
+        assert_eq!(map.chunk_of(IVec2::new(0, 0)), IVec2::new(0, 0));
+        assert_eq!(map.chunk_of(IVec2::new(7, 8)), IVec2::new(0, 1));
+        assert_eq!(map.chunk_of(IVec2::new(-1, -8)), IVec2::new(-1, -1));
+        assert_eq!(map.chunk_of(IVec2::new(-9, 0)), IVec2::new(-2, 0));
+    }
+
+    #[test]
+    fn chunks_are_generated_lazily_and_once() {
+        let mut map = ChunkedMap::new(4, TileGeneratorDefault::with_seed(3));
+        assert!(map.tiles.is_empty());
+
+        assert!(map.ensure_chunk(IVec2::new(2, -1)));
+        assert!(!map.ensure_chunk(IVec2::new(2, -1)));
+        assert_eq!(map.tiles.len(), 16);
+        assert!(map.tile(IVec2::new(8, -4)).is_some());
+        assert!(map.tile(IVec2::new(0, 0)).is_none());
+    }
+
+    #[test]
+    fn ensure_around_generates_the_surrounding_square() {
+        let mut map = ChunkedMap::new(4, TileGeneratorDefault::with_seed(5));
+
+        let generated = map.ensure_around(IVec2::new(5, 5), 1);
+        assert_eq!(generated.len(), 9);
+        assert_eq!(generated[0], IVec2::new(1, 1));
+        assert!(map.ensure_around(IVec2::new(5, 5), 1).is_empty());
+    }
+
+    #[test]
+    fn exits_match_across_chunk_seams() {
+        let mut map = ChunkedMap::new(
+            4,
+            TileGeneratorDefault::with_seed_and_probabilities(9, 0.6, 0.3),
+        );
+        map.ensure_chunk(IVec2::new(0, 0));
+        map.ensure_chunk(IVec2::new(1, 0));
+        map.ensure_chunk(IVec2::new(0, -1));
+
+        for (&position, tile) in &map.tiles {
+            for direction in tile.directions() {
+                let neighbor = position + direction.offset();
+                if let Some(neighbor_tile) = map.tile(neighbor) {
+                    assert!(
+                        neighbor_tile.directions().contains(&direction.opposite()),
+                        "exit {direction} from {position} has no matching exit"
+                    );
+                }
+            }
+        }
+    }
+}
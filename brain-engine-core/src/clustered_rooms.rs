@@ -0,0 +1,273 @@
+use crate::map_tile::{Direction, MapTile, Tile, TileSet};
+use crate::tile_generator::TileGenerator;
+use crate::tile_grid::TileGrid;
+
+use bevy::prelude::*;
+
+/// Generates contiguous rectangular rooms joined by corridors.
+///
+/// The plane is split into square cells one room wide plus a two tile
+/// margin. Every cell holds one room whose width and height are picked
+/// between the minimum and maximum room size, and each room is joined to the
+/// rooms of the cells east and north of it by an L-shaped corridor. Tiles
+/// outside rooms and corridors are walls ([`MapTile::ZERO`] corridors).
+///
+/// Every tile is a pure function of the seed and its position, so the
+/// generator works for [`crate::Map`], [`crate::ChunkedMap`] and parallel
+/// generation alike, in any generation order.
+#[derive(Resource, Debug, Clone, PartialEq, Eq)]
+pub struct ClusteredRoomGenerator {
+    seed: u64,
+    min_room_size: i32,
+    max_room_size: i32,
+    bounds: Option<IVec2>,
+}
+
+/// A room, both corners inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Room {
+    min: IVec2,
+    max: IVec2,
+}
+
+impl Room {
+    fn contains(&self, position: IVec2) -> bool {
+        position.cmpge(self.min).all() && position.cmple(self.max).all()
+    }
+
+    fn center(&self) -> IVec2 {
+        (self.min + self.max) / 2
+    }
+}
+
+/// A straight run of corridor tiles, both ends inclusive.
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    from: IVec2,
+    to: IVec2,
+}
+
+impl Segment {
+    fn contains(&self, position: IVec2) -> bool {
+        position.cmpge(self.from.min(self.to)).all() && position.cmple(self.from.max(self.to)).all()
+    }
+}
+
+impl ClusteredRoomGenerator {
+    /// Rooms between 3 and 6 tiles wide and high, on an unbounded plane.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            min_room_size: 3,
+            max_room_size: 6,
+            bounds: None,
+        }
+    }
+
+    /// Sets the range of room widths and heights, in tiles.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min` is zero or greater than `max`.
+    pub fn with_room_size(mut self, min: u32, max: u32) -> Self {
+        assert!(
+            0 < min && min <= max,
+            "invalid room size range {min}..={max}"
+        );
+        self.min_room_size = min as i32;
+        self.max_room_size = max as i32;
+        self
+    }
+
+    /// Only places rooms in cells that fit entirely inside a map of
+    /// `dimensions`, so no corridor leads off the map and every room is
+    /// reachable from every other. The leftover strip at the far edges is
+    /// wall.
+    pub fn with_bounds(mut self, dimensions: UVec2) -> Self {
+        self.bounds = Some(dimensions.as_ivec2());
+        self
+    }
+
+    /// Side length of a cell: one room plus a tile of margin on each side,
+    /// so rooms of neighboring cells never touch.
+    fn cell_size(&self) -> i32 {
+        self.max_room_size + 2
+    }
+
+    fn cell_of(&self, position: IVec2) -> IVec2 {
+        position.div_euclid(IVec2::splat(self.cell_size()))
+    }
+
+    fn room(&self, cell: IVec2) -> Option<Room> {
+        if let Some(bounds) = self.bounds {
+            let end = (cell + 1) * self.cell_size();
+            if cell.cmplt(IVec2::ZERO).any() || end.cmpgt(bounds).any() {
+                return None;
+            }
+        }
+
+        let mut random = self.cell_random(cell);
+        let mut pick = |range: i32| (random.next() % range as u64) as i32;
+        let sizes = self.max_room_size - self.min_room_size + 1;
+        let size = IVec2::new(
+            self.min_room_size + pick(sizes),
+            self.min_room_size + pick(sizes),
+        );
+        let offset = IVec2::new(
+            1 + pick(self.max_room_size - size.x + 1),
+            1 + pick(self.max_room_size - size.y + 1),
+        );
+        let min = cell * self.cell_size() + offset;
+        Some(Room {
+            min,
+            max: min + size - 1,
+        })
+    }
+
+    /// The corridor from the room of `cell` to the room of the cell in
+    /// `direction` (east or north), as two segments meeting at a corner.
+    fn corridor(&self, cell: IVec2, direction: Direction) -> Option<[Segment; 2]> {
+        let from = self.room(cell)?.center();
+        let to = self.room(cell + direction.offset())?.center();
+        let corner = match direction {
+            Direction::East => IVec2::new(to.x, from.y),
+            _ => IVec2::new(from.x, to.y),
+        };
+        Some([Segment { from, to: corner }, Segment { from: corner, to }])
+    }
+
+    /// Corridor segments that can pass through `cell`: those leaving it and
+    /// those arriving from the west and south.
+    fn segments_through(&self, cell: IVec2) -> Vec<Segment> {
+        [
+            (cell, Direction::East),
+            (cell, Direction::North),
+            (cell - IVec2::X, Direction::East),
+            (cell - IVec2::Y, Direction::North),
+        ]
+        .into_iter()
+        .filter_map(|(start, direction)| self.corridor(start, direction))
+        .flatten()
+        .collect()
+    }
+
+    fn cell_random(&self, cell: IVec2) -> SplitMix64 {
+        SplitMix64(
+            self.seed
+                ^ (cell.x as u32 as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+                ^ (cell.y as u32 as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f),
+        )
+    }
+}
+
+impl TileGenerator for ClusteredRoomGenerator {
+    fn tile_at(&self, _tiles: &TileGrid, location: IVec2) -> Tile {
+        let room = self
+            .room(self.cell_of(location))
+            .filter(|room| room.contains(location));
+        let segments = self.segments_through(self.cell_of(location));
+
+        let mut map_tile = MapTile::ZERO;
+        for direction in Direction::all() {
+            let neighbor = location + direction.offset();
+            let same_room = room.is_some_and(|room| room.contains(neighbor));
+            let along_corridor = segments
+                .iter()
+                .any(|segment| segment.contains(location) && segment.contains(neighbor));
+            if same_room || along_corridor {
+                map_tile = map_tile.with(direction);
+            }
+        }
+
+        let tile_set = if room.is_some() {
+            TileSet::Room
+        } else {
+            TileSet::Corridor
+        };
+        Tile::new(tile_set, map_tile)
+    }
+}
+
+/// Small, fast generator for per-cell random values.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut value = self.0;
+        value = (value ^ (value >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        value = (value ^ (value >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        value ^ (value >> 31)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunked_map::ChunkedMap;
+    use crate::map::Map;
+    use itertools::iproduct;
+
+    fn bounded_map(seed: u64) -> Map<ClusteredRoomGenerator> {
+        let generator = ClusteredRoomGenerator::new(seed)
+            .with_room_size(3, 5)
+            .with_bounds(UVec2::splat(30));
+        Map::new(30, generator)
+    }
+
+    #[test]
+    fn rooms_are_contiguous_rectangles_of_the_configured_size() {
+        let map = bounded_map(1);
+
+        let rooms = map.detect_rooms();
+
+        // 30 tiles hold four 7-tile cells per side.
+        assert_eq!(rooms.len(), 16);
+        for room in rooms {
+            assert!(room.is_rectangular());
+            let size = room.bounds.size() + 1;
+            assert!((3..=5).contains(&size.x) && (3..=5).contains(&size.y));
+            assert!(!room.exits.is_empty());
+        }
+    }
+
+    #[test]
+    fn every_walkable_tile_is_connected() {
+        for seed in 0..5 {
+            let map = bounded_map(seed);
+            let walkable: Vec<IVec2> = map
+                .tiles
+                .iter()
+                .filter(|(_, tile)| tile.map_tile != MapTile::ZERO)
+                .map(|(position, _)| *position)
+                .collect();
+
+            let reached = map.reachable_from(walkable[0]);
+
+            assert_eq!(reached.len(), walkable.len(), "seed {seed}");
+        }
+    }
+
+    #[test]
+    fn exits_match_and_ignore_generation_order() {
+        let generator = ClusteredRoomGenerator::new(5);
+        let map = Map::new(40, generator.clone());
+        let mut chunked = ChunkedMap::new(8, generator);
+        chunked.ensure_around(IVec2::new(20, 20), 3);
+
+        for (x, y) in iproduct!(0..40, 0..40) {
+            let position = IVec2::new(x, y);
+            assert_eq!(chunked.tile(position), Some(&map.tiles[&position]));
+            for direction in [Direction::North, Direction::East] {
+                let Some(neighbor) = map.tiles.get(&(position + direction.offset())) else {
+                    continue;
+                };
+                assert_eq!(
+                    map.tiles[&position].map_tile.contains(direction),
+                    neighbor.map_tile.contains(direction.opposite()),
+                    "mismatched exits at {position} towards {direction}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,201 @@
+//! Maps made of zones, each filled by its own generator.
+//!
+//! ```
+//! use bevy::prelude::*;
+//! use brain_engine_core::{
+//!     CompositeGenerator, Map, TileGeneratorDefault, TileGeneratorDrunkard, TileGeneratorMaze,
+//! };
+//!
+//! // Caves in the west, a maze in the east and rooms and corridors between.
+//! let generator = CompositeGenerator::new(TileGeneratorDefault::with_seed(1))
+//!     .with_zone(IRect::new(0, 0, 7, 15), TileGeneratorDrunkard::new(2))
+//!     .with_zone(IRect::new(16, 0, 23, 15), TileGeneratorMaze::new(3));
+//! let map = Map::new(24, generator);
+//! assert_eq!(map.validate(), Ok(()));
+//! ```
+
+use crate::generation_context::GenerationContext;
+use crate::map_tile::{Direction, Tile};
+use crate::tile_generator::{BoxedTileGenerator, TileGenerator};
+use crate::tile_grid::TileGrid;
+
+use bevy::prelude::*;
+
+/// Splits the map into rectangular zones and generates each with its own
+/// generator, such as caves, a maze and rooms on one map.
+///
+/// Each zone generator sees its zone, clipped to the map, as the bounds of
+/// its map, so bounded generators such as [`crate::TileGeneratorMaze`] fill
+/// just the zone. Tiles outside every zone come from the fallback generator,
+/// which sees the whole map. Where zones overlap, the first zone added wins.
+///
+/// Exits across the seams between zones always line up: every seam between
+/// two zones is closed except for one door in the middle of each stretch
+/// where they touch. Maps without bounds, such as [`crate::ChunkedMap`],
+/// get no doors; there, exits across a seam follow whichever side was
+/// generated first.
+#[derive(Resource)]
+pub struct CompositeGenerator {
+    zones: Vec<(IRect, BoxedTileGenerator)>,
+    fallback: BoxedTileGenerator,
+}
+
+impl CompositeGenerator {
+    /// A map filled by `fallback` until zones are added.
+    pub fn new(fallback: impl TileGenerator + Send + Sync + 'static) -> Self {
+        Self {
+            zones: Vec::new(),
+            fallback: Box::new(fallback),
+        }
+    }
+
+    /// Fills `zone` (both corners inclusive) with `generator`.
+    pub fn with_zone(
+        mut self,
+        zone: IRect,
+        generator: impl TileGenerator + Send + Sync + 'static,
+    ) -> Self {
+        self.zones.push((zone, Box::new(generator)));
+        self
+    }
+
+    /// The index of the zone `position` lies in, `None` for the fallback.
+    pub fn zone_of(&self, position: IVec2) -> Option<usize> {
+        self.zones
+            .iter()
+            .position(|(zone, _)| zone.contains(position))
+    }
+
+    /// Whether the edge from `position` towards `direction` is the door of
+    /// its seam: the middle of the run of edges along that line that join
+    /// the same two zones. Both sides of the edge agree.
+    fn is_door(&self, position: IVec2, direction: Direction, bounds: IRect) -> bool {
+        let offset = direction.offset();
+        let along = IVec2::new(offset.y, offset.x).abs();
+        let (here, there) = (self.zone_of(position), self.zone_of(position + offset));
+        let on_seam = |position: IVec2| {
+            bounds.contains(position)
+                && bounds.contains(position + offset)
+                && self.zone_of(position) == here
+                && self.zone_of(position + offset) == there
+        };
+        let mut start = position;
+        while on_seam(start - along) {
+            start -= along;
+        }
+        let mut end = position;
+        while on_seam(end + along) {
+            end += along;
+        }
+        position == start + along * ((end - start).dot(along) / 2)
+    }
+
+    /// Matches the exits of `tile` across zone seams to the neighbors
+    /// already placed, and otherwise to the doors when there are bounds.
+    fn reconcile(
+        &self,
+        tiles: &TileGrid,
+        location: IVec2,
+        mut tile: Tile,
+        bounds: Option<IRect>,
+    ) -> Tile {
+        let zone = self.zone_of(location);
+        for direction in Direction::all() {
+            let neighbor = location + direction.offset();
+            if self.zone_of(neighbor) == zone
+                || bounds.is_some_and(|bounds| !bounds.contains(neighbor))
+            {
+                continue;
+            }
+            let open = match (tiles.get(&neighbor), bounds) {
+                (Some(placed), _) => placed.map_tile.contains(direction.opposite()),
+                (None, Some(bounds)) => self.is_door(location, direction, bounds),
+                (None, None) => continue,
+            };
+            tile.map_tile = if open {
+                tile.map_tile.with(direction)
+            } else {
+                tile.map_tile.without(direction)
+            };
+        }
+        tile
+    }
+}
+
+impl TileGenerator for CompositeGenerator {
+    fn tile_at(&self, tiles: &TileGrid, location: IVec2) -> Tile {
+        self.generate(&GenerationContext::new(tiles, location))
+    }
+
+    fn tile_at_within(&self, tiles: &TileGrid, location: IVec2, bounds: IRect) -> Tile {
+        self.generate(&GenerationContext::new(tiles, location).within(bounds))
+    }
+
+    fn generate(&self, context: &GenerationContext) -> Tile {
+        let location = context.location();
+        let tile = match (self.zone_of(location), context.bounds()) {
+            (Some(index), Some(bounds)) => {
+                let (zone, generator) = &self.zones[index];
+                generator.generate(&context.within(zone.intersect(bounds)))
+            }
+            (Some(index), None) => self.zones[index].1.generate(context),
+            (None, _) => self.fallback.generate(context),
+        };
+        self.reconcile(context.tiles(), location, tile, context.bounds())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Map;
+    use crate::map_tile::{MapTile, TileSet};
+    use crate::maze::TileGeneratorMaze;
+    use crate::open_field::OpenFieldGenerator;
+    use crate::tile_generator::TileGeneratorDefault;
+    use itertools::iproduct;
+
+    #[test]
+    fn zones_use_their_own_generator() {
+        let generator = CompositeGenerator::new(OpenFieldGenerator)
+            .with_zone(IRect::new(4, 0, 11, 7), TileGeneratorMaze::new(5));
+        let map = Map::new(12, generator);
+
+        assert_eq!(map.validate(), Ok(()));
+        // The maze fills its zone as if it were a map of its own, so every
+        // maze tile can reach every other.
+        let maze = map.dijkstra_map(&[IVec2::new(4, 0)]);
+        for (x, y) in iproduct!(4..12, 0..8) {
+            assert!(maze.contains_key(&IVec2::new(x, y)));
+        }
+        assert_eq!(map.tiles[&IVec2::new(0, 10)].tile_set, TileSet::Field);
+    }
+
+    #[test]
+    fn each_seam_has_one_door() {
+        let generator = CompositeGenerator::new(OpenFieldGenerator)
+            .with_zone(IRect::new(0, 0, 4, 9), OpenFieldGenerator);
+        let map = Map::new(10, generator);
+
+        let doors: Vec<IVec2> = (0..10)
+            .map(|y| IVec2::new(4, y))
+            .filter(|position| map.can_move(*position, *position + IVec2::X))
+            .collect();
+        assert_eq!(doors, vec![IVec2::new(4, 4)]);
+        assert_eq!(map.validate(), Ok(()));
+        assert_eq!(map.dijkstra_map(&[IVec2::ZERO]).len(), 100);
+    }
+
+    #[test]
+    fn seams_line_up_for_any_generators() {
+        for seed in 0..8 {
+            let generator = CompositeGenerator::new(TileGeneratorDefault::with_seed(seed))
+                .with_zone(IRect::new(2, 2, 9, 6), TileGeneratorMaze::new(seed))
+                .with_zone(IRect::new(6, 5, 13, 13), OpenFieldGenerator);
+            let map = Map::new(14, generator);
+
+            assert_eq!(map.validate(), Ok(()), "seed {seed}");
+            assert_ne!(map.tiles[&IVec2::new(2, 2)].map_tile, MapTile::ZERO);
+        }
+    }
+}
@@ -0,0 +1,332 @@
+//! Map generation described by data, so designers can tune it without
+//! touching Rust.
+//!
+//! A [`GeneratorConfig`] deserializes from any serde format; with the `ron`
+//! and `toml` features it also parses RON and TOML directly:
+//!
+//! ```toml
+//! size = 32
+//! passes = ["ensure_connected", { prune_dead_ends = { iterations = 2 } }]
+//!
+//! [generator.default]
+//! seed = 7
+//! room_probability = 0.5
+//! ```
+
+use crate::clustered_rooms::ClusteredRoomGenerator;
+use crate::map::{DynMap, Map};
+use crate::open_field::OpenFieldGenerator;
+use crate::pipeline::{
+    AddLoops, EnsureConnected, GeneratorPipeline, PruneDeadEnds, StraightenCorridors,
+};
+use crate::tile_generator::{BoxedTileGenerator, TileGenerator, TileGeneratorDefault};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Everything [`Map::from_config`] needs to generate a map.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GeneratorConfig {
+    /// The number of tiles along each side of the map.
+    pub size: usize,
+    pub generator: GeneratorType,
+    /// Post-processing passes, run in order after generation.
+    #[serde(default)]
+    pub passes: Vec<PipelinePass>,
+}
+
+/// The tile generator and its parameters.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum GeneratorType {
+    /// [`TileGeneratorDefault`], unseeded when `seed` is left out.
+    Default {
+        #[serde(default)]
+        seed: Option<u64>,
+        #[serde(default = "default_probability")]
+        tile_exit_probability: f64,
+        #[serde(default = "default_probability")]
+        room_probability: f64,
+    },
+    /// [`ClusteredRoomGenerator`], keeping every room on the map.
+    ClusteredRooms {
+        seed: u64,
+        #[serde(default = "default_min_room_size")]
+        min_room_size: u32,
+        #[serde(default = "default_max_room_size")]
+        max_room_size: u32,
+    },
+    /// [`OpenFieldGenerator`].
+    OpenField,
+}
+
+/// A [`crate::MapTransform`] run after generation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum PipelinePass {
+    /// [`EnsureConnected`].
+    EnsureConnected,
+    /// [`PruneDeadEnds`], until none remain when `iterations` is left out.
+    PruneDeadEnds {
+        #[serde(default)]
+        iterations: Option<usize>,
+    },
+    /// [`StraightenCorridors`].
+    StraightenCorridors,
+    /// [`AddLoops`].
+    AddLoops { count: usize },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeneratorConfigError {
+    ZeroSize,
+    /// A probability outside `0.0..=1.0`, with the name of its field.
+    InvalidProbability {
+        field: &'static str,
+        value: f64,
+    },
+    /// A room size range that is empty or starts at zero.
+    InvalidRoomSize {
+        min: u32,
+        max: u32,
+    },
+}
+
+impl fmt::Display for GeneratorConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeneratorConfigError::ZeroSize => write!(f, "map size must be at least 1"),
+            GeneratorConfigError::InvalidProbability { field, value } => {
+                write!(f, "{field} must be between 0 and 1, got {value}")
+            }
+            GeneratorConfigError::InvalidRoomSize { min, max } => {
+                write!(f, "invalid room size range {min}..={max}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for GeneratorConfigError {}
+
+fn default_probability() -> f64 {
+    0.35
+}
+
+fn default_min_room_size() -> u32 {
+    3
+}
+
+fn default_max_room_size() -> u32 {
+    6
+}
+
+impl GeneratorConfig {
+    #[cfg(feature = "ron")]
+    pub fn from_ron(text: &str) -> Result<Self, ron::error::SpannedError> {
+        ron::from_str(text)
+    }
+
+    #[cfg(feature = "toml")]
+    pub fn from_toml(text: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(text)
+    }
+
+    /// Checks the values a parser can't, such as probabilities being in
+    /// range.
+    pub fn validate(&self) -> Result<(), GeneratorConfigError> {
+        if self.size == 0 {
+            return Err(GeneratorConfigError::ZeroSize);
+        }
+        match self.generator {
+            GeneratorType::Default {
+                tile_exit_probability,
+                room_probability,
+                ..
+            } => {
+                for (field, value) in [
+                    ("tile_exit_probability", tile_exit_probability),
+                    ("room_probability", room_probability),
+                ] {
+                    if !(0.0..=1.0).contains(&value) {
+                        return Err(GeneratorConfigError::InvalidProbability { field, value });
+                    }
+                }
+            }
+            GeneratorType::ClusteredRooms {
+                min_room_size,
+                max_room_size,
+                ..
+            } => {
+                if min_room_size == 0 || min_room_size > max_room_size {
+                    return Err(GeneratorConfigError::InvalidRoomSize {
+                        min: min_room_size,
+                        max: max_room_size,
+                    });
+                }
+            }
+            GeneratorType::OpenField => {}
+        }
+        Ok(())
+    }
+
+    /// Builds the configured generator for a map of [`GeneratorConfig::size`].
+    pub fn build_generator(&self) -> BoxedTileGenerator {
+        match self.generator {
+            GeneratorType::Default {
+                seed,
+                tile_exit_probability,
+                room_probability,
+            } => {
+                let mut builder = TileGeneratorDefault::builder()
+                    .tile_exit_probability(tile_exit_probability)
+                    .room_probability(room_probability);
+                if let Some(seed) = seed {
+                    builder = builder.seed(seed);
+                }
+                Box::new(builder.build())
+            }
+            GeneratorType::ClusteredRooms {
+                seed,
+                min_room_size,
+                max_room_size,
+            } => Box::new(
+                ClusteredRoomGenerator::new(seed)
+                    .with_room_size(min_room_size, max_room_size)
+                    .with_bounds(UVec2::splat(self.size as u32)),
+            ),
+            GeneratorType::OpenField => Box::new(OpenFieldGenerator),
+        }
+    }
+
+    /// Chains the configured passes, in order.
+    pub fn build_pipeline<G: TileGenerator>(&self) -> GeneratorPipeline<G> {
+        self.passes
+            .iter()
+            .fold(GeneratorPipeline::new(), |pipeline, pass| match *pass {
+                PipelinePass::EnsureConnected => pipeline.then(EnsureConnected),
+                PipelinePass::PruneDeadEnds { iterations } => {
+                    pipeline.then(iterations.map_or(PruneDeadEnds::all(), |iterations| {
+                        PruneDeadEnds { iterations }
+                    }))
+                }
+                PipelinePass::StraightenCorridors => pipeline.then(StraightenCorridors),
+                PipelinePass::AddLoops { count } => pipeline.then(AddLoops { count }),
+            })
+    }
+}
+
+impl DynMap {
+    /// Validates `config`, generates a map with its generator and runs its
+    /// passes.
+    pub fn from_config(config: &GeneratorConfig) -> Result<Self, GeneratorConfigError> {
+        config.validate()?;
+        let mut map = Map::new(config.size, config.build_generator());
+        config.build_pipeline().apply(&mut map);
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::MapTransform;
+
+    fn clustered_config() -> GeneratorConfig {
+        GeneratorConfig {
+            size: 20,
+            generator: GeneratorType::ClusteredRooms {
+                seed: 4,
+                min_room_size: 3,
+                max_room_size: 5,
+            },
+            passes: vec![PipelinePass::PruneDeadEnds { iterations: None }],
+        }
+    }
+
+    #[test]
+    fn maps_follow_their_config() {
+        let config = clustered_config();
+
+        let map = Map::from_config(&config).unwrap();
+
+        let mut expected = Map::new(
+            20,
+            ClusteredRoomGenerator::new(4)
+                .with_room_size(3, 5)
+                .with_bounds(UVec2::splat(20)),
+        );
+        PruneDeadEnds::all().apply(&mut expected);
+        assert_eq!(map.tiles, expected.tiles);
+    }
+
+    #[test]
+    fn seeded_configs_repeat_their_maps() {
+        let json = r#"{"size": 12, "generator": {"default": {"seed": 9}}, "passes": ["ensure_connected"]}"#;
+        let config: GeneratorConfig = serde_json::from_str(json).unwrap();
+
+        assert_eq!(
+            config.generator,
+            GeneratorType::Default {
+                seed: Some(9),
+                tile_exit_probability: 0.35,
+                room_probability: 0.35
+            }
+        );
+        let map = Map::from_config(&config).unwrap();
+        assert_eq!(map.tiles, Map::from_config(&config).unwrap().tiles);
+        assert_eq!(map.x, 12);
+    }
+
+    #[test]
+    fn invalid_configs_are_rejected() {
+        let mut config = clustered_config();
+        config.generator = GeneratorType::ClusteredRooms {
+            seed: 1,
+            min_room_size: 4,
+            max_room_size: 2,
+        };
+        assert_eq!(
+            Map::from_config(&config).err(),
+            Some(GeneratorConfigError::InvalidRoomSize { min: 4, max: 2 })
+        );
+
+        config.generator = GeneratorType::Default {
+            seed: None,
+            tile_exit_probability: 1.5,
+            room_probability: 0.0,
+        };
+        assert_eq!(
+            config.validate(),
+            Err(GeneratorConfigError::InvalidProbability {
+                field: "tile_exit_probability",
+                value: 1.5
+            })
+        );
+    }
+
+    #[cfg(feature = "ron")]
+    #[test]
+    fn configs_parse_from_ron() {
+        let text = "(size: 20, generator: clustered_rooms(seed: 4, max_room_size: 5), \
+                    passes: [prune_dead_ends()])";
+
+        assert_eq!(GeneratorConfig::from_ron(text), Ok(clustered_config()));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn configs_parse_from_toml() {
+        let text = "size = 20\n\
+                    passes = [{ prune_dead_ends = {} }]\n\
+                    [generator.clustered_rooms]\n\
+                    seed = 4\n\
+                    max_room_size = 5\n";
+
+        assert_eq!(
+            GeneratorConfig::from_toml(text).unwrap(),
+            clustered_config()
+        );
+    }
+}
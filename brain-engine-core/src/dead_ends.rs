@@ -0,0 +1,135 @@
+use crate::map::Map;
+use crate::map_tile::{Direction, MapTile, TileSet};
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use itertools::iproduct;
+
+impl<G: TileGenerator> Map<G> {
+    /// Returns the corridor tiles that connect to exactly one neighbor, in
+    /// x-then-y order. Exits that lead off the map or into a wall don't count
+    /// as connections.
+    pub fn dead_ends(&self) -> Vec<IVec2> {
+        iproduct!(0..self.x as i32, 0..self.y as i32)
+            .map(|(x, y)| IVec2::new(x, y))
+            .filter(|position| {
+                self.tiles[position].tile_set == TileSet::Corridor
+                    && self.neighbors(*position).count() == 1
+            })
+            .collect()
+    }
+
+    /// Seals corridor dead ends: each is turned into a [`MapTile::ZERO`] tile
+    /// and the neighbor it connected to loses its exit towards it.
+    ///
+    /// Sealing a dead end can leave its neighbor as a new dead end, so this
+    /// repeats for up to `iterations` passes, stopping early once no dead ends
+    /// remain. Returns the number of tiles sealed.
+    pub fn prune_dead_ends(&mut self, iterations: usize) -> usize {
+        let before = self.tiles_before(self.positions());
+        let mut sealed = 0;
+        for _ in 0..iterations {
+            let dead_ends = self.dead_ends();
+            if dead_ends.is_empty() {
+                break;
+            }
+            for position in dead_ends {
+                // An earlier dead end in this pass may have been its neighbor.
+                // Looked up through adjacent() rather than the offset, which
+                // spans the whole map across a toroidal seam.
+                let connection = Direction::all()
+                    .into_iter()
+                    .map(|direction| (direction, self.adjacent(position, direction)))
+                    .find(|(_, neighbor)| self.can_move(position, *neighbor));
+                if let Some((direction, neighbor)) = connection {
+                    self.remove_exit(neighbor, direction.opposite());
+                }
+                self.tiles.get_mut(&position).unwrap().map_tile = MapTile::ZERO;
+                sealed += 1;
+            }
+        }
+        self.publish_changes(before);
+        sealed
+    }
+
+    fn remove_exit(&mut self, position: IVec2, direction: Direction) {
+        if let Some(tile) = self.tiles.get_mut(&position) {
+            tile.map_tile = tile.map_tile.without(direction);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::open_field::OpenFieldGenerator;
+    use crate::testing::GridBuilder;
+    use crate::tile_generator::TileGeneratorDefault;
+
+    /// A room at (0, 0) with a corridor running east to (3, 0).
+    fn stub_corridor() -> Map<OpenFieldGenerator> {
+        GridBuilder::new("    \n    \n╻   \n┗──╴").build(OpenFieldGenerator)
+    }
+
+    #[test]
+    fn each_iteration_seals_one_layer_of_dead_ends() {
+        let mut map = stub_corridor();
+        assert_eq!(map.dead_ends(), vec![IVec2::new(3, 0)]);
+
+        assert_eq!(map.prune_dead_ends(1), 1);
+        assert_eq!(map.tiles[&IVec2::new(3, 0)].map_tile, MapTile::ZERO);
+        assert_eq!(map.tiles[&IVec2::new(2, 0)].map_tile, MapTile::W);
+
+        assert_eq!(map.prune_dead_ends(10), 2);
+        assert_eq!(map.tiles[&IVec2::new(0, 0)].map_tile, MapTile::N);
+        assert!(map.dead_ends().is_empty());
+    }
+
+    #[test]
+    fn rooms_are_never_pruned() {
+        let mut map = stub_corridor();
+
+        map.prune_dead_ends(10);
+
+        assert_eq!(map.tiles[&IVec2::new(0, 1)].map_tile, MapTile::S);
+        assert_eq!(map.tiles[&IVec2::new(0, 1)].tile_set, TileSet::Room);
+    }
+
+    #[test]
+    fn dead_ends_are_sealed_across_toroidal_seams() {
+        let mut map = GridBuilder::new("    \n    \n    \n─╴ ╶")
+            .toroidal()
+            .build(OpenFieldGenerator);
+        assert_eq!(map.dead_ends(), vec![IVec2::new(1, 0), IVec2::new(3, 0)]);
+
+        assert_eq!(map.prune_dead_ends(10), 2);
+        assert!(map.dead_ends().is_empty());
+        assert_eq!(map.validate(), Ok(()));
+
+        let mut map = Map::new_toroidal(16, TileGeneratorDefault::with_seed(3));
+        map.prune_dead_ends(usize::MAX);
+        assert!(map.dead_ends().is_empty());
+        assert_eq!(map.validate(), Ok(()));
+    }
+
+    #[test]
+    fn pruning_a_generated_map_keeps_exits_consistent() {
+        let mut map = Map::new(
+            20,
+            TileGeneratorDefault::with_seed_and_probabilities(13, 0.5, 0.3),
+        );
+        assert!(!map.dead_ends().is_empty());
+
+        map.prune_dead_ends(usize::MAX);
+
+        assert!(map.dead_ends().is_empty());
+        for (x, y) in iproduct!(0..19, 0..20) {
+            let position = IVec2::new(x, y);
+            let east = map.tiles[&position].map_tile.contains(Direction::East);
+            let west = map.tiles[&(position + IVec2::X)]
+                .map_tile
+                .contains(Direction::West);
+            assert_eq!(east, west, "mismatched exits at {position}");
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use crate::map::Map;
+use crate::tile_generator::TileGenerator;
+use crate::tile_grid::TileGrid;
+
+use bevy::prelude::*;
+use std::time::{Duration, Instant};
+
+/// Connectivity a map needs to pass the quality gate, see
+/// [`crate::MapStats::connectivity`].
+pub const MIN_CONNECTIVITY: f64 = 0.75;
+/// Most maps generated while looking for one that passes the quality gate.
+pub const MAX_ATTEMPTS: usize = 8;
+
+/// A step of [`Map::generate_with_deadline`] after the first generation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GenerationPass {
+    /// Rerolling until the map reaches [`MIN_CONNECTIVITY`].
+    QualityGate,
+    /// Sealing every corridor dead end with [`Map::prune_dead_ends`].
+    PruneDeadEnds,
+}
+
+/// What [`Map::generate_with_deadline`] managed within its budget.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenerationReport {
+    pub elapsed: Duration,
+    /// Maps generated, at least 1.
+    pub attempts: usize,
+    /// Connectivity of the finished map.
+    pub connectivity: f64,
+    pub completed: Vec<GenerationPass>,
+    /// Passes that ran to the end within the budget without reaching their
+    /// goal: a quality gate that used all [`MAX_ATTEMPTS`] and keeps the
+    /// most connected map seen.
+    pub failed: Vec<GenerationPass>,
+    /// Passes cut short or never started because time ran out. A skipped
+    /// quality gate keeps the most connected map seen; skipped pruning may
+    /// have sealed some layers of dead ends.
+    pub skipped: Vec<GenerationPass>,
+}
+
+impl<G: TileGenerator> Map<G> {
+    /// Regenerates the map within `budget`, for loading screens that must
+    /// not stall. The first generation always runs, so there is always a
+    /// complete, consistent map. After that, a pass only starts or continues
+    /// while the time spent plus the cost of its last step fits the budget;
+    /// the report lists the passes that failed and those that didn't finish.
+    pub fn generate_with_deadline(&mut self, budget: Duration) -> GenerationReport {
+        let started = Instant::now();
+        let fits = |step: Duration| started.elapsed() + step <= budget;
+
+        let mut step_started = Instant::now();
+        self.regenerate();
+        let mut step = step_started.elapsed();
+        let mut attempts = 1;
+        let mut connectivity = self.stats().connectivity();
+        let mut best: Option<(f64, TileGrid)> = None;
+        while connectivity < MIN_CONNECTIVITY && attempts < MAX_ATTEMPTS && fits(step) {
+            if best.as_ref().is_none_or(|(best, _)| connectivity > *best) {
+                best = Some((connectivity, self.tiles.clone()));
+            }
+            step_started = Instant::now();
+            self.regenerate();
+            step = step_started.elapsed();
+            attempts += 1;
+            connectivity = self.stats().connectivity();
+        }
+        let mut report = GenerationReport {
+            elapsed: Duration::ZERO,
+            attempts,
+            connectivity: 0.0,
+            completed: Vec::new(),
+            failed: Vec::new(),
+            skipped: Vec::new(),
+        };
+        if connectivity >= MIN_CONNECTIVITY {
+            report.completed.push(GenerationPass::QualityGate);
+        } else {
+            if let Some((_, tiles)) = best.filter(|(best, _)| *best > connectivity) {
+                self.tiles = tiles;
+            }
+            if attempts == MAX_ATTEMPTS {
+                report.failed.push(GenerationPass::QualityGate);
+            } else {
+                report.skipped.push(GenerationPass::QualityGate);
+            }
+        }
+
+        let mut step = Duration::ZERO;
+        let pruned = loop {
+            if self.dead_ends().is_empty() {
+                break true;
+            }
+            if !fits(step) {
+                break false;
+            }
+            step_started = Instant::now();
+            self.prune_dead_ends(1);
+            step = step_started.elapsed();
+        };
+        if pruned {
+            report.completed.push(GenerationPass::PruneDeadEnds);
+        } else {
+            report.skipped.push(GenerationPass::PruneDeadEnds);
+        }
+
+        report.connectivity = self.stats().connectivity();
+        report.elapsed = started.elapsed();
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile_generator::TileGeneratorDefault;
+
+    fn map() -> Map<TileGeneratorDefault> {
+        Map::new(
+            16,
+            TileGeneratorDefault::with_seed_and_probabilities(3, 0.5, 0.3),
+        )
+    }
+
+    #[test]
+    fn a_generous_budget_runs_every_pass() {
+        let mut map = map();
+
+        let report = map.generate_with_deadline(Duration::from_secs(60));
+
+        if report.failed.contains(&GenerationPass::QualityGate) {
+            assert_eq!(report.attempts, MAX_ATTEMPTS);
+        }
+        assert!(report.skipped.is_empty());
+        assert!(report.completed.contains(&GenerationPass::PruneDeadEnds));
+        assert!(map.dead_ends().is_empty());
+        assert_eq!(map.stats().connectivity(), report.connectivity);
+        assert_eq!(map.validate(), Ok(()));
+    }
+
+    #[test]
+    fn an_empty_budget_still_gives_a_complete_map() {
+        let mut map = map();
+
+        let report = map.generate_with_deadline(Duration::ZERO);
+
+        assert_eq!(report.attempts, 1);
+        assert_eq!(map.tiles.len(), 16 * 16);
+        assert_eq!(map.validate(), Ok(()));
+        assert!(report.failed.is_empty());
+        assert!(report.skipped.contains(&GenerationPass::PruneDeadEnds));
+        assert!(!map.dead_ends().is_empty());
+    }
+}
@@ -0,0 +1,234 @@
+use crate::map::Map;
+use crate::map_tile::{Biome, MapTile, Tile, TileSet};
+use crate::share_code::{read_varint, unzigzag, write_varint, zigzag};
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use std::collections::BTreeSet;
+
+/// Tile byte flag for a present tile; a zero byte means the tile was removed.
+const PRESENT: u8 = 0x20;
+/// Tile byte flag for [`TileSet::Room`]; the low four bits are the exits.
+const ROOM: u8 = 0x10;
+/// Tile byte flag for [`TileSet::Field`]. Corridors set neither flag.
+const FIELD: u8 = 0x40;
+/// Tile byte flag for a tile outside the [`Biome::Dungeon`]: a second byte
+/// follows with the index of its biome in [`Biome::all`].
+const BIOME: u8 = 0x80;
+
+/// The tiles that changed between two maps, from [`Map::diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MapDiff {
+    /// Dimensions of the target map.
+    pub x: usize,
+    pub y: usize,
+    /// Changed positions in x-then-y order with their new tile, `None` where
+    /// the tile was removed.
+    pub changes: Vec<(IVec2, Option<Tile>)>,
+}
+
+impl MapDiff {
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Compact encoding for sending over the network: the varint dimensions,
+    /// then per change the zigzag varint x and y and the tile, one byte or
+    /// two with a biome.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(4 + self.changes.len() * 3);
+        write_varint(&mut bytes, self.x as u64);
+        write_varint(&mut bytes, self.y as u64);
+        for (position, tile) in &self.changes {
+            write_varint(&mut bytes, zigzag(position.x));
+            write_varint(&mut bytes, zigzag(position.y));
+            encode_tile(&mut bytes, *tile);
+        }
+        bytes
+    }
+
+    /// Reads [`MapDiff::to_bytes`] output. Returns None for malformed input.
+    pub fn from_bytes(mut bytes: &[u8]) -> Option<Self> {
+        let mut diff = MapDiff {
+            x: usize::try_from(read_varint(&mut bytes)?).ok()?,
+            y: usize::try_from(read_varint(&mut bytes)?).ok()?,
+            changes: Vec::new(),
+        };
+        while !bytes.is_empty() {
+            let x = unzigzag(read_varint(&mut bytes)?)?;
+            let y = unzigzag(read_varint(&mut bytes)?)?;
+            diff.changes
+                .push((IVec2::new(x, y), decode_tile(&mut bytes)?));
+        }
+        Some(diff)
+    }
+}
+
+/// Appends one byte per tile, see [`PRESENT`], [`ROOM`], [`FIELD`] and
+/// [`BIOME`], and the biome byte if there is one. A missing tile is a zero
+/// byte.
+pub(crate) fn encode_tile(bytes: &mut Vec<u8>, tile: Option<Tile>) {
+    let Some(tile) = tile else {
+        bytes.push(0);
+        return;
+    };
+    let tile_set = match tile.tile_set {
+        TileSet::Room => ROOM,
+        TileSet::Corridor => 0,
+        TileSet::Field => FIELD,
+    };
+    let byte = PRESENT | tile_set | tile.map_tile as u8;
+    match tile.biome {
+        Biome::Dungeon => bytes.push(byte),
+        biome => bytes.extend([byte | BIOME, biome as u8]),
+    }
+}
+
+/// Reads [`encode_tile`] output from the front of `bytes`: None when
+/// malformed or truncated, `Some(None)` for a missing tile.
+pub(crate) fn decode_tile(bytes: &mut &[u8]) -> Option<Option<Tile>> {
+    let (&byte, rest) = bytes.split_first()?;
+    *bytes = rest;
+    if byte == 0 {
+        return Some(None);
+    }
+    if byte & PRESENT == 0 {
+        return None;
+    }
+    let biome = if byte & BIOME != 0 {
+        let (&biome, rest) = bytes.split_first()?;
+        *bytes = rest;
+        // The dungeon is never written, so every tile has one encoding.
+        Biome::all()
+            .get(biome as usize)
+            .copied()
+            .filter(|biome| *biome != Biome::Dungeon)?
+    } else {
+        Biome::Dungeon
+    };
+    let tile_set = match (byte & ROOM != 0, byte & FIELD != 0) {
+        (false, false) => TileSet::Corridor,
+        (true, false) => TileSet::Room,
+        (false, true) => TileSet::Field,
+        (true, true) => return None,
+    };
+    let map_tile = MapTile::from_bits(byte & 0x0f)?;
+    Some(Some(Tile::new(tile_set, map_tile).with_biome(biome)))
+}
+
+impl<G: TileGenerator> Map<G> {
+    /// Lists the tiles that differ between this map and `other`, so
+    /// [`Map::apply`] can turn a copy of this map into `other` without
+    /// resending the whole grid.
+    pub fn diff<H: TileGenerator>(&self, other: &Map<H>) -> MapDiff {
+        let positions: BTreeSet<(i32, i32)> = self
+            .tiles
+            .keys()
+            .chain(other.tiles.keys())
+            .map(|position| (position.x, position.y))
+            .collect();
+        let changes = positions
+            .into_iter()
+            .map(|(x, y)| IVec2::new(x, y))
+            .filter_map(|position| {
+                let tile = other.tiles.get(&position).copied();
+                (self.tiles.get(&position).copied() != tile).then_some((position, tile))
+            })
+            .collect();
+        MapDiff {
+            x: other.x,
+            y: other.y,
+            changes,
+        }
+    }
+
+    /// Applies the changes of a [`Map::diff`]. The generator is untouched,
+    /// so a later regeneration still uses this map's generator.
+    pub fn apply(&mut self, diff: &MapDiff) {
+        let before = self.tiles_before(diff.changes.iter().map(|(position, _)| *position));
+        if (self.x, self.y) != (diff.x, diff.y) {
+            self.size = diff.x;
+            self.x = diff.x;
+            self.y = diff.y;
+        }
+        for (position, tile) in &diff.changes {
+            match tile {
+                Some(tile) => self.tiles.insert(*position, *tile),
+                None => self.tiles.remove(position),
+            };
+        }
+        self.publish_changes(before);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile_generator::TileGeneratorDefault;
+
+    #[test]
+    fn equal_maps_have_an_empty_diff() {
+        let map = Map::new(8, TileGeneratorDefault::with_seed(4));
+        let copy = Map::new(8, TileGeneratorDefault::with_seed(4));
+
+        assert!(map.diff(&copy).is_empty());
+    }
+
+    #[test]
+    fn diffs_list_only_changed_tiles() {
+        let mut client = Map::new(8, TileGeneratorDefault::with_seed(4));
+        let mut server = Map::new(8, TileGeneratorDefault::with_seed(4));
+        let door = Tile::new(TileSet::Room, MapTile::NESW);
+        server.tiles.insert(IVec2::new(3, 5), door);
+        server.tiles.remove(&IVec2::new(1, 1));
+        let field = Tile::new(TileSet::Field, MapTile::ZERO);
+        server.tiles.insert(IVec2::new(6, 0), field);
+
+        let diff = client.diff(&server);
+
+        assert_eq!(
+            diff.changes,
+            vec![
+                (IVec2::new(1, 1), None),
+                (IVec2::new(3, 5), Some(door)),
+                (IVec2::new(6, 0), Some(field))
+            ]
+        );
+        client.apply(&diff);
+        assert_eq!(client.tiles, server.tiles);
+    }
+
+    #[test]
+    fn diffs_carry_new_dimensions() {
+        let mut small = Map::new(4, TileGeneratorDefault::with_seed(2));
+        let large = Map::new(6, TileGeneratorDefault::with_seed(3));
+
+        small.apply(&small.diff(&large));
+
+        assert_eq!((small.x, small.y), (6, 6));
+        assert_eq!(small.tiles, large.tiles);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let first = Map::new(12, TileGeneratorDefault::with_seed(5));
+        let second = Map::new(12, TileGeneratorDefault::with_seed(6));
+        let mut diff = first.diff(&second);
+        let lava = Tile::new(TileSet::Field, MapTile::NE).with_biome(Biome::Lava);
+        diff.changes.push((IVec2::new(2, 2), Some(lava)));
+        diff.changes.push((IVec2::new(-3, 40), None));
+
+        let bytes = diff.to_bytes();
+
+        assert_eq!(MapDiff::from_bytes(&bytes), Some(diff));
+        assert_eq!(MapDiff::from_bytes(&bytes[..bytes.len() - 1]), None);
+        assert_eq!(MapDiff::from_bytes(&[1, 1, 0, 0, 0x80]), None);
+        assert_eq!(MapDiff::from_bytes(&[1, 1, 0, 0, 0x70]), None);
+        assert_eq!(MapDiff::from_bytes(&[1, 1, 0, 0, 0xa0]), None);
+        assert_eq!(MapDiff::from_bytes(&[1, 1, 0, 0, 0xa0, 0]), None);
+    }
+}
@@ -0,0 +1,279 @@
+//! A set of [`Direction`]s stored as the exit bits of [`MapTile`], for code
+//! that collects or combines exits without `u8` arithmetic.
+//!
+//! ```
+//! use brain_engine_core::{Direction, DirectionSet, MapTile};
+//!
+//! let exits: DirectionSet = [Direction::North, Direction::East].into_iter().collect();
+//! assert_eq!(MapTile::from(exits), MapTile::NE);
+//! assert_eq!(exits | Direction::South, DirectionSet::from(MapTile::NES));
+//! assert_eq!(!exits, DirectionSet::from(MapTile::SW));
+//! assert_eq!(exits.into_iter().collect::<Vec<_>>(), [Direction::North, Direction::East]);
+//! ```
+
+use crate::map_tile::{Direction, MapTile};
+
+use std::fmt;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not, Sub, SubAssign};
+
+/// Up to four directions. Iterates in North, East, South, West order.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DirectionSet(u8);
+
+impl DirectionSet {
+    pub const EMPTY: DirectionSet = DirectionSet(0);
+    pub const ALL: DirectionSet = DirectionSet(0b1111);
+
+    /// The set whose bits are `bits`, with [`Direction`] values as the bit
+    /// for each direction. Returns None above 15.
+    pub const fn from_bits(bits: u8) -> Option<DirectionSet> {
+        if bits > Self::ALL.0 {
+            None
+        } else {
+            Some(DirectionSet(bits))
+        }
+    }
+
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub const fn contains(self, direction: Direction) -> bool {
+        self.0 & direction as u8 != 0
+    }
+
+    /// Returns true when every direction of `other` is in this set.
+    pub const fn contains_all(self, other: DirectionSet) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub const fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub const fn with(self, direction: Direction) -> DirectionSet {
+        DirectionSet(self.0 | direction as u8)
+    }
+
+    pub const fn without(self, direction: Direction) -> DirectionSet {
+        DirectionSet(self.0 & !(direction as u8))
+    }
+
+    /// Adds `direction`, returning false when it was already in the set.
+    pub fn insert(&mut self, direction: Direction) -> bool {
+        let added = !self.contains(direction);
+        *self = self.with(direction);
+        added
+    }
+
+    /// Removes `direction`, returning false when it wasn't in the set.
+    pub fn remove(&mut self, direction: Direction) -> bool {
+        let removed = self.contains(direction);
+        *self = self.without(direction);
+        removed
+    }
+
+    pub const fn union(self, other: DirectionSet) -> DirectionSet {
+        DirectionSet(self.0 | other.0)
+    }
+
+    pub const fn intersection(self, other: DirectionSet) -> DirectionSet {
+        DirectionSet(self.0 & other.0)
+    }
+
+    pub const fn difference(self, other: DirectionSet) -> DirectionSet {
+        DirectionSet(self.0 & !other.0)
+    }
+
+    /// The directions not in this set.
+    pub const fn complement(self) -> DirectionSet {
+        DirectionSet(!self.0 & Self::ALL.0)
+    }
+
+    /// Every subset of this set, from the empty set to the set itself.
+    pub fn subsets(self) -> impl Iterator<Item = DirectionSet> {
+        (0..=Self::ALL.0)
+            .map(DirectionSet)
+            .filter(move |subset| self.contains_all(*subset))
+    }
+
+    pub fn iter(self) -> Iter {
+        Iter(self)
+    }
+}
+
+impl fmt::Display for DirectionSet {
+    /// The exit codes of [`MapTile`], e.g. `NE`, or `ZERO` when empty.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", MapTile::from(*self))
+    }
+}
+
+impl From<Direction> for DirectionSet {
+    fn from(direction: Direction) -> Self {
+        DirectionSet(direction as u8)
+    }
+}
+
+impl From<MapTile> for DirectionSet {
+    fn from(map_tile: MapTile) -> Self {
+        DirectionSet(map_tile as u8)
+    }
+}
+
+impl From<DirectionSet> for MapTile {
+    fn from(directions: DirectionSet) -> Self {
+        MapTile::from_bits(directions.0).expect("direction sets hold at most four bits")
+    }
+}
+
+impl<T: Into<DirectionSet>> BitOr<T> for DirectionSet {
+    type Output = DirectionSet;
+
+    fn bitor(self, other: T) -> DirectionSet {
+        self.union(other.into())
+    }
+}
+
+impl<T: Into<DirectionSet>> BitOrAssign<T> for DirectionSet {
+    fn bitor_assign(&mut self, other: T) {
+        *self = *self | other;
+    }
+}
+
+impl<T: Into<DirectionSet>> BitAnd<T> for DirectionSet {
+    type Output = DirectionSet;
+
+    fn bitand(self, other: T) -> DirectionSet {
+        self.intersection(other.into())
+    }
+}
+
+impl<T: Into<DirectionSet>> BitAndAssign<T> for DirectionSet {
+    fn bitand_assign(&mut self, other: T) {
+        *self = *self & other;
+    }
+}
+
+impl<T: Into<DirectionSet>> Sub<T> for DirectionSet {
+    type Output = DirectionSet;
+
+    fn sub(self, other: T) -> DirectionSet {
+        self.difference(other.into())
+    }
+}
+
+impl<T: Into<DirectionSet>> SubAssign<T> for DirectionSet {
+    fn sub_assign(&mut self, other: T) {
+        *self = *self - other;
+    }
+}
+
+impl Not for DirectionSet {
+    type Output = DirectionSet;
+
+    fn not(self) -> DirectionSet {
+        self.complement()
+    }
+}
+
+impl FromIterator<Direction> for DirectionSet {
+    fn from_iter<I: IntoIterator<Item = Direction>>(directions: I) -> Self {
+        let mut set = DirectionSet::EMPTY;
+        set.extend(directions);
+        set
+    }
+}
+
+impl Extend<Direction> for DirectionSet {
+    fn extend<I: IntoIterator<Item = Direction>>(&mut self, directions: I) {
+        for direction in directions {
+            self.insert(direction);
+        }
+    }
+}
+
+impl IntoIterator for DirectionSet {
+    type Item = Direction;
+    type IntoIter = Iter;
+
+    fn into_iter(self) -> Iter {
+        self.iter()
+    }
+}
+
+/// The directions of a [`DirectionSet`], in North, East, South, West order.
+#[derive(Clone, Debug)]
+pub struct Iter(DirectionSet);
+
+impl Iterator for Iter {
+    type Item = Direction;
+
+    fn next(&mut self) -> Option<Direction> {
+        let direction = Direction::all()
+            .into_iter()
+            .find(|direction| self.0.contains(*direction))?;
+        self.0.remove(direction);
+        Some(direction)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len(), Some(self.0.len()))
+    }
+}
+
+impl ExactSizeIterator for Iter {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sets_convert_to_and_from_map_tiles() {
+        for bits in 0..16 {
+            let set = DirectionSet::from_bits(bits).unwrap();
+            let map_tile = MapTile::from(set);
+            assert_eq!(DirectionSet::from(map_tile), set);
+            assert_eq!(set.len(), map_tile.directions().len());
+            assert_eq!(set.to_string(), map_tile.to_string());
+        }
+        assert_eq!(DirectionSet::from_bits(16), None);
+    }
+
+    #[test]
+    fn iteration_follows_compass_order() {
+        let set: DirectionSet = [Direction::West, Direction::North, Direction::West]
+            .into_iter()
+            .collect();
+
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.iter().len(), 2);
+        assert_eq!(
+            set.into_iter().collect::<Vec<_>>(),
+            [Direction::North, Direction::West]
+        );
+        assert_eq!(DirectionSet::EMPTY.iter().next(), None);
+    }
+
+    #[test]
+    fn operators_combine_sets_and_directions() {
+        let mut set = DirectionSet::from(Direction::North) | Direction::East;
+        assert!(!set.insert(Direction::North));
+        assert!(set.remove(Direction::East));
+        assert!(!set.remove(Direction::East));
+        set |= MapTile::SW;
+        set -= Direction::South;
+
+        assert_eq!(MapTile::from(set), MapTile::NW);
+        assert_eq!(set & MapTile::NE, Direction::North.into());
+        assert_eq!(!set, DirectionSet::from(MapTile::ES));
+        assert!(DirectionSet::ALL.contains_all(set));
+        assert!(!set.contains_all(DirectionSet::ALL));
+        assert_eq!(set.subsets().count(), 4);
+        assert!((set - set).is_empty());
+    }
+}
@@ -0,0 +1,231 @@
+//! Cave-like maps carved by random walks, the drunkard's walk of many
+//! roguelikes.
+//!
+//! ```
+//! use bevy::prelude::*;
+//! use brain_engine_core::{Map, TileGeneratorDrunkard};
+//!
+//! let generator = TileGeneratorDrunkard::new(3).with_walkers(4).with_coverage(0.5);
+//! let map = Map::new(16, generator);
+//! // Every carved tile is reachable from the center, where the walk started.
+//! assert!(map.dijkstra_map(&[IVec2::new(8, 8)]).len() >= 128);
+//! ```
+
+use crate::generation_context::GenerationContext;
+use crate::map_tile::{Direction, MapTile, Tile, TileSet};
+use crate::tile_generator::TileGenerator;
+use crate::tile_grid::TileGrid;
+
+use bevy::prelude::*;
+use rand::seq::IndexedRandom;
+use rand::{SeedableRng, rngs::StdRng};
+use std::sync::Mutex;
+
+/// Steps each walker may take per map tile before the walk gives up on the
+/// coverage target.
+const MAX_STEPS_PER_TILE: usize = 64;
+
+/// Generates winding [`TileSet::Corridor`] passages by random walks.
+///
+/// Every walker starts on the start tile and takes random steps, opening
+/// the exits between the tiles it walks, until the carved tiles cover the
+/// target share of the map. Every carved tile is reachable from the start;
+/// the rest of the map is [`MapTile::ZERO`] wall.
+///
+/// Like [`crate::TileGeneratorMaze`], the walk needs the extent of the map:
+/// on toroidal maps the walkers cross the seams, and unbounded maps such as
+/// [`crate::ChunkedMap`] are all wall. Exits are fitted to the tiles already
+/// placed around each tile.
+#[derive(Resource, Debug)]
+pub struct TileGeneratorDrunkard {
+    seed: u64,
+    walkers: usize,
+    coverage: f64,
+    start: Option<IVec2>,
+    /// The last carved map, in row-major order, with the bounds it fills
+    /// and whether it wraps around them.
+    carved: Mutex<Option<(IRect, bool, Vec<MapTile>)>>,
+}
+
+impl TileGeneratorDrunkard {
+    /// One walker from the center of the map, carving 40% of it.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            walkers: 1,
+            coverage: 0.4,
+            start: None,
+            carved: Mutex::new(None),
+        }
+    }
+
+    /// Walkers taking turns to step, at least 1. More walkers give more,
+    /// shorter branches around the start.
+    pub fn with_walkers(mut self, walkers: usize) -> Self {
+        self.walkers = walkers.max(1);
+        self.carved = Mutex::new(None);
+        self
+    }
+
+    /// The share of the map to carve, from 0.0 to 1.0.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `coverage` is outside `0.0..=1.0`.
+    pub fn with_coverage(mut self, coverage: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&coverage),
+            "coverage must be between 0 and 1, got {coverage}"
+        );
+        self.coverage = coverage;
+        self.carved = Mutex::new(None);
+        self
+    }
+
+    /// Starts the walkers on `start` instead of the center of the map. A
+    /// start off the map is moved to the nearest tile on it.
+    pub fn with_start(mut self, start: IVec2) -> Self {
+        self.start = Some(start);
+        self.carved = Mutex::new(None);
+        self
+    }
+
+    fn carve(&self, bounds: IRect, wraps: bool) -> Vec<MapTile> {
+        let size = bounds.size() + 1;
+        let index = |position: IVec2| (position.y * size.x + position.x) as usize;
+        let len = size.element_product() as usize;
+        let mut tiles = vec![MapTile::ZERO; len];
+        let mut visited = vec![false; len];
+        let mut rng = StdRng::seed_from_u64(self.seed);
+
+        let start = self
+            .start
+            .map_or(size / 2, |start| start - bounds.min)
+            .clamp(IVec2::ZERO, size - 1);
+        visited[index(start)] = true;
+        let mut carved = 1;
+        let target = (self.coverage * len as f64).ceil() as usize;
+        let mut walkers = vec![start; self.walkers];
+        for _ in 0..len * MAX_STEPS_PER_TILE {
+            if carved >= target {
+                break;
+            }
+            for walker in &mut walkers {
+                let step = |direction: Direction| {
+                    let next = *walker + direction.offset();
+                    if wraps {
+                        Some(next.rem_euclid(size))
+                    } else {
+                        (next.cmpge(IVec2::ZERO).all() && next.cmplt(size).all()).then_some(next)
+                    }
+                };
+                let directions: Vec<Direction> = Direction::all()
+                    .into_iter()
+                    .filter(|direction| step(*direction).is_some())
+                    .collect();
+                let Some(&direction) = directions.choose(&mut rng) else {
+                    continue;
+                };
+                let next = step(direction).unwrap();
+                tiles[index(*walker)] = tiles[index(*walker)].with(direction);
+                tiles[index(next)] = tiles[index(next)].with(direction.opposite());
+                if !visited[index(next)] {
+                    visited[index(next)] = true;
+                    carved += 1;
+                }
+                *walker = next;
+            }
+        }
+        tiles
+    }
+
+    /// The exits of `location` in the walk filling `bounds`, walking it
+    /// first unless it is the last one walked.
+    fn carved_tile(&self, bounds: IRect, wraps: bool, location: IVec2) -> MapTile {
+        let mut carved = self.carved.lock().unwrap();
+        if carved
+            .as_ref()
+            .is_none_or(|(carved, carved_wraps, _)| (*carved, *carved_wraps) != (bounds, wraps))
+        {
+            *carved = Some((bounds, wraps, self.carve(bounds, wraps)));
+        }
+        let (_, _, tiles) = carved.as_ref().unwrap();
+        let offset = location - bounds.min;
+        tiles[(offset.y * (bounds.width() + 1) + offset.x) as usize]
+    }
+}
+
+impl TileGenerator for TileGeneratorDrunkard {
+    fn tile_at(&self, tiles: &TileGrid, location: IVec2) -> Tile {
+        self.generate(&GenerationContext::new(tiles, location))
+    }
+
+    fn tile_at_within(&self, tiles: &TileGrid, location: IVec2, bounds: IRect) -> Tile {
+        self.generate(&GenerationContext::new(tiles, location).within(bounds))
+    }
+
+    fn generate(&self, context: &GenerationContext) -> Tile {
+        let carved = match (context.bounds(), context.wrapped_bounds()) {
+            (Some(bounds), _) => self.carved_tile(bounds, false, context.location()),
+            (None, Some(bounds)) => self.carved_tile(bounds, true, context.location()),
+            (None, None) => MapTile::ZERO,
+        };
+        Tile::new(TileSet::Corridor, context.fit_exits(carved.into()).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Map;
+
+    #[test]
+    fn walks_reach_the_coverage_target() {
+        for walkers in [1, 5] {
+            let generator = TileGeneratorDrunkard::new(11)
+                .with_walkers(walkers)
+                .with_coverage(0.5);
+            let map = Map::new(20, generator);
+
+            let reachable = map.dijkstra_map(&[IVec2::new(10, 10)]);
+            assert!(reachable.len() >= 200, "{walkers} walkers");
+            let carved = map
+                .tiles
+                .values()
+                .filter(|tile| tile.map_tile != MapTile::ZERO)
+                .count();
+            assert_eq!(carved, reachable.len());
+            assert_eq!(map.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn walks_repeat_for_a_seed_and_start() {
+        let generator = || TileGeneratorDrunkard::new(4).with_start(IVec2::new(-5, 2));
+        let first = Map::new(12, generator());
+        let second = Map::new(12, generator());
+
+        assert_eq!(first.tiles, second.tiles);
+        assert_ne!(first.tiles[&IVec2::new(0, 2)].map_tile, MapTile::ZERO);
+    }
+
+    #[test]
+    fn toroidal_walks_cross_the_seams() {
+        let generator = TileGeneratorDrunkard::new(6)
+            .with_start(IVec2::ZERO)
+            .with_coverage(0.5);
+        let map = Map::new_toroidal(12, generator);
+
+        let reachable = map.dijkstra_map(&[IVec2::ZERO]);
+        assert!(reachable.len() >= 72);
+        assert!(
+            (0..12).any(|y| map.tiles[&IVec2::new(0, y)]
+                .map_tile
+                .contains(Direction::West))
+                || (0..12).any(|x| map.tiles[&IVec2::new(x, 0)]
+                    .map_tile
+                    .contains(Direction::South))
+        );
+        assert_eq!(map.validate(), Ok(()));
+    }
+}
@@ -0,0 +1,220 @@
+//! Fog of war: which tiles the player sees now and which they have seen.
+//!
+//! ```
+//! use bevy::prelude::*;
+//! use brain_engine_core::{ExplorationState, Map, TileGeneratorDefault};
+//!
+//! let mut map = Map::new(12, TileGeneratorDefault::with_seed(4));
+//! map.mark_visible_from(IVec2::new(2, 2), 4);
+//! map.mark_visible_from(IVec2::new(9, 9), 4);
+//!
+//! assert_eq!(map.exploration_state(IVec2::new(2, 2)), ExplorationState::Seen);
+//! assert_eq!(map.exploration_state(IVec2::new(9, 9)), ExplorationState::Visible);
+//! ```
+
+use crate::map::Map;
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// How much the player knows about a tile.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ExplorationState {
+    /// Never seen: draw nothing.
+    #[default]
+    Unseen,
+    /// Seen before but not in view now: draw it remembered, e.g. dimmed.
+    Seen,
+    /// In view now.
+    Visible,
+}
+
+/// The tiles of a [`Map`] that are visible or were seen, see
+/// [`Map::mark_visible`]. Every other tile is unseen.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Exploration {
+    visible: HashSet<IVec2>,
+    /// Every tile ever visible, including the visible ones.
+    seen: HashSet<IVec2>,
+}
+
+impl Exploration {
+    pub fn state(&self, position: IVec2) -> ExplorationState {
+        if self.visible.contains(&position) {
+            ExplorationState::Visible
+        } else if self.seen.contains(&position) {
+            ExplorationState::Seen
+        } else {
+            ExplorationState::Unseen
+        }
+    }
+
+    /// True for tiles that are visible now or were seen before.
+    pub fn is_explored(&self, position: IVec2) -> bool {
+        self.seen.contains(&position)
+    }
+
+    /// Moves every tile by `offset`, keeping those `keep` accepts, for maps
+    /// that are resized or cropped.
+    pub(crate) fn shift(&mut self, offset: IVec2, keep: impl Fn(IVec2) -> bool) {
+        for tiles in [&mut self.visible, &mut self.seen] {
+            *tiles = tiles
+                .iter()
+                .map(|position| *position + offset)
+                .filter(|position| keep(*position))
+                .collect();
+        }
+    }
+}
+
+impl<G: TileGenerator> Map<G> {
+    /// Makes exactly `positions` visible, such as the field of view this
+    /// turn. Tiles that were visible before become [`ExplorationState::Seen`];
+    /// positions off the map are ignored. Tiles seen for the first time are
+    /// sent to subscribers as a [`crate::MapEvent::RegionRevealed`].
+    pub fn mark_visible(&mut self, positions: impl IntoIterator<Item = IVec2>) {
+        let visible: HashSet<IVec2> = positions
+            .into_iter()
+            .filter(|position| self.in_bounds(*position))
+            .collect();
+        let mut revealed: Vec<IVec2> = visible
+            .iter()
+            .filter(|position| !self.exploration.seen.contains(position))
+            .copied()
+            .collect();
+        revealed.sort_by_key(|position| (position.x, position.y));
+        self.exploration.seen.extend(&visible);
+        self.exploration.visible = visible;
+        self.publish_revealed(revealed);
+    }
+
+    /// Makes the tiles [`Map::visible_from`] `origin` within `radius` the
+    /// visible ones.
+    pub fn mark_visible_from(&mut self, origin: IVec2, radius: u32) {
+        let visible = self.visible_from(origin, radius);
+        self.mark_visible(visible);
+    }
+
+    /// Marks every tile unseen again, e.g. for a new level on the same map.
+    pub fn forget_exploration(&mut self) {
+        self.exploration = Exploration::default();
+    }
+
+    pub fn exploration_state(&self, position: IVec2) -> ExplorationState {
+        self.exploration.state(position)
+    }
+
+    pub fn is_visible(&self, position: IVec2) -> bool {
+        self.exploration.visible.contains(&position)
+    }
+
+    /// True for tiles that are visible now or were seen before.
+    pub fn is_explored(&self, position: IVec2) -> bool {
+        self.exploration.is_explored(position)
+    }
+
+    /// The visible tiles, in x-then-y order.
+    pub fn visible_tiles(&self) -> Vec<IVec2> {
+        self.positions()
+            .filter(|position| self.is_visible(*position))
+            .collect()
+    }
+
+    /// The visible and seen tiles, in x-then-y order.
+    pub fn explored_tiles(&self) -> Vec<IVec2> {
+        self.positions()
+            .filter(|position| self.is_explored(*position))
+            .collect()
+    }
+
+    /// For autoexplore: the unexplored tile fewest steps away from `from`
+    /// that can be walked to, and the tile to step onto towards it. Ties go
+    /// to the tile reached first through exits in NESW order. `None` once
+    /// everything reachable is explored.
+    pub fn autoexplore(&self, from: IVec2) -> Option<(IVec2, IVec2)> {
+        let mut came_from = HashMap::from([(from, from)]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(current) = queue.pop_front() {
+            for neighbor in self.neighbors(current) {
+                if came_from.contains_key(&neighbor) {
+                    continue;
+                }
+                came_from.insert(neighbor, current);
+                if !self.is_explored(neighbor) {
+                    let mut step = neighbor;
+                    while came_from[&step] != from {
+                        step = came_from[&step];
+                    }
+                    return Some((neighbor, step));
+                }
+                queue.push_back(neighbor);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::open_field::OpenFieldGenerator;
+
+    #[test]
+    fn visible_tiles_become_seen_when_out_of_view() {
+        let mut map = Map::new(6, OpenFieldGenerator);
+        assert_eq!(map.exploration_state(IVec2::ZERO), ExplorationState::Unseen);
+
+        map.mark_visible([IVec2::ZERO, IVec2::new(1, 0), IVec2::new(-1, 0)]);
+        map.mark_visible([IVec2::new(1, 0), IVec2::new(2, 0)]);
+
+        assert_eq!(map.exploration_state(IVec2::ZERO), ExplorationState::Seen);
+        assert_eq!(
+            map.exploration_state(IVec2::new(1, 0)),
+            ExplorationState::Visible
+        );
+        assert_eq!(
+            map.exploration_state(IVec2::new(-1, 0)),
+            ExplorationState::Unseen
+        );
+        assert_eq!(
+            map.visible_tiles(),
+            vec![IVec2::new(1, 0), IVec2::new(2, 0)]
+        );
+        assert_eq!(map.explored_tiles().len(), 3);
+
+        map.forget_exploration();
+        assert!(map.explored_tiles().is_empty());
+    }
+
+    #[test]
+    fn autoexplore_heads_for_the_nearest_unexplored_tile() {
+        let mut map = Map::new(6, OpenFieldGenerator);
+        map.mark_visible([IVec2::new(0, 0), IVec2::new(1, 0), IVec2::new(2, 0)]);
+        map.mark_visible([IVec2::new(0, 1), IVec2::new(1, 1)]);
+
+        assert_eq!(
+            map.autoexplore(IVec2::new(2, 0)),
+            Some((IVec2::new(2, 1), IVec2::new(2, 1)))
+        );
+        assert_eq!(
+            map.autoexplore(IVec2::new(0, 0)),
+            Some((IVec2::new(0, 2), IVec2::new(0, 1)))
+        );
+
+        map.mark_visible(map.positions().collect::<Vec<_>>());
+        assert_eq!(map.autoexplore(IVec2::new(0, 0)), None);
+    }
+
+    #[test]
+    fn field_of_view_marks_what_the_origin_sees() {
+        let mut map = Map::new(9, OpenFieldGenerator);
+        map.place_obstacle(IVec2::new(4, 5));
+
+        map.mark_visible_from(IVec2::new(4, 4), 3);
+
+        let visible: HashSet<IVec2> = map.visible_tiles().into_iter().collect();
+        assert_eq!(visible, map.visible_from(IVec2::new(4, 4), 3));
+        assert!(!map.is_visible(IVec2::new(4, 6)));
+    }
+}
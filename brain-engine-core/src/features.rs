@@ -0,0 +1,244 @@
+use crate::map::Map;
+use crate::map_file::{TileExtension, TileExtensions};
+use crate::map_tile::Tile;
+use crate::share_code::{fnv1a, write_varint, zigzag};
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use rand::{SeedableRng, rngs::StdRng};
+
+/// Features (props, items, decorations) placed on map tiles, at most one
+/// per tile.
+///
+/// The layer is always kept in x-then-y position order, whatever order the
+/// features were inserted in, so iterating or encoding it never depends on
+/// hashing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeatureLayer<F> {
+    features: Vec<(IVec2, F)>,
+}
+
+impl<F> Default for FeatureLayer<F> {
+    fn default() -> Self {
+        Self {
+            features: Vec::new(),
+        }
+    }
+}
+
+impl<F> FeatureLayer<F> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Places `feature` at `position`, returning the feature it replaces.
+    pub fn insert(&mut self, position: IVec2, feature: F) -> Option<F> {
+        match self.search(position) {
+            Ok(index) => Some(std::mem::replace(&mut self.features[index].1, feature)),
+            Err(index) => {
+                self.features.insert(index, (position, feature));
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, position: IVec2) -> Option<F> {
+        let index = self.search(position).ok()?;
+        Some(self.features.remove(index).1)
+    }
+
+    pub fn get(&self, position: IVec2) -> Option<&F> {
+        let index = self.search(position).ok()?;
+        Some(&self.features[index].1)
+    }
+
+    /// Iterates the features in x-then-y position order.
+    pub fn iter(&self) -> impl Iterator<Item = (IVec2, &F)> + '_ {
+        self.features
+            .iter()
+            .map(|(position, feature)| (*position, feature))
+    }
+
+    pub fn len(&self) -> usize {
+        self.features.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty()
+    }
+
+    fn search(&self, position: IVec2) -> Result<usize, usize> {
+        self.features
+            .binary_search_by_key(&(position.x, position.y), |(other, _)| (other.x, other.y))
+    }
+}
+
+impl<F: TileExtension> FeatureLayer<F> {
+    /// Canonical encoding of the layer: per feature, in position order, the
+    /// zigzag varint x and y, then the length-prefixed
+    /// [`TileExtension::encode`] bytes. Equal layers give equal bytes on
+    /// every platform.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for (position, feature) in &self.features {
+            write_varint(&mut bytes, zigzag(position.x));
+            write_varint(&mut bytes, zigzag(position.y));
+            let encoded = feature.encode();
+            write_varint(&mut bytes, encoded.len() as u64);
+            bytes.extend(encoded);
+        }
+        bytes
+    }
+
+    /// Stores every feature in `extensions` under [`TileExtension::KEY`], so
+    /// the layer is saved with the map file.
+    pub fn write_to(&self, extensions: &mut TileExtensions) {
+        for (position, feature) in &self.features {
+            extensions.insert(*position, feature);
+        }
+    }
+}
+
+/// Places features deterministically from a seed.
+///
+/// Every rule is named and draws from its own random stream, derived from
+/// the seed and the name, and sees the tiles in x-then-y order. The same
+/// seed, map and rule therefore give the same [`FeatureLayer`] on every
+/// platform, regardless of `HashMap` order and of which other rules ran
+/// before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeaturePlacer {
+    seed: u64,
+}
+
+impl FeaturePlacer {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// The random stream of the rule called `rule`.
+    pub fn stream(&self, rule: &str) -> StdRng {
+        StdRng::seed_from_u64(self.seed ^ fnv1a(rule.as_bytes()))
+    }
+
+    /// Calls `place` for every tile of `map` in x-then-y order with the
+    /// rule's random stream, and collects the features it returns.
+    pub fn place<G, F>(
+        &self,
+        map: &Map<G>,
+        rule: &str,
+        mut place: impl FnMut(IVec2, Tile, &mut StdRng) -> Option<F>,
+    ) -> FeatureLayer<F>
+    where
+        G: TileGenerator,
+    {
+        let mut random = self.stream(rule);
+        let mut layer = FeatureLayer::new();
+        for position in map.positions() {
+            if let Some(feature) = place(position, map.tiles[&position], &mut random) {
+                layer.features.push((position, feature));
+            }
+        }
+        layer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_tile::{MapTile, TileSet};
+    use crate::tile_generator::TileGeneratorDefault;
+    use rand::Rng;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct Chest(u8);
+
+    impl TileExtension for Chest {
+        const KEY: &'static str = "chest";
+
+        fn encode(&self) -> Vec<u8> {
+            vec![self.0]
+        }
+
+        fn decode(bytes: &[u8]) -> Option<Self> {
+            match bytes {
+                [gold] => Some(Chest(*gold)),
+                _ => None,
+            }
+        }
+    }
+
+    fn chests(placer: &FeaturePlacer, map: &Map<TileGeneratorDefault>) -> FeatureLayer<Chest> {
+        placer.place(map, "chests", |_, tile, random| {
+            let roll: f64 = random.random();
+            (tile.tile_set == TileSet::Room && tile.map_tile != MapTile::ZERO && roll < 0.2)
+                .then(|| Chest(random.random_range(1..=50)))
+        })
+    }
+
+    #[test]
+    fn layers_stay_in_position_order() {
+        let mut layer = FeatureLayer::new();
+        layer.insert(IVec2::new(2, 0), Chest(1));
+        layer.insert(IVec2::new(0, 5), Chest(2));
+        layer.insert(IVec2::new(0, 1), Chest(3));
+
+        assert_eq!(layer.insert(IVec2::new(0, 5), Chest(4)), Some(Chest(2)));
+        assert_eq!(
+            layer.iter().collect::<Vec<_>>(),
+            vec![
+                (IVec2::new(0, 1), &Chest(3)),
+                (IVec2::new(0, 5), &Chest(4)),
+                (IVec2::new(2, 0), &Chest(1)),
+            ]
+        );
+        assert_eq!(layer.remove(IVec2::new(0, 1)), Some(Chest(3)));
+        assert_eq!(layer.get(IVec2::new(0, 1)), None);
+    }
+
+    #[test]
+    fn same_seed_gives_byte_identical_layers() {
+        let map = Map::new(16, TileGeneratorDefault::with_seed(9));
+        let placer = FeaturePlacer::new(4);
+
+        let bytes = chests(&placer, &map).to_bytes();
+
+        assert!(!bytes.is_empty());
+        assert_eq!(bytes, chests(&placer, &map).to_bytes());
+        // Pinned so a platform- or hash-order-dependent change fails here.
+        assert_eq!(fnv1a(&bytes), 0x46de_578f_2762_3a4f);
+    }
+
+    #[test]
+    fn rules_do_not_disturb_each_other() {
+        let map = Map::new(16, TileGeneratorDefault::with_seed(9));
+        let placer = FeaturePlacer::new(4);
+        let alone = chests(&placer, &map);
+
+        let torches = placer.place(&map, "torches", |_, _, random| {
+            random.random_bool(0.5).then_some(Chest(0))
+        });
+
+        assert!(!torches.is_empty());
+        assert_eq!(chests(&placer, &map), alone);
+        assert_ne!(
+            placer.stream("chests").random::<u64>(),
+            placer.stream("torches").random::<u64>()
+        );
+    }
+
+    #[test]
+    fn layers_are_saved_as_tile_extensions() {
+        let mut layer = FeatureLayer::new();
+        layer.insert(IVec2::new(3, 4), Chest(7));
+        let mut extensions = TileExtensions::new();
+
+        layer.write_to(&mut extensions);
+
+        assert_eq!(extensions.get::<Chest>(IVec2::new(3, 4)), Some(Chest(7)));
+    }
+}
@@ -0,0 +1,284 @@
+//! What a generator sees of the map it is filling, see
+//! [`TileGenerator::generate`].
+//!
+//! ```
+//! use bevy::prelude::*;
+//! use brain_engine_core::{
+//!     GenerationContext, Map, MapTile, Tile, TileGenerator, TileGrid, TileSet,
+//! };
+//!
+//! /// Corridors that join every neighbor the map has room for.
+//! struct OpenCorridors;
+//!
+//! impl TileGenerator for OpenCorridors {
+//!     fn tile_at(&self, tiles: &TileGrid, location: IVec2) -> Tile {
+//!         self.generate(&GenerationContext::new(tiles, location))
+//!     }
+//!
+//!     fn generate(&self, context: &GenerationContext) -> Tile {
+//!         let exits = context.required_exits() | context.free_exits();
+//!         Tile::new(TileSet::Corridor, exits.into())
+//!     }
+//! }
+//!
+//! let map = Map::new(3, OpenCorridors);
+//! assert_eq!(map.tiles[&IVec2::ZERO].map_tile, MapTile::NE);
+//! assert_eq!(map.tiles[&IVec2::ONE].map_tile, MapTile::NESW);
+//! ```
+
+use crate::direction_set::DirectionSet;
+use crate::map_tile::{Direction, Tile};
+use crate::tile_grid::TileGrid;
+
+use bevy::prelude::*;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+/// The tile being generated, the tiles placed so far, the bounds of the map
+/// and how far generation has got.
+#[derive(Debug, Clone, Copy)]
+pub struct GenerationContext<'a> {
+    tiles: &'a TileGrid,
+    location: IVec2,
+    bounds: Option<IRect>,
+    wrapped_bounds: Option<IRect>,
+    visible_area: Option<IRect>,
+    progress: Option<(usize, usize)>,
+}
+
+impl<'a> GenerationContext<'a> {
+    /// The context for the tile at `location` of an unbounded map, such as
+    /// a [`crate::ChunkedMap`], with unknown progress.
+    pub fn new(tiles: &'a TileGrid, location: IVec2) -> Self {
+        Self {
+            tiles,
+            location,
+            bounds: None,
+            wrapped_bounds: None,
+            visible_area: None,
+            progress: None,
+        }
+    }
+
+    /// The same context for a map whose tiles all lie within `bounds`, both
+    /// corners inclusive.
+    pub fn within(self, bounds: IRect) -> Self {
+        Self {
+            bounds: Some(bounds),
+            ..self
+        }
+    }
+
+    /// The same context for a toroidal map filling `bounds`, both corners
+    /// inclusive, whose edges wrap around. [`GenerationContext::bounds`]
+    /// stays unset, as every neighbor is on the map, and the tiles across
+    /// the seams are visible just past the opposite edge.
+    pub fn wrapping(self, bounds: IRect) -> Self {
+        Self {
+            wrapped_bounds: Some(bounds),
+            ..self
+        }
+    }
+
+    /// The same context for a generator that only sees the tiles within
+    /// `area`, both corners inclusive, such as a chunk generated on its own
+    /// thread and the ring of tiles around it. Tiles outside `area` may be
+    /// placed already but are missing from [`GenerationContext::tiles`], and
+    /// [`GenerationContext::tile`] panics when asked for them.
+    pub fn visible_within(self, area: IRect) -> Self {
+        Self {
+            visible_area: Some(area),
+            ..self
+        }
+    }
+
+    /// The same context after `generated` of the `total` tiles of this
+    /// generation run were placed.
+    pub fn with_progress(self, generated: usize, total: usize) -> Self {
+        Self {
+            progress: Some((generated, total)),
+            ..self
+        }
+    }
+
+    /// The tiles placed so far, or only those within
+    /// [`GenerationContext::visible_area`] when it is set.
+    pub fn tiles(&self) -> &'a TileGrid {
+        self.tiles
+    }
+
+    pub fn location(&self) -> IVec2 {
+        self.location
+    }
+
+    pub fn bounds(&self) -> Option<IRect> {
+        self.bounds
+    }
+
+    /// The extent of a toroidal map, see [`GenerationContext::wrapping`].
+    pub fn wrapped_bounds(&self) -> Option<IRect> {
+        self.wrapped_bounds
+    }
+
+    /// The only area whose tiles this generator sees, see
+    /// [`GenerationContext::visible_within`]. `None` when it sees them all.
+    pub fn visible_area(&self) -> Option<IRect> {
+        self.visible_area
+    }
+
+    /// Returns true when `position` lies within the bounds, or always on an
+    /// unbounded map.
+    pub fn in_bounds(&self, position: IVec2) -> bool {
+        self.bounds.is_none_or(|bounds| bounds.contains(position))
+    }
+
+    /// The tile placed at `position`, `None` while it is still to generate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `position` is outside [`GenerationContext::visible_area`],
+    /// where the answer is unknown.
+    pub fn tile(&self, position: IVec2) -> Option<Tile> {
+        assert!(
+            self.visible_area.is_none_or(|area| area.contains(position)),
+            "{position} is outside the area visible to this generator"
+        );
+        self.tiles.get(&position).copied()
+    }
+
+    /// The placed tile next to the one being generated, towards `direction`.
+    pub fn neighbor(&self, direction: Direction) -> Option<Tile> {
+        self.tile(self.location + direction.offset())
+    }
+
+    /// The directions whose placed neighbors have an exit towards this
+    /// tile, which it needs to match.
+    pub fn required_exits(&self) -> DirectionSet {
+        Direction::all()
+            .into_iter()
+            .filter(|direction| {
+                self.neighbor(*direction)
+                    .is_some_and(|tile| tile.map_tile.contains(direction.opposite()))
+            })
+            .collect()
+    }
+
+    /// The directions an exit would lead nowhere: off the bounds, or into a
+    /// placed neighbor without an exit back.
+    pub fn blocked_exits(&self) -> DirectionSet {
+        !(self.required_exits() | self.free_exits())
+    }
+
+    /// The directions whose neighbor is within the bounds and still to
+    /// generate, so this tile decides whether they are joined.
+    pub fn free_exits(&self) -> DirectionSet {
+        Direction::all()
+            .into_iter()
+            .filter(|direction| {
+                let neighbor = self.location + direction.offset();
+                self.in_bounds(neighbor) && !self.tiles.contains_key(&neighbor)
+            })
+            .collect()
+    }
+
+    /// `exits` with the required exits opened and the blocked ones closed,
+    /// for generators that plan a layout up front, such as a maze, and must
+    /// still fit the tiles already placed around it.
+    pub fn fit_exits(&self, exits: DirectionSet) -> DirectionSet {
+        (exits - self.blocked_exits()) | self.required_exits()
+    }
+
+    /// A random stream for this tile from `seed` and the tile's location
+    /// alone, like [`crate::RandomDrawMode::PositionHashed`], so the tile
+    /// doesn't depend on the order the map is generated in.
+    pub fn rng(&self, seed: u64) -> StdRng {
+        StdRng::seed_from_u64(seed ^ position_hash(self.location))
+    }
+
+    /// The number of tiles this generation run placed so far, when known.
+    pub fn generated(&self) -> Option<usize> {
+        self.progress.map(|(generated, _)| generated)
+    }
+
+    /// The number of tiles this generation run places, when known.
+    pub fn total(&self) -> Option<usize> {
+        self.progress.map(|(_, total)| total)
+    }
+
+    /// The share of this generation run already placed, from 0.0 to 1.0.
+    pub fn progress(&self) -> Option<f32> {
+        self.progress
+            .map(|(generated, total)| generated as f32 / total.max(1) as f32)
+    }
+}
+
+/// Mixes a position into a seed, so nearby positions get unrelated streams.
+pub(crate) fn position_hash(position: IVec2) -> u64 {
+    (position.x as u32 as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+        ^ (position.y as u32 as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_tile::{MapTile, TileSet};
+    use rand::Rng;
+
+    #[test]
+    fn exits_are_split_by_what_the_neighbors_decided() {
+        let mut tiles = TileGrid::new();
+        tiles.insert(IVec2::new(1, 2), Tile::new(TileSet::Room, MapTile::S));
+        tiles.insert(IVec2::new(2, 1), Tile::new(TileSet::Room, MapTile::N));
+        let context =
+            GenerationContext::new(&tiles, IVec2::new(1, 1)).within(IRect::new(0, 0, 1, 3));
+
+        assert_eq!(context.required_exits(), Direction::North.into());
+        assert_eq!(context.blocked_exits(), Direction::East.into());
+        assert_eq!(context.free_exits(), DirectionSet::from(MapTile::SW));
+        assert_eq!(
+            context.neighbor(Direction::East),
+            Some(Tile::new(TileSet::Room, MapTile::N))
+        );
+        assert!(!context.in_bounds(IVec2::new(2, 1)));
+        assert_eq!(
+            context.fit_exits(MapTile::ES.into()),
+            DirectionSet::from(MapTile::NS)
+        );
+    }
+
+    #[test]
+    fn unbounded_contexts_leave_every_empty_neighbor_free() {
+        let tiles = TileGrid::new();
+        let context = GenerationContext::new(&tiles, IVec2::new(-5, 9));
+
+        assert_eq!(context.free_exits(), DirectionSet::ALL);
+        assert!(context.blocked_exits().is_empty());
+        assert_eq!(context.progress(), None);
+        assert_eq!(context.with_progress(3, 12).progress(), Some(0.25));
+    }
+
+    #[test]
+    #[should_panic(expected = "outside the area visible to this generator")]
+    fn tiles_outside_the_visible_area_are_unknown() {
+        let tiles = TileGrid::new();
+        let context =
+            GenerationContext::new(&tiles, IVec2::ONE).visible_within(IRect::new(0, 0, 2, 2));
+
+        assert_eq!(context.neighbor(Direction::North), None);
+        context.tile(IVec2::new(3, 1));
+    }
+
+    #[test]
+    fn random_streams_depend_on_the_seed_and_location() {
+        let tiles = TileGrid::new();
+        let draw = |seed: u64, location: IVec2| {
+            GenerationContext::new(&tiles, location)
+                .rng(seed)
+                .random::<u64>()
+        };
+
+        assert_eq!(draw(4, IVec2::ONE), draw(4, IVec2::ONE));
+        assert_ne!(draw(4, IVec2::ONE), draw(4, IVec2::X));
+        assert_ne!(draw(4, IVec2::ONE), draw(5, IVec2::ONE));
+    }
+}
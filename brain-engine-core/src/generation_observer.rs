@@ -0,0 +1,120 @@
+use crate::map_tile::Tile;
+use crate::tile_grid::TileGrid;
+
+use bevy::prelude::*;
+
+/// Receives progress while a [`crate::Map`] is generated, e.g. to animate
+/// generation in a UI, log decisions or record a replay.
+///
+/// Pass one to [`crate::Map::new_observed`] or
+/// [`crate::Map::regenerate_observed`]. Every method has an empty default, so
+/// implement only what you need. Closures taking `(IVec2, Tile)` are
+/// observers that only see placed tiles.
+pub trait GenerationObserver {
+    /// Called before the first tile is placed, with the map dimensions.
+    fn generation_started(&mut self, _dimensions: UVec2) {}
+
+    /// Called after each tile is generated and inserted into the map.
+    fn tile_placed(&mut self, _position: IVec2, _tile: Tile) {}
+
+    /// Called once every tile has been placed.
+    fn generation_finished(&mut self, _tiles: &TileGrid) {}
+}
+
+impl<F: FnMut(IVec2, Tile)> GenerationObserver for F {
+    fn tile_placed(&mut self, position: IVec2, tile: Tile) {
+        self(position, tile)
+    }
+}
+
+/// Observer that ignores everything.
+pub(crate) struct NoObserver;
+
+impl GenerationObserver for NoObserver {}
+
+/// Records every placement in order, so a generation can be replayed tile by
+/// tile after the fact.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GenerationRecorder {
+    pub dimensions: UVec2,
+    pub placements: Vec<(IVec2, Tile)>,
+    pub finished: bool,
+}
+
+impl GenerationRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rebuilds the tiles as they were after the first `steps` placements.
+    pub fn replay(&self, steps: usize) -> TileGrid {
+        let mut tiles = TileGrid::with_size(self.dimensions);
+        tiles.extend(self.placements.iter().take(steps).copied());
+        tiles
+    }
+}
+
+impl GenerationObserver for GenerationRecorder {
+    fn generation_started(&mut self, dimensions: UVec2) {
+        self.dimensions = dimensions;
+        self.placements.clear();
+        self.finished = false;
+    }
+
+    fn tile_placed(&mut self, position: IVec2, tile: Tile) {
+        self.placements.push((position, tile));
+    }
+
+    fn generation_finished(&mut self, _tiles: &TileGrid) {
+        self.finished = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Map;
+    use crate::tile_generator::TileGeneratorDefault;
+
+    #[test]
+    fn recorder_sees_every_tile_in_generation_order() {
+        let mut recorder = GenerationRecorder::new();
+        let map = Map::new_observed(4, TileGeneratorDefault::with_seed(2), &mut recorder);
+
+        assert!(recorder.finished);
+        assert_eq!(recorder.dimensions, UVec2::new(4, 4));
+        assert_eq!(recorder.placements.len(), 16);
+        assert_eq!(recorder.placements[0].0, IVec2::new(0, 0));
+        assert_eq!(recorder.placements[1].0, IVec2::new(0, 1));
+        assert_eq!(recorder.replay(16), map.tiles);
+        assert_eq!(recorder.replay(3).len(), 3);
+    }
+
+    #[test]
+    fn observing_does_not_change_the_generated_map() {
+        let mut placed = 0;
+        let observed = Map::new_observed(
+            6,
+            TileGeneratorDefault::with_seed(9),
+            &mut |_: IVec2, _: Tile| placed += 1,
+        );
+        let unobserved = Map::new(6, TileGeneratorDefault::with_seed(9));
+
+        assert_eq!(placed, 36);
+        assert_eq!(observed.tiles, unobserved.tiles);
+    }
+
+    #[test]
+    fn regenerate_observed_restarts_the_recording() {
+        let mut map = Map::new(3, TileGeneratorDefault::with_seed(4));
+        let mut recorder = GenerationRecorder::new();
+        recorder
+            .placements
+            .push((IVec2::new(9, 9), map.tiles[&IVec2::ZERO]));
+
+        map.regenerate_observed(&mut recorder);
+
+        assert_eq!(recorder.placements.len(), 9);
+        assert_eq!(recorder.replay(9), map.tiles);
+    }
+}
@@ -0,0 +1,163 @@
+use crate::map_tile::{Direction, MapTile, TileSet};
+
+use bevy::prelude::*;
+use std::fmt;
+
+/// What a random draw of [`crate::TileGeneratorDefault`] decided.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Decision {
+    /// Whether to open the exit towards `direction`.
+    Exit { direction: Direction, open: bool },
+    /// The exit combination picked by a weighted generator.
+    Exits(MapTile),
+    /// Whether the tile is a room or a corridor.
+    TileSet(TileSet),
+}
+
+impl fmt::Display for Decision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Decision::Exit { direction, open } => {
+                let state = if *open { "open" } else { "closed" };
+                write!(f, "exit {direction}: {state}")
+            }
+            Decision::Exits(map_tile) => write!(f, "exits: {map_tile}"),
+            Decision::TileSet(tile_set) => write!(f, "tile set: {tile_set}"),
+        }
+    }
+}
+
+/// One random draw made while generating the tile at `position`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Roll {
+    pub position: IVec2,
+    pub decision: Decision,
+    /// False for draws made only to keep the random stream aligned, see
+    /// [`crate::RandomDrawMode::Fixed`], such as the roll for an exit a
+    /// neighbor already decided.
+    pub used: bool,
+}
+
+impl fmt::Display for Roll {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.position, self.decision)?;
+        if !self.used {
+            write!(f, " (discarded)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Every random draw a generator made, in the order it made them, see
+/// [`crate::TileGeneratorDefault::start_trace`]. Use it to find the roll
+/// behind an unexpected tile, or diff the traces of two versions of a
+/// generator to see where they diverge.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GenerationTrace {
+    pub rolls: Vec<Roll>,
+}
+
+impl GenerationTrace {
+    /// The rolls for the tile at `position`, with their index in the trace.
+    pub fn rolls_at(&self, position: IVec2) -> impl Iterator<Item = (usize, &Roll)> + '_ {
+        self.rolls
+            .iter()
+            .enumerate()
+            .filter(move |(_, roll)| roll.position == position)
+    }
+
+    /// The index of the first roll where `self` and `other` differ, `None`
+    /// when one trace is the start of the other.
+    pub fn first_divergence(&self, other: &GenerationTrace) -> Option<usize> {
+        self.rolls
+            .iter()
+            .zip(&other.rolls)
+            .position(|(roll, other)| roll != other)
+    }
+}
+
+impl fmt::Display for GenerationTrace {
+    /// One numbered roll per line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, roll) in self.rolls.iter().enumerate() {
+            writeln!(f, "#{index} {roll}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Map;
+    use crate::tile_generator::{RandomDrawMode, TileGeneratorDefault};
+
+    fn traced(seed: u64, draw_mode: RandomDrawMode) -> Map<TileGeneratorDefault> {
+        let mut generator = TileGeneratorDefault::builder()
+            .seed(seed)
+            .draw_mode(draw_mode)
+            .build();
+        generator.start_trace();
+        Map::new(4, generator)
+    }
+
+    #[test]
+    fn traces_explain_each_tile() {
+        let map = traced(3, RandomDrawMode::Fixed);
+        let trace = map.generator.take_trace().unwrap();
+
+        // Four exit rolls and a tile set roll for every tile.
+        assert_eq!(trace.rolls.len(), 16 * 5);
+        let position = IVec2::new(2, 1);
+        let tile = map.tiles[&position];
+        let rolls: Vec<Roll> = trace.rolls_at(position).map(|(_, roll)| *roll).collect();
+        assert_eq!(rolls.len(), 5);
+        for roll in &rolls[..4] {
+            let Decision::Exit { direction, open } = roll.decision else {
+                panic!("expected an exit roll, got {roll}");
+            };
+            if roll.used {
+                assert_eq!(tile.map_tile.contains(direction), open);
+            }
+        }
+        assert_eq!(rolls[4].decision, Decision::TileSet(tile.tile_set));
+        // The west neighbor was placed first, so its roll was discarded.
+        assert!(!rolls[3].used);
+        assert!(map.generator.take_trace().unwrap().rolls.is_empty());
+    }
+
+    #[test]
+    fn legacy_traces_only_hold_used_rolls() {
+        let map = traced(3, RandomDrawMode::Legacy);
+        let trace = map.generator.take_trace().unwrap();
+
+        assert!(trace.rolls.iter().all(|roll| roll.used));
+        assert!(trace.rolls.len() < 16 * 5);
+        assert_eq!(
+            trace.to_string().lines().next().unwrap(),
+            format!("#0 {}", trace.rolls[0])
+        );
+    }
+
+    #[test]
+    fn divergence_points_at_the_first_different_roll() {
+        let first = traced(1, RandomDrawMode::Fixed)
+            .generator
+            .take_trace()
+            .unwrap();
+        let same = traced(1, RandomDrawMode::Fixed)
+            .generator
+            .take_trace()
+            .unwrap();
+        let other = traced(2, RandomDrawMode::Fixed)
+            .generator
+            .take_trace()
+            .unwrap();
+
+        assert_eq!(first.first_divergence(&same), None);
+        let index = first.first_divergence(&other).unwrap();
+        assert_ne!(first.rolls[index], other.rolls[index]);
+        assert_eq!(first.rolls[..index], other.rolls[..index]);
+        assert_eq!(TileGeneratorDefault::with_seed(1).take_trace(), None);
+    }
+}
@@ -0,0 +1,93 @@
+use crate::map::Map;
+use crate::map_tile::Direction;
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use petgraph::graph::{NodeIndex, UnGraph};
+use std::collections::HashMap;
+
+/// A map as a petgraph graph, from [`Map::to_graph`]: one node per tile,
+/// weighted with its position, and one edge of weight 1 per pair of
+/// neighbors [`Map::can_move`] joins.
+#[derive(Debug, Clone)]
+pub struct MapGraph {
+    pub graph: UnGraph<IVec2, u32>,
+    nodes: HashMap<IVec2, NodeIndex>,
+}
+
+impl MapGraph {
+    /// The node of the tile at `position`.
+    pub fn node(&self, position: IVec2) -> Option<NodeIndex> {
+        self.nodes.get(&position).copied()
+    }
+
+    pub fn position(&self, node: NodeIndex) -> Option<IVec2> {
+        self.graph.node_weight(node).copied()
+    }
+}
+
+impl<G: TileGenerator> Map<G> {
+    /// Converts the map for use with petgraph's algorithms, such as minimum
+    /// spanning trees or centrality measures. Nodes are added in x-then-y
+    /// order, so the same map always gives the same indices.
+    pub fn to_graph(&self) -> MapGraph {
+        let mut graph = UnGraph::with_capacity(self.tiles.len(), self.tiles.len() * 2);
+        let mut nodes = HashMap::with_capacity(self.tiles.len());
+        for position in self.positions() {
+            if self.tiles.contains_key(&position) {
+                nodes.insert(position, graph.add_node(position));
+            }
+        }
+        for position in self.positions() {
+            for direction in [Direction::North, Direction::East] {
+                let neighbor = self.adjacent(position, direction);
+                if self.can_move(position, neighbor) {
+                    graph.add_edge(nodes[&position], nodes[&neighbor], 1);
+                }
+            }
+        }
+        MapGraph { graph, nodes }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile_generator::TileGeneratorDefault;
+    use petgraph::algo::{connected_components, dijkstra};
+
+    #[test]
+    fn graphs_match_the_map() {
+        let map = Map::new(12, TileGeneratorDefault::with_seed(8));
+
+        let map_graph = map.to_graph();
+
+        let stats = map.stats();
+        assert_eq!(map_graph.graph.node_count(), 144);
+        assert_eq!(map_graph.graph.edge_count(), stats.connections);
+        assert_eq!(
+            connected_components(&map_graph.graph),
+            stats.connected_areas + stats.closed_tiles
+        );
+    }
+
+    #[test]
+    fn graph_distances_match_paths() {
+        let map = Map::new(10, TileGeneratorDefault::with_seed(3));
+        let map_graph = map.to_graph();
+        let start = IVec2::new(4, 4);
+
+        let distances = dijkstra(
+            &map_graph.graph,
+            map_graph.node(start).unwrap(),
+            None,
+            |edge| *edge.weight(),
+        );
+
+        for (node, distance) in distances {
+            let position = map_graph.position(node).unwrap();
+            let path = map.find_path(start, position).unwrap();
+            assert_eq!(path.len() as u32 - 1, distance);
+        }
+    }
+}
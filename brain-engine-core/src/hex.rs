@@ -0,0 +1,476 @@
+//! Maps on a hexagonal grid: six [`HexDirection`]s per tile instead of the
+//! four of [`Direction`](crate::Direction), with exits stored in a
+//! [`HexMapTile`] the way [`MapTile`](crate::MapTile) stores them.
+//!
+//! Positions are offset coordinates in rows, every odd row moved half a
+//! tile east, which is how [`ScreenProjection::HexPointyTop`] draws them.
+//!
+//! ```
+//! use bevy::prelude::*;
+//! use brain_engine_core::{HexDirection, HexMap, TileGeneratorDefault};
+//!
+//! let map = HexMap::new(8, 6, TileGeneratorDefault::with_seed(4));
+//! assert_eq!(map.adjacent(IVec2::new(2, 1), HexDirection::NorthEast), IVec2::new(3, 2));
+//! assert!(map.validate());
+//! ```
+//!
+//! [`ScreenProjection::HexPointyTop`]: crate::ScreenProjection::HexPointyTop
+
+use crate::map_tile::TileSet;
+use crate::open_field::OpenFieldGenerator;
+use crate::tile_generator::TileGeneratorDefault;
+
+use bevy::prelude::*;
+use itertools::iproduct;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+/// A step to one of the six neighbors of a hex tile, counter-clockwise
+/// from east. The values are the exit bits of [`HexMapTile`].
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum HexDirection {
+    East = 1,
+    NorthEast = 2,
+    NorthWest = 4,
+    West = 8,
+    SouthWest = 16,
+    SouthEast = 32,
+}
+
+impl fmt::Display for HexDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HexDirection::East => write!(f, "East"),
+            HexDirection::NorthEast => write!(f, "NorthEast"),
+            HexDirection::NorthWest => write!(f, "NorthWest"),
+            HexDirection::West => write!(f, "West"),
+            HexDirection::SouthWest => write!(f, "SouthWest"),
+            HexDirection::SouthEast => write!(f, "SouthEast"),
+        }
+    }
+}
+
+impl HexDirection {
+    pub const fn all() -> [HexDirection; 6] {
+        [
+            HexDirection::East,
+            HexDirection::NorthEast,
+            HexDirection::NorthWest,
+            HexDirection::West,
+            HexDirection::SouthWest,
+            HexDirection::SouthEast,
+        ]
+    }
+
+    pub const fn opposite(self) -> HexDirection {
+        self.rotate_counter_clockwise_by(3)
+    }
+
+    pub const fn rotate_clockwise(self) -> HexDirection {
+        self.rotate_counter_clockwise_by(5)
+    }
+
+    pub const fn rotate_counter_clockwise(self) -> HexDirection {
+        self.rotate_counter_clockwise_by(1)
+    }
+
+    const fn rotate_counter_clockwise_by(self, steps: u32) -> HexDirection {
+        let index = (self as u8).trailing_zeros() + steps;
+        HexDirection::all()[(index % 6) as usize]
+    }
+
+    /// The step from `position` towards the direction. Rows alternate, so
+    /// the diagonal steps depend on whether `position` is on an odd row.
+    pub const fn offset(self, position: IVec2) -> IVec2 {
+        let odd = position.y.rem_euclid(2);
+        match self {
+            HexDirection::East => IVec2::new(1, 0),
+            HexDirection::NorthEast => IVec2::new(odd, 1),
+            HexDirection::NorthWest => IVec2::new(odd - 1, 1),
+            HexDirection::West => IVec2::new(-1, 0),
+            HexDirection::SouthWest => IVec2::new(odd - 1, -1),
+            HexDirection::SouthEast => IVec2::new(odd, -1),
+        }
+    }
+
+    /// Inverse of [`HexDirection::offset`]; `None` unless `to` is next to
+    /// `from`.
+    pub fn between(from: IVec2, to: IVec2) -> Option<HexDirection> {
+        HexDirection::all()
+            .into_iter()
+            .find(|direction| from + direction.offset(from) == to)
+    }
+}
+
+/// The exits of a hex tile, one bit per [`HexDirection`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct HexMapTile(u8);
+
+impl HexMapTile {
+    pub const ZERO: HexMapTile = HexMapTile(0);
+    pub const ALL: HexMapTile = HexMapTile(0b11_1111);
+
+    /// Builds a tile from 0-6 directions. Returns None if the slice
+    /// contains duplicate directions.
+    pub fn from_directions(directions: &[HexDirection]) -> Option<HexMapTile> {
+        let map_tile = directions
+            .iter()
+            .copied()
+            .fold(HexMapTile::ZERO, HexMapTile::with);
+        (map_tile.len() == directions.len()).then_some(map_tile)
+    }
+
+    /// The tile whose exit mask is `bits`. Returns None above 63.
+    pub const fn from_bits(bits: u8) -> Option<HexMapTile> {
+        if bits > Self::ALL.0 {
+            None
+        } else {
+            Some(HexMapTile(bits))
+        }
+    }
+
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub const fn contains(self, direction: HexDirection) -> bool {
+        self.0 & direction as u8 != 0
+    }
+
+    pub const fn with(self, direction: HexDirection) -> HexMapTile {
+        HexMapTile(self.0 | direction as u8)
+    }
+
+    pub const fn without(self, direction: HexDirection) -> HexMapTile {
+        HexMapTile(self.0 & !(direction as u8))
+    }
+
+    /// The number of exits.
+    pub const fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Turns every exit one direction clockwise.
+    pub const fn rotate_clockwise(self) -> HexMapTile {
+        HexMapTile((self.0 >> 1 | self.0 << 5) & Self::ALL.0)
+    }
+
+    /// Turns every exit one direction counter-clockwise.
+    pub const fn rotate_counter_clockwise(self) -> HexMapTile {
+        HexMapTile((self.0 << 1 | self.0 >> 5) & Self::ALL.0)
+    }
+
+    /// The directions the tile has exits towards, counter-clockwise from
+    /// east.
+    pub fn directions(self) -> Vec<HexDirection> {
+        HexDirection::all()
+            .into_iter()
+            .filter(|direction| self.contains(*direction))
+            .collect()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct HexTile {
+    pub tile_set: TileSet,
+    pub map_tile: HexMapTile,
+}
+
+impl HexTile {
+    pub fn new(tile_set: TileSet, map_tile: HexMapTile) -> Self {
+        Self { tile_set, map_tile }
+    }
+}
+
+/// Generates the tiles of a [`HexMap`], like [`crate::TileGenerator`] does
+/// for square maps.
+pub trait HexTileGenerator {
+    /// Generates the tile at `location`, given the tiles generated so far.
+    /// Exits must match the neighbors in `tiles` and stay inside `bounds`,
+    /// whose corners are both inclusive.
+    fn hex_tile_at(
+        &self,
+        tiles: &HashMap<IVec2, HexTile>,
+        location: IVec2,
+        bounds: IRect,
+    ) -> HexTile;
+}
+
+/// Opens each exit towards an ungenerated neighbor with
+/// `tile_exit_probability` and picks the tile set with `room_probability`,
+/// as on square maps. Weights don't apply to hex tiles.
+impl HexTileGenerator for TileGeneratorDefault {
+    fn hex_tile_at(
+        &self,
+        tiles: &HashMap<IVec2, HexTile>,
+        location: IVec2,
+        bounds: IRect,
+    ) -> HexTile {
+        let (open, tile_set) = self.draw_exits(location, HexDirection::all().len());
+        let map_tile = HexDirection::all()
+            .into_iter()
+            .zip(open)
+            .filter(|(direction, open)| {
+                let neighbor = location + direction.offset(location);
+                match tiles.get(&neighbor) {
+                    Some(tile) => tile.map_tile.contains(direction.opposite()),
+                    None => *open && bounds.contains(neighbor),
+                }
+            })
+            .fold(HexMapTile::ZERO, |map_tile, (direction, _)| {
+                map_tile.with(direction)
+            });
+        HexTile::new(tile_set, map_tile)
+    }
+}
+
+/// Opens every exit that stays on the map.
+impl HexTileGenerator for OpenFieldGenerator {
+    fn hex_tile_at(
+        &self,
+        _tiles: &HashMap<IVec2, HexTile>,
+        location: IVec2,
+        bounds: IRect,
+    ) -> HexTile {
+        let map_tile = HexDirection::all()
+            .into_iter()
+            .filter(|direction| bounds.contains(location + direction.offset(location)))
+            .fold(HexMapTile::ZERO, HexMapTile::with);
+        HexTile::new(TileSet::Field, map_tile)
+    }
+}
+
+/// A map of `x` by `y` hex tiles, generated in x-then-y order like
+/// [`crate::Map`].
+#[derive(Resource)]
+pub struct HexMap<G: HexTileGenerator> {
+    pub x: usize,
+    pub y: usize,
+    pub tiles: HashMap<IVec2, HexTile>,
+    pub generator: G,
+}
+
+impl<G: HexTileGenerator> HexMap<G> {
+    pub fn new(x: usize, y: usize, generator: G) -> Self {
+        let mut map = Self {
+            x,
+            y,
+            tiles: HashMap::with_capacity(x * y),
+            generator,
+        };
+        map.regenerate();
+        map
+    }
+
+    /// Rerolls every tile in place, keeping the dimensions and generator.
+    pub fn regenerate(&mut self) {
+        self.tiles.clear();
+        let bounds = self.bounds();
+        for position in self.positions() {
+            let tile = self.generator.hex_tile_at(&self.tiles, position, bounds);
+            self.tiles.insert(position, tile);
+        }
+    }
+
+    /// Returns every tile position in x-then-y order.
+    pub fn positions(&self) -> impl Iterator<Item = IVec2> + use<G> {
+        iproduct!(0..self.x as i32, 0..self.y as i32).map(|(x, y)| IVec2::new(x, y))
+    }
+
+    /// The map grid as a rectangle with both corners inclusive.
+    pub fn bounds(&self) -> IRect {
+        IRect::new(0, 0, self.x as i32 - 1, self.y as i32 - 1)
+    }
+
+    /// Returns true when `position` lies on the map grid.
+    pub fn in_bounds(&self, position: IVec2) -> bool {
+        position.cmpge(IVec2::ZERO).all()
+            && position
+                .cmplt(IVec2::new(self.x as i32, self.y as i32))
+                .all()
+    }
+
+    /// The position one step from `position` towards `direction`. It may be
+    /// off the map.
+    pub fn adjacent(&self, position: IVec2, direction: HexDirection) -> IVec2 {
+        position + direction.offset(position)
+    }
+
+    /// Returns true when `from` and `to` are neighbors on the map with exits
+    /// towards each other.
+    pub fn can_move(&self, from: IVec2, to: IVec2) -> bool {
+        let Some(direction) = HexDirection::between(from, to) else {
+            return false;
+        };
+        let (Some(from_tile), Some(to_tile)) = (self.tiles.get(&from), self.tiles.get(&to)) else {
+            return false;
+        };
+        from_tile.map_tile.contains(direction) && to_tile.map_tile.contains(direction.opposite())
+    }
+
+    /// Returns the positions [`HexMap::can_move`] allows stepping to from
+    /// `position`, counter-clockwise from east.
+    pub fn neighbors(&self, position: IVec2) -> impl Iterator<Item = IVec2> + '_ {
+        HexDirection::all()
+            .into_iter()
+            .map(move |direction| self.adjacent(position, direction))
+            .filter(move |neighbor| self.can_move(position, *neighbor))
+    }
+
+    /// The fewest steps between `from` and `to` on an open hex grid.
+    pub fn distance(&self, from: IVec2, to: IVec2) -> u32 {
+        let axial = |position: IVec2| IVec2::new(position.x - position.y.div_euclid(2), position.y);
+        let difference = axial(to) - axial(from);
+        ((difference.x.abs() + difference.y.abs() + (difference.x + difference.y).abs()) / 2) as u32
+    }
+
+    /// A shortest walk from `from` to `to`, both included, or `None` when
+    /// `to` can't be reached.
+    pub fn find_path(&self, from: IVec2, to: IVec2) -> Option<Vec<IVec2>> {
+        if !self.in_bounds(from) || !self.in_bounds(to) {
+            return None;
+        }
+        let mut came_from = HashMap::from([(from, from)]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(current) = queue.pop_front() {
+            if current == to {
+                let mut path = vec![to];
+                while *path.last().unwrap() != from {
+                    path.push(came_from[path.last().unwrap()]);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for neighbor in self.neighbors(current) {
+                if let Entry::Vacant(entry) = came_from.entry(neighbor) {
+                    entry.insert(current);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns every position that can be walked to from `position`,
+    /// including `position` itself. Empty when `position` is off the map.
+    pub fn reachable_from(&self, position: IVec2) -> HashSet<IVec2> {
+        if !self.in_bounds(position) {
+            return HashSet::new();
+        }
+        let mut reached = HashSet::from([position]);
+        let mut queue = VecDeque::from([position]);
+        while let Some(current) = queue.pop_front() {
+            for neighbor in self.neighbors(current) {
+                if reached.insert(neighbor) {
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        reached
+    }
+
+    /// Returns true when every tile of the grid is generated, every exit
+    /// stays on the map and every exit is matched by one back.
+    pub fn validate(&self) -> bool {
+        self.positions().all(|position| {
+            let Some(tile) = self.tiles.get(&position) else {
+                return false;
+            };
+            tile.map_tile.directions().into_iter().all(|direction| {
+                let neighbor = self.adjacent(position, direction);
+                self.tiles
+                    .get(&neighbor)
+                    .is_some_and(|other| other.map_tile.contains(direction.opposite()))
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::screen::{Screen, ScreenProjection};
+
+    #[test]
+    fn directions_step_back_with_their_opposite() {
+        for position in [IVec2::new(2, 2), IVec2::new(2, 3), IVec2::new(-1, -1)] {
+            for direction in HexDirection::all() {
+                let neighbor = position + direction.offset(position);
+                assert_eq!(neighbor + direction.opposite().offset(neighbor), position);
+                assert_eq!(HexDirection::between(position, neighbor), Some(direction));
+            }
+        }
+        assert_eq!(
+            HexDirection::SouthEast.rotate_counter_clockwise(),
+            HexDirection::East
+        );
+        assert_eq!(
+            HexDirection::East.rotate_clockwise(),
+            HexDirection::SouthEast
+        );
+    }
+
+    #[test]
+    fn neighbors_are_one_tile_away_on_screen() {
+        let screen =
+            Screen::new(UVec2::new(6, 6), 32.0).with_projection(ScreenProjection::HexPointyTop);
+        for position in [IVec2::new(2, 2), IVec2::new(2, 3)] {
+            let center = screen.pixel_position(position);
+            for direction in HexDirection::all() {
+                let neighbor = screen.pixel_position(position + direction.offset(position));
+                assert!(
+                    (center.distance(neighbor) - 32.0).abs() < 0.001,
+                    "{direction}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn exits_rotate_and_round_trip() {
+        let map_tile =
+            HexMapTile::from_directions(&[HexDirection::East, HexDirection::SouthEast]).unwrap();
+
+        assert_eq!(map_tile.bits(), 0b10_0001);
+        assert_eq!(
+            map_tile.rotate_counter_clockwise().directions(),
+            [HexDirection::East, HexDirection::NorthEast]
+        );
+        assert_eq!(
+            map_tile.rotate_clockwise().rotate_counter_clockwise(),
+            map_tile
+        );
+        assert_eq!(
+            HexMapTile::from_directions(&[HexDirection::West, HexDirection::West]),
+            None
+        );
+        assert_eq!(HexMapTile::from_bits(64), None);
+    }
+
+    #[test]
+    fn generated_maps_are_consistent_and_walkable() {
+        for seed in 0..8 {
+            let map = HexMap::new(12, 9, TileGeneratorDefault::with_seed(seed));
+            assert!(map.validate());
+            for from in map.positions() {
+                for to in map.neighbors(from) {
+                    assert!(map.can_move(to, from));
+                }
+            }
+        }
+
+        let field = HexMap::new(7, 5, OpenFieldGenerator);
+        let (from, to) = (IVec2::new(0, 0), IVec2::new(6, 4));
+        assert!(field.validate());
+        assert_eq!(field.reachable_from(from).len(), 7 * 5);
+        let path = field.find_path(from, to).unwrap();
+        assert_eq!(path.len() as u32 - 1, field.distance(from, to));
+        assert_eq!(field.distance(from, to), 8);
+    }
+}
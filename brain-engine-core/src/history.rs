@@ -0,0 +1,256 @@
+//! Undo and redo for level editors.
+//!
+//! ```
+//! use bevy::prelude::*;
+//! use brain_engine_core::{Map, MapHistory, OpenFieldGenerator};
+//!
+//! let mut history = MapHistory::new(Map::new(8, OpenFieldGenerator));
+//! history.edit(|map| map.place_obstacle(IVec2::new(2, 3)));
+//! assert!(history.undo());
+//! assert!(!history.map().is_obstacle(IVec2::new(2, 3)));
+//! ```
+
+use crate::diff::MapDiff;
+use crate::map::Map;
+use crate::tile_generator::TileGenerator;
+use crate::tile_grid::TileGrid;
+
+use bevy::prelude::*;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+/// One recorded edit: the diffs that revert it and that replay it.
+#[derive(Debug, Clone)]
+struct Edit {
+    undo: MapDiff,
+    redo: MapDiff,
+}
+
+/// The tiles and dimensions of a map at one moment.
+#[derive(Debug, Clone)]
+struct Snapshot {
+    x: usize,
+    y: usize,
+    tiles: TileGrid,
+}
+
+/// Owns a [`Map`] and records every edit made through [`MapHistory::edit`],
+/// so edits can be undone and redone.
+///
+/// Each call to `edit` is one undo step however many tiles it changes, so a
+/// whole brush stroke or a batch of mutations is undone at once. Only tiles
+/// and dimensions are recorded; the generator and the topology are left as
+/// they are.
+pub struct MapHistory<G: TileGenerator> {
+    map: Map<G>,
+    undo: VecDeque<Edit>,
+    redo: Vec<Edit>,
+    snapshots: HashMap<String, Snapshot>,
+    limit: Option<usize>,
+}
+
+impl<G: TileGenerator> MapHistory<G> {
+    /// Starts an empty history on `map`, keeping every edit.
+    pub fn new(map: Map<G>) -> Self {
+        Self {
+            map,
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            snapshots: HashMap::new(),
+            limit: None,
+        }
+    }
+
+    /// Keeps at most `limit` undo steps, forgetting the oldest first.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self.forget_oldest();
+        self
+    }
+
+    pub fn map(&self) -> &Map<G> {
+        &self.map
+    }
+
+    pub fn into_map(self) -> Map<G> {
+        self.map
+    }
+
+    /// Runs `mutate` on the map and records the tiles it changed as one undo
+    /// step, clearing the redo steps. Edits that change nothing are not
+    /// recorded.
+    pub fn edit<R>(&mut self, mutate: impl FnOnce(&mut Map<G>) -> R) -> R {
+        let before = Snapshot {
+            x: self.map.x,
+            y: self.map.y,
+            tiles: self.map.tiles.clone(),
+        };
+        let result = mutate(&mut self.map);
+        self.record(before);
+        result
+    }
+
+    /// Reverts the latest edit. Returns false when there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(edit) = self.undo.pop_back() else {
+            return false;
+        };
+        self.map.apply(&edit.undo);
+        self.redo.push(edit);
+        true
+    }
+
+    /// Replays the latest undone edit. Returns false when there is nothing to
+    /// redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(edit) = self.redo.pop() else {
+            return false;
+        };
+        self.map.apply(&edit.redo);
+        self.undo.push_back(edit);
+        true
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Saves the current tiles under `name`, replacing any snapshot already
+    /// saved under it.
+    pub fn snapshot(&mut self, name: impl Into<String>) {
+        let snapshot = Snapshot {
+            x: self.map.x,
+            y: self.map.y,
+            tiles: self.map.tiles.clone(),
+        };
+        self.snapshots.insert(name.into(), snapshot);
+    }
+
+    /// Puts back the tiles saved by [`MapHistory::snapshot`], as one edit
+    /// that can be undone. Returns false when no snapshot has this name.
+    pub fn restore(&mut self, name: &str) -> bool {
+        let Some(snapshot) = self.snapshots.get(name).cloned() else {
+            return false;
+        };
+        self.edit(|map| {
+            map.size = snapshot.x;
+            map.x = snapshot.x;
+            map.y = snapshot.y;
+            map.tiles = snapshot.tiles;
+        });
+        true
+    }
+
+    /// Forgets every undo and redo step, keeping the snapshots.
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+
+    fn record(&mut self, before: Snapshot) {
+        let positions: BTreeSet<(i32, i32)> = before
+            .tiles
+            .keys()
+            .chain(self.map.tiles.keys())
+            .map(|position| (position.x, position.y))
+            .collect();
+        let mut undo = Vec::new();
+        let mut redo = Vec::new();
+        for (x, y) in positions {
+            let position = IVec2::new(x, y);
+            let old = before.tiles.get(&position).copied();
+            let new = self.map.tiles.get(&position).copied();
+            if old != new {
+                undo.push((position, old));
+                redo.push((position, new));
+            }
+        }
+        let resized = (before.x, before.y) != (self.map.x, self.map.y);
+        if undo.is_empty() && !resized {
+            return;
+        }
+        self.undo.push_back(Edit {
+            undo: MapDiff {
+                x: before.x,
+                y: before.y,
+                changes: undo,
+            },
+            redo: MapDiff {
+                x: self.map.x,
+                y: self.map.y,
+                changes: redo,
+            },
+        });
+        self.forget_oldest();
+        self.redo.clear();
+    }
+
+    fn forget_oldest(&mut self) {
+        let limit = self.limit.unwrap_or(usize::MAX);
+        while self.undo.len() > limit {
+            self.undo.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_tile::{MapTile, Tile, TileSet};
+    use crate::open_field::OpenFieldGenerator;
+    use crate::tile_generator::TileGeneratorDefault;
+
+    #[test]
+    fn edits_undo_and_redo_as_one_step() {
+        let original = Map::new(8, TileGeneratorDefault::with_seed(3));
+        let mut history = MapHistory::new(Map::new(8, TileGeneratorDefault::with_seed(3)));
+
+        let room = Tile::new(TileSet::Room, MapTile::NESW);
+        let stroke = history.edit(|map| {
+            map.tiles.remove(&IVec2::new(7, 7));
+            map.paint_rect(IRect::new(1, 1, 3, 3), room)
+        });
+        let edited = history.map().tiles.clone();
+
+        assert!(!stroke.is_empty());
+        assert!(history.undo());
+        assert_eq!(history.map().tiles, original.tiles);
+        assert!(!history.undo());
+        assert!(history.redo());
+        assert_eq!(history.map().tiles, edited);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn new_edits_clear_redo_and_respect_the_limit() {
+        let mut history = MapHistory::new(Map::new(6, OpenFieldGenerator)).with_limit(2);
+
+        for x in 0..3 {
+            history.edit(|map| map.place_obstacle(IVec2::new(x, 0)));
+        }
+        history.edit(|map| map.place_obstacle(IVec2::new(0, 0)));
+        assert!(history.undo());
+        assert!(history.undo());
+        assert!(!history.undo());
+        assert!(history.map().is_obstacle(IVec2::new(0, 0)));
+
+        history.edit(|map| map.place_obstacle(IVec2::new(5, 5)));
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn snapshots_restore_as_an_undoable_edit() {
+        let mut history = MapHistory::new(Map::new(6, OpenFieldGenerator));
+        history.snapshot("empty");
+        history.edit(|map| map.place_obstacle(IVec2::new(2, 2)));
+
+        assert!(history.restore("empty"));
+        assert!(!history.map().is_obstacle(IVec2::new(2, 2)));
+        assert!(history.undo());
+        assert!(history.map().is_obstacle(IVec2::new(2, 2)));
+        assert!(!history.restore("missing"));
+    }
+}
@@ -0,0 +1,161 @@
+//! Difficulty by dungeon depth: [`LevelParams`] describe how big and how
+//! tangled a level is, and the built-in generators scale to them.
+//!
+//! ```
+//! use brain_engine_core::{ClusteredRoomGenerator, LevelParams, Map};
+//!
+//! let level = LevelParams::for_depth(6);
+//! let map = Map::new(level.map_size(), ClusteredRoomGenerator::new(3).with_level(level));
+//! assert!(map.detect_rooms().len() >= level.target_room_count as usize);
+//! ```
+
+use crate::clustered_rooms::ClusteredRoomGenerator;
+use crate::maze::TileGeneratorMaze;
+use crate::tile_generator::TileGeneratorBuilder;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Side length of the square a room of up to 6 tiles needs, with the
+/// margin [`ClusteredRoomGenerator`] keeps around it.
+const ROOM_CELL_SIZE: usize = 8;
+
+/// The shape of a dungeon level, usually from [`LevelParams::for_depth`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LevelParams {
+    /// How far down the level is, 0 for the first floor.
+    pub depth: u32,
+    /// How many rooms the level should have at least, which sets
+    /// [`LevelParams::map_size`].
+    pub target_room_count: u32,
+    /// How many loops to add, from 0.0 for a single route between any two
+    /// places to 1.0 for as many as the generator makes.
+    pub loop_factor: f64,
+    /// The share of dead ends to keep, from 0.0 for none to 1.0 for all
+    /// the generator makes.
+    pub dead_end_tolerance: f64,
+}
+
+impl Default for LevelParams {
+    fn default() -> Self {
+        Self::for_depth(0)
+    }
+}
+
+impl LevelParams {
+    /// The default progression: 4 rooms on the first floor and one more per
+    /// floor below, 0.1 more loops per floor up to 0.6, and 0.1 fewer dead
+    /// ends per floor down to 0.2.
+    pub fn for_depth(depth: u32) -> Self {
+        Self {
+            depth,
+            target_room_count: 4 + depth,
+            loop_factor: (0.1 * depth as f64).min(0.6),
+            dead_end_tolerance: (1.0 - 0.1 * depth as f64).max(0.2),
+        }
+    }
+
+    /// The side length of a square map with room for
+    /// [`LevelParams::target_room_count`] rooms of up to 6 tiles.
+    pub fn map_size(&self) -> usize {
+        let rooms_per_side = (self.target_room_count.max(1) as f64).sqrt().ceil() as usize;
+        rooms_per_side * ROOM_CELL_SIZE
+    }
+
+    fn clamped_loop_factor(&self) -> f64 {
+        self.loop_factor.clamp(0.0, 1.0)
+    }
+
+    fn clamped_dead_end_tolerance(&self) -> f64 {
+        self.dead_end_tolerance.clamp(0.0, 1.0)
+    }
+}
+
+impl TileGeneratorBuilder {
+    /// Opens more exits the higher the [`LevelParams::loop_factor`], from
+    /// the default 0.35 up to 0.75, and weighs dead ends by the
+    /// [`LevelParams::dead_end_tolerance`].
+    pub fn level(self, level: LevelParams) -> Self {
+        self.tile_exit_probability(0.35 + 0.4 * level.clamped_loop_factor())
+            .exit_count_weight(1, level.clamped_dead_end_tolerance())
+    }
+}
+
+impl TileGeneratorMaze {
+    /// Braids away the dead ends the [`LevelParams::dead_end_tolerance`]
+    /// doesn't keep, or at least the [`LevelParams::loop_factor`] of them,
+    /// as braiding is what adds loops to a maze.
+    pub fn with_level(self, level: LevelParams) -> Self {
+        self.with_braid(
+            level
+                .clamped_loop_factor()
+                .max(1.0 - level.clamped_dead_end_tolerance()),
+        )
+    }
+}
+
+impl ClusteredRoomGenerator {
+    /// Places rooms of 3 to 6 tiles within a map of
+    /// [`LevelParams::map_size`], so the map holds at least
+    /// [`LevelParams::target_room_count`] connected rooms.
+    pub fn with_level(self, level: LevelParams) -> Self {
+        self.with_room_size(3, 6)
+            .with_bounds(UVec2::splat(level.map_size() as u32))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Map;
+    use crate::tile_generator::TileGeneratorDefault;
+
+    #[test]
+    fn deeper_levels_are_bigger_and_loopier() {
+        let (first, deep) = (LevelParams::for_depth(0), LevelParams::for_depth(9));
+
+        assert!(deep.map_size() > first.map_size());
+        assert!(deep.loop_factor > first.loop_factor);
+        assert!(deep.dead_end_tolerance < first.dead_end_tolerance);
+        assert_eq!(LevelParams::for_depth(40).dead_end_tolerance, 0.2);
+        assert_eq!(LevelParams::default(), first);
+    }
+
+    #[test]
+    fn clustered_rooms_fill_the_level() {
+        for depth in [0, 3, 12] {
+            let level = LevelParams::for_depth(depth);
+            let map = Map::new(
+                level.map_size(),
+                ClusteredRoomGenerator::new(8).with_level(level),
+            );
+
+            assert!(map.detect_rooms().len() >= level.target_room_count as usize);
+            assert_eq!(map.stats().connectivity(), 1.0);
+        }
+    }
+
+    #[test]
+    fn deeper_mazes_have_fewer_dead_ends() {
+        let dead_ends = |depth| {
+            let generator = TileGeneratorMaze::new(5).with_level(LevelParams::for_depth(depth));
+            Map::new(16, generator).dead_ends().len()
+        };
+
+        assert!(dead_ends(8) < dead_ends(0));
+    }
+
+    #[test]
+    fn deeper_default_maps_have_more_connections() {
+        let connections = |depth| {
+            let generator = TileGeneratorDefault::builder()
+                .seed(2)
+                .level(LevelParams::for_depth(depth))
+                .build();
+            Map::new(24, generator).stats().connections
+        };
+
+        assert!(connections(6) > connections(0));
+    }
+}
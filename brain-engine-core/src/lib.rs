@@ -2,14 +2,139 @@
 //!
 //! This library provides map generation functionality with configurable tile generators.
 //! It can be used standalone or integrated with Bevy game engine.
+//!
+//! `use brain_engine_core::prelude::*;` imports the commonly used types.
 
+pub mod adjacency;
+pub mod agent;
+pub mod ascii;
+pub mod binary_map;
+pub mod biome;
+pub mod brush;
+pub mod checkpoint;
+pub mod chunked_map;
+pub mod clustered_rooms;
+pub mod composite;
+pub mod config;
+pub mod dead_ends;
+pub mod deadline;
+pub mod diff;
+pub mod direction_set;
+pub mod drunkard;
+pub mod exploration;
+pub mod features;
+pub mod generation_context;
+pub mod generation_observer;
+pub mod generation_trace;
+#[cfg(feature = "graph")]
+pub mod graph;
+pub mod hex;
+pub mod history;
+pub mod level;
+pub mod loops;
 pub mod map;
+pub mod map_builder;
+pub mod map_events;
+pub mod map_file;
 pub mod map_tile;
+pub mod maze;
+pub mod migration;
+pub mod movement;
+pub mod net;
+pub mod occupancy;
+pub mod open_field;
+pub mod parallel_generation;
+pub mod pathfinding;
+pub mod pipeline;
+pub mod prefab;
+pub mod prelude;
+#[cfg(feature = "image")]
+pub mod raster;
+pub mod recording;
+pub mod region_graph;
+pub mod render;
+pub mod resize;
+pub mod room_templates;
+pub mod rooms;
 pub mod screen;
+pub mod share_code;
+pub mod shared_chunked_map;
+pub mod simulation;
+pub mod spawn;
+pub mod stats;
+pub mod straighten;
+pub mod symmetry;
+pub mod testing;
+pub mod texture;
 pub mod tile_generator;
+pub mod tile_grid;
+pub mod topology;
+pub mod validation;
+pub mod visibility;
+pub mod wall_geometry;
 
 // Re-export commonly used types for convenience
-pub use map::Map;
-pub use map_tile::{Direction, MapTile, Tile, TileSet};
-pub use screen::Screen;
-pub use tile_generator::{TileGenerator, TileGeneratorDefault};
+pub use adjacency::{
+    NEIGHBOR8_OFFSETS, is_adjacent8, is_diagonally_adjacent, is_orthogonally_adjacent,
+};
+pub use agent::Agent;
+pub use ascii::{CORRIDOR_GLYPHS, FIELD_GLYPH, OBSTACLE_GLYPH, ROOM_GLYPHS, glyph};
+pub use binary_map::{BINARY_MAP_VERSION, BinaryMapError};
+pub use biome::{BiomeAssigner, BiomeGenerator};
+pub use checkpoint::{CheckpointError, Checkpoints, FastTravel};
+pub use chunked_map::ChunkedMap;
+pub use clustered_rooms::ClusteredRoomGenerator;
+pub use composite::CompositeGenerator;
+pub use config::{GeneratorConfig, GeneratorConfigError, GeneratorType, PipelinePass};
+pub use deadline::{GenerationPass, GenerationReport};
+pub use diff::MapDiff;
+pub use direction_set::DirectionSet;
+pub use drunkard::TileGeneratorDrunkard;
+pub use exploration::{Exploration, ExplorationState};
+pub use features::{FeatureLayer, FeaturePlacer};
+pub use generation_context::GenerationContext;
+pub use generation_observer::{GenerationObserver, GenerationRecorder};
+pub use generation_trace::{Decision, GenerationTrace, Roll};
+#[cfg(feature = "graph")]
+pub use graph::MapGraph;
+pub use hex::{HexDirection, HexMap, HexMapTile, HexTile, HexTileGenerator};
+pub use history::MapHistory;
+pub use level::LevelParams;
+pub use map::{DynMap, Map};
+pub use map_builder::{BuiltMap, DEFAULT_MAX_ATTEMPTS, MapBuilder, MapBuilderError};
+pub use map_events::{MapEvent, MapSubscribers};
+pub use map_file::{MAP_FILE_VERSION, MapFile, MapFileError, TileExtension, TileExtensions};
+pub use map_tile::{Biome, Direction, MapTile, ParseTileError, Tile, TileSet};
+pub use maze::{MazeAlgorithm, TileGeneratorMaze};
+pub use migration::{MapMigrations, MapMigrator, MigrationError, VersionedMap};
+pub use movement::{MovementCosts, MovementSpeeds};
+pub use net::{MoveAck, MoveInput, MovePredictor};
+pub use occupancy::{Occupancy, OccupyError};
+pub use open_field::OpenFieldGenerator;
+pub use pathfinding::{IncrementalPathfinder, PathfindingStatus};
+pub use pipeline::{
+    AddLoops, EnsureConnected, GeneratorPipeline, MapTransform, PruneDeadEnds, StraightenCorridors,
+};
+pub use prefab::{KEEP_GLYPH, Prefab, PrefabError};
+pub use recording::{MovementRecorder, MovementRecording, RecordedMove, ReplayError};
+pub use region_graph::{
+    Region, RegionConnection, RegionExit, RegionGraph, RegionKind, RegionLabels, tile_to_json,
+};
+pub use render::{Annotation, GridStyle, RenderColor, RenderStyle};
+pub use room_templates::{RoomMarker, RoomTemplate, RoomTemplates};
+pub use rooms::RoomArea;
+pub use screen::{Screen, ScreenAnchor, ScreenPadding, ScreenProjection};
+pub use share_code::{MAX_SHARE_CODE_SIZE, ShareCodeError, ShareRegistry, ShareableGenerator};
+pub use shared_chunked_map::SharedChunkedMap;
+pub use simulation::{Action, MoveOutcome, Simulation};
+pub use spawn::{Spawn, SpawnPlanner, SpawnRule};
+pub use stats::MapStats;
+pub use symmetry::{SymmetricGenerator, Symmetry};
+pub use texture::{FileNameResolver, TextureResolver, VariantResolver};
+pub use tile_generator::{
+    BoxedTileGenerator, RandomDrawMode, TileGenerator, TileGeneratorBuilder, TileGeneratorDefault,
+};
+pub use tile_grid::TileGrid;
+pub use topology::Topology;
+pub use validation::MapViolation;
+pub use wall_geometry::{WallGeometry, WallSegment};
@@ -0,0 +1,240 @@
+use crate::map::Map;
+use crate::map_tile::{Direction, MapTile};
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+
+impl<G: TileGenerator> Map<G> {
+    /// Adds cycles to tree-like maps such as perfect mazes: joins up to
+    /// `count` pairs of neighboring tiles that aren't joined but can reach
+    /// each other the long way round, always the pair whose way round is
+    /// longest at that point, so each loop cuts the biggest detour left.
+    ///
+    /// The way round is measured along a breadth-first spanning tree of the
+    /// map, rebuilt after every loop. On tree-like maps that is the only way
+    /// round; on maps that already have cycles it may be longer than the
+    /// shortest one.
+    ///
+    /// Both tiles of a pair need an exit already, so walls and obstacles
+    /// stay closed, and tiles that can't reach each other are never joined.
+    /// Ties go to the pair with the westernmost, then southernmost tile.
+    /// Returns the number of loops added, fewer than `count` once no pairs
+    /// are left.
+    pub fn add_loops(&mut self, count: usize) -> usize {
+        // Detours mostly get shorter as loops are added, so a stored detour
+        // is treated as an upper bound: the best pair is the first whose
+        // detour is still as long when measured again.
+        let mut forest = SpanningForest::new(self);
+        let mut candidates = BinaryHeap::new();
+        for position in self.positions() {
+            for (index, direction) in [Direction::East, Direction::North].into_iter().enumerate() {
+                let Some(neighbor) = self.loop_partner(position, direction) else {
+                    continue;
+                };
+                if let Some(detour) = forest.path_length(position, neighbor) {
+                    candidates.push((detour, Reverse((position.x, position.y, index))));
+                }
+            }
+        }
+
+        let mut added = 0;
+        while added < count {
+            let Some((detour, Reverse((x, y, index)))) = candidates.pop() else {
+                break;
+            };
+            let position = IVec2::new(x, y);
+            let direction = [Direction::East, Direction::North][index];
+            let neighbor = self.adjacent(position, direction);
+            let Some(current) = forest.path_length(position, neighbor) else {
+                continue;
+            };
+            if current < detour {
+                candidates.push((current, Reverse((x, y, index))));
+                continue;
+            }
+            let before = self.tiles_before([position, neighbor]);
+            for (tile, towards) in [(position, direction), (neighbor, direction.opposite())] {
+                let tile = self.tiles.get_mut(&tile).unwrap();
+                tile.map_tile = tile.map_tile.with(towards);
+            }
+            self.publish_changes(before);
+            added += 1;
+            if added < count {
+                forest = SpanningForest::new(self);
+            }
+        }
+        added
+    }
+
+    /// The tile next to `position` towards `direction` when both have exits
+    /// but not towards each other.
+    fn loop_partner(&self, position: IVec2, direction: Direction) -> Option<IVec2> {
+        let neighbor = self.adjacent(position, direction);
+        if !self.in_bounds(neighbor) || neighbor == position {
+            return None;
+        }
+        let tile = self.tiles.get(&position)?.map_tile;
+        let other = self.tiles.get(&neighbor)?.map_tile;
+        let joined = tile.contains(direction) && other.contains(direction.opposite());
+        (tile != MapTile::ZERO && other != MapTile::ZERO && !joined).then_some(neighbor)
+    }
+}
+
+/// A breadth-first spanning tree of every area of a map, with the
+/// ancestor tables to measure the path between two tiles along it in
+/// logarithmic time.
+struct SpanningForest {
+    width: usize,
+    depth: Vec<u32>,
+    /// The first tile of each tile's area, which its tree is rooted at.
+    root: Vec<usize>,
+    /// `ancestors[k][i]` is the `2^k`th ancestor of tile `i`, or the root
+    /// when the tree isn't that deep.
+    ancestors: Vec<Vec<usize>>,
+}
+
+impl SpanningForest {
+    fn new<G: TileGenerator>(map: &Map<G>) -> Self {
+        let width = map.x;
+        let len = map.x * map.y;
+        let index = |position: IVec2| position.y as usize * width + position.x as usize;
+        let mut parent = vec![0; len];
+        let mut depth = vec![0; len];
+        let mut root = vec![usize::MAX; len];
+        for start in map.positions() {
+            let start_index = index(start);
+            if root[start_index] != usize::MAX {
+                continue;
+            }
+            root[start_index] = start_index;
+            parent[start_index] = start_index;
+            let mut queue = VecDeque::from([start]);
+            while let Some(current) = queue.pop_front() {
+                let current_index = index(current);
+                for neighbor in map.neighbors(current) {
+                    let neighbor_index = index(neighbor);
+                    if root[neighbor_index] == usize::MAX {
+                        root[neighbor_index] = start_index;
+                        parent[neighbor_index] = current_index;
+                        depth[neighbor_index] = depth[current_index] + 1;
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+        }
+
+        // Enough levels to climb the depth of a tree spanning every tile.
+        let levels = (usize::BITS - len.leading_zeros()).max(1);
+        let mut ancestors = vec![parent];
+        for _ in 1..levels {
+            let previous = ancestors.last().unwrap();
+            let next = previous
+                .iter()
+                .map(|&ancestor| previous[ancestor])
+                .collect();
+            ancestors.push(next);
+        }
+        Self {
+            width,
+            depth,
+            root,
+            ancestors,
+        }
+    }
+
+    /// The number of steps from `from` to `to` along the tree, `None` when
+    /// they are in different areas.
+    fn path_length(&self, from: IVec2, to: IVec2) -> Option<u32> {
+        let (mut deeper, mut other) = (self.index(from), self.index(to));
+        if self.root[deeper] != self.root[other] {
+            return None;
+        }
+        if self.depth[deeper] < self.depth[other] {
+            std::mem::swap(&mut deeper, &mut other);
+        }
+        let length = self.depth[deeper] + self.depth[other];
+
+        let climb = self.depth[deeper] - self.depth[other];
+        for (level, ancestors) in self.ancestors.iter().enumerate() {
+            if climb & (1 << level) != 0 {
+                deeper = ancestors[deeper];
+            }
+        }
+        if deeper != other {
+            for ancestors in self.ancestors.iter().rev() {
+                if ancestors[deeper] != ancestors[other] {
+                    deeper = ancestors[deeper];
+                    other = ancestors[other];
+                }
+            }
+            deeper = self.ancestors[0][deeper];
+        }
+        Some(length - 2 * self.depth[deeper])
+    }
+
+    fn index(&self, position: IVec2) -> usize {
+        position.y as usize * self.width + position.x as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze::TileGeneratorMaze;
+    use crate::open_field::OpenFieldGenerator;
+
+    #[test]
+    fn the_longest_detour_is_cut_first() {
+        let mut map = Map::from_ascii("┌┬─┐\n│├┐│\n╵╵╵╵", OpenFieldGenerator).unwrap();
+
+        assert_eq!(map.add_loops(1), 1);
+
+        assert_eq!(map.render_ascii(), "┌┬─┐\n│├┐│\n╵╵└┘\n");
+    }
+
+    #[test]
+    fn walls_and_separate_areas_are_never_joined() {
+        let mut map = Map::from_ascii("╶╴╶╴\n  ╷ ", OpenFieldGenerator).unwrap();
+
+        assert_eq!(map.add_loops(3), 0);
+        assert_eq!(map.render_ascii(), "╶╴╶╴\n  ╷ \n");
+    }
+
+    #[test]
+    fn loops_are_added_to_perfect_mazes() {
+        let mut map = Map::new(16, TileGeneratorMaze::new(2));
+        let before = map.stats();
+        assert_eq!(before.connections, 16 * 16 - 1);
+
+        assert_eq!(map.add_loops(6), 6);
+
+        let after = map.stats();
+        assert_eq!(after.connections, before.connections + 6);
+        assert_eq!(map.validate(), Ok(()));
+    }
+
+    #[test]
+    fn tree_paths_match_the_shortest_walk_in_a_maze() {
+        let map = Map::new(12, TileGeneratorMaze::new(5));
+        let forest = SpanningForest::new(&map);
+
+        for (from, to) in [((0, 0), (11, 11)), ((3, 7), (8, 2)), ((5, 5), (5, 6))] {
+            let (from, to) = (IVec2::new(from.0, from.1), IVec2::new(to.0, to.1));
+            let shortest = map.find_path(from, to).map(|path| path.len() as u32 - 1);
+            assert_eq!(forest.path_length(from, to), shortest);
+        }
+    }
+
+    #[test]
+    fn large_mazes_get_loops_quickly() {
+        let mut map = Map::new(128, TileGeneratorMaze::new(7));
+        let started = std::time::Instant::now();
+
+        assert_eq!(map.add_loops(4), 4);
+
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+        assert_eq!(map.validate(), Ok(()));
+    }
+}
@@ -1,77 +1,196 @@
-use crate::map_tile::{Direction, Tile};
-use crate::tile_generator::TileGenerator;
+use crate::exploration::Exploration;
+use crate::generation_context::GenerationContext;
+use crate::generation_observer::{GenerationObserver, NoObserver};
+use crate::map_events::MapSubscribers;
+use crate::map_tile::{Direction, Tile, TileSet};
+use crate::movement::MovementCosts;
+use crate::occupancy::Occupancy;
+use crate::texture::{FileNameResolver, TextureResolver};
+use crate::tile_generator::{BoxedTileGenerator, TileGenerator};
+use crate::tile_grid::TileGrid;
+use crate::topology::Topology;
 
 use bevy::prelude::*;
 use itertools::iproduct;
-use std::collections::HashMap;
+use std::collections::{HashSet, VecDeque};
+use std::ops::Range;
+
+/// A [`Map`] with a type-erased generator, so the generator can be picked at
+/// runtime and systems can take `Res<DynMap>` without being generic over it.
+pub type DynMap = Map<BoxedTileGenerator>;
 
 #[derive(Resource)]
 pub struct Map<G: TileGenerator> {
     pub size: usize,
     pub x: usize,
     pub y: usize,
-    pub tiles: HashMap<IVec2, Tile>,
+    pub tiles: TileGrid,
     pub generator: G,
+    /// Whether the edges wrap around. Set it before generating, as
+    /// [`Map::new_toroidal`] does, so exits across the seams line up.
+    pub topology: Topology,
+    /// What each step costs [`Map::find_path`], see [`Map::move_cost`].
+    pub movement_costs: MovementCosts,
+    /// The fog of war, see [`Map::mark_visible`].
+    pub exploration: Exploration,
+    /// The entities standing on each tile, see [`Map::try_occupy`].
+    pub occupancy: Occupancy,
+    /// Receivers of change events, see [`Map::subscribe`].
+    pub(crate) subscribers: MapSubscribers,
 }
 
 impl<G: TileGenerator> Map<G> {
     pub fn new(size: usize, generator: G) -> Self {
+        Self::new_observed(size, generator, &mut NoObserver)
+    }
+
+    /// Like [`Map::new`], but reports every placed tile to `observer`.
+    pub fn new_observed(size: usize, generator: G, observer: &mut dyn GenerationObserver) -> Self {
         let mut map = Self {
             size,
             x: size,
             y: size,
-            tiles: HashMap::new(),
+            tiles: TileGrid::with_size(UVec2::splat(size as u32)),
             generator,
+            topology: Topology::Bounded,
+            movement_costs: MovementCosts::default(),
+            exploration: Exploration::default(),
+            occupancy: Occupancy::default(),
+            subscribers: MapSubscribers::default(),
         };
-        for (x, y) in iproduct!(0..map.x, 0..map.y) {
-            let position = IVec2::new(x as i32, y as i32);
-            let tile = map.generator.tile_at(&map.tiles, position);
-            map.tiles.insert(position, tile);
-        }
+        map.regenerate_observed(observer);
         map
     }
 
+    /// Rerolls every tile in place, keeping the dimensions and generator.
+    pub fn regenerate(&mut self) {
+        self.regenerate_observed(&mut NoObserver);
+    }
+
+    /// Like [`Map::regenerate`], but reports every placed tile to `observer`.
+    pub fn regenerate_observed(&mut self, observer: &mut dyn GenerationObserver) {
+        let before = self.tiles_before(self.positions());
+        self.tiles.clear();
+        observer.generation_started(UVec2::new(self.x as u32, self.y as u32));
+        self.generate_tiles(0..self.x as i32, 0..self.y as i32, observer);
+        observer.generation_finished(&self.tiles);
+        self.publish_changes(before);
+    }
+
+    /// Rerolls the tiles inside `region` (both corners inclusive, clipped to
+    /// the map). Tiles outside the region are kept, and the generator sees
+    /// them as neighbors, so exits across the region boundary still line up.
+    pub fn regenerate_region(&mut self, region: IRect) {
+        let min = region.min.max(IVec2::ZERO);
+        let max = region.max.min(IVec2::new(self.x as i32, self.y as i32) - 1);
+        let (xs, ys) = (min.x..max.x + 1, min.y..max.y + 1);
+        let before =
+            self.tiles_before(iproduct!(xs.clone(), ys.clone()).map(|(x, y)| IVec2::new(x, y)));
+        for (x, y) in iproduct!(xs.clone(), ys.clone()) {
+            self.tiles.remove(&IVec2::new(x, y));
+        }
+        self.generate_tiles(xs, ys, &mut NoObserver);
+        self.publish_changes(before);
+    }
+
+    pub(crate) fn generate_tiles(
+        &mut self,
+        xs: Range<i32>,
+        ys: Range<i32>,
+        observer: &mut dyn GenerationObserver,
+    ) {
+        let bounds = self.bounds();
+        let wraps = self.topology == Topology::Toroidal;
+        if wraps {
+            let edges: Vec<(IVec2, Tile)> = self
+                .tiles
+                .iter()
+                .map(|(position, tile)| (*position, *tile))
+                .collect();
+            for (position, tile) in edges {
+                self.mirror_across_seams(position, tile);
+            }
+        }
+        let total = xs.len() * ys.len();
+        for (generated, (x, y)) in iproduct!(xs, ys).enumerate() {
+            let position = IVec2::new(x, y);
+            let context =
+                GenerationContext::new(&self.tiles, position).with_progress(generated, total);
+            let tile = if wraps {
+                self.generator.generate(&context.wrapping(bounds))
+            } else {
+                self.generator.generate(&context.within(bounds))
+            };
+            self.tiles.insert(position, tile);
+            self.mirror_across_seams(position, tile);
+            observer.tile_placed(position, tile);
+        }
+        if wraps {
+            self.tiles.retain(|position, _| bounds.contains(*position));
+        }
+    }
+
+    /// Returns every tile position with the file name of its texture, as
+    /// produced by [`FileNameResolver`].
     pub fn iterate_tiles(&self) -> impl Iterator<Item = (IVec2, String)> + '_ {
-        iproduct!(0..self.x, 0..self.y).map(|(x, y)| {
-            let position = IVec2::new(x as i32, y as i32);
-            let tile = self.tiles.get(&position).unwrap();
-            let texture_file_name = format!(
-                "{}-{}-{}.png",
-                tile.tile_set,
-                tile.map_tile as u8,
-                tile.map_tile
-            );
-            (position, texture_file_name)
+        self.iterate_tiles_with(FileNameResolver)
+    }
+
+    /// Returns every tile position, in x-then-y order, with its tile set and
+    /// its index in a texture atlas holding one tile set: the exit mask,
+    /// `MapTile as u8`, which runs from 0 to 15 in the order of the bundled
+    /// file names.
+    pub fn iterate_tiles_atlas(&self) -> impl Iterator<Item = (IVec2, TileSet, usize)> + '_ {
+        self.positions().map(|position| {
+            let tile = self.tiles[&position];
+            (position, tile.tile_set, tile.map_tile as usize)
         })
     }
 
+    /// Returns every tile position, in x-then-y order, with the texture
+    /// `resolver` picks for it.
+    pub fn iterate_tiles_with<R: TextureResolver>(
+        &self,
+        resolver: R,
+    ) -> impl Iterator<Item = (IVec2, R::Texture)> {
+        self.positions()
+            .map(move |position| (position, resolver.texture(position, self.tiles[&position])))
+    }
+
+    /// Returns every tile position in x-then-y order, the order tiles are
+    /// generated in. Code whose output must not depend on `HashMap`
+    /// iteration order should walk the map this way.
+    pub fn positions(&self) -> impl Iterator<Item = IVec2> + use<G> {
+        iproduct!(0..self.x as i32, 0..self.y as i32).map(|(x, y)| IVec2::new(x, y))
+    }
+
+    /// The map grid as a rectangle with both corners inclusive.
+    pub fn bounds(&self) -> IRect {
+        IRect::new(0, 0, self.x as i32 - 1, self.y as i32 - 1)
+    }
+
+    /// Returns true when `position` lies on the map grid.
+    pub fn in_bounds(&self, position: IVec2) -> bool {
+        position.x >= 0
+            && position.y >= 0
+            && position.x < self.x as i32
+            && position.y < self.y as i32
+    }
+
     pub fn can_move(&self, from: IVec2, to: IVec2) -> bool {
         if from == to {
             return false;
         }
 
-        let max_x = self.x as i32;
-        let max_y = self.y as i32;
-        if from.x < 0
-            || from.y < 0
-            || from.x >= max_x
-            || from.y >= max_y
-            || to.x < 0
-            || to.y < 0
-            || to.x >= max_x
-            || to.y >= max_y
-        {
+        if !self.in_bounds(from) || !self.in_bounds(to) {
             return false;
         }
 
-        let delta = to - from;
-
-        let direction = match (delta.x, delta.y) {
-            (0, 1) => Direction::North,
-            (1, 0) => Direction::East,
-            (0, -1) => Direction::South,
-            (-1, 0) => Direction::West,
-            _ => return false,
+        let Some(direction) = Direction::all()
+            .into_iter()
+            .find(|direction| self.adjacent(from, *direction) == to)
+        else {
+            return false;
         };
 
         let Some(from_tile) = self.tiles.get(&from) else {
@@ -80,30 +199,162 @@ impl<G: TileGenerator> Map<G> {
         let Some(to_tile) = self.tiles.get(&to) else {
             return false;
         };
+        from_tile.map_tile.contains(direction) && to_tile.map_tile.contains(direction.opposite())
+    }
+
+    /// The cost of stepping from `from` onto `to` under the map's
+    /// [`MovementCosts`], or `None` when [`Map::can_move`] forbids the step.
+    pub fn move_cost(&self, from: IVec2, to: IVec2) -> Option<u32> {
+        self.can_move(from, to)
+            .then(|| self.movement_costs.cost(self.tiles[&to]))
+    }
 
-        from_tile.map_tile.directions().contains(&direction)
-            && to_tile.map_tile.directions().contains(&direction.opposite())
+    /// Returns the positions [`Map::can_move`] allows stepping to from
+    /// `position`, in NESW order.
+    pub fn neighbors(&self, position: IVec2) -> impl Iterator<Item = IVec2> + '_ {
+        Direction::all()
+            .into_iter()
+            .map(move |direction| self.adjacent(position, direction))
+            .filter(move |neighbor| self.can_move(position, *neighbor))
+    }
+
+    /// Returns every position that can be walked to from `position`,
+    /// including `position` itself. Empty when `position` is off the map.
+    pub fn reachable_from(&self, position: IVec2) -> HashSet<IVec2> {
+        if !self.in_bounds(position) {
+            return HashSet::new();
+        }
+        let mut reached = HashSet::from([position]);
+        let mut queue = VecDeque::from([position]);
+        while let Some(current) = queue.pop_front() {
+            for neighbor in self.neighbors(current) {
+                if reached.insert(neighbor) {
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        reached
+    }
+}
+
+impl<G: TileGenerator + Send + Sync + 'static> Map<G> {
+    /// Erases the generator type, keeping the generated tiles.
+    pub fn into_dyn(self) -> DynMap {
+        Map {
+            size: self.size,
+            x: self.x,
+            y: self.y,
+            tiles: self.tiles,
+            generator: Box::new(self.generator),
+            topology: self.topology,
+            movement_costs: self.movement_costs,
+            exploration: self.exploration,
+            occupancy: self.occupancy,
+            subscribers: self.subscribers,
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::map_tile::{MapTile, TileSet};
-    use crate::tile_generator::TileGenerator;
+    use crate::map_tile::MapTile;
+    use crate::open_field::OpenFieldGenerator;
+    use crate::tile_generator::{TileGenerator, TileGeneratorDefault};
 
     struct StaticGenerator;
 
     impl TileGenerator for StaticGenerator {
-        fn tile_at(
-            &self,
-            _tiles: &std::collections::HashMap<IVec2, Tile>,
-            _location: IVec2,
-        ) -> Tile {
+        fn tile_at(&self, _tiles: &TileGrid, _location: IVec2) -> Tile {
             Tile::new(TileSet::Room, MapTile::NESW)
         }
     }
 
+    #[test]
+    fn dyn_map_uses_generator_chosen_at_runtime() {
+        let generator_name = "static";
+        let generator: BoxedTileGenerator = match generator_name {
+            "static" => Box::new(StaticGenerator),
+            _ => Box::new(TileGeneratorDefault::new()),
+        };
+        let map: DynMap = Map::new(3, generator);
+
+        assert_eq!(map.tiles.len(), 9);
+        assert!(map.can_move(IVec2::new(0, 0), IVec2::new(1, 0)));
+    }
+
+    #[test]
+    fn into_dyn_keeps_generated_tiles() {
+        let map = Map::new(4, TileGeneratorDefault::with_seed(9));
+        let tiles = map.tiles.clone();
+
+        let map = map.into_dyn();
+        assert_eq!(map.tiles, tiles);
+        assert_eq!((map.x, map.y), (4, 4));
+    }
+
+    fn assert_exits_line_up<G: TileGenerator>(map: &Map<G>) {
+        for (&position, tile) in &map.tiles {
+            for direction in [Direction::North, Direction::East] {
+                let Some(neighbor) = map.tiles.get(&(position + direction.offset())) else {
+                    continue;
+                };
+                assert_eq!(
+                    tile.map_tile.directions().contains(&direction),
+                    neighbor
+                        .map_tile
+                        .directions()
+                        .contains(&direction.opposite()),
+                    "mismatched exits between {position} and its {direction:?} neighbor"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn regenerate_rerolls_every_tile_deterministically() {
+        let mut map = Map::new(8, TileGeneratorDefault::with_seed(3));
+        let original = map.tiles.clone();
+
+        map.generator.reseed(3);
+        map.regenerate();
+        assert_eq!(map.tiles, original);
+
+        map.generator.reseed(4);
+        map.regenerate();
+        assert_eq!(map.tiles.len(), 64);
+        assert_ne!(map.tiles, original);
+        assert_exits_line_up(&map);
+    }
+
+    #[test]
+    fn regenerate_region_keeps_tiles_outside_the_region() {
+        let mut map = Map::new(10, TileGeneratorDefault::with_seed(5));
+        let original = map.tiles.clone();
+        let region = IRect::new(2, 3, 6, 7);
+
+        map.regenerate_region(region);
+
+        assert_eq!(map.tiles.len(), 100);
+        for (position, tile) in &original {
+            if !region.contains(*position) {
+                assert_eq!(map.tiles[position], *tile);
+            }
+        }
+        assert_exits_line_up(&map);
+    }
+
+    #[test]
+    fn regenerate_region_is_clipped_to_the_map() {
+        let mut map = Map::new(4, TileGeneratorDefault::with_seed(5));
+
+        map.regenerate_region(IRect::new(-5, -5, 20, 1));
+
+        assert_eq!(map.tiles.len(), 16);
+        assert!(map.tiles.keys().all(|position| map.in_bounds(*position)));
+        assert_exits_line_up(&map);
+    }
+
     #[test]
     fn cannot_move_out_of_bounds() {
         let map = Map::new(2, StaticGenerator);
@@ -151,11 +402,7 @@ mod tests {
     fn iterate_tiles_generates_correct_room_asset_names() {
         struct RoomGenerator;
         impl TileGenerator for RoomGenerator {
-            fn tile_at(
-                &self,
-                _tiles: &std::collections::HashMap<IVec2, Tile>,
-                _location: IVec2,
-            ) -> Tile {
+            fn tile_at(&self, _tiles: &TileGrid, _location: IVec2) -> Tile {
                 Tile::new(TileSet::Room, MapTile::NS)
             }
         }
@@ -169,15 +416,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn iterate_tiles_atlas_yields_exit_masks() {
+        let mut map = Map::new(2, OpenFieldGenerator);
+        map.tiles
+            .insert(IVec2::new(1, 0), Tile::new(TileSet::Corridor, MapTile::NS));
+
+        let tiles: Vec<_> = map.iterate_tiles_atlas().collect();
+
+        assert_eq!(tiles.len(), 4);
+        assert_eq!(tiles[2], (IVec2::new(1, 0), TileSet::Corridor, 5));
+        let (_, tile_set, index) = tiles[0];
+        assert_eq!(tile_set, TileSet::Field);
+        assert_eq!(index, map.tiles[&IVec2::ZERO].map_tile as usize);
+    }
+
     #[test]
     fn iterate_tiles_generates_correct_corridor_asset_names() {
         struct CorridorGenerator;
         impl TileGenerator for CorridorGenerator {
-            fn tile_at(
-                &self,
-                _tiles: &std::collections::HashMap<IVec2, Tile>,
-                _location: IVec2,
-            ) -> Tile {
+            fn tile_at(&self, _tiles: &TileGrid, _location: IVec2) -> Tile {
                 Tile::new(TileSet::Corridor, MapTile::EW)
             }
         }
@@ -195,11 +453,7 @@ mod tests {
     fn iterate_tiles_handles_mixed_room_and_corridor_types() {
         struct MixedGenerator;
         impl TileGenerator for MixedGenerator {
-            fn tile_at(
-                &self,
-                _tiles: &std::collections::HashMap<IVec2, Tile>,
-                location: IVec2,
-            ) -> Tile {
+            fn tile_at(&self, _tiles: &TileGrid, location: IVec2) -> Tile {
                 // Create a pattern: rooms on even x, corridors on odd x
                 if location.x % 2 == 0 {
                     Tile::new(TileSet::Room, MapTile::NESW)
@@ -226,6 +480,27 @@ mod tests {
         assert_eq!(corridor_tiles.len(), 2); // x=1, y=0 and x=1, y=1
     }
 
+    #[test]
+    fn iterate_tiles_with_uses_the_given_resolver() {
+        let map = Map::new(2, StaticGenerator);
+
+        let indices: Vec<_> = map
+            .iterate_tiles_with(|position: IVec2, tile: Tile| {
+                position.x * 100 + position.y * 10 + tile.map_tile as i32
+            })
+            .collect();
+
+        assert_eq!(
+            indices,
+            vec![
+                (IVec2::new(0, 0), 15),
+                (IVec2::new(0, 1), 25),
+                (IVec2::new(1, 0), 115),
+                (IVec2::new(1, 1), 125),
+            ]
+        );
+    }
+
     #[test]
     fn map_can_move_works_with_tiles() {
         let mut map = Map::new(3, StaticGenerator);
@@ -239,4 +514,45 @@ mod tests {
         // Movement should work regardless of tile_set
         assert!(map.can_move(IVec2::new(0, 0), IVec2::new(1, 0)));
     }
+
+    #[test]
+    fn neighbors_and_reachable_from_follow_matching_exits() {
+        let mut map = Map::new(3, StaticGenerator);
+        for position in map.tiles.keys().copied().collect::<Vec<_>>() {
+            map.tiles
+                .insert(position, Tile::new(TileSet::Room, MapTile::ZERO));
+        }
+        // (0, 0) - (1, 0) - (1, 1) are connected; (2, 0) only has an exit
+        // back towards (1, 0), which has no exit towards it.
+        map.tiles
+            .insert(IVec2::new(0, 0), Tile::new(TileSet::Room, MapTile::E));
+        map.tiles
+            .insert(IVec2::new(1, 0), Tile::new(TileSet::Room, MapTile::NW));
+        map.tiles
+            .insert(IVec2::new(1, 1), Tile::new(TileSet::Room, MapTile::S));
+        map.tiles
+            .insert(IVec2::new(2, 0), Tile::new(TileSet::Room, MapTile::W));
+
+        let neighbors: Vec<_> = map.neighbors(IVec2::new(1, 0)).collect();
+        assert_eq!(neighbors, vec![IVec2::new(1, 1), IVec2::new(0, 0)]);
+        assert_eq!(map.neighbors(IVec2::new(2, 0)).count(), 0);
+
+        assert_eq!(
+            map.reachable_from(IVec2::new(1, 1)),
+            HashSet::from([IVec2::new(0, 0), IVec2::new(1, 0), IVec2::new(1, 1)])
+        );
+        assert_eq!(
+            map.reachable_from(IVec2::new(2, 2)),
+            HashSet::from([IVec2::new(2, 2)])
+        );
+        assert!(map.reachable_from(IVec2::new(-1, 0)).is_empty());
+    }
+
+    #[test]
+    fn edge_exits_do_not_lead_off_the_map() {
+        let map = Map::new(2, StaticGenerator);
+
+        assert_eq!(map.neighbors(IVec2::new(0, 0)).count(), 2);
+        assert_eq!(map.reachable_from(IVec2::new(0, 0)).len(), 4);
+    }
 }
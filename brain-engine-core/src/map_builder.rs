@@ -0,0 +1,261 @@
+//! Maps with a guaranteed start and exit, for games that need both before
+//! play can begin.
+//!
+//! ```
+//! use bevy::prelude::*;
+//! use brain_engine_core::{MapBuilder, TileGeneratorDefault};
+//!
+//! let built = MapBuilder::new(16, TileGeneratorDefault::with_seed(2))
+//!     .with_start(IVec2::ZERO)
+//!     .with_start_exits(2)
+//!     .with_min_exit_distance(12)
+//!     .build()
+//!     .unwrap();
+//! assert!(built.map.neighbors(built.start).count() >= 2);
+//! assert!((built.exit - built.start).abs().element_sum() >= 12);
+//! ```
+
+use crate::map::Map;
+use crate::map_tile::Direction;
+use crate::pipeline::{EnsureConnected, MapTransform};
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use std::fmt;
+
+/// Maps [`MapBuilder`] generates, by default, before repairing the last one.
+pub const DEFAULT_MAX_ATTEMPTS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapBuilderError {
+    StartOutOfBounds(IVec2),
+    /// The start has fewer neighbors on the map than the exits asked for,
+    /// e.g. 3 exits in a corner.
+    TooManyStartExits(usize),
+    /// No tile the start reaches is this far away, even after repairs.
+    NoExit {
+        min_distance: u32,
+    },
+}
+
+impl fmt::Display for MapBuilderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapBuilderError::StartOutOfBounds(start) => write!(f, "start {start} is off the map"),
+            MapBuilderError::TooManyStartExits(exits) => {
+                write!(f, "the start can't have {exits} exits")
+            }
+            MapBuilderError::NoExit { min_distance } => {
+                write!(
+                    f,
+                    "no reachable tile is {min_distance} tiles from the start"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for MapBuilderError {}
+
+/// A map from [`MapBuilder::build`] with its special positions.
+pub struct BuiltMap<G: TileGenerator> {
+    pub map: Map<G>,
+    pub start: IVec2,
+    /// The tile farthest from the start, by walking distance, among those
+    /// far enough away.
+    pub exit: IVec2,
+    /// Maps generated, at least 1.
+    pub attempts: usize,
+    /// Whether the last map had to be repaired because no attempt met the
+    /// constraints.
+    pub repaired: bool,
+}
+
+/// Generates maps until the start and exit constraints hold.
+///
+/// Every attempt regenerates the map, so seeded generators still give the
+/// same result every time. When no attempt succeeds, the last map is
+/// repaired: the start gets exits towards its neighbors until it has enough,
+/// and every walkable area is joined up with [`EnsureConnected`].
+pub struct MapBuilder<G: TileGenerator> {
+    size: usize,
+    generator: G,
+    start: IVec2,
+    start_exits: usize,
+    min_exit_distance: u32,
+    max_attempts: usize,
+}
+
+impl<G: TileGenerator> MapBuilder<G> {
+    /// Starts at `(0, 0)` with at least one exit, and puts the exit anywhere
+    /// else the start reaches.
+    pub fn new(size: usize, generator: G) -> Self {
+        Self {
+            size,
+            generator,
+            start: IVec2::ZERO,
+            start_exits: 1,
+            min_exit_distance: 1,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        }
+    }
+
+    pub fn with_start(mut self, start: IVec2) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// The fewest neighbors the start must connect to.
+    pub fn with_start_exits(mut self, exits: usize) -> Self {
+        self.start_exits = exits;
+        self
+    }
+
+    /// The smallest Manhattan distance between the start and the exit.
+    pub fn with_min_exit_distance(mut self, distance: u32) -> Self {
+        self.min_exit_distance = distance;
+        self
+    }
+
+    /// Maps to generate before repairing the last one, at least 1.
+    pub fn with_max_attempts(mut self, attempts: usize) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    pub fn build(self) -> Result<BuiltMap<G>, MapBuilderError> {
+        let mut map = Map::new(self.size, self.generator);
+        let start = self.start;
+        if !map.in_bounds(start) {
+            return Err(MapBuilderError::StartOutOfBounds(start));
+        }
+        let possible_exits = map.directions_in_bounds(start).count();
+        if self.start_exits > possible_exits {
+            return Err(MapBuilderError::TooManyStartExits(self.start_exits));
+        }
+
+        let find_exit = |map: &Map<G>| {
+            if map.neighbors(start).count() < self.start_exits {
+                return None;
+            }
+            map.dijkstra_map(&[start])
+                .into_iter()
+                .filter(|(position, _)| {
+                    (*position - start).abs().element_sum() as u32 >= self.min_exit_distance
+                })
+                .max_by_key(|(position, steps)| (*steps, position.x, position.y))
+                .map(|(position, _)| position)
+        };
+
+        for attempts in 1..=self.max_attempts {
+            if let Some(exit) = find_exit(&map) {
+                return Ok(BuiltMap {
+                    map,
+                    start,
+                    exit,
+                    attempts,
+                    repaired: false,
+                });
+            }
+            if attempts < self.max_attempts {
+                map.regenerate();
+            }
+        }
+
+        let mut missing = self.start_exits - map.neighbors(start).count().min(self.start_exits);
+        for direction in map.directions_in_bounds(start).collect::<Vec<_>>() {
+            let neighbor = start + direction.offset();
+            if missing == 0 || map.can_move(start, neighbor) {
+                continue;
+            }
+            if let Some(tile) = map.tiles.get_mut(&start) {
+                tile.map_tile = tile.map_tile.with(direction);
+            }
+            if let Some(tile) = map.tiles.get_mut(&neighbor) {
+                tile.map_tile = tile.map_tile.with(direction.opposite());
+            }
+            missing -= 1;
+        }
+        EnsureConnected.apply(&mut map);
+        let exit = find_exit(&map).ok_or(MapBuilderError::NoExit {
+            min_distance: self.min_exit_distance,
+        })?;
+        Ok(BuiltMap {
+            map,
+            start,
+            exit,
+            attempts: self.max_attempts,
+            repaired: true,
+        })
+    }
+}
+
+impl<G: TileGenerator> Map<G> {
+    /// The directions from `position` that lead to a tile on the map.
+    fn directions_in_bounds(&self, position: IVec2) -> impl Iterator<Item = Direction> + '_ {
+        Direction::all()
+            .into_iter()
+            .filter(move |direction| self.in_bounds(position + direction.offset()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clustered_rooms::ClusteredRoomGenerator;
+    use crate::tile_generator::TileGeneratorDefault;
+
+    #[test]
+    fn built_maps_meet_their_constraints() {
+        for seed in 0..8 {
+            let built = MapBuilder::new(12, TileGeneratorDefault::with_seed(seed))
+                .with_start(IVec2::new(0, 5))
+                .with_start_exits(2)
+                .with_min_exit_distance(10)
+                .build()
+                .unwrap();
+
+            assert!(built.map.neighbors(built.start).count() >= 2, "seed {seed}");
+            assert!((built.exit - built.start).abs().element_sum() >= 10);
+            assert!(built.map.find_path(built.start, built.exit).is_some());
+            assert_eq!(built.map.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn unsatisfiable_maps_are_repaired() {
+        // The start sits in the wall margin of the first room cell.
+        let generator = ClusteredRoomGenerator::new(3).with_bounds(UVec2::splat(16));
+
+        let built = MapBuilder::new(16, generator)
+            .with_start_exits(2)
+            .with_min_exit_distance(8)
+            .with_max_attempts(1)
+            .build()
+            .unwrap();
+
+        assert!(built.repaired);
+        assert_eq!(built.attempts, 1);
+        assert_eq!(built.map.neighbors(IVec2::ZERO).count(), 2);
+        assert!(built.map.find_path(built.start, built.exit).is_some());
+        assert_eq!(built.map.validate(), Ok(()));
+    }
+
+    #[test]
+    fn impossible_constraints_are_rejected() {
+        let builder = || MapBuilder::new(4, TileGeneratorDefault::with_seed(1));
+
+        assert_eq!(
+            builder().with_start(IVec2::new(4, 0)).build().err(),
+            Some(MapBuilderError::StartOutOfBounds(IVec2::new(4, 0)))
+        );
+        assert_eq!(
+            builder().with_start_exits(3).build().err(),
+            Some(MapBuilderError::TooManyStartExits(3))
+        );
+        assert_eq!(
+            builder().with_min_exit_distance(7).build().err(),
+            Some(MapBuilderError::NoExit { min_distance: 7 })
+        );
+    }
+}
@@ -0,0 +1,310 @@
+//! Events for runtime map changes, so UIs and game systems can react to
+//! edits without polling the whole grid.
+//!
+//! ```
+//! use bevy::prelude::*;
+//! use brain_engine_core::{Map, MapEvent, OpenFieldGenerator};
+//!
+//! let mut map = Map::new(4, OpenFieldGenerator);
+//! let events = map.subscribe();
+//! map.place_obstacle(IVec2::new(1, 1));
+//!
+//! let changed = events
+//!     .try_iter()
+//!     .filter(|event| matches!(event, MapEvent::TileChanged { .. }))
+//!     .count();
+//! assert_eq!(changed, 5);
+//! ```
+
+use crate::diff::MapDiff;
+use crate::map::Map;
+use crate::map_tile::{Direction, Tile};
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+use std::sync::mpsc::{Receiver, Sender, channel};
+
+/// A change made through the map's editing methods, see [`Map::subscribe`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MapEvent {
+    /// The tile at `position` changed; `None` where there is no tile.
+    TileChanged {
+        position: IVec2,
+        before: Option<Tile>,
+        after: Option<Tile>,
+    },
+    /// The tiles at `position` and next to it towards `direction` now have
+    /// exits towards each other, after a change to either.
+    DoorOpened {
+        position: IVec2,
+        direction: Direction,
+    },
+    /// Tiles that were never seen before became visible, see
+    /// [`Map::mark_visible`].
+    RegionRevealed { positions: Vec<IVec2> },
+}
+
+/// The channels of a map's subscribers. Disconnected subscribers are
+/// dropped at the next event.
+#[derive(Debug, Default)]
+pub struct MapSubscribers {
+    senders: Vec<Sender<MapEvent>>,
+}
+
+impl MapSubscribers {
+    pub fn is_empty(&self) -> bool {
+        self.senders.is_empty()
+    }
+
+    fn send(&mut self, event: MapEvent) {
+        self.senders
+            .retain(|sender| sender.send(event.clone()).is_ok());
+    }
+}
+
+impl<G: TileGenerator> Map<G> {
+    /// Returns a receiver for the [`MapEvent`]s of every later change made
+    /// with [`Map::apply`], the brushes, [`Map::stamp`],
+    /// [`Map::place_obstacle`], [`Map::clear_obstacle`],
+    /// [`Map::mark_visible`], regeneration, [`Map::resize`], [`Map::crop`]
+    /// and the post-processing passes such as [`Map::prune_dead_ends`],
+    /// [`Map::add_loops`] and [`Map::straighten_corridors`]. Writes straight
+    /// to `tiles` send no events.
+    ///
+    /// Events are queued until received; drop the receiver to unsubscribe.
+    pub fn subscribe(&mut self) -> Receiver<MapEvent> {
+        let (sender, receiver) = channel();
+        self.subscribers.senders.push(sender);
+        receiver
+    }
+
+    /// The tiles at `positions`, to pass to [`Map::publish_changes`] after
+    /// changing them. Empty without subscribers, so unobserved maps don't
+    /// pay for events.
+    pub(crate) fn tiles_before(
+        &self,
+        positions: impl IntoIterator<Item = IVec2>,
+    ) -> Vec<(IVec2, Option<Tile>)> {
+        if self.subscribers.is_empty() {
+            return Vec::new();
+        }
+        positions
+            .into_iter()
+            .map(|position| (position, self.tiles.get(&position).copied()))
+            .collect()
+    }
+
+    /// Sends [`MapEvent::TileChanged`] for every tile in `undo` that
+    /// changed and [`MapEvent::DoorOpened`] for every exit pair the change
+    /// joined.
+    pub(crate) fn publish_undo(&mut self, undo: &MapDiff) {
+        if !self.subscribers.is_empty() {
+            self.publish_changes(undo.changes.clone());
+        }
+    }
+
+    /// Like [`Map::publish_undo`] for the earlier tiles from
+    /// [`Map::tiles_before`].
+    pub(crate) fn publish_changes(&mut self, before: Vec<(IVec2, Option<Tile>)>) {
+        let before: HashMap<IVec2, Option<Tile>> = before
+            .into_iter()
+            .filter(|(position, tile)| self.tiles.get(position).copied() != *tile)
+            .collect();
+        let mut changed: Vec<IVec2> = before.keys().copied().collect();
+        changed.sort_by_key(|position| (position.x, position.y));
+
+        let was_open = |position: IVec2, direction: Direction, neighbor: IVec2| {
+            let tile = |position: IVec2| {
+                before
+                    .get(&position)
+                    .copied()
+                    .unwrap_or_else(|| self.tiles.get(&position).copied())
+            };
+            is_open(tile(position), tile(neighbor), direction)
+        };
+        let mut doors = Vec::new();
+        let mut edges = HashSet::new();
+        for &position in &changed {
+            for direction in Direction::all() {
+                let neighbor = self.adjacent(position, direction);
+                let tiles = (
+                    self.tiles.get(&position).copied(),
+                    self.tiles.get(&neighbor).copied(),
+                );
+                if is_open(tiles.0, tiles.1, direction)
+                    && !was_open(position, direction, neighbor)
+                    && edges.insert(edge(position, neighbor))
+                {
+                    doors.push(MapEvent::DoorOpened {
+                        position,
+                        direction,
+                    });
+                }
+            }
+        }
+
+        let events: Vec<MapEvent> = changed
+            .iter()
+            .map(|position| MapEvent::TileChanged {
+                position: *position,
+                before: before[position],
+                after: self.tiles.get(position).copied(),
+            })
+            .chain(doors)
+            .collect();
+        for event in events {
+            self.subscribers.send(event);
+        }
+    }
+
+    pub(crate) fn publish_revealed(&mut self, positions: Vec<IVec2>) {
+        if !positions.is_empty() {
+            self.subscribers
+                .send(MapEvent::RegionRevealed { positions });
+        }
+    }
+}
+
+fn is_open(tile: Option<Tile>, neighbor: Option<Tile>, direction: Direction) -> bool {
+    tile.zip(neighbor).is_some_and(|(tile, neighbor)| {
+        tile.map_tile.contains(direction) && neighbor.map_tile.contains(direction.opposite())
+    })
+}
+
+/// The same key for both sides of an edge.
+fn edge(position: IVec2, neighbor: IVec2) -> (IVec2, IVec2) {
+    if (position.x, position.y) < (neighbor.x, neighbor.y) {
+        (position, neighbor)
+    } else {
+        (neighbor, position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_tile::{MapTile, TileSet};
+    use crate::open_field::OpenFieldGenerator;
+    use crate::testing::GridBuilder;
+
+    #[test]
+    fn obstacle_edits_report_changed_tiles_and_doors() {
+        let mut map = Map::new(4, OpenFieldGenerator);
+        map.place_obstacle(IVec2::new(1, 0));
+        let events = map.subscribe();
+
+        map.clear_obstacle(IVec2::new(1, 0));
+
+        let received: Vec<MapEvent> = events.try_iter().collect();
+        assert_eq!(
+            received[0],
+            MapEvent::TileChanged {
+                position: IVec2::new(0, 0),
+                before: Some(Tile::new(TileSet::Field, MapTile::N)),
+                after: Some(Tile::new(TileSet::Field, MapTile::NE)),
+            }
+        );
+        let doors: Vec<&MapEvent> = received
+            .iter()
+            .filter(|event| matches!(event, MapEvent::DoorOpened { .. }))
+            .collect();
+        assert_eq!(doors.len(), 3);
+        assert!(doors.contains(&&MapEvent::DoorOpened {
+            position: IVec2::ZERO,
+            direction: Direction::East,
+        }));
+    }
+
+    #[test]
+    fn diffs_and_stamps_send_events() {
+        let mut map = Map::new(3, OpenFieldGenerator);
+        let events = map.subscribe();
+        let field = map.tiles[&IVec2::ZERO];
+        let undo = map.paint_rect(
+            IRect::new(0, 0, 2, 0),
+            Tile::new(TileSet::Room, MapTile::EW),
+        );
+        assert_eq!(events.try_iter().count(), 3);
+
+        let room = map.tiles[&IVec2::ZERO];
+        map.apply(&undo);
+        assert_eq!(
+            events.try_iter().next(),
+            Some(MapEvent::TileChanged {
+                position: IVec2::ZERO,
+                before: Some(room),
+                after: Some(field),
+            })
+        );
+
+        let vault: crate::prefab::Prefab = "┌┐\n└┘".parse().unwrap();
+        map.stamp(&vault, IVec2::ZERO);
+        assert!(events.try_iter().count() >= 4);
+    }
+
+    #[test]
+    fn post_processing_and_resizing_send_events() {
+        let mut map = GridBuilder::new("╶──╴").build(OpenFieldGenerator);
+        let events = map.subscribe();
+
+        assert_eq!(map.prune_dead_ends(usize::MAX), 4);
+        let sealed = events
+            .try_iter()
+            .filter(|event| matches!(event, MapEvent::TileChanged { .. }))
+            .count();
+        assert_eq!(sealed, 4);
+
+        map.crop(IRect::new(0, 0, 0, 0));
+        let received: Vec<MapEvent> = events.try_iter().collect();
+        assert_eq!(received.len(), 3);
+        assert!(
+            received
+                .iter()
+                .all(|event| matches!(event, MapEvent::TileChanged { after: None, .. }))
+        );
+
+        map.resize(2, 1);
+        assert!(matches!(
+            events.try_iter().next(),
+            Some(MapEvent::TileChanged {
+                before: None,
+                after: Some(_),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn newly_seen_tiles_are_revealed_once() {
+        let mut map = Map::new(4, OpenFieldGenerator);
+        let events = map.subscribe();
+
+        map.mark_visible([IVec2::ZERO, IVec2::X]);
+        map.mark_visible([IVec2::X, IVec2::Y]);
+        map.mark_visible([IVec2::ZERO]);
+
+        let received: Vec<MapEvent> = events.try_iter().collect();
+        assert_eq!(
+            received,
+            vec![
+                MapEvent::RegionRevealed {
+                    positions: vec![IVec2::ZERO, IVec2::X],
+                },
+                MapEvent::RegionRevealed {
+                    positions: vec![IVec2::Y],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn dropped_receivers_unsubscribe() {
+        let mut map = Map::new(2, OpenFieldGenerator);
+        drop(map.subscribe());
+
+        map.place_obstacle(IVec2::ZERO);
+
+        assert!(map.subscribers.is_empty());
+    }
+}
@@ -0,0 +1,472 @@
+use crate::exploration::Exploration;
+use crate::map::Map;
+use crate::map_events::MapSubscribers;
+use crate::map_tile::{Biome, Tile};
+use crate::movement::MovementCosts;
+use crate::occupancy::Occupancy;
+use crate::tile_generator::TileGenerator;
+use crate::tile_grid::TileGrid;
+use crate::topology::Topology;
+
+use bevy::prelude::*;
+use itertools::iproduct;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Write};
+
+/// Version written in the header of every map file.
+pub const MAP_FILE_VERSION: u32 = 1;
+
+pub(crate) const MAGIC: &str = "brain-engine-map";
+/// Largest width or height a map file may declare, so that every position
+/// on the map fits in an `IVec2`.
+const MAX_SIZE: usize = i32::MAX as usize;
+
+/// Per-tile data that a game wants saved alongside the map.
+///
+/// Implement this for a game type and store values with
+/// [`TileExtensions::insert`]; they are written to the map file under
+/// [`TileExtension::KEY`] and read back with [`TileExtensions::get`].
+pub trait TileExtension: Sized {
+    /// Identifies the extension in map files. Must be non-empty and must not
+    /// contain whitespace.
+    const KEY: &'static str;
+
+    fn encode(&self) -> Vec<u8>;
+
+    /// Returns `None` when the bytes are not a valid encoding.
+    fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+/// Extension blobs attached to map tiles, grouped by extension key.
+///
+/// Blobs are kept encoded, so data written by extensions that this build does
+/// not know about is preserved and written back unchanged.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TileExtensions {
+    blobs: BTreeMap<String, HashMap<IVec2, Vec<u8>>>,
+}
+
+impl TileExtensions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores `value` for the tile at `position`, replacing any earlier value
+    /// of the same extension.
+    pub fn insert<E: TileExtension>(&mut self, position: IVec2, value: &E) {
+        self.insert_raw(E::KEY, position, value.encode());
+    }
+
+    /// Decodes the value stored for the tile at `position`, if any.
+    pub fn get<E: TileExtension>(&self, position: IVec2) -> Option<E> {
+        self.raw(E::KEY, position).and_then(E::decode)
+    }
+
+    /// Removes and decodes the value stored for the tile at `position`.
+    pub fn remove<E: TileExtension>(&mut self, position: IVec2) -> Option<E> {
+        let blobs = self.blobs.get_mut(E::KEY)?;
+        let bytes = blobs.remove(&position)?;
+        if blobs.is_empty() {
+            self.blobs.remove(E::KEY);
+        }
+        E::decode(&bytes)
+    }
+
+    /// Stores an encoded blob under `key`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `key` is empty or contains whitespace.
+    pub fn insert_raw(&mut self, key: &str, position: IVec2, bytes: Vec<u8>) {
+        assert!(is_valid_key(key), "invalid tile extension key '{key}'");
+        self.blobs
+            .entry(key.to_string())
+            .or_default()
+            .insert(position, bytes);
+    }
+
+    pub fn raw(&self, key: &str, position: IVec2) -> Option<&[u8]> {
+        self.blobs.get(key)?.get(&position).map(Vec::as_slice)
+    }
+
+    /// Iterates the extension keys that have data, in key order.
+    pub fn keys(&self) -> impl Iterator<Item = &str> + '_ {
+        self.blobs.keys().map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blobs.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MapFileError {
+    /// The first line is not a map file header.
+    MissingHeader,
+    /// The file was written by a newer, unsupported format version.
+    UnsupportedVersion(String),
+    /// A line could not be parsed.
+    InvalidLine { line: usize, reason: String },
+    /// The file has no tile for this position.
+    MissingTile(IVec2),
+}
+
+impl fmt::Display for MapFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapFileError::MissingHeader => write!(f, "not a map file"),
+            MapFileError::UnsupportedVersion(version) => {
+                write!(f, "unsupported map file version '{version}'")
+            }
+            MapFileError::InvalidLine { line, reason } => write!(f, "line {line}: {reason}"),
+            MapFileError::MissingTile(position) => write!(f, "no tile at {position}"),
+        }
+    }
+}
+
+impl std::error::Error for MapFileError {}
+
+/// A map as stored on disk: its tiles plus any [`TileExtensions`].
+///
+/// The format is line based text:
+///
+/// ```text
+/// brain-engine-map 1
+/// size 2 1
+/// topology toroidal
+/// tile 0 0 room E
+/// tile 1 0 corridor W water
+/// ext loot 1 0 0a03
+/// ```
+///
+/// The `topology` line is left out for [`Topology::Bounded`] maps. Tiles
+/// outside the [`Biome::Dungeon`] end with their biome. Extension
+/// blobs are hex encoded. Blank lines and lines starting with `#`
+/// are ignored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MapFile {
+    pub x: usize,
+    pub y: usize,
+    pub topology: Topology,
+    pub tiles: HashMap<IVec2, Tile>,
+    pub extensions: TileExtensions,
+}
+
+impl MapFile {
+    pub fn from_map<G: TileGenerator>(map: &Map<G>, extensions: TileExtensions) -> Self {
+        Self {
+            x: map.x,
+            y: map.y,
+            topology: map.topology,
+            tiles: map
+                .tiles
+                .iter()
+                .map(|(position, tile)| (*position, *tile))
+                .collect(),
+            extensions,
+        }
+    }
+
+    /// Builds a [`Map`] from the stored tiles. The generator is only used for
+    /// later regeneration; no tiles are generated here.
+    pub fn into_map<G: TileGenerator>(self, generator: G) -> (Map<G>, TileExtensions) {
+        let mut tiles = TileGrid::with_size(UVec2::new(self.x as u32, self.y as u32));
+        tiles.extend(self.tiles);
+        let map = Map {
+            size: self.x,
+            x: self.x,
+            y: self.y,
+            tiles,
+            generator,
+            topology: self.topology,
+            movement_costs: MovementCosts::default(),
+            exploration: Exploration::default(),
+            occupancy: Occupancy::default(),
+            subscribers: MapSubscribers::default(),
+        };
+        (map, self.extensions)
+    }
+
+    pub fn write(&self) -> String {
+        let mut text = format!("{MAGIC} {MAP_FILE_VERSION}\nsize {} {}\n", self.x, self.y);
+        if self.topology == Topology::Toroidal {
+            writeln!(text, "topology toroidal").unwrap();
+        }
+        for (x, y) in iproduct!(0..self.x as i32, 0..self.y as i32) {
+            if let Some(tile) = self.tiles.get(&IVec2::new(x, y)) {
+                write!(text, "tile {x} {y} {} {}", tile.tile_set, tile.map_tile).unwrap();
+                match tile.biome {
+                    Biome::Dungeon => writeln!(text).unwrap(),
+                    biome => writeln!(text, " {biome}").unwrap(),
+                }
+            }
+        }
+        for (key, blobs) in &self.extensions.blobs {
+            let mut positions: Vec<_> = blobs.keys().copied().collect();
+            positions.sort_by_key(|position| (position.x, position.y));
+            for position in positions {
+                let hex: String = blobs[&position]
+                    .iter()
+                    .map(|byte| format!("{byte:02x}"))
+                    .collect();
+                let line = format!("ext {key} {} {} {hex}", position.x, position.y);
+                writeln!(text, "{}", line.trim_end()).unwrap();
+            }
+        }
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Self, MapFileError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'));
+
+        let (_, header) = lines.next().ok_or(MapFileError::MissingHeader)?;
+        match header.split_whitespace().collect::<Vec<_>>()[..] {
+            [MAGIC, "1"] => {}
+            [MAGIC, version] => {
+                return Err(MapFileError::UnsupportedVersion(version.to_string()));
+            }
+            _ => return Err(MapFileError::MissingHeader),
+        }
+
+        let mut map_file = MapFile {
+            x: 0,
+            y: 0,
+            topology: Topology::Bounded,
+            tiles: HashMap::new(),
+            extensions: TileExtensions::new(),
+        };
+        let mut has_size = false;
+        for (line_number, line) in lines {
+            let invalid = |reason: &str| MapFileError::InvalidLine {
+                line: line_number,
+                reason: reason.to_string(),
+            };
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields[..] {
+                ["size", x, y] if !has_size => {
+                    let side = |text: &str| text.parse().ok().filter(|side| *side <= MAX_SIZE);
+                    map_file.x = side(x).ok_or_else(|| invalid("invalid width"))?;
+                    map_file.y = side(y).ok_or_else(|| invalid("invalid height"))?;
+                    has_size = true;
+                }
+                ["topology", topology] if has_size => {
+                    map_file.topology = match topology {
+                        "bounded" => Topology::Bounded,
+                        "toroidal" => Topology::Toroidal,
+                        _ => return Err(invalid("invalid topology")),
+                    };
+                }
+                ["tile", x, y, tile_set, map_tile, ref biome @ ..]
+                    if has_size && biome.len() <= 1 =>
+                {
+                    let position = map_file
+                        .parse_position(x, y)
+                        .ok_or_else(|| invalid("tile position is outside the map"))?;
+                    let biome = match biome.first() {
+                        Some(biome) => biome.parse().map_err(|_| invalid("invalid biome"))?,
+                        None => Biome::Dungeon,
+                    };
+                    let tile = Tile::new(
+                        tile_set.parse().map_err(|_| invalid("invalid tile set"))?,
+                        map_tile.parse().map_err(|_| invalid("invalid exit code"))?,
+                    )
+                    .with_biome(biome);
+                    map_file.tiles.insert(position, tile);
+                }
+                ["ext", key, x, y, ref hex @ ..] if has_size && hex.len() <= 1 => {
+                    let position = map_file
+                        .parse_position(x, y)
+                        .ok_or_else(|| invalid("extension position is outside the map"))?;
+                    let bytes = decode_hex(hex.first().unwrap_or(&""))
+                        .ok_or_else(|| invalid("invalid extension data"))?;
+                    map_file.extensions.insert_raw(key, position, bytes);
+                }
+                _ => return Err(invalid("unexpected line")),
+            }
+        }
+
+        for (x, y) in iproduct!(0..map_file.x as i32, 0..map_file.y as i32) {
+            let position = IVec2::new(x, y);
+            if !map_file.tiles.contains_key(&position) {
+                return Err(MapFileError::MissingTile(position));
+            }
+        }
+        Ok(map_file)
+    }
+
+    fn parse_position(&self, x: &str, y: &str) -> Option<IVec2> {
+        let position = IVec2::new(x.parse().ok()?, y.parse().ok()?);
+        let in_bounds = position.x >= 0
+            && position.y >= 0
+            && position.x < self.x as i32
+            && position.y < self.y as i32;
+        in_bounds.then_some(position)
+    }
+}
+
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty() && !key.contains(char::is_whitespace)
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&hex[index..index + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile_generator::TileGeneratorDefault;
+
+    #[derive(Debug, PartialEq)]
+    struct Loot {
+        gold: u16,
+    }
+
+    impl TileExtension for Loot {
+        const KEY: &'static str = "loot";
+
+        fn encode(&self) -> Vec<u8> {
+            self.gold.to_le_bytes().to_vec()
+        }
+
+        fn decode(bytes: &[u8]) -> Option<Self> {
+            Some(Loot {
+                gold: u16::from_le_bytes(bytes.try_into().ok()?),
+            })
+        }
+    }
+
+    #[test]
+    fn map_and_extensions_round_trip() {
+        let mut map = Map::new(5, TileGeneratorDefault::with_seed(11));
+        map.tiles.get_mut(&IVec2::new(3, 3)).unwrap().biome = Biome::Lava;
+        let mut extensions = TileExtensions::new();
+        extensions.insert(IVec2::new(1, 2), &Loot { gold: 300 });
+        extensions.insert_raw("empty", IVec2::new(0, 0), Vec::new());
+
+        let text = MapFile::from_map(&map, extensions.clone()).write();
+        assert!(
+            text.lines()
+                .any(|line| line.starts_with("tile 3 3 ") && line.ends_with(" lava"))
+        );
+        let (loaded, loaded_extensions) = MapFile::parse(&text)
+            .unwrap()
+            .into_map(TileGeneratorDefault::new());
+
+        assert_eq!(loaded.tiles, map.tiles);
+        assert_eq!(loaded_extensions, extensions);
+        assert_eq!(
+            loaded_extensions.get::<Loot>(IVec2::new(1, 2)),
+            Some(Loot { gold: 300 })
+        );
+        assert_eq!(
+            loaded_extensions.raw("empty", IVec2::new(0, 0)),
+            Some(&[][..])
+        );
+    }
+
+    #[test]
+    fn toroidal_maps_keep_their_wrap_exits() {
+        let map = Map::new_toroidal(5, TileGeneratorDefault::with_seed(6));
+
+        let text = MapFile::from_map(&map, TileExtensions::new()).write();
+        assert!(text.contains("\ntopology toroidal\n"));
+        let (loaded, _) = MapFile::parse(&text)
+            .unwrap()
+            .into_map(TileGeneratorDefault::new());
+
+        assert_eq!(loaded.topology, Topology::Toroidal);
+        assert_eq!(loaded.tiles, map.tiles);
+        assert_eq!(loaded.validate(), Ok(()));
+        assert!(
+            !MapFile::from_map(
+                &Map::new(2, TileGeneratorDefault::new()),
+                TileExtensions::new()
+            )
+            .write()
+            .contains("topology")
+        );
+    }
+
+    #[test]
+    fn unknown_extensions_survive_a_round_trip() {
+        let text = "\
+brain-engine-map 1
+size 2 1
+tile 0 0 room E
+tile 1 0 corridor W
+ext weather 1 0 ff00
+";
+        let mut map_file = MapFile::parse(text).unwrap();
+        map_file
+            .extensions
+            .insert(IVec2::new(0, 0), &Loot { gold: 7 });
+
+        let written = map_file.write();
+        assert!(written.contains("ext weather 1 0 ff00\n"));
+        assert_eq!(
+            MapFile::parse(&written)
+                .unwrap()
+                .extensions
+                .raw("weather", IVec2::new(1, 0)),
+            Some(&[0xff, 0x00][..])
+        );
+    }
+
+    #[test]
+    fn parse_reports_malformed_files() {
+        assert_eq!(MapFile::parse(""), Err(MapFileError::MissingHeader));
+        assert_eq!(
+            MapFile::parse("brain-engine-map 9\n"),
+            Err(MapFileError::UnsupportedVersion("9".to_string()))
+        );
+        assert_eq!(
+            MapFile::parse("brain-engine-map 1\nsize 1 1\n"),
+            Err(MapFileError::MissingTile(IVec2::new(0, 0)))
+        );
+        assert!(matches!(
+            MapFile::parse("brain-engine-map 1\nsize 1 1\ntopology flat\ntile 0 0 room ZERO\n"),
+            Err(MapFileError::InvalidLine { line: 3, .. })
+        ));
+        assert!(matches!(
+            MapFile::parse("brain-engine-map 1\nsize 4294967296 1\n"),
+            Err(MapFileError::InvalidLine { line: 2, .. })
+        ));
+        assert!(matches!(
+            MapFile::parse("brain-engine-map 1\nsize 1 1\ntile 0 0 room Q\n"),
+            Err(MapFileError::InvalidLine { line: 3, .. })
+        ));
+        assert!(matches!(
+            MapFile::parse("brain-engine-map 1\nsize 1 1\ntile 0 0 room ZERO\next a 0 0 f\n"),
+            Err(MapFileError::InvalidLine { line: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn removing_the_last_value_drops_the_key() {
+        let mut extensions = TileExtensions::new();
+        extensions.insert(IVec2::new(3, 3), &Loot { gold: 1 });
+
+        assert_eq!(
+            extensions.remove::<Loot>(IVec2::new(3, 3)),
+            Some(Loot { gold: 1 })
+        );
+        assert!(extensions.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid tile extension key")]
+    fn extension_keys_cannot_contain_whitespace() {
+        TileExtensions::new().insert_raw("two words", IVec2::ZERO, Vec::new());
+    }
+}
@@ -1,9 +1,17 @@
+use crate::direction_set::DirectionSet;
+
+use bevy::prelude::IVec2;
+use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::str::FromStr;
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum TileSet {
     Room,
     Corridor,
+    /// Open ground, see [`crate::OpenFieldGenerator`].
+    Field,
 }
 
 impl fmt::Display for TileSet {
@@ -11,19 +19,107 @@ impl fmt::Display for TileSet {
         match self {
             TileSet::Room => write!(f, "room"),
             TileSet::Corridor => write!(f, "corridor"),
+            TileSet::Field => write!(f, "field"),
         }
     }
 }
 
+impl FromStr for TileSet {
+    type Err = ParseTileError;
+
+    /// Parses the names written by `Display`, e.g. `room`.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "room" => Ok(TileSet::Room),
+            "corridor" => Ok(TileSet::Corridor),
+            "field" => Ok(TileSet::Field),
+            _ => Err(ParseTileError(name.to_string())),
+        }
+    }
+}
+
+/// The terrain of a tile, independent of its [`TileSet`]: a room can be a
+/// flooded cave and a field can be grass or lava. Generators leave tiles in
+/// the [`Biome::Dungeon`]; wrap one in a [`crate::BiomeGenerator`] to assign
+/// others.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Biome {
+    #[default]
+    Dungeon,
+    Cave,
+    Grass,
+    Water,
+    Lava,
+}
+
+impl Biome {
+    pub const fn all() -> [Biome; 5] {
+        [
+            Biome::Dungeon,
+            Biome::Cave,
+            Biome::Grass,
+            Biome::Water,
+            Biome::Lava,
+        ]
+    }
+}
+
+impl fmt::Display for Biome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Biome::Dungeon => write!(f, "dungeon"),
+            Biome::Cave => write!(f, "cave"),
+            Biome::Grass => write!(f, "grass"),
+            Biome::Water => write!(f, "water"),
+            Biome::Lava => write!(f, "lava"),
+        }
+    }
+}
+
+impl FromStr for Biome {
+    type Err = ParseTileError;
+
+    /// Parses the names written by `Display`, e.g. `water`.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Biome::all()
+            .into_iter()
+            .find(|biome| biome.to_string() == name)
+            .ok_or_else(|| ParseTileError(name.to_string()))
+    }
+}
+
+/// Returned when a tile set name, biome name or exit code cannot be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseTileError(pub String);
+
+impl fmt::Display for ParseTileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid tile '{}'", self.0)
+    }
+}
+
+impl std::error::Error for ParseTileError {}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub struct Tile {
     pub tile_set: TileSet,
     pub map_tile: MapTile,
+    pub biome: Biome,
 }
 
 impl Tile {
+    /// A tile in the [`Biome::Dungeon`].
     pub fn new(tile_set: TileSet, map_tile: MapTile) -> Self {
-        Self { tile_set, map_tile }
+        Self {
+            tile_set,
+            map_tile,
+            biome: Biome::Dungeon,
+        }
+    }
+
+    pub fn with_biome(self, biome: Biome) -> Self {
+        Self { biome, ..self }
     }
 
     pub fn directions(&self) -> Vec<Direction> {
@@ -32,7 +128,8 @@ impl Tile {
 }
 
 #[repr(u8)]
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Direction {
     North = 1,
     East = 2,
@@ -79,6 +176,45 @@ impl Direction {
         }
     }
 
+    /// Reflects the direction left to right, swapping East and West.
+    pub const fn mirror_horizontal(self) -> Direction {
+        match self {
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+            direction => direction,
+        }
+    }
+
+    /// Reflects the direction top to bottom, swapping North and South.
+    pub const fn mirror_vertical(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            direction => direction,
+        }
+    }
+
+    /// Unit step in tile coordinates, with North pointing towards +y.
+    pub const fn offset(self) -> IVec2 {
+        match self {
+            Direction::North => IVec2::new(0, 1),
+            Direction::East => IVec2::new(1, 0),
+            Direction::South => IVec2::new(0, -1),
+            Direction::West => IVec2::new(-1, 0),
+        }
+    }
+
+    /// Inverse of [`Direction::offset`]; `None` unless `offset` is a unit step.
+    pub fn from_offset(offset: IVec2) -> Option<Direction> {
+        match (offset.x, offset.y) {
+            (0, 1) => Some(Direction::North),
+            (1, 0) => Some(Direction::East),
+            (0, -1) => Some(Direction::South),
+            (-1, 0) => Some(Direction::West),
+            _ => None,
+        }
+    }
+
     pub const fn all() -> [Direction; 4] {
         [
             Direction::North,
@@ -92,7 +228,7 @@ impl Direction {
 #[repr(u8)]
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum MapTile {
-    ZERO = 0 as u8, // 0
+    ZERO = 0, // 0
     // single exit (4)
     N = Direction::North as u8, // 1
     E = Direction::East as u8,  // 2
@@ -123,24 +259,16 @@ pub enum MapTile {
 impl MapTile {
     /// Build a Tile from 0-4 Direction enum values.
     /// Validates that only a maximum of one of each direction is allowed.
-    /// Returns None if the slice is empty, longer than 4 elements, or contains duplicate directions.
+    /// Returns None if the slice contains duplicate directions.
     pub fn from_directions(directions: &[Direction]) -> Option<MapTile> {
-        // Only allow 0-4 directions
-        if directions.len() > 4 {
-            return None;
-        }
-
-        // Check for duplicates using HashSet
-        use std::collections::HashSet;
-        let unique_dirs: HashSet<_> = directions.iter().collect();
-        if unique_dirs.len() != directions.len() {
-            return None;
-        }
-
-        // Sum the direction values (works because they're powers of 2)
-        let sum: u8 = directions.iter().map(|&dir| dir as u8).sum();
+        let exits: DirectionSet = directions.iter().copied().collect();
+        // Duplicates collapse in the set, so the lengths differ.
+        (exits.len() == directions.len()).then(|| exits.into())
+    }
 
-        match sum {
+    /// Builds the tile whose exit mask is `bits`. Returns None above 15.
+    pub(crate) const fn from_bits(bits: u8) -> Option<MapTile> {
+        match bits {
             0 => Some(MapTile::ZERO),
             1 => Some(MapTile::N),
             2 => Some(MapTile::E),
@@ -161,25 +289,77 @@ impl MapTile {
         }
     }
 
-    /// Return a Vec of Direction enum values representing this tile's exits in canonical NESW order.
-    pub fn directions(self) -> Vec<Direction> {
-        let mut dirs = Vec::new();
-        let bits = self as u8;
-
-        if bits & Direction::North as u8 != 0 {
-            dirs.push(Direction::North);
-        }
-        if bits & Direction::East as u8 != 0 {
-            dirs.push(Direction::East);
+    /// Like [`MapTile::from_bits`] for masks that can't exceed 15.
+    const fn from_mask(bits: u8) -> MapTile {
+        match MapTile::from_bits(bits & 0b1111) {
+            Some(map_tile) => map_tile,
+            None => unreachable!(),
         }
-        if bits & Direction::South as u8 != 0 {
-            dirs.push(Direction::South);
-        }
-        if bits & Direction::West as u8 != 0 {
-            dirs.push(Direction::West);
+    }
+
+    /// The directions the tile has exits towards.
+    pub const fn exits(self) -> DirectionSet {
+        match DirectionSet::from_bits(self as u8) {
+            Some(exits) => exits,
+            None => unreachable!(),
         }
+    }
 
-        dirs
+    /// Returns true when the tile has an exit towards `direction`.
+    pub const fn contains(self, direction: Direction) -> bool {
+        self.exits().contains(direction)
+    }
+
+    /// Returns this tile with an exit added towards `direction`.
+    pub const fn with(self, direction: Direction) -> MapTile {
+        MapTile::from_mask(self as u8 | direction as u8)
+    }
+
+    /// Returns this tile with its exit towards `direction` removed.
+    pub const fn without(self, direction: Direction) -> MapTile {
+        MapTile::from_mask(self as u8 & !(direction as u8))
+    }
+
+    /// Returns the tile with the exits of both tiles.
+    pub const fn union(self, other: MapTile) -> MapTile {
+        MapTile::from_mask(self as u8 | other as u8)
+    }
+
+    /// Returns the tile with only the exits both tiles share.
+    pub const fn intersection(self, other: MapTile) -> MapTile {
+        MapTile::from_mask(self as u8 & other as u8)
+    }
+
+    /// Turns every exit a quarter turn clockwise, e.g. `NE` becomes `ES`.
+    pub const fn rotate_clockwise(self) -> MapTile {
+        let bits = self as u8;
+        MapTile::from_mask(bits << 1 | bits >> 3)
+    }
+
+    /// Turns every exit a quarter turn counter clockwise, e.g. `NE` becomes
+    /// `NW`.
+    pub const fn rotate_counter_clockwise(self) -> MapTile {
+        let bits = self as u8;
+        MapTile::from_mask(bits >> 1 | bits << 3)
+    }
+
+    /// Reflects the tile left to right, swapping its East and West exits,
+    /// e.g. `NE` becomes `NW`.
+    pub const fn mirror_horizontal(self) -> MapTile {
+        let bits = self as u8;
+        MapTile::from_mask(bits & 0b0101 | (bits & 0b0010) << 2 | (bits & 0b1000) >> 2)
+    }
+
+    /// Reflects the tile top to bottom, swapping its North and South exits,
+    /// e.g. `NE` becomes `ES`.
+    pub const fn mirror_vertical(self) -> MapTile {
+        let bits = self as u8;
+        MapTile::from_mask(bits & 0b1010 | (bits & 0b0001) << 2 | (bits & 0b0100) >> 2)
+    }
+
+    /// Return a Vec of Direction enum values representing this tile's exits in canonical NESW order.
+    pub fn directions(self) -> Vec<Direction> {
+        self.exits().into_iter().collect()
     }
 }
 
@@ -204,6 +384,31 @@ impl fmt::Display for MapTile {
     }
 }
 
+impl FromStr for MapTile {
+    type Err = ParseTileError;
+
+    /// Parses the exit codes written by `Display`, e.g. `NES` or `ZERO`.
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        if code == "ZERO" {
+            return Ok(MapTile::ZERO);
+        }
+        let directions = code
+            .chars()
+            .map(|letter| match letter {
+                'N' => Some(Direction::North),
+                'E' => Some(Direction::East),
+                'S' => Some(Direction::South),
+                'W' => Some(Direction::West),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .filter(|directions| !directions.is_empty());
+        directions
+            .and_then(|directions| MapTile::from_directions(&directions))
+            .ok_or_else(|| ParseTileError(code.to_string()))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -304,6 +509,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn direction_offsets_roundtrip() {
+        for direction in Direction::all() {
+            assert_eq!(Direction::from_offset(direction.offset()), Some(direction));
+            assert_eq!(direction.offset(), -direction.opposite().offset());
+        }
+        assert_eq!(Direction::from_offset(IVec2::new(1, 1)), None);
+        assert_eq!(Direction::from_offset(IVec2::ZERO), None);
+    }
+
     #[test]
     fn displays_are_readable() {
         assert_eq!(MapTile::ZERO.to_string(), "ZERO");
@@ -311,6 +526,21 @@ mod tests {
         assert_eq!(Direction::North.to_string(), "North");
     }
 
+    #[test]
+    fn display_codes_parse_back() {
+        for bits in 0..16 {
+            let directions: Vec<_> = DirectionSet::from_bits(bits).unwrap().into_iter().collect();
+            let map_tile = MapTile::from_directions(&directions).unwrap();
+            assert_eq!(map_tile.to_string().parse(), Ok(map_tile));
+        }
+        for tile_set in [TileSet::Room, TileSet::Corridor] {
+            assert_eq!(tile_set.to_string().parse(), Ok(tile_set));
+        }
+        assert!("".parse::<MapTile>().is_err());
+        assert!("NN".parse::<MapTile>().is_err());
+        assert!("hall".parse::<TileSet>().is_err());
+    }
+
     #[test]
     fn tile_set_displays_correctly() {
         assert_eq!(TileSet::Room.to_string(), "room");
@@ -329,7 +559,10 @@ mod tests {
         let room_tile = Tile::new(TileSet::Room, MapTile::NE);
         let corridor_tile = Tile::new(TileSet::Corridor, MapTile::ESW);
 
-        assert_eq!(room_tile.directions(), vec![Direction::North, Direction::East]);
+        assert_eq!(
+            room_tile.directions(),
+            vec![Direction::North, Direction::East]
+        );
         assert_eq!(
             corridor_tile.directions(),
             vec![Direction::East, Direction::South, Direction::West]
@@ -337,6 +570,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::clone_on_copy)]
     fn tile_can_be_cloned_and_copied() {
         let tile1 = Tile::new(TileSet::Corridor, MapTile::NS);
         let tile2 = tile1;
@@ -347,4 +581,72 @@ mod tests {
         assert_eq!(tile1.tile_set, TileSet::Corridor);
         assert_eq!(tile1.map_tile, MapTile::NS);
     }
+
+    #[test]
+    fn exits_can_be_added_and_removed_one_at_a_time() {
+        let map_tile = MapTile::ZERO
+            .with(Direction::North)
+            .with(Direction::West)
+            .with(Direction::North);
+
+        assert_eq!(map_tile, MapTile::NW);
+        assert!(map_tile.contains(Direction::West));
+        assert!(!map_tile.contains(Direction::East));
+        assert_eq!(map_tile.without(Direction::North), MapTile::W);
+        assert_eq!(map_tile.without(Direction::South), MapTile::NW);
+    }
+
+    #[test]
+    fn union_and_intersection_combine_exits() {
+        assert_eq!(MapTile::NE.union(MapTile::ES), MapTile::NES);
+        assert_eq!(MapTile::NE.intersection(MapTile::ES), MapTile::E);
+        assert_eq!(MapTile::NS.intersection(MapTile::EW), MapTile::ZERO);
+    }
+
+    #[test]
+    fn rotation_turns_every_exit() {
+        assert_eq!(MapTile::NE.rotate_clockwise(), MapTile::ES);
+        assert_eq!(MapTile::W.rotate_clockwise(), MapTile::N);
+        assert_eq!(MapTile::NE.rotate_counter_clockwise(), MapTile::NW);
+        for bits in 0..16 {
+            let map_tile = MapTile::from_bits(bits).unwrap();
+            let rotated: Vec<Direction> = map_tile
+                .directions()
+                .into_iter()
+                .map(Direction::rotate_clockwise)
+                .collect();
+            assert_eq!(
+                map_tile.rotate_clockwise(),
+                MapTile::from_directions(&rotated).unwrap()
+            );
+            assert_eq!(
+                map_tile.rotate_clockwise().rotate_counter_clockwise(),
+                map_tile
+            );
+        }
+    }
+
+    #[test]
+    fn mirroring_reflects_every_exit() {
+        assert_eq!(MapTile::NE.mirror_horizontal(), MapTile::NW);
+        assert_eq!(MapTile::NE.mirror_vertical(), MapTile::ES);
+        assert_eq!(MapTile::NS.mirror_vertical(), MapTile::NS);
+        for bits in 0..16 {
+            let map_tile = MapTile::from_bits(bits).unwrap();
+            let mirrored: Vec<Direction> = map_tile
+                .directions()
+                .into_iter()
+                .map(Direction::mirror_horizontal)
+                .collect();
+            assert_eq!(
+                map_tile.mirror_horizontal(),
+                MapTile::from_directions(&mirrored).unwrap()
+            );
+            assert_eq!(map_tile.mirror_vertical().mirror_vertical(), map_tile);
+            assert_eq!(
+                map_tile.mirror_horizontal().mirror_vertical(),
+                map_tile.rotate_clockwise().rotate_clockwise()
+            );
+        }
+    }
 }
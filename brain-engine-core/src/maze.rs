@@ -0,0 +1,394 @@
+//! Perfect mazes: every tile is reachable and there is exactly one path
+//! between any two tiles, unless braiding adds loops.
+//!
+//! ```
+//! use bevy::prelude::*;
+//! use brain_engine_core::{Map, MazeAlgorithm, TileGeneratorMaze};
+//!
+//! let generator = TileGeneratorMaze::new(7).with_algorithm(MazeAlgorithm::Kruskal);
+//! let map = Map::new(12, generator);
+//! assert!(map.find_path(IVec2::ZERO, IVec2::new(11, 11)).is_some());
+//! ```
+
+use crate::generation_context::GenerationContext;
+use crate::map_tile::{Direction, MapTile, Tile, TileSet};
+use crate::tile_generator::TileGenerator;
+use crate::tile_grid::TileGrid;
+
+use bevy::prelude::*;
+use rand::seq::{IndexedRandom, SliceRandom};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use std::sync::Mutex;
+
+/// How [`TileGeneratorMaze`] carves its spanning tree.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum MazeAlgorithm {
+    /// Depth-first search: long, winding passages with few branches.
+    #[default]
+    RecursiveBacktracker,
+    /// Randomized Prim: grows from one tile, giving many short dead ends.
+    Prim,
+    /// Randomized Kruskal: joins random walls between separate areas, for an
+    /// even texture without a visible starting point.
+    Kruskal,
+}
+
+/// Generates a maze of [`TileSet::Corridor`] tiles filling the map.
+///
+/// The maze is carved once per map from the seed, so a seed always gives the
+/// same maze for the same map size. Braiding removes that share of the dead
+/// ends by opening a wall next to each, which adds loops.
+///
+/// Mazes need the extent of the map, so bounded and toroidal
+/// [`crate::Map`]s get one, carved across the seams on toroidal maps;
+/// unbounded maps such as [`crate::ChunkedMap`] are all wall. Exits are
+/// fitted to the tiles already placed around each tile, so regenerating a
+/// region or growing the map keeps the exits consistent.
+#[derive(Resource, Debug)]
+pub struct TileGeneratorMaze {
+    seed: u64,
+    algorithm: MazeAlgorithm,
+    braid: f64,
+    /// The last carved maze, in row-major order, with the bounds it fills
+    /// and whether it wraps around them.
+    maze: Mutex<Option<(IRect, bool, Vec<MapTile>)>>,
+}
+
+impl TileGeneratorMaze {
+    /// A perfect maze carved by [`MazeAlgorithm::RecursiveBacktracker`].
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            algorithm: MazeAlgorithm::default(),
+            braid: 0.0,
+            maze: Mutex::new(None),
+        }
+    }
+
+    pub fn with_algorithm(mut self, algorithm: MazeAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self.maze = Mutex::new(None);
+        self
+    }
+
+    /// Removes this share of the dead ends, from 0.0 for a perfect maze to
+    /// 1.0 for none at all.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `braid` is outside `0.0..=1.0`.
+    pub fn with_braid(mut self, braid: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&braid),
+            "braid must be between 0 and 1, got {braid}"
+        );
+        self.braid = braid;
+        self.maze = Mutex::new(None);
+        self
+    }
+
+    fn carve(&self, size: IVec2, wraps: bool) -> Vec<MapTile> {
+        let mut maze = Maze {
+            size,
+            wraps,
+            tiles: vec![MapTile::ZERO; size.element_product() as usize],
+        };
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        match self.algorithm {
+            MazeAlgorithm::RecursiveBacktracker => maze.backtrack(&mut rng),
+            MazeAlgorithm::Prim => maze.prim(&mut rng),
+            MazeAlgorithm::Kruskal => maze.kruskal(&mut rng),
+        }
+        if self.braid > 0.0 {
+            maze.braid(&mut rng, self.braid);
+        }
+        maze.tiles
+    }
+
+    /// The exits of `location` in the maze filling `bounds`, carving it
+    /// first unless it is the last one carved.
+    fn carved_tile(&self, bounds: IRect, wraps: bool, location: IVec2) -> MapTile {
+        let mut maze = self.maze.lock().unwrap();
+        if maze
+            .as_ref()
+            .is_none_or(|(carved, carved_wraps, _)| (*carved, *carved_wraps) != (bounds, wraps))
+        {
+            *maze = Some((bounds, wraps, self.carve(bounds.size() + 1, wraps)));
+        }
+        let (_, _, tiles) = maze.as_ref().unwrap();
+        let offset = location - bounds.min;
+        let width = bounds.width() + 1;
+        tiles[(offset.y * width + offset.x) as usize]
+    }
+}
+
+impl TileGenerator for TileGeneratorMaze {
+    fn tile_at(&self, tiles: &TileGrid, location: IVec2) -> Tile {
+        self.generate(&GenerationContext::new(tiles, location))
+    }
+
+    fn tile_at_within(&self, tiles: &TileGrid, location: IVec2, bounds: IRect) -> Tile {
+        self.generate(&GenerationContext::new(tiles, location).within(bounds))
+    }
+
+    fn generate(&self, context: &GenerationContext) -> Tile {
+        let carved = match (context.bounds(), context.wrapped_bounds()) {
+            (Some(bounds), _) => self.carved_tile(bounds, false, context.location()),
+            (None, Some(bounds)) => self.carved_tile(bounds, true, context.location()),
+            (None, None) => MapTile::ZERO,
+        };
+        Tile::new(TileSet::Corridor, context.fit_exits(carved.into()).into())
+    }
+}
+
+/// A maze being carved, positions relative to its bottom-left tile.
+struct Maze {
+    size: IVec2,
+    /// Whether steps off one edge arrive at the opposite edge.
+    wraps: bool,
+    tiles: Vec<MapTile>,
+}
+
+impl Maze {
+    fn index(&self, position: IVec2) -> usize {
+        (position.y * self.size.x + position.x) as usize
+    }
+
+    fn contains(&self, position: IVec2) -> bool {
+        position.cmpge(IVec2::ZERO).all() && position.cmplt(self.size).all()
+    }
+
+    fn positions(&self) -> impl Iterator<Item = IVec2> + use<> {
+        let size = self.size;
+        (0..size.y).flat_map(move |y| (0..size.x).map(move |x| IVec2::new(x, y)))
+    }
+
+    /// The tile one step from `position` towards `direction`, `None` off
+    /// the edge of a maze that doesn't wrap.
+    fn step(&self, position: IVec2, direction: Direction) -> Option<IVec2> {
+        let next = position + direction.offset();
+        if self.wraps {
+            Some(next.rem_euclid(self.size))
+        } else {
+            self.contains(next).then_some(next)
+        }
+    }
+
+    fn neighbors(&self, position: IVec2) -> impl Iterator<Item = Direction> + '_ {
+        Direction::all()
+            .into_iter()
+            .filter(move |direction| self.step(position, *direction).is_some())
+    }
+
+    fn is_dead_end(&self, position: IVec2) -> bool {
+        self.tiles[self.index(position)].directions().len() == 1
+    }
+
+    fn open(&mut self, position: IVec2, direction: Direction) {
+        let index = self.index(position);
+        self.tiles[index] = self.tiles[index].with(direction);
+        let neighbor = self.index(self.step(position, direction).unwrap());
+        self.tiles[neighbor] = self.tiles[neighbor].with(direction.opposite());
+    }
+
+    fn backtrack(&mut self, rng: &mut StdRng) {
+        let mut visited = vec![false; self.tiles.len()];
+        let mut stack = vec![IVec2::ZERO];
+        visited[0] = true;
+        while let Some(&current) = stack.last() {
+            let unvisited: Vec<Direction> = self
+                .neighbors(current)
+                .filter(|direction| !visited[self.index(self.step(current, *direction).unwrap())])
+                .collect();
+            let Some(&direction) = unvisited.choose(rng) else {
+                stack.pop();
+                continue;
+            };
+            let next = self.step(current, direction).unwrap();
+            visited[self.index(next)] = true;
+            self.open(current, direction);
+            stack.push(next);
+        }
+    }
+
+    fn prim(&mut self, rng: &mut StdRng) {
+        let mut visited = vec![false; self.tiles.len()];
+        visited[0] = true;
+        let mut frontier: Vec<(IVec2, Direction)> = self
+            .neighbors(IVec2::ZERO)
+            .map(|direction| (IVec2::ZERO, direction))
+            .collect();
+        while !frontier.is_empty() {
+            let (position, direction) = frontier.swap_remove(rng.random_range(0..frontier.len()));
+            let next = self.step(position, direction).unwrap();
+            if visited[self.index(next)] {
+                continue;
+            }
+            visited[self.index(next)] = true;
+            self.open(position, direction);
+            frontier.extend(self.neighbors(next).map(|direction| (next, direction)));
+        }
+    }
+
+    fn kruskal(&mut self, rng: &mut StdRng) {
+        let mut walls: Vec<(IVec2, Direction)> = self
+            .positions()
+            .flat_map(|position| [(position, Direction::East), (position, Direction::North)])
+            .filter(|(position, direction)| self.step(*position, *direction).is_some())
+            .collect();
+        walls.shuffle(rng);
+        let mut areas = UnionFind::new(self.tiles.len());
+        for (position, direction) in walls {
+            let neighbor = self.step(position, direction).unwrap();
+            if areas.union(self.index(position), self.index(neighbor)) {
+                self.open(position, direction);
+            }
+        }
+    }
+
+    /// Opens a wall at `braid` of the dead ends, preferring walls towards
+    /// other dead ends so one opening removes two.
+    fn braid(&mut self, rng: &mut StdRng, braid: f64) {
+        let mut dead_ends: Vec<IVec2> = self
+            .positions()
+            .filter(|position| self.is_dead_end(*position))
+            .collect();
+        dead_ends.shuffle(rng);
+        for position in dead_ends {
+            if !self.is_dead_end(position) || !rng.random_bool(braid) {
+                continue;
+            }
+            let map_tile = self.tiles[self.index(position)];
+            let closed: Vec<Direction> = self
+                .neighbors(position)
+                .filter(|direction| !map_tile.contains(*direction))
+                .collect();
+            let towards_dead_end = closed
+                .iter()
+                .copied()
+                .find(|direction| self.is_dead_end(self.step(position, *direction).unwrap()));
+            let Some(direction) = towards_dead_end.or_else(|| closed.choose(rng).copied()) else {
+                continue;
+            };
+            self.open(position, direction);
+        }
+    }
+}
+
+/// Disjoint sets of tile indices, for [`Maze::kruskal`].
+struct UnionFind {
+    parents: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(len: usize) -> Self {
+        Self {
+            parents: (0..len).collect(),
+        }
+    }
+
+    fn find(&mut self, mut index: usize) -> usize {
+        while self.parents[index] != index {
+            self.parents[index] = self.parents[self.parents[index]];
+            index = self.parents[index];
+        }
+        index
+    }
+
+    /// Joins the sets of `a` and `b`. Returns false when they were already
+    /// one set.
+    fn union(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        self.parents[a] = b;
+        a != b
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Map;
+
+    const ALGORITHMS: [MazeAlgorithm; 3] = [
+        MazeAlgorithm::RecursiveBacktracker,
+        MazeAlgorithm::Prim,
+        MazeAlgorithm::Kruskal,
+    ];
+
+    fn passages<G: TileGenerator>(map: &Map<G>) -> usize {
+        map.tiles
+            .values()
+            .map(|tile| tile.map_tile.directions().len())
+            .sum::<usize>()
+            / 2
+    }
+
+    #[test]
+    fn mazes_are_perfect() {
+        for algorithm in ALGORITHMS {
+            let map = Map::new(9, TileGeneratorMaze::new(3).with_algorithm(algorithm));
+
+            // A spanning tree of n tiles has n - 1 edges.
+            assert_eq!(passages(&map), 80, "{algorithm:?}");
+            assert_eq!(map.dijkstra_map(&[IVec2::ZERO]).len(), 81);
+            assert_eq!(map.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn mazes_repeat_for_a_seed() {
+        let first = Map::new(
+            10,
+            TileGeneratorMaze::new(5).with_algorithm(MazeAlgorithm::Prim),
+        );
+        let second = Map::new(
+            10,
+            TileGeneratorMaze::new(5).with_algorithm(MazeAlgorithm::Prim),
+        );
+
+        assert_eq!(first.tiles, second.tiles);
+    }
+
+    #[test]
+    fn braiding_removes_dead_ends() {
+        let dead_ends = |braid| {
+            let map = Map::new(12, TileGeneratorMaze::new(8).with_braid(braid));
+            assert_eq!(map.validate(), Ok(()));
+            map.dead_ends().len()
+        };
+
+        assert!(dead_ends(0.0) > 0);
+        assert_eq!(dead_ends(1.0), 0);
+    }
+
+    #[test]
+    fn toroidal_mazes_carve_across_the_seams() {
+        for algorithm in ALGORITHMS {
+            let map = Map::new_toroidal(8, TileGeneratorMaze::new(3).with_algorithm(algorithm));
+
+            assert_eq!(passages(&map), 63, "{algorithm:?}");
+            assert_eq!(map.dijkstra_map(&[IVec2::ZERO]).len(), 64);
+            assert!(
+                (0..8).any(|y| map.tiles[&IVec2::new(7, y)]
+                    .map_tile
+                    .contains(Direction::East))
+                    || (0..8).any(|x| map.tiles[&IVec2::new(x, 7)]
+                        .map_tile
+                        .contains(Direction::North)),
+                "{algorithm:?}"
+            );
+            assert_eq!(map.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn regenerated_regions_fit_the_tiles_around_them() {
+        let mut map = Map::new(10, TileGeneratorMaze::new(2));
+        let generator = TileGeneratorMaze::new(9);
+        map.generator = generator;
+
+        map.regenerate_region(IRect::new(3, 3, 6, 6));
+
+        assert_eq!(map.validate(), Ok(()));
+    }
+}
@@ -0,0 +1,272 @@
+//! Loading map files written by older versions of the format, so saved games
+//! keep working when the way tiles are stored changes.
+//!
+//! The `brain-engine-map <version>` header of a map file is its schema
+//! version. [`VersionedMap`] splits a file into that version and the lines
+//! after it, and [`MapMigrations`] upgrades the lines one version at a time
+//! until they are in [`MAP_FILE_VERSION`], which [`MapFile::parse`] reads:
+//!
+//! ```
+//! use brain_engine_core::{MapMigrations, MapMigrator};
+//!
+//! /// Version 0 called corridors halls.
+//! struct RenameHalls;
+//!
+//! impl MapMigrator for RenameHalls {
+//!     fn source_version(&self) -> u32 {
+//!         0
+//!     }
+//!
+//!     fn migrate(&self, body: &str) -> Result<String, String> {
+//!         Ok(body.replace(" hall ", " corridor "))
+//!     }
+//! }
+//!
+//! let old_save = "brain-engine-map 0\nsize 1 1\ntile 0 0 hall ZERO\n";
+//! let file = MapMigrations::new().with(RenameHalls).load(old_save).unwrap();
+//! assert_eq!(file.x, 1);
+//! ```
+
+use crate::map_file::{MAGIC, MAP_FILE_VERSION, MapFile, MapFileError};
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Upgrades the body of a map file, everything after the header, from
+/// [`MapMigrator::source_version`] to the version after it.
+///
+/// Add one to [`MapMigrations`] for every version a game still has saves
+/// in.
+pub trait MapMigrator {
+    /// The version this migrator reads. It writes `source_version() + 1`.
+    fn source_version(&self) -> u32;
+
+    /// Returns the rewritten body, or why it can't be migrated.
+    fn migrate(&self, body: &str) -> Result<String, String>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationError {
+    /// The first line is not a map file header.
+    MissingHeader,
+    /// The file was written by a newer version than this build reads.
+    NewerVersion(u32),
+    /// No migrator reads this version.
+    MissingMigrator(u32),
+    /// The migrator for `version` rejected the file.
+    Failed { version: u32, reason: String },
+    /// The migrated file is not a valid map file.
+    MapFile(MapFileError),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MigrationError::MissingHeader => write!(f, "not a map file"),
+            MigrationError::NewerVersion(version) => write!(
+                f,
+                "map file version {version} is newer than the supported version {MAP_FILE_VERSION}"
+            ),
+            MigrationError::MissingMigrator(version) => {
+                write!(f, "no migration from map file version {version}")
+            }
+            MigrationError::Failed { version, reason } => {
+                write!(
+                    f,
+                    "migrating from map file version {version} failed: {reason}"
+                )
+            }
+            MigrationError::MapFile(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for MigrationError {}
+
+/// A map file split into the schema version in its header and the body
+/// written in that version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionedMap {
+    pub version: u32,
+    pub body: String,
+}
+
+impl VersionedMap {
+    /// Wraps `file` in the current [`MAP_FILE_VERSION`].
+    pub fn from_map_file(file: &MapFile) -> Self {
+        Self::parse(&file.write()).expect("map files always have a header")
+    }
+
+    /// Reads the header, skipping blank and comment lines before it like
+    /// [`MapFile::parse`] does. The body is not checked.
+    pub fn parse(text: &str) -> Result<Self, MigrationError> {
+        let mut lines = text
+            .lines()
+            .skip_while(|line| line.trim().is_empty() || line.trim().starts_with('#'));
+        let header = lines.next().ok_or(MigrationError::MissingHeader)?;
+        let version = match header.split_whitespace().collect::<Vec<_>>()[..] {
+            [MAGIC, version] => version.parse().map_err(|_| MigrationError::MissingHeader)?,
+            _ => return Err(MigrationError::MissingHeader),
+        };
+        let body = lines.map(|line| format!("{line}\n")).collect();
+        Ok(Self { version, body })
+    }
+
+    pub fn write(&self) -> String {
+        format!("{MAGIC} {}\n{}", self.version, self.body)
+    }
+
+    /// Runs the migrators of `migrations` in order until the body is in
+    /// [`MAP_FILE_VERSION`].
+    pub fn migrate(mut self, migrations: &MapMigrations) -> Result<Self, MigrationError> {
+        if self.version > MAP_FILE_VERSION {
+            return Err(MigrationError::NewerVersion(self.version));
+        }
+        while self.version < MAP_FILE_VERSION {
+            let migrator = migrations
+                .migrators
+                .get(&self.version)
+                .ok_or(MigrationError::MissingMigrator(self.version))?;
+            self.body = migrator
+                .migrate(&self.body)
+                .map_err(|reason| MigrationError::Failed {
+                    version: self.version,
+                    reason,
+                })?;
+            self.version += 1;
+        }
+        Ok(self)
+    }
+
+    /// Parses the body as a [`MapFile`]. Only files in [`MAP_FILE_VERSION`]
+    /// parse, so [`VersionedMap::migrate`] older ones first.
+    pub fn into_map_file(self) -> Result<MapFile, MigrationError> {
+        MapFile::parse(&self.write()).map_err(|error| match error {
+            MapFileError::UnsupportedVersion(_) if self.version > MAP_FILE_VERSION => {
+                MigrationError::NewerVersion(self.version)
+            }
+            MapFileError::UnsupportedVersion(_) => MigrationError::MissingMigrator(self.version),
+            error => MigrationError::MapFile(error),
+        })
+    }
+}
+
+/// The [`MapMigrator`]s a game loads saves with, one per old version.
+#[derive(Default)]
+pub struct MapMigrations {
+    migrators: BTreeMap<u32, Box<dyn MapMigrator>>,
+}
+
+impl MapMigrations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `migrator`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if another migrator already reads the same version.
+    pub fn with(mut self, migrator: impl MapMigrator + 'static) -> Self {
+        let version = migrator.source_version();
+        let previous = self.migrators.insert(version, Box::new(migrator));
+        assert!(
+            previous.is_none(),
+            "two migrators from map file version {version}"
+        );
+        self
+    }
+
+    /// Parses a map file of any version these migrations reach, migrating
+    /// it to [`MAP_FILE_VERSION`] first.
+    pub fn load(&self, text: &str) -> Result<MapFile, MigrationError> {
+        VersionedMap::parse(text)?.migrate(self)?.into_map_file()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Map;
+    use crate::map_file::TileExtensions;
+    use crate::map_tile::TileSet;
+    use crate::tile_generator::TileGeneratorDefault;
+    use bevy::prelude::*;
+
+    /// Version 0 wrote tile sets in capitals.
+    struct LowercaseTileSets;
+
+    impl MapMigrator for LowercaseTileSets {
+        fn source_version(&self) -> u32 {
+            0
+        }
+
+        fn migrate(&self, body: &str) -> Result<String, String> {
+            body.lines()
+                .map(
+                    |line| match line.split_whitespace().collect::<Vec<_>>()[..] {
+                        ["tile", x, y, "ROOM", exits] => Ok(format!("tile {x} {y} room {exits}\n")),
+                        ["tile", x, y, "CORRIDOR", exits] => {
+                            Ok(format!("tile {x} {y} corridor {exits}\n"))
+                        }
+                        ["tile", ..] => Err(format!("unknown tile '{line}'")),
+                        _ => Ok(format!("{line}\n")),
+                    },
+                )
+                .collect()
+        }
+    }
+
+    #[test]
+    fn current_files_load_without_migrators() {
+        let map = Map::new(4, TileGeneratorDefault::with_seed(2));
+        let file = MapFile::from_map(&map, TileExtensions::new());
+        let versioned = VersionedMap::from_map_file(&file);
+
+        assert_eq!(versioned.version, MAP_FILE_VERSION);
+        assert_eq!(versioned.write(), file.write());
+        assert_eq!(MapMigrations::new().load(&file.write()), Ok(file));
+    }
+
+    #[test]
+    fn old_files_are_migrated_before_parsing() {
+        let old_save =
+            "# saved by 0.1\nbrain-engine-map 0\nsize 2 1\ntile 0 0 ROOM E\ntile 1 0 CORRIDOR W\n";
+        let migrations = MapMigrations::new().with(LowercaseTileSets);
+
+        let file = migrations.load(old_save).unwrap();
+
+        assert_eq!(file.tiles[&IVec2::new(0, 0)].tile_set, TileSet::Room);
+        assert_eq!(file.tiles[&IVec2::new(1, 0)].tile_set, TileSet::Corridor);
+    }
+
+    #[test]
+    fn loading_reports_versions_it_cannot_reach() {
+        let old_save = "brain-engine-map 0\nsize 1 1\ntile 0 0 VAULT ZERO\n";
+
+        assert_eq!(
+            MapMigrations::new().load(old_save),
+            Err(MigrationError::MissingMigrator(0))
+        );
+        assert!(matches!(
+            MapMigrations::new().with(LowercaseTileSets).load(old_save),
+            Err(MigrationError::Failed { version: 0, .. })
+        ));
+        assert_eq!(
+            MapMigrations::new().load("brain-engine-map 7\nsize 0 0\n"),
+            Err(MigrationError::NewerVersion(7))
+        );
+        assert_eq!(
+            MapMigrations::new().load("brain-engine-mop 1\n"),
+            Err(MigrationError::MissingHeader)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "two migrators from map file version 0")]
+    fn each_version_has_one_migrator() {
+        let _ = MapMigrations::new()
+            .with(LowercaseTileSets)
+            .with(LowercaseTileSets);
+    }
+}
@@ -0,0 +1,140 @@
+use crate::map_tile::{Biome, Tile, TileSet};
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+/// Multipliers applied to an entity's movement speed while it walks onto a
+/// tile of a given [`TileSet`]. Tile sets without a modifier move at 1.0.
+///
+/// By default corridors are quicker to cross than rooms.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct MovementSpeeds {
+    modifiers: HashMap<TileSet, f32>,
+}
+
+impl Default for MovementSpeeds {
+    fn default() -> Self {
+        Self::uniform()
+            .with_modifier(TileSet::Room, 1.0)
+            .with_modifier(TileSet::Corridor, 1.5)
+    }
+}
+
+impl MovementSpeeds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every tile set moves at the base speed.
+    pub fn uniform() -> Self {
+        Self {
+            modifiers: HashMap::new(),
+        }
+    }
+
+    pub fn with_modifier(mut self, tile_set: TileSet, modifier: f32) -> Self {
+        self.set_modifier(tile_set, modifier);
+        self
+    }
+
+    pub fn set_modifier(&mut self, tile_set: TileSet, modifier: f32) {
+        self.modifiers.insert(tile_set, modifier);
+    }
+
+    pub fn modifier(&self, tile_set: TileSet) -> f32 {
+        self.modifiers.get(&tile_set).copied().unwrap_or(1.0)
+    }
+
+    /// Returns `base_speed` scaled by the modifier for `tile_set`.
+    pub fn speed(&self, base_speed: f32, tile_set: TileSet) -> f32 {
+        base_speed * self.modifier(tile_set)
+    }
+}
+
+/// The cost of stepping onto a tile, for [`crate::Map::move_cost`] and
+/// [`crate::Map::find_path`]: the cost of its [`TileSet`] times the cost of
+/// its [`Biome`]. Missing costs are 1, and every cost is at least 1.
+///
+/// By default every tile set costs 1, water 3 and lava 5.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MovementCosts {
+    tile_sets: HashMap<TileSet, u32>,
+    biomes: HashMap<Biome, u32>,
+}
+
+impl Default for MovementCosts {
+    fn default() -> Self {
+        Self::uniform()
+            .with_biome_cost(Biome::Water, 3)
+            .with_biome_cost(Biome::Lava, 5)
+    }
+}
+
+impl MovementCosts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every step costs 1, so paths are the ones with the fewest steps.
+    pub fn uniform() -> Self {
+        Self {
+            tile_sets: HashMap::new(),
+            biomes: HashMap::new(),
+        }
+    }
+
+    pub fn with_tile_set_cost(mut self, tile_set: TileSet, cost: u32) -> Self {
+        self.tile_sets.insert(tile_set, cost.max(1));
+        self
+    }
+
+    pub fn with_biome_cost(mut self, biome: Biome, cost: u32) -> Self {
+        self.biomes.insert(biome, cost.max(1));
+        self
+    }
+
+    /// The cost of stepping onto `tile`.
+    pub fn cost(&self, tile: Tile) -> u32 {
+        let tile_set = self.tile_sets.get(&tile.tile_set).copied().unwrap_or(1);
+        let biome = self.biomes.get(&tile.biome).copied().unwrap_or(1);
+        tile_set.saturating_mul(biome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_tile::MapTile;
+
+    #[test]
+    fn corridors_are_faster_than_rooms_by_default() {
+        let speeds = MovementSpeeds::new();
+
+        assert!(speeds.speed(100.0, TileSet::Corridor) > speeds.speed(100.0, TileSet::Room));
+        assert_eq!(speeds.speed(100.0, TileSet::Room), 100.0);
+    }
+
+    #[test]
+    fn missing_modifiers_default_to_base_speed() {
+        let speeds = MovementSpeeds::uniform().with_modifier(TileSet::Room, 0.5);
+
+        assert_eq!(speeds.modifier(TileSet::Room), 0.5);
+        assert_eq!(speeds.modifier(TileSet::Corridor), 1.0);
+    }
+
+    #[test]
+    fn costs_multiply_tile_set_and_biome() {
+        let costs = MovementCosts::new().with_tile_set_cost(TileSet::Room, 2);
+        let room = Tile::new(TileSet::Room, MapTile::NESW);
+
+        assert_eq!(costs.cost(room), 2);
+        assert_eq!(costs.cost(room.with_biome(Biome::Water)), 6);
+        assert_eq!(costs.cost(Tile::new(TileSet::Corridor, MapTile::NS)), 1);
+        assert_eq!(
+            MovementCosts::uniform()
+                .with_biome_cost(Biome::Lava, 0)
+                .cost(room.with_biome(Biome::Lava)),
+            1
+        );
+    }
+}
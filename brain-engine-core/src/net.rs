@@ -0,0 +1,232 @@
+//! Client-side movement prediction for networked play.
+//!
+//! A client applies its own moves straight away and sends them to the server
+//! as numbered [`MoveInput`]s. The server applies them with the same rule,
+//! [`step`], and answers with a [`MoveAck`] holding the confirmed position
+//! and the last input it processed. [`MovePredictor::reconcile`] then replays
+//! the inputs the server hasn't seen yet on top of the confirmed position.
+
+use crate::map::Map;
+use crate::map_tile::Direction;
+use crate::region_graph::position_json;
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// One move sent from a client to the server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveInput {
+    /// Counts up from 0 per client, so acknowledgements can refer to it.
+    pub sequence: u32,
+    pub direction: Direction,
+}
+
+/// The server's answer: where the entity is after every input up to and
+/// including `sequence`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveAck {
+    pub sequence: u32,
+    #[serde(with = "position_json")]
+    pub position: IVec2,
+}
+
+/// The movement rule shared by server and clients: a step succeeds when
+/// [`Map::can_move`] allows it, otherwise the entity stays put. Steps
+/// cross the seams of toroidal maps like [`crate::Agent::try_move`].
+pub fn step<G: TileGenerator>(map: &Map<G>, position: IVec2, direction: Direction) -> IVec2 {
+    let target = map.adjacent(position, direction);
+    if map.can_move(position, target) {
+        target
+    } else {
+        position
+    }
+}
+
+/// Predicts a client's own position from the last confirmed position and
+/// the inputs still waiting for acknowledgement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MovePredictor {
+    confirmed: IVec2,
+    predicted: IVec2,
+    pending: VecDeque<MoveInput>,
+    next_sequence: u32,
+}
+
+impl MovePredictor {
+    pub fn new(start: IVec2) -> Self {
+        Self {
+            confirmed: start,
+            predicted: start,
+            pending: VecDeque::new(),
+            next_sequence: 0,
+        }
+    }
+
+    /// The last position the server confirmed.
+    pub fn confirmed(&self) -> IVec2 {
+        self.confirmed
+    }
+
+    /// Where the entity should be drawn now.
+    pub fn predicted(&self) -> IVec2 {
+        self.predicted
+    }
+
+    /// Inputs sent but not yet acknowledged, oldest first.
+    pub fn pending(&self) -> impl Iterator<Item = &MoveInput> {
+        self.pending.iter()
+    }
+
+    /// Applies a move locally and returns the input to send to the server.
+    pub fn push<G: TileGenerator>(&mut self, map: &Map<G>, direction: Direction) -> MoveInput {
+        let input = MoveInput {
+            sequence: self.next_sequence,
+            direction,
+        };
+        self.next_sequence += 1;
+        self.pending.push_back(input);
+        self.predicted = step(map, self.predicted, direction);
+        input
+    }
+
+    /// Takes a server acknowledgement: drops the inputs it covers and
+    /// replays the rest from the confirmed position. Returns the corrected
+    /// prediction when it differs from the previous one, i.e. when the
+    /// client mispredicted and has to snap or blend to the new position.
+    /// Acknowledgements older than one already seen are ignored.
+    pub fn reconcile<G: TileGenerator>(&mut self, map: &Map<G>, ack: MoveAck) -> Option<IVec2> {
+        if ack.sequence >= self.next_sequence {
+            return None;
+        }
+        if self
+            .pending
+            .front()
+            .is_none_or(|input| ack.sequence < input.sequence)
+        {
+            return None;
+        }
+        while self
+            .pending
+            .front()
+            .is_some_and(|input| input.sequence <= ack.sequence)
+        {
+            self.pending.pop_front();
+        }
+
+        self.confirmed = ack.position;
+        let predicted = self.pending.iter().fold(self.confirmed, |position, input| {
+            step(map, position, input.direction)
+        });
+        let previous = std::mem::replace(&mut self.predicted, predicted);
+        (predicted != previous).then_some(predicted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::open_field::OpenFieldGenerator;
+    use crate::testing::GridBuilder;
+
+    /// A corridor running east along row 0; every other tile is closed.
+    fn east_corridor() -> Map<OpenFieldGenerator> {
+        GridBuilder::new("    \n    \n    \n╶──╴").build(OpenFieldGenerator)
+    }
+
+    #[test]
+    fn steps_follow_the_map_rules() {
+        let map = east_corridor();
+
+        assert_eq!(step(&map, IVec2::ZERO, Direction::East), IVec2::new(1, 0));
+        assert_eq!(step(&map, IVec2::ZERO, Direction::North), IVec2::ZERO);
+        assert_eq!(
+            step(&map, IVec2::new(3, 0), Direction::East),
+            IVec2::new(3, 0)
+        );
+    }
+
+    #[test]
+    fn steps_cross_toroidal_seams() {
+        let map = GridBuilder::new("    \n    \n    \n────")
+            .toroidal()
+            .build(OpenFieldGenerator);
+        let mut predictor = MovePredictor::new(IVec2::new(3, 0));
+
+        predictor.push(&map, Direction::East);
+
+        assert_eq!(predictor.predicted(), IVec2::ZERO);
+        assert_eq!(step(&map, IVec2::ZERO, Direction::West), IVec2::new(3, 0));
+    }
+
+    #[test]
+    fn confirmed_inputs_keep_the_prediction() {
+        let map = east_corridor();
+        let mut predictor = MovePredictor::new(IVec2::ZERO);
+
+        let first = predictor.push(&map, Direction::East);
+        predictor.push(&map, Direction::East);
+        assert_eq!(predictor.predicted(), IVec2::new(2, 0));
+
+        let ack = MoveAck {
+            sequence: first.sequence,
+            position: IVec2::new(1, 0),
+        };
+        assert_eq!(predictor.reconcile(&map, ack), None);
+        assert_eq!(predictor.confirmed(), IVec2::new(1, 0));
+        assert_eq!(predictor.pending().count(), 1);
+    }
+
+    #[test]
+    fn corrections_replay_pending_inputs() {
+        let map = east_corridor();
+        let mut predictor = MovePredictor::new(IVec2::ZERO);
+        let first = predictor.push(&map, Direction::East);
+        predictor.push(&map, Direction::East);
+
+        // The server saw the entity blocked, e.g. by another player.
+        let ack = MoveAck {
+            sequence: first.sequence,
+            position: IVec2::ZERO,
+        };
+
+        assert_eq!(predictor.reconcile(&map, ack), Some(IVec2::new(1, 0)));
+        assert_eq!(predictor.predicted(), IVec2::new(1, 0));
+    }
+
+    #[test]
+    fn stale_acknowledgements_are_ignored() {
+        let map = east_corridor();
+        let mut predictor = MovePredictor::new(IVec2::ZERO);
+        let first = predictor.push(&map, Direction::East);
+        let second = predictor.push(&map, Direction::East);
+        predictor.reconcile(
+            &map,
+            MoveAck {
+                sequence: second.sequence,
+                position: IVec2::new(2, 0),
+            },
+        );
+
+        let stale = MoveAck {
+            sequence: first.sequence,
+            position: IVec2::new(1, 0),
+        };
+        assert_eq!(predictor.reconcile(&map, stale), None);
+        assert_eq!(predictor.confirmed(), IVec2::new(2, 0));
+    }
+
+    #[test]
+    fn messages_serialize_positions_as_arrays() {
+        let ack = MoveAck {
+            sequence: 3,
+            position: IVec2::new(4, 5),
+        };
+
+        let json = serde_json::to_string(&ack).unwrap();
+
+        assert_eq!(json, r#"{"sequence":3,"position":[4,5]}"#);
+        assert_eq!(serde_json::from_str::<MoveAck>(&json).unwrap(), ack);
+    }
+}
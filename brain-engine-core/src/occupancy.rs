@@ -0,0 +1,184 @@
+//! Which entity stands on each tile, so entities can block each other.
+//!
+//! ```
+//! use bevy::prelude::*;
+//! use brain_engine_core::{Map, OccupyError, OpenFieldGenerator};
+//!
+//! let mut map = Map::new(4, OpenFieldGenerator);
+//! map.occupancy.blocks_movement = true;
+//! map.try_occupy(IVec2::new(1, 0), 7).unwrap();
+//!
+//! assert!(map.is_blocked_by_occupant(IVec2::new(1, 0)));
+//! assert_eq!(map.try_occupy(IVec2::new(1, 0), 8), Err(OccupyError::Occupied(7)));
+//! ```
+
+use crate::map::Map;
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OccupyError {
+    /// The position is off the map.
+    OffMap,
+    /// Another entity, with this ID, stands on the tile.
+    Occupied(u64),
+}
+
+impl fmt::Display for OccupyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OccupyError::OffMap => write!(f, "the tile is off the map"),
+            OccupyError::Occupied(id) => write!(f, "the tile is occupied by {id}"),
+        }
+    }
+}
+
+impl std::error::Error for OccupyError {}
+
+/// The entity standing on each tile of a [`Map`], at most one per tile,
+/// identified by any `u64` the game uses, such as `Entity::to_bits`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Occupancy {
+    occupants: HashMap<IVec2, u64>,
+    /// Whether [`crate::Agent::try_move`], [`crate::Simulation`] and
+    /// [`Map::find_path`] treat occupied tiles as blocked. Off by default.
+    /// [`Map::can_move`] only looks at the layout either way.
+    pub blocks_movement: bool,
+}
+
+impl Occupancy {
+    pub fn occupant(&self, position: IVec2) -> Option<u64> {
+        self.occupants.get(&position).copied()
+    }
+
+    pub fn is_occupied(&self, position: IVec2) -> bool {
+        self.occupants.contains_key(&position)
+    }
+
+    /// Every occupied tile with its occupant, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (IVec2, u64)> + '_ {
+        self.occupants.iter().map(|(position, id)| (*position, *id))
+    }
+
+    /// Moves every occupant by `offset`, dropping those `keep` rejects, for
+    /// maps that are resized or cropped.
+    pub(crate) fn shift(&mut self, offset: IVec2, keep: impl Fn(IVec2) -> bool) {
+        self.occupants = self
+            .occupants
+            .drain()
+            .map(|(position, id)| (position + offset, id))
+            .filter(|(position, _)| keep(*position))
+            .collect();
+    }
+}
+
+impl<G: TileGenerator> Map<G> {
+    /// Places entity `id` on the tile at `position`. Standing there already
+    /// is fine; another occupant or a position off the map is an error.
+    pub fn try_occupy(&mut self, position: IVec2, id: u64) -> Result<(), OccupyError> {
+        if !self.in_bounds(position) {
+            return Err(OccupyError::OffMap);
+        }
+        match self.occupancy.occupant(position) {
+            Some(occupant) if occupant != id => Err(OccupyError::Occupied(occupant)),
+            _ => {
+                self.occupancy.occupants.insert(position, id);
+                Ok(())
+            }
+        }
+    }
+
+    /// Frees the tile at `position`, returning the entity that stood there.
+    pub fn vacate(&mut self, position: IVec2) -> Option<u64> {
+        self.occupancy.occupants.remove(&position)
+    }
+
+    pub fn is_occupied(&self, position: IVec2) -> bool {
+        self.occupancy.is_occupied(position)
+    }
+
+    /// Whether [`Occupancy::blocks_movement`] is on and someone stands on
+    /// the tile at `position`, for movement rules on top of
+    /// [`Map::can_move`].
+    pub fn is_blocked_by_occupant(&self, position: IVec2) -> bool {
+        self.occupancy.blocks_movement && self.occupancy.is_occupied(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::Agent;
+    use crate::map_tile::Direction;
+    use crate::open_field::OpenFieldGenerator;
+
+    #[test]
+    fn tiles_hold_one_occupant() {
+        let mut map = Map::new(3, OpenFieldGenerator);
+
+        assert_eq!(map.try_occupy(IVec2::ZERO, 1), Ok(()));
+        assert_eq!(map.try_occupy(IVec2::ZERO, 1), Ok(()));
+        assert_eq!(
+            map.try_occupy(IVec2::ZERO, 2),
+            Err(OccupyError::Occupied(1))
+        );
+        assert_eq!(
+            map.try_occupy(IVec2::new(3, 0), 2),
+            Err(OccupyError::OffMap)
+        );
+        assert!(map.is_occupied(IVec2::ZERO));
+
+        assert_eq!(map.vacate(IVec2::ZERO), Some(1));
+        assert_eq!(map.vacate(IVec2::ZERO), None);
+        assert_eq!(map.try_occupy(IVec2::ZERO, 2), Ok(()));
+    }
+
+    #[test]
+    fn occupants_block_movement_when_enabled() {
+        let mut map = Map::new(3, OpenFieldGenerator);
+        map.try_occupy(IVec2::new(1, 0), 5).unwrap();
+        assert!(map.can_move(IVec2::ZERO, IVec2::new(1, 0)));
+
+        assert!(Agent::new(IVec2::ZERO).try_move(&map, Direction::East));
+
+        map.occupancy.blocks_movement = true;
+
+        assert!(map.can_move(IVec2::ZERO, IVec2::new(1, 0)));
+        assert!(!Agent::new(IVec2::ZERO).try_move(&map, Direction::East));
+        assert!(Agent::new(IVec2::new(1, 0)).try_move(&map, Direction::East));
+        let path = map.find_path(IVec2::ZERO, IVec2::new(2, 0)).unwrap();
+        assert!(!path.contains(&IVec2::new(1, 0)));
+        assert_eq!(
+            map.find_path(IVec2::ZERO, IVec2::new(1, 0)),
+            Some(vec![IVec2::ZERO, IVec2::new(1, 0)])
+        );
+    }
+
+    #[test]
+    fn occupants_do_not_change_the_layout() {
+        let mut map = Map::new(3, OpenFieldGenerator);
+        map.occupancy.blocks_movement = true;
+        for x in 0..3 {
+            map.try_occupy(IVec2::new(x, 1), x as u64).unwrap();
+        }
+
+        assert_eq!(map.validate(), Ok(()));
+        assert_eq!(map.reachable_from(IVec2::ZERO).len(), 9);
+        assert!(map.line_of_sight(IVec2::ZERO, IVec2::new(0, 2)));
+    }
+
+    #[test]
+    fn occupants_move_with_crops() {
+        let mut map = Map::new(4, OpenFieldGenerator);
+        map.try_occupy(IVec2::new(2, 2), 3).unwrap();
+        map.try_occupy(IVec2::ZERO, 4).unwrap();
+
+        map.crop(IRect::new(1, 1, 3, 3));
+
+        assert_eq!(map.occupancy.occupant(IVec2::new(1, 1)), Some(3));
+        assert_eq!(map.occupancy.iter().count(), 1);
+    }
+}
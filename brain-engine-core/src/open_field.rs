@@ -0,0 +1,150 @@
+//! Open-field maps for outdoor areas such as an overworld.
+//!
+//! Field tiles are open towards every neighbor on the map, so players roam
+//! freely instead of following corridors. Obstacles (rocks, trees, walls) are
+//! placed explicitly with [`Map::place_obstacle`]: an obstacle is a
+//! [`TileSet::Field`] tile without exits, and its neighbors lose their exits
+//! towards it, so [`Map::can_move`], pathfinding and the renderers treat it
+//! like any other wall.
+
+use crate::map::Map;
+use crate::map_tile::{Direction, MapTile, Tile, TileSet};
+use crate::tile_generator::TileGenerator;
+use crate::tile_grid::TileGrid;
+
+use bevy::prelude::*;
+
+/// Generates a fully open [`TileSet::Field`]. On a bounded map the border
+/// tiles have no exits off the map; unbounded and toroidal maps are open
+/// everywhere.
+#[derive(Resource, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpenFieldGenerator;
+
+impl TileGenerator for OpenFieldGenerator {
+    fn tile_at(&self, _tiles: &TileGrid, _location: IVec2) -> Tile {
+        Tile::new(TileSet::Field, MapTile::NESW)
+    }
+
+    fn tile_at_within(&self, _tiles: &TileGrid, location: IVec2, bounds: IRect) -> Tile {
+        let map_tile = Direction::all()
+            .into_iter()
+            .filter(|direction| bounds.contains(location + direction.offset()))
+            .fold(MapTile::ZERO, MapTile::with);
+        Tile::new(TileSet::Field, map_tile)
+    }
+}
+
+impl<G: TileGenerator> Map<G> {
+    /// Returns true for a field tile without exits.
+    ///
+    /// A field tile walled in by obstacles on every side has no exits
+    /// either, so it reads as an obstacle too.
+    pub fn is_obstacle(&self, position: IVec2) -> bool {
+        self.tiles
+            .get(&position)
+            .is_some_and(|tile| tile.tile_set == TileSet::Field && tile.map_tile == MapTile::ZERO)
+    }
+
+    /// Turns the tile at `position` into an obstacle, closing the exits of
+    /// its neighbors towards it. Returns false when `position` is off the
+    /// map.
+    pub fn place_obstacle(&mut self, position: IVec2) -> bool {
+        if !self.in_bounds(position) {
+            return false;
+        }
+        let before = self.tiles_before(self.neighborhood(position));
+        for direction in Direction::all() {
+            let neighbor = self.adjacent(position, direction);
+            if let Some(tile) = self.tiles.get_mut(&neighbor) {
+                tile.map_tile = tile.map_tile.without(direction.opposite());
+            }
+        }
+        self.tiles
+            .insert(position, Tile::new(TileSet::Field, MapTile::ZERO));
+        self.publish_changes(before);
+        true
+    }
+
+    /// Turns the obstacle at `position` back into open field, joined to
+    /// every neighboring field tile that isn't an obstacle. Returns false
+    /// when there is no obstacle at `position`.
+    pub fn clear_obstacle(&mut self, position: IVec2) -> bool {
+        if !self.is_obstacle(position) {
+            return false;
+        }
+        let before = self.tiles_before(self.neighborhood(position));
+        let mut map_tile = MapTile::ZERO;
+        for direction in Direction::all() {
+            let neighbor = self.adjacent(position, direction);
+            if !self.in_bounds(neighbor) || self.is_obstacle(neighbor) {
+                continue;
+            }
+            if let Some(tile) = self.tiles.get_mut(&neighbor)
+                && tile.tile_set == TileSet::Field
+            {
+                tile.map_tile = tile.map_tile.with(direction.opposite());
+                map_tile = map_tile.with(direction);
+            }
+        }
+        self.tiles
+            .insert(position, Tile::new(TileSet::Field, map_tile));
+        self.publish_changes(before);
+        true
+    }
+
+    /// `position` and the tiles next to it.
+    fn neighborhood(&self, position: IVec2) -> Vec<IVec2> {
+        let neighbors = Direction::all().map(|direction| self.adjacent(position, direction));
+        [position].into_iter().chain(neighbors).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fields_are_open_up_to_the_border() {
+        let map = Map::new(4, OpenFieldGenerator);
+
+        assert_eq!(map.tiles[&IVec2::new(1, 2)].map_tile, MapTile::NESW);
+        assert_eq!(map.tiles[&IVec2::new(0, 0)].map_tile, MapTile::NE);
+        assert_eq!(map.tiles[&IVec2::new(3, 3)].map_tile, MapTile::SW);
+        assert_eq!(map.reachable_from(IVec2::ZERO).len(), 16);
+        assert_eq!(map.validate(), Ok(()));
+    }
+
+    #[test]
+    fn obstacles_block_movement_until_cleared() {
+        let mut map = Map::new(3, OpenFieldGenerator);
+        let before = map.tiles.clone();
+
+        assert!(map.place_obstacle(IVec2::new(1, 1)));
+
+        assert!(map.is_obstacle(IVec2::new(1, 1)));
+        assert!(!map.can_move(IVec2::new(1, 0), IVec2::new(1, 1)));
+        assert_eq!(
+            map.find_path(IVec2::new(1, 0), IVec2::new(1, 2))
+                .unwrap()
+                .len(),
+            5
+        );
+        assert_eq!(map.render_ascii(), "...\n.#.\n...\n");
+        assert_eq!(map.validate(), Ok(()));
+
+        assert!(map.clear_obstacle(IVec2::new(1, 1)));
+        assert_eq!(map.tiles, before);
+        assert!(!map.clear_obstacle(IVec2::new(1, 1)));
+    }
+
+    #[test]
+    fn toroidal_fields_wrap() {
+        let mut map = Map::new_toroidal(3, OpenFieldGenerator);
+
+        map.place_obstacle(IVec2::new(0, 0));
+
+        assert!(map.can_move(IVec2::new(2, 1), IVec2::new(0, 1)));
+        assert!(!map.can_move(IVec2::new(2, 0), IVec2::new(0, 0)));
+        assert_eq!(map.validate(), Ok(()));
+    }
+}
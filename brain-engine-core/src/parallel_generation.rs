@@ -0,0 +1,203 @@
+use crate::exploration::Exploration;
+use crate::generation_context::GenerationContext;
+use crate::map::Map;
+use crate::map_events::MapSubscribers;
+use crate::map_tile::Tile;
+use crate::movement::MovementCosts;
+use crate::occupancy::Occupancy;
+use crate::tile_generator::TileGenerator;
+use crate::tile_grid::TileGrid;
+use crate::topology::Topology;
+
+use bevy::prelude::*;
+use itertools::iproduct;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Side length of the chunks [`Map::new_parallel`] hands to worker threads.
+const PARALLEL_CHUNK_SIZE: i32 = 32;
+
+impl<G: TileGenerator + Sync> Map<G> {
+    /// Like [`Map::new`], but generates the tiles on every available core.
+    ///
+    /// The map is split into square chunks coloured like a checkerboard.
+    /// All "black" chunks are generated first, in parallel and independently
+    /// of each other. The "white" chunks are then generated in parallel too:
+    /// they only touch black chunks, so each one sees all of its neighbours
+    /// and exits line up across every seam, as with [`Map::new`].
+    ///
+    /// Tiles are drawn in a different order than [`Map::new`], and a seeded
+    /// generator's random numbers are shared between threads, so the result
+    /// is a valid map but not the one [`Map::new`] would produce, and not
+    /// reproducible from a seed.
+    ///
+    /// Each chunk's generator only sees the chunk and the ring of tiles
+    /// around it (see [`GenerationContext::visible_within`]), which is all
+    /// that exits need, so generators that look further afield should use
+    /// [`Map::new`].
+    pub fn new_parallel(size: usize, generator: G) -> Self {
+        let mut map = Self {
+            size,
+            x: size,
+            y: size,
+            tiles: TileGrid::with_size(UVec2::splat(size as u32)),
+            topology: Topology::Bounded,
+            generator,
+            movement_costs: MovementCosts::default(),
+            exploration: Exploration::default(),
+            occupancy: Occupancy::default(),
+            subscribers: MapSubscribers::default(),
+        };
+
+        let chunk_count = (size as i32 + PARALLEL_CHUNK_SIZE - 1) / PARALLEL_CHUNK_SIZE;
+        let (black, white): (Vec<IVec2>, Vec<IVec2>) = iproduct!(0..chunk_count, 0..chunk_count)
+            .map(|(x, y)| IVec2::new(x, y))
+            .partition(|chunk| (chunk.x + chunk.y) % 2 == 0);
+        let placed = AtomicUsize::new(0);
+        for chunks in [black, white] {
+            let generated = map.generate_chunks_in_parallel(&chunks, &placed);
+            map.tiles.extend(generated);
+        }
+        map
+    }
+
+    /// Generates each chunk on a worker thread, seeing the tiles already in
+    /// the map around it, and returns the new tiles. `placed` counts the
+    /// tiles placed by every thread, for the contexts' progress.
+    fn generate_chunks_in_parallel(
+        &self,
+        chunks: &[IVec2],
+        placed: &AtomicUsize,
+    ) -> Vec<(IVec2, Tile)> {
+        let workers = thread::available_parallelism()
+            .map_or(1, |workers| workers.get())
+            .min(chunks.len());
+        let next_chunk = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..workers)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut generated = Vec::new();
+                        loop {
+                            let index = next_chunk.fetch_add(1, Ordering::Relaxed);
+                            let Some(chunk) = chunks.get(index) else {
+                                break generated;
+                            };
+                            generated.extend(self.generate_chunk(*chunk, placed));
+                        }
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        })
+    }
+
+    fn generate_chunk(&self, chunk: IVec2, placed: &AtomicUsize) -> Vec<(IVec2, Tile)> {
+        let min = chunk * PARALLEL_CHUNK_SIZE;
+        let max = (min + PARALLEL_CHUNK_SIZE).min(IVec2::new(self.x as i32, self.y as i32));
+
+        // Exits only depend on direct neighbours, so the chunk and the ring
+        // of tiles just outside it is all the generator sees of the shared
+        // map. Copying more would cost a copy of the map per chunk.
+        let visible_area = IRect::from_corners(min - 1, max);
+        let mut tiles = TileGrid::new();
+        for (x, y) in iproduct!(
+            visible_area.min.x..=visible_area.max.x,
+            visible_area.min.y..=visible_area.max.y
+        ) {
+            let position = IVec2::new(x, y);
+            if let Some(tile) = self.tiles.get(&position) {
+                tiles.insert(position, *tile);
+            }
+        }
+
+        let bounds = self.bounds();
+        let total = self.x * self.y;
+        let mut generated = Vec::new();
+        for (x, y) in iproduct!(min.x..max.x, min.y..max.y) {
+            let position = IVec2::new(x, y);
+            let context = GenerationContext::new(&tiles, position)
+                .within(bounds)
+                .visible_within(visible_area)
+                .with_progress(placed.fetch_add(1, Ordering::Relaxed), total);
+            let tile = self.generator.generate(&context);
+            tiles.insert(position, tile);
+            generated.push((position, tile));
+        }
+        generated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::direction_set::DirectionSet;
+    use crate::map_tile::{Direction, TileSet};
+    use crate::tile_generator::TileGeneratorDefault;
+
+    /// Open rooms that check what their chunk's context shows them.
+    struct CheckedContexts;
+
+    impl TileGenerator for CheckedContexts {
+        fn tile_at(&self, tiles: &TileGrid, location: IVec2) -> Tile {
+            self.generate(&GenerationContext::new(tiles, location))
+        }
+
+        fn generate(&self, context: &GenerationContext) -> Tile {
+            let visible_area = context.visible_area().unwrap();
+            for direction in Direction::all() {
+                assert!(visible_area.contains(context.location() + direction.offset()));
+            }
+            assert!(context.generated().unwrap() < context.total().unwrap());
+            Tile::new(TileSet::Room, context.fit_exits(DirectionSet::ALL).into())
+        }
+    }
+
+    #[test]
+    fn parallel_maps_fill_every_tile() {
+        let map = Map::new_parallel(70, TileGeneratorDefault::new());
+
+        assert_eq!(map.tiles.len(), 70 * 70);
+        assert_eq!(map.validate(), Ok(()));
+    }
+
+    #[test]
+    fn chunks_see_their_neighbors_and_progress() {
+        let map = Map::new_parallel(40, CheckedContexts);
+
+        assert_eq!(map.validate(), Ok(()));
+    }
+
+    #[test]
+    fn exits_line_up_across_chunk_seams() {
+        let map = Map::new_parallel(
+            100,
+            TileGeneratorDefault::with_seed_and_probabilities(8, 0.6, 0.3),
+        );
+
+        for (x, y) in iproduct!(0..100, 0..100) {
+            let position = IVec2::new(x, y);
+            for direction in [Direction::North, Direction::East] {
+                let neighbor = position + direction.offset();
+                if !map.in_bounds(neighbor) {
+                    continue;
+                }
+                assert_eq!(
+                    map.tiles[&position].map_tile.contains(direction),
+                    map.tiles[&neighbor].map_tile.contains(direction.opposite()),
+                    "mismatched exits at {position} towards {direction}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn small_maps_fit_in_a_single_chunk() {
+        let map = Map::new_parallel(3, TileGeneratorDefault::with_seed(1));
+
+        assert_eq!(map.tiles.len(), 9);
+    }
+}
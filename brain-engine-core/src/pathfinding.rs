@@ -0,0 +1,380 @@
+use crate::map::Map;
+use crate::map_tile::Direction;
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+
+impl<G: TileGenerator> Map<G> {
+    /// Finds a cheapest path between two tiles using A*, adding up
+    /// [`Map::move_cost`] for every step. With uniform
+    /// [`Map::movement_costs`] this is a shortest path.
+    ///
+    /// The returned path starts with `from` and ends with `to`, and every
+    /// consecutive pair of positions satisfies [`Map::can_move`]. Tiles
+    /// [`Map::is_blocked_by_occupant`] are avoided, except `to` itself so
+    /// paths can lead up to an occupant. Returns `None` when either end is
+    /// off the map or `to` is unreachable.
+    pub fn find_path(&self, from: IVec2, to: IVec2) -> Option<Vec<IVec2>> {
+        match IncrementalPathfinder::new(self, from, to).step(self, usize::MAX) {
+            PathfindingStatus::Found(path) => Some(path),
+            PathfindingStatus::InProgress | PathfindingStatus::NotFound => None,
+        }
+    }
+
+    /// The tile to step onto from `from` to follow [`Map::find_path`] to
+    /// `to`, one move per turn. `None` when `to` is unreachable or already
+    /// reached.
+    ///
+    /// Call it again every turn rather than keeping the path, so travel
+    /// adapts when doors change or occupants block the way.
+    pub fn next_step_toward(&self, from: IVec2, to: IVec2) -> Option<IVec2> {
+        self.find_path(from, to)?.get(1).copied()
+    }
+
+    /// Steps from every tile to the nearest of `sources`, through exits
+    /// [`Map::can_move`] allows. Tiles no source reaches are left out, and
+    /// so are sources off the map.
+    ///
+    /// Compute it once for flee or approach maps and for scaling difficulty
+    /// by distance, instead of calling [`Map::find_path`] per tile.
+    pub fn dijkstra_map(&self, sources: &[IVec2]) -> HashMap<IVec2, u32> {
+        let mut distances = HashMap::new();
+        let mut queue = VecDeque::new();
+        for source in sources {
+            if self.in_bounds(*source) && distances.insert(*source, 0).is_none() {
+                queue.push_back(*source);
+            }
+        }
+        while let Some(current) = queue.pop_front() {
+            let distance = distances[&current] + 1;
+            for neighbor in self.neighbors(current) {
+                if let Entry::Vacant(entry) = distances.entry(neighbor) {
+                    entry.insert(distance);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        distances
+    }
+}
+
+/// Progress of an [`IncrementalPathfinder`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathfindingStatus {
+    /// The search ran out of budget; call [`IncrementalPathfinder::step`]
+    /// again to continue.
+    InProgress,
+    /// A cheapest path, as returned by [`Map::find_path`].
+    Found(Vec<IVec2>),
+    /// Either end is off the map or the destination is unreachable.
+    NotFound,
+}
+
+/// A resumable A* search that can be spread across frames.
+///
+/// Each [`step`](Self::step) expands at most `budget_nodes` tiles, so many
+/// searches on a big map can share a fixed per-frame budget. Pass the same
+/// map to every step; changing it mid-search gives undefined paths.
+#[derive(Debug, Clone)]
+pub struct IncrementalPathfinder {
+    from: IVec2,
+    to: IVec2,
+    width: i32,
+    came_from: Vec<Option<IVec2>>,
+    cost_so_far: Vec<u32>,
+    /// Ordered by estimated total cost, then by remaining distance so the
+    /// search pushes towards the goal, then by position so that ties are
+    /// broken the same way on every run.
+    open: BinaryHeap<Reverse<(u32, u32, i32, i32)>>,
+    expanded_nodes: usize,
+    outcome: Option<PathfindingStatus>,
+}
+
+impl IncrementalPathfinder {
+    pub fn new<G: TileGenerator>(map: &Map<G>, from: IVec2, to: IVec2) -> Self {
+        // The map is bounded, so search state lives in flat vectors indexed
+        // by `y * width + x` rather than in hash maps.
+        let mut pathfinder = Self {
+            from,
+            to,
+            width: map.x as i32,
+            came_from: vec![None; map.x * map.y],
+            cost_so_far: vec![u32::MAX; map.x * map.y],
+            open: BinaryHeap::new(),
+            expanded_nodes: 0,
+            outcome: None,
+        };
+        if map.in_bounds(from) && map.in_bounds(to) {
+            let index = pathfinder.index(from);
+            pathfinder.cost_so_far[index] = 0;
+            let remaining = map.distance(from, to);
+            pathfinder
+                .open
+                .push(Reverse((remaining, remaining, from.x, from.y)));
+        }
+        pathfinder
+    }
+
+    pub fn from(&self) -> IVec2 {
+        self.from
+    }
+
+    pub fn to(&self) -> IVec2 {
+        self.to
+    }
+
+    /// Total number of tiles expanded so far.
+    pub fn expanded_nodes(&self) -> usize {
+        self.expanded_nodes
+    }
+
+    /// Continues the search, expanding at most `budget_nodes` tiles. Once the
+    /// search has finished, further calls return the same result.
+    pub fn step<G: TileGenerator>(
+        &mut self,
+        map: &Map<G>,
+        budget_nodes: usize,
+    ) -> PathfindingStatus {
+        if let Some(outcome) = &self.outcome {
+            return outcome.clone();
+        }
+
+        let mut expanded = 0;
+        while expanded < budget_nodes {
+            let Some(Reverse((estimate, _, x, y))) = self.open.pop() else {
+                return self.finish(PathfindingStatus::NotFound);
+            };
+            let current = IVec2::new(x, y);
+            if current == self.to {
+                let path = self.reconstruct_path();
+                return self.finish(PathfindingStatus::Found(path));
+            }
+
+            let cost = self.cost_so_far[self.index(current)];
+            if estimate > cost + map.distance(current, self.to) {
+                // A cheaper route to `current` was already expanded.
+                continue;
+            }
+            expanded += 1;
+            self.expanded_nodes += 1;
+            for direction in Direction::all() {
+                let next = map.adjacent(current, direction);
+                let Some(step_cost) = map.move_cost(current, next) else {
+                    continue;
+                };
+                if next != self.to && map.is_blocked_by_occupant(next) {
+                    continue;
+                }
+                let next_cost = cost.saturating_add(step_cost);
+                let next_index = self.index(next);
+                if self.cost_so_far[next_index] <= next_cost {
+                    continue;
+                }
+                self.cost_so_far[next_index] = next_cost;
+                self.came_from[next_index] = Some(current);
+                let remaining = map.distance(next, self.to);
+                self.open
+                    .push(Reverse((next_cost + remaining, remaining, next.x, next.y)));
+            }
+        }
+
+        PathfindingStatus::InProgress
+    }
+
+    fn finish(&mut self, outcome: PathfindingStatus) -> PathfindingStatus {
+        // The search state is no longer needed.
+        self.open.clear();
+        self.came_from = Vec::new();
+        self.cost_so_far = Vec::new();
+        self.outcome = Some(outcome.clone());
+        outcome
+    }
+
+    fn index(&self, position: IVec2) -> usize {
+        (position.y * self.width + position.x) as usize
+    }
+
+    fn reconstruct_path(&self) -> Vec<IVec2> {
+        let mut path = vec![self.to];
+        let mut current = self.to;
+        while current != self.from {
+            current = self.came_from[self.index(current)].expect("every reached tile has a parent");
+            path.push(current);
+        }
+        path.reverse();
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_tile::Biome;
+    use crate::movement::MovementCosts;
+    use crate::open_field::OpenFieldGenerator;
+    use crate::testing::GridBuilder;
+    use crate::tile_generator::TileGeneratorDefault;
+
+    #[test]
+    fn open_grid_path_is_manhattan_length() {
+        let map = Map::new(6, OpenFieldGenerator);
+        let path = map.find_path(IVec2::new(0, 0), IVec2::new(5, 3)).unwrap();
+
+        assert_eq!(path.len(), 9);
+        assert_eq!(path.first(), Some(&IVec2::new(0, 0)));
+        assert_eq!(path.last(), Some(&IVec2::new(5, 3)));
+    }
+
+    #[test]
+    fn path_to_self_is_a_single_tile() {
+        let map = GridBuilder::new("  \n  ").build(OpenFieldGenerator);
+
+        assert_eq!(
+            map.find_path(IVec2::new(1, 1), IVec2::new(1, 1)),
+            Some(vec![IVec2::new(1, 1)])
+        );
+    }
+
+    #[test]
+    fn no_path_through_walls_or_off_the_map() {
+        let map = GridBuilder::new("   \n   \n   ").build(OpenFieldGenerator);
+
+        assert_eq!(map.find_path(IVec2::new(0, 0), IVec2::new(2, 2)), None);
+        assert_eq!(map.find_path(IVec2::new(0, 0), IVec2::new(3, 0)), None);
+    }
+
+    #[test]
+    fn path_follows_the_only_corridor() {
+        // A U-shaped corridor: up the west column, across the top, down the
+        // east column.
+        let map = GridBuilder::new("┌─┐\n│ │\n╵ ╵").build(OpenFieldGenerator);
+
+        let path = map.find_path(IVec2::new(0, 0), IVec2::new(2, 0)).unwrap();
+        let expected =
+            [(0, 0), (0, 1), (0, 2), (1, 2), (2, 2), (2, 1), (2, 0)].map(|(x, y)| IVec2::new(x, y));
+        assert_eq!(path, expected);
+    }
+
+    #[test]
+    fn paths_go_around_costly_tiles() {
+        let mut map = Map::new(5, OpenFieldGenerator);
+        for x in 1..4 {
+            map.tiles.get_mut(&IVec2::new(x, 2)).unwrap().biome = Biome::Water;
+        }
+        let (from, to) = (IVec2::new(0, 2), IVec2::new(4, 2));
+
+        let path = map.find_path(from, to).unwrap();
+        assert_eq!(path.len(), 7);
+        assert!(
+            path.iter()
+                .all(|position| map.tiles[position].biome != Biome::Water)
+        );
+        assert_eq!(map.move_cost(from, IVec2::new(1, 2)), Some(3));
+
+        map.movement_costs = MovementCosts::uniform();
+        assert_eq!(map.find_path(from, to).unwrap().len(), 5);
+    }
+
+    #[test]
+    fn next_step_is_the_second_tile_of_the_path() {
+        let map = Map::new(
+            16,
+            TileGeneratorDefault::with_seed_and_probabilities(3, 0.7, 0.3),
+        );
+        let from = IVec2::new(8, 8);
+
+        for to in map.positions() {
+            let expected = map
+                .find_path(from, to)
+                .and_then(|path| path.get(1).copied());
+            assert_eq!(map.next_step_toward(from, to), expected, "{to}");
+        }
+        assert_eq!(map.next_step_toward(from, from), None);
+    }
+
+    #[test]
+    fn dijkstra_maps_measure_steps_to_the_nearest_source() {
+        let map = Map::new(
+            16,
+            TileGeneratorDefault::with_seed_and_probabilities(9, 0.7, 0.3),
+        );
+        let sources = [IVec2::new(2, 3), IVec2::new(12, 10), IVec2::new(-1, 0)];
+
+        let distances = map.dijkstra_map(&sources);
+
+        assert_eq!(distances[&sources[0]], 0);
+        assert!(!distances.contains_key(&sources[2]));
+        for position in map.positions() {
+            let nearest = sources[..2]
+                .iter()
+                .filter_map(|source| map.find_path(*source, position))
+                .map(|path| path.len() as u32 - 1)
+                .min();
+            assert_eq!(distances.get(&position).copied(), nearest, "{position}");
+        }
+    }
+
+    #[test]
+    fn every_step_of_a_generated_path_is_a_valid_move() {
+        let map = Map::new(
+            24,
+            TileGeneratorDefault::with_seed_and_probabilities(4, 0.7, 0.3),
+        );
+        let from = IVec2::new(12, 12);
+
+        for (x, y) in [(0, 0), (23, 23), (5, 19), (17, 2)] {
+            if let Some(path) = map.find_path(from, IVec2::new(x, y)) {
+                for step in path.windows(2) {
+                    assert!(map.can_move(step[0], step[1]));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn incremental_search_matches_find_path() {
+        let map = Map::new(
+            32,
+            TileGeneratorDefault::with_seed_and_probabilities(6, 0.7, 0.3),
+        );
+        let from = IVec2::new(0, 0);
+
+        for to in [IVec2::new(31, 31), IVec2::new(10, 25), IVec2::new(30, 2)] {
+            let mut pathfinder = IncrementalPathfinder::new(&map, from, to);
+            let status = loop {
+                match pathfinder.step(&map, 5) {
+                    PathfindingStatus::InProgress => continue,
+                    status => break status,
+                }
+            };
+
+            match map.find_path(from, to) {
+                Some(path) => assert_eq!(status, PathfindingStatus::Found(path)),
+                None => assert_eq!(status, PathfindingStatus::NotFound),
+            }
+            assert_eq!(pathfinder.step(&map, 5), status);
+        }
+    }
+
+    #[test]
+    fn step_respects_the_node_budget() {
+        let map = Map::new(10, OpenFieldGenerator);
+        let mut pathfinder = IncrementalPathfinder::new(&map, IVec2::new(0, 0), IVec2::new(9, 9));
+
+        assert_eq!(pathfinder.step(&map, 3), PathfindingStatus::InProgress);
+        assert_eq!(pathfinder.expanded_nodes(), 3);
+        assert_eq!(pathfinder.step(&map, 0), PathfindingStatus::InProgress);
+        assert_eq!(pathfinder.expanded_nodes(), 3);
+    }
+
+    #[test]
+    fn incremental_search_off_the_map_is_not_found() {
+        let map = Map::new(3, OpenFieldGenerator);
+        let mut pathfinder = IncrementalPathfinder::new(&map, IVec2::new(0, 0), IVec2::new(5, 0));
+
+        assert_eq!(pathfinder.step(&map, 100), PathfindingStatus::NotFound);
+    }
+}
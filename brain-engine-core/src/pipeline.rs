@@ -0,0 +1,221 @@
+use crate::map::Map;
+use crate::map_tile::{MapTile, TileSet};
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use std::collections::HashSet;
+
+/// A post-processing pass run on a generated map, e.g. by a
+/// [`GeneratorPipeline`].
+///
+/// Closures taking `&mut Map<G>` are transforms too, which covers one-off
+/// steps such as placing doors or decorating.
+pub trait MapTransform<G: TileGenerator> {
+    fn apply(&self, map: &mut Map<G>);
+}
+
+impl<G: TileGenerator, F: Fn(&mut Map<G>)> MapTransform<G> for F {
+    fn apply(&self, map: &mut Map<G>) {
+        self(map)
+    }
+}
+
+/// Joins every walkable area to the largest one with straight corridors, see
+/// [`Map::paint_line`], so the whole map can be walked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EnsureConnected;
+
+impl<G: TileGenerator> MapTransform<G> for EnsureConnected {
+    fn apply(&self, map: &mut Map<G>) {
+        loop {
+            let mut areas = walkable_areas(map);
+            if areas.len() < 2 {
+                return;
+            }
+            let largest = (0..areas.len())
+                .max_by_key(|index| (areas[*index].len(), std::cmp::Reverse(*index)))
+                .unwrap();
+            let main = areas.swap_remove(largest);
+            let Some((from, to)) = areas[0]
+                .iter()
+                .flat_map(|from| main.iter().map(move |to| (*from, *to)))
+                .min_by_key(|(from, to)| {
+                    (
+                        (*from - *to).abs().element_sum(),
+                        from.x,
+                        from.y,
+                        to.x,
+                        to.y,
+                    )
+                })
+            else {
+                return;
+            };
+            map.paint_line(from, to, TileSet::Corridor);
+        }
+    }
+}
+
+/// Seals corridor dead ends with [`Map::prune_dead_ends`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PruneDeadEnds {
+    pub iterations: usize,
+}
+
+impl PruneDeadEnds {
+    /// Keeps pruning until no dead ends remain.
+    pub fn all() -> Self {
+        Self {
+            iterations: usize::MAX,
+        }
+    }
+}
+
+impl<G: TileGenerator> MapTransform<G> for PruneDeadEnds {
+    fn apply(&self, map: &mut Map<G>) {
+        map.prune_dead_ends(self.iterations);
+    }
+}
+
+/// Replaces winding corridors with straight ones, see
+/// [`Map::straighten_corridors`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StraightenCorridors;
+
+impl<G: TileGenerator> MapTransform<G> for StraightenCorridors {
+    fn apply(&self, map: &mut Map<G>) {
+        map.straighten_corridors();
+    }
+}
+
+/// Adds `count` loops with [`Map::add_loops`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddLoops {
+    pub count: usize,
+}
+
+impl<G: TileGenerator> MapTransform<G> for AddLoops {
+    fn apply(&self, map: &mut Map<G>) {
+        map.add_loops(self.count);
+    }
+}
+
+/// A chain of [`MapTransform`]s run in order after generation, so callers
+/// describe the steps once instead of hand-wiring them:
+///
+/// ```
+/// use brain_engine_core::{EnsureConnected, GeneratorPipeline, Map, PruneDeadEnds};
+/// use brain_engine_core::TileGeneratorDefault;
+///
+/// let pipeline = GeneratorPipeline::new()
+///     .then(EnsureConnected)
+///     .then(PruneDeadEnds::all());
+/// let map = pipeline.generate(16, TileGeneratorDefault::with_seed(3));
+/// assert!(map.dead_ends().is_empty());
+/// ```
+pub struct GeneratorPipeline<G: TileGenerator> {
+    transforms: Vec<Box<dyn MapTransform<G>>>,
+}
+
+impl<G: TileGenerator> Default for GeneratorPipeline<G> {
+    fn default() -> Self {
+        Self {
+            transforms: Vec::new(),
+        }
+    }
+}
+
+impl<G: TileGenerator> GeneratorPipeline<G> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `transform` to the end of the chain.
+    pub fn then(mut self, transform: impl MapTransform<G> + 'static) -> Self {
+        self.transforms.push(Box::new(transform));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.transforms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+
+    /// Generates a map with [`Map::new`] and runs every transform on it.
+    pub fn generate(&self, size: usize, generator: G) -> Map<G> {
+        let mut map = Map::new(size, generator);
+        self.apply(&mut map);
+        map
+    }
+
+    /// Runs every transform on `map`, in order.
+    pub fn apply(&self, map: &mut Map<G>) {
+        for transform in &self.transforms {
+            transform.apply(map);
+        }
+    }
+}
+
+/// The separate areas of walkable tiles, in x-major order of their first
+/// tile.
+fn walkable_areas<G: TileGenerator>(map: &Map<G>) -> Vec<Vec<IVec2>> {
+    let mut visited = HashSet::new();
+    let mut areas = Vec::new();
+    for position in map.positions() {
+        let walkable = map
+            .tiles
+            .get(&position)
+            .is_some_and(|tile| tile.map_tile != MapTile::ZERO);
+        if !walkable || visited.contains(&position) {
+            continue;
+        }
+        let area = map.reachable_from(position);
+        let mut area: Vec<IVec2> = area.into_iter().collect();
+        area.sort_by_key(|position| (position.x, position.y));
+        visited.extend(area.iter().copied());
+        areas.push(area);
+    }
+    areas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile_generator::TileGeneratorDefault;
+
+    fn generator() -> TileGeneratorDefault {
+        TileGeneratorDefault::with_seed_and_probabilities(7, 0.4, 0.3)
+    }
+
+    #[test]
+    fn ensure_connected_joins_every_area() {
+        let mut map = Map::new(16, generator());
+        assert!(walkable_areas(&map).len() > 1);
+
+        EnsureConnected.apply(&mut map);
+
+        assert_eq!(walkable_areas(&map).len(), 1);
+        assert_eq!(map.validate(), Ok(()));
+    }
+
+    #[test]
+    fn transforms_run_in_order() {
+        let pipeline = GeneratorPipeline::new()
+            .then(EnsureConnected)
+            .then(PruneDeadEnds::all())
+            .then(|map: &mut Map<TileGeneratorDefault>| {
+                map.tiles.get_mut(&IVec2::ZERO).unwrap().tile_set = TileSet::Room;
+            });
+
+        let map = pipeline.generate(16, generator());
+
+        assert_eq!(pipeline.len(), 3);
+        assert!(map.dead_ends().is_empty());
+        assert_eq!(map.stats().connected_areas, 1);
+        assert_eq!(map.tiles[&IVec2::ZERO].tile_set, TileSet::Room);
+        assert_eq!(map.validate(), Ok(()));
+    }
+}
@@ -0,0 +1,325 @@
+//! Hand-authored set pieces, such as a boss room or a vault, stamped into
+//! procedural maps.
+//!
+//! Prefabs are written with the glyphs [`Map::render_ascii`] draws, the
+//! northernmost row first, with `?` for cells that leave the map as it is:
+//!
+//! ```
+//! use bevy::prelude::*;
+//! use brain_engine_core::{Map, Prefab, TileGeneratorDefault};
+//!
+//! let vault: Prefab = "┏┳┓\n┣╋┫\n┗┻┛".parse().unwrap();
+//! let mut map = Map::new(10, TileGeneratorDefault::with_seed(4));
+//! map.stamp(&vault, IVec2::new(3, 3));
+//! assert_eq!(map.validate(), Ok(()));
+//! ```
+
+use crate::ascii::{CORRIDOR_GLYPHS, FIELD_GLYPH, OBSTACLE_GLYPH, ROOM_GLYPHS, glyph};
+use crate::diff::MapDiff;
+use crate::map::Map;
+use crate::map_tile::{Direction, MapTile, Tile, TileSet};
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
+
+/// Glyph for prefab cells that keep the map's tile.
+pub const KEEP_GLYPH: char = '?';
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrefabError {
+    /// A character that isn't a tile glyph or [`KEEP_GLYPH`], with its
+    /// 1-based line and column.
+    InvalidGlyph {
+        line: usize,
+        column: usize,
+        character: char,
+    },
+    /// The tile at this prefab position has an exit towards a neighboring
+    /// prefab tile without an exit back.
+    MismatchedExits(IVec2),
+}
+
+impl fmt::Display for PrefabError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrefabError::InvalidGlyph {
+                line,
+                column,
+                character,
+            } => write!(
+                f,
+                "line {line}, column {column}: invalid glyph '{character}'"
+            ),
+            PrefabError::MismatchedExits(position) => {
+                write!(
+                    f,
+                    "exits of the tile at {position} don't match its neighbors"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PrefabError {}
+
+/// A small grid of tiles to [`Map::stamp`] into a map. Position `(0, 0)` is
+/// the bottom-left cell; cells without a tile leave the map unchanged.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Prefab {
+    size: UVec2,
+    tiles: HashMap<IVec2, Tile>,
+}
+
+impl Prefab {
+    /// Builds a prefab from tiles at non-negative positions. Exits between
+    /// neighboring tiles must match; exits at the edge are reconciled with
+    /// the map when stamped.
+    pub fn new(tiles: HashMap<IVec2, Tile>) -> Result<Self, PrefabError> {
+        let size = tiles.keys().fold(UVec2::ZERO, |size, position| {
+            size.max(position.as_uvec2() + 1)
+        });
+        let prefab = Self { size, tiles };
+        let mut positions: Vec<IVec2> = prefab.tiles.keys().copied().collect();
+        positions.sort_by_key(|position| (position.x, position.y));
+        for position in positions {
+            let tile = prefab.tiles[&position];
+            let mismatched = Direction::all().into_iter().any(|direction| {
+                prefab
+                    .tiles
+                    .get(&(position + direction.offset()))
+                    .is_some_and(|neighbor| {
+                        tile.map_tile.contains(direction)
+                            != neighbor.map_tile.contains(direction.opposite())
+                    })
+            });
+            if mismatched {
+                return Err(PrefabError::MismatchedExits(position));
+            }
+        }
+        Ok(prefab)
+    }
+
+    /// Width and height of the grid.
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    pub fn get(&self, position: IVec2) -> Option<Tile> {
+        self.tiles.get(&position).copied()
+    }
+
+    pub fn tiles(&self) -> &HashMap<IVec2, Tile> {
+        &self.tiles
+    }
+}
+
+impl FromStr for Prefab {
+    type Err = PrefabError;
+
+    /// Parses the glyphs of [`Map::render_ascii`]. Field tiles (`.`) are
+    /// open towards every neighbor but obstacles and [`KEEP_GLYPH`] cells.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let lines: Vec<&str> = text.lines().collect();
+        let mut tiles = HashMap::new();
+        for (row, line) in lines.iter().enumerate() {
+            let y = (lines.len() - 1 - row) as i32;
+            for (x, character) in line.chars().enumerate() {
+                let position = IVec2::new(x as i32, y);
+                let tile = match character {
+                    KEEP_GLYPH => continue,
+                    FIELD_GLYPH => Tile::new(TileSet::Field, MapTile::NESW),
+                    OBSTACLE_GLYPH => Tile::new(TileSet::Field, MapTile::ZERO),
+                    _ => parse_glyph(character).ok_or(PrefabError::InvalidGlyph {
+                        line: row + 1,
+                        column: x + 1,
+                        character,
+                    })?,
+                };
+                tiles.insert(position, tile);
+            }
+        }
+
+        let open_fields: Vec<IVec2> = tiles
+            .iter()
+            .filter(|(_, tile)| tile.tile_set == TileSet::Field && tile.map_tile != MapTile::ZERO)
+            .map(|(position, _)| *position)
+            .collect();
+        for position in open_fields {
+            let closed = Direction::all().into_iter().filter(|direction| {
+                tiles
+                    .get(&(position + direction.offset()))
+                    .is_some_and(|neighbor| match neighbor.tile_set {
+                        TileSet::Field => neighbor.map_tile == MapTile::ZERO,
+                        _ => !neighbor.map_tile.contains(direction.opposite()),
+                    })
+            });
+            let map_tile = closed.fold(MapTile::NESW, MapTile::without);
+            tiles.get_mut(&position).unwrap().map_tile = map_tile;
+        }
+        Prefab::new(tiles)
+    }
+}
+
+impl fmt::Display for Prefab {
+    /// Writes the glyphs [`Prefab::from_str`] parses, one line per row.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for y in (0..self.size.y as i32).rev() {
+            let line: String = (0..self.size.x as i32)
+                .map(|x| self.get(IVec2::new(x, y)).map_or(KEEP_GLYPH, glyph))
+                .collect();
+            writeln!(f, "{line}")?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_glyph(character: char) -> Option<Tile> {
+    [
+        (TileSet::Corridor, &CORRIDOR_GLYPHS),
+        (TileSet::Room, &ROOM_GLYPHS),
+    ]
+    .into_iter()
+    .find_map(|(tile_set, glyphs)| {
+        let bits = glyphs.iter().position(|glyph| *glyph == character)?;
+        Some(Tile::new(tile_set, MapTile::from_bits(bits as u8)?))
+    })
+}
+
+impl<G: TileGenerator> Map<G> {
+    /// Places `prefab` with its bottom-left cell at `offset`, clipped to the
+    /// map, and returns the diff that undoes it.
+    ///
+    /// The prefab decides the exits on its boundary: a neighboring map tile
+    /// gets an exit back towards every prefab exit and loses its exits
+    /// towards prefab walls. Prefab exits that would lead off the map or to
+    /// a missing tile are closed.
+    pub fn stamp(&mut self, prefab: &Prefab, offset: IVec2) -> MapDiff {
+        let mut changed = BTreeMap::new();
+        for (position, tile) in &prefab.tiles {
+            let target = offset + *position;
+            if !self.in_bounds(target) {
+                continue;
+            }
+            let mut tile = *tile;
+            for direction in Direction::all() {
+                let inside = *position + direction.offset();
+                if prefab.tiles.contains_key(&inside) && self.in_bounds(offset + inside) {
+                    continue;
+                }
+                let neighbor = self.adjacent(target, direction);
+                let neighbor_tile = changed
+                    .get(&(neighbor.x, neighbor.y))
+                    .copied()
+                    .or_else(|| self.tiles.get(&neighbor).copied())
+                    .filter(|_| self.in_bounds(neighbor));
+                let Some(mut neighbor_tile) = neighbor_tile else {
+                    tile.map_tile = tile.map_tile.without(direction);
+                    continue;
+                };
+                neighbor_tile.map_tile = if tile.map_tile.contains(direction) {
+                    neighbor_tile.map_tile.with(direction.opposite())
+                } else {
+                    neighbor_tile.map_tile.without(direction.opposite())
+                };
+                changed.insert((neighbor.x, neighbor.y), neighbor_tile);
+            }
+            changed.insert((target.x, target.y), tile);
+        }
+
+        let mut undo = MapDiff {
+            x: self.x,
+            y: self.y,
+            changes: Vec::new(),
+        };
+        for ((x, y), tile) in changed {
+            let position = IVec2::new(x, y);
+            let previous = self.tiles.insert(position, tile);
+            if previous != Some(tile) {
+                undo.changes.push((position, previous));
+            }
+        }
+        self.publish_undo(&undo);
+        undo
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::open_field::OpenFieldGenerator;
+    use crate::tile_generator::TileGeneratorDefault;
+
+    const BOSS_ROOM: &str = "\
+?│?
+┏╋┓
+┗┻┛";
+
+    #[test]
+    fn prefabs_round_trip_through_ascii() {
+        let prefab: Prefab = BOSS_ROOM.parse().unwrap();
+
+        assert_eq!(prefab.size(), UVec2::new(3, 3));
+        assert_eq!(prefab.tiles().len(), 7);
+        assert_eq!(
+            prefab.get(IVec2::new(1, 2)),
+            Some(Tile::new(TileSet::Corridor, MapTile::NS))
+        );
+        assert_eq!(prefab.get(IVec2::new(0, 2)), None);
+        assert_eq!(prefab.to_string(), format!("{BOSS_ROOM}\n"));
+    }
+
+    #[test]
+    fn invalid_prefabs_are_rejected() {
+        assert_eq!(
+            "┏┓\n┗x".parse::<Prefab>(),
+            Err(PrefabError::InvalidGlyph {
+                line: 2,
+                column: 2,
+                character: 'x'
+            })
+        );
+        assert_eq!(
+            "┏┃".parse::<Prefab>(),
+            Err(PrefabError::MismatchedExits(IVec2::new(0, 0)))
+        );
+    }
+
+    #[test]
+    fn field_glyphs_avoid_obstacles() {
+        let prefab: Prefab = ".#\n..".parse().unwrap();
+
+        assert_eq!(prefab.get(IVec2::new(0, 1)).unwrap().map_tile, MapTile::NWS);
+        assert_eq!(prefab.get(IVec2::new(1, 0)).unwrap().map_tile, MapTile::ESW);
+    }
+
+    #[test]
+    fn stamping_reconciles_the_boundary() {
+        let mut map = Map::new(8, TileGeneratorDefault::with_seed(6));
+        let before = map.tiles.clone();
+        let prefab: Prefab = BOSS_ROOM.parse().unwrap();
+
+        let undo = map.stamp(&prefab, IVec2::new(2, 1));
+
+        assert_eq!(map.validate(), Ok(()));
+        assert_eq!(map.tiles[&IVec2::new(3, 1)].map_tile, MapTile::NEW);
+        assert!(map.can_move(IVec2::new(3, 3), IVec2::new(3, 4)));
+        assert!(!map.can_move(IVec2::new(2, 1), IVec2::new(2, 0)));
+        map.apply(&undo);
+        assert_eq!(map.tiles, before);
+    }
+
+    #[test]
+    fn stamps_are_clipped_to_the_map() {
+        let mut map = Map::new(3, OpenFieldGenerator);
+        let prefab: Prefab = "━━━━".parse().unwrap();
+
+        map.stamp(&prefab, IVec2::new(1, 0));
+
+        assert_eq!(map.tiles[&IVec2::new(1, 0)].map_tile, MapTile::EW);
+        assert_eq!(map.tiles[&IVec2::new(2, 0)].map_tile, MapTile::W);
+        assert_eq!(map.validate(), Ok(()));
+    }
+}
@@ -0,0 +1,54 @@
+//! The commonly used types, for glob importing:
+//!
+//! ```
+//! use bevy::prelude::*;
+//! use brain_engine_core::prelude::*;
+//!
+//! let map = Map::new(8, TileGeneratorDefault::with_seed(1));
+//! let path = map.find_path(IVec2::new(0, 0), IVec2::new(7, 7));
+//! ```
+//!
+//! Bevy types such as `IVec2` are not re-exported; import them from
+//! `bevy::prelude` as usual.
+
+pub use crate::agent::Agent;
+pub use crate::binary_map::BinaryMapError;
+pub use crate::biome::BiomeGenerator;
+pub use crate::checkpoint::{CheckpointError, Checkpoints};
+pub use crate::composite::CompositeGenerator;
+pub use crate::config::{GeneratorConfig, GeneratorConfigError};
+pub use crate::direction_set::DirectionSet;
+pub use crate::drunkard::TileGeneratorDrunkard;
+pub use crate::exploration::ExplorationState;
+pub use crate::features::{FeatureLayer, FeaturePlacer};
+pub use crate::generation_context::GenerationContext;
+pub use crate::generation_observer::GenerationObserver;
+pub use crate::hex::{HexDirection, HexMap, HexMapTile, HexTile, HexTileGenerator};
+pub use crate::history::MapHistory;
+pub use crate::level::LevelParams;
+pub use crate::map::{DynMap, Map};
+pub use crate::map_builder::{BuiltMap, MapBuilder, MapBuilderError};
+pub use crate::map_file::{MapFile, MapFileError, TileExtension, TileExtensions};
+pub use crate::map_tile::{Biome, Direction, MapTile, ParseTileError, Tile, TileSet};
+pub use crate::maze::{MazeAlgorithm, TileGeneratorMaze};
+pub use crate::migration::{MapMigrations, MapMigrator, MigrationError};
+pub use crate::movement::{MovementCosts, MovementSpeeds};
+pub use crate::occupancy::{Occupancy, OccupyError};
+pub use crate::open_field::OpenFieldGenerator;
+pub use crate::pathfinding::{IncrementalPathfinder, PathfindingStatus};
+pub use crate::pipeline::{GeneratorPipeline, MapTransform};
+pub use crate::prefab::{Prefab, PrefabError};
+pub use crate::recording::{MovementRecorder, MovementRecording, ReplayError};
+pub use crate::room_templates::{RoomMarker, RoomTemplate, RoomTemplates};
+pub use crate::rooms::RoomArea;
+pub use crate::screen::{Screen, ScreenAnchor, ScreenPadding, ScreenProjection};
+pub use crate::share_code::{ShareCodeError, ShareRegistry};
+pub use crate::simulation::{Action, MoveOutcome, Simulation};
+pub use crate::stats::MapStats;
+pub use crate::symmetry::{SymmetricGenerator, Symmetry};
+pub use crate::tile_generator::{
+    BoxedTileGenerator, TileGenerator, TileGeneratorBuilder, TileGeneratorDefault,
+};
+pub use crate::tile_grid::TileGrid;
+pub use crate::topology::Topology;
+pub use crate::validation::MapViolation;
@@ -0,0 +1,144 @@
+use crate::map::Map;
+use crate::render::{RenderColor, RenderStyle};
+use crate::tile_generator::TileGenerator;
+use crate::wall_geometry::WallGeometry;
+
+use bevy::prelude::*;
+use image::{ImageError, ImageFormat, Rgb, RgbImage};
+use std::io::Cursor;
+
+impl<G: TileGenerator> Map<G> {
+    /// Rasterizes the map with the same colours, grid and walls as
+    /// [`Map::render_svg`], with `style.tile_size` rounded to whole pixels.
+    /// A small tile size such as 4 gives a minimap.
+    pub fn render_to_image(&self, style: &RenderStyle) -> RgbImage {
+        let tile_size = style.tile_size.round().max(1.0);
+        let mut image = RgbImage::from_pixel(
+            self.x as u32 * tile_size as u32,
+            self.y as u32 * tile_size as u32,
+            pixel(style.background),
+        );
+        let mut fill = |area: Rect, color: RenderColor| {
+            fill_rect(&mut image, area, tile_size, pixel(color));
+        };
+
+        for position in self.positions() {
+            if let Some(tile) = self.tiles.get(&position) {
+                let corner = position.as_vec2();
+                fill(
+                    Rect::from_corners(corner, corner + Vec2::ONE),
+                    style.tile_color(tile.tile_set, tile.map_tile),
+                );
+            }
+        }
+        if let Some(grid) = style.grid {
+            let half = grid.width / tile_size / 2.0;
+            let (width, height) = (self.x as f32, self.y as f32);
+            for x in 0..=self.x {
+                let x = x as f32;
+                fill(Rect::new(x - half, 0.0, x + half, height), grid.color);
+            }
+            for y in 0..=self.y {
+                let y = y as f32;
+                fill(Rect::new(0.0, y - half, width, y + half), grid.color);
+            }
+        }
+        let thickness = style.wall_thickness / tile_size;
+        for wall in WallGeometry::from_map(self).to_rects(thickness) {
+            fill(wall, style.wall_color);
+        }
+        image
+    }
+
+    /// [`Map::render_to_image`] encoded as a PNG file.
+    pub fn render_png(&self, style: &RenderStyle) -> Result<Vec<u8>, ImageError> {
+        let mut bytes = Vec::new();
+        self.render_to_image(style)
+            .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)?;
+        Ok(bytes)
+    }
+}
+
+fn pixel(color: RenderColor) -> Rgb<u8> {
+    Rgb([color.r, color.g, color.b])
+}
+
+/// Fills `area`, given in tile units with y up, covering at least one pixel
+/// in each direction so thin lines never vanish.
+fn fill_rect(image: &mut RgbImage, area: Rect, tile_size: f32, color: Rgb<u8>) {
+    let height = image.height() as f32;
+    let to_pixels = |start: f32, end: f32, limit: f32| {
+        let start = start.round().clamp(0.0, limit);
+        let end = end.round().max(start + 1.0).min(limit);
+        start as u32..end as u32
+    };
+    let columns = to_pixels(
+        area.min.x * tile_size,
+        area.max.x * tile_size,
+        image.width() as f32,
+    );
+    let rows = to_pixels(
+        height - area.max.y * tile_size,
+        height - area.min.y * tile_size,
+        height,
+    );
+    for y in rows {
+        for x in columns.clone() {
+            image.put_pixel(x, y, color);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_tile::{MapTile, Tile, TileSet};
+    use crate::open_field::OpenFieldGenerator;
+
+    #[test]
+    fn images_draw_tiles_and_walls() {
+        let mut map = Map::new(2, OpenFieldGenerator);
+        map.tiles
+            .insert(IVec2::new(0, 0), Tile::new(TileSet::Room, MapTile::N));
+        let style = RenderStyle::default();
+
+        let image = map.render_to_image(&style);
+
+        assert_eq!(image.dimensions(), (64, 64));
+        // Tile (0, 0) is the bottom-left tile.
+        assert_eq!(*image.get_pixel(16, 48), pixel(style.room_color));
+        assert_eq!(*image.get_pixel(48, 16), pixel(style.field_color));
+        // The room has no exit east, so the edge at x = 32 is a wall.
+        assert_eq!(*image.get_pixel(32, 48), pixel(style.wall_color));
+        assert_eq!(*image.get_pixel(32, 16), pixel(style.field_color));
+    }
+
+    #[test]
+    fn minimaps_keep_one_pixel_walls_and_grid_lines() {
+        let map = Map::new(3, OpenFieldGenerator);
+        let style = RenderStyle {
+            tile_size: 4.0,
+            wall_thickness: 0.5,
+            ..RenderStyle::print()
+        };
+
+        let image = map.render_to_image(&style);
+
+        assert_eq!(image.dimensions(), (12, 12));
+        assert_eq!(*image.get_pixel(0, 5), pixel(style.wall_color));
+        assert_eq!(*image.get_pixel(4, 5), pixel(style.grid.unwrap().color));
+        assert_eq!(*image.get_pixel(5, 5), pixel(style.field_color));
+    }
+
+    #[test]
+    fn png_exports_decode_to_the_same_image() {
+        let map = Map::new(5, OpenFieldGenerator);
+        let style = RenderStyle::default();
+
+        let png = map.render_png(&style).unwrap();
+
+        assert!(png.starts_with(b"\x89PNG"));
+        let decoded = image::load_from_memory_with_format(&png, ImageFormat::Png).unwrap();
+        assert_eq!(decoded.to_rgb8(), map.render_to_image(&style));
+    }
+}
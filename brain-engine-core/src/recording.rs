@@ -0,0 +1,258 @@
+//! Recording and replaying agent movement, for ghost runs and for
+//! reproducing traversal bugs players report.
+//!
+//! ```
+//! use bevy::prelude::*;
+//! use brain_engine_core::{Map, MovementRecorder, MovementRecording, OpenFieldGenerator};
+//!
+//! let map = Map::new(4, OpenFieldGenerator);
+//! let mut recorder = MovementRecorder::new(IVec2::new(0, 0));
+//! recorder.record(1, IVec2::new(1, 0));
+//! recorder.record(3, IVec2::new(1, 1));
+//!
+//! let json = recorder.finish().to_json();
+//! let recording = MovementRecording::load(&json, &map).unwrap();
+//! assert_eq!(recording.position_at(2), IVec2::new(1, 0));
+//! ```
+
+use crate::map::Map;
+use crate::region_graph::position_json;
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// One step of a recording: the agent arrived on `to` during `turn`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecordedMove {
+    pub turn: u32,
+    #[serde(with = "position_json")]
+    pub to: IVec2,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The recording is not valid JSON for a [`MovementRecording`].
+    Parse(String),
+    /// The move at `index` has an earlier turn than the move before it.
+    TurnOutOfOrder { index: usize, turn: u32 },
+    /// [`Map::can_move`] forbids the move at `index`.
+    IllegalMove {
+        index: usize,
+        turn: u32,
+        from: IVec2,
+        to: IVec2,
+    },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Parse(reason) => write!(f, "invalid recording: {reason}"),
+            ReplayError::TurnOutOfOrder { index, turn } => {
+                write!(
+                    f,
+                    "move {index} at turn {turn} is earlier than the move before it"
+                )
+            }
+            ReplayError::IllegalMove {
+                index,
+                turn,
+                from,
+                to,
+            } => write!(
+                f,
+                "move {index} at turn {turn} from {from} to {to} is not allowed by the map"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// Where an agent started and every tile it moved onto, in order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MovementRecording {
+    #[serde(with = "position_json")]
+    pub start: IVec2,
+    pub moves: Vec<RecordedMove>,
+}
+
+impl MovementRecording {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("movement recordings always serialize")
+    }
+
+    /// Parses a recording and checks it against `map` with
+    /// [`MovementRecording::validate`].
+    pub fn load<G: TileGenerator>(json: &str, map: &Map<G>) -> Result<Self, ReplayError> {
+        let recording: Self =
+            serde_json::from_str(json).map_err(|error| ReplayError::Parse(error.to_string()))?;
+        recording.validate(map)?;
+        Ok(recording)
+    }
+
+    /// Checks that turns never go backwards and that [`Map::can_move`]
+    /// allows every move, reporting the first one that fails. A recording
+    /// that fails on the map it was made on points at the move where the
+    /// game let the agent through a wall.
+    pub fn validate<G: TileGenerator>(&self, map: &Map<G>) -> Result<(), ReplayError> {
+        let mut position = self.start;
+        let mut last_turn = 0;
+        for (index, recorded) in self.moves.iter().enumerate() {
+            if recorded.turn < last_turn {
+                return Err(ReplayError::TurnOutOfOrder {
+                    index,
+                    turn: recorded.turn,
+                });
+            }
+            if !map.can_move(position, recorded.to) {
+                return Err(ReplayError::IllegalMove {
+                    index,
+                    turn: recorded.turn,
+                    from: position,
+                    to: recorded.to,
+                });
+            }
+            position = recorded.to;
+            last_turn = recorded.turn;
+        }
+        Ok(())
+    }
+
+    /// The start tile followed by every tile moved onto, with the turn of
+    /// the move (0 for the start).
+    pub fn replay(&self) -> impl Iterator<Item = (u32, IVec2)> + '_ {
+        std::iter::once((0, self.start)).chain(
+            self.moves
+                .iter()
+                .map(|recorded| (recorded.turn, recorded.to)),
+        )
+    }
+
+    /// Where the agent stood at the end of `turn`, for drawing a ghost
+    /// alongside a live run.
+    pub fn position_at(&self, turn: u32) -> IVec2 {
+        self.moves
+            .iter()
+            .take_while(|recorded| recorded.turn <= turn)
+            .last()
+            .map_or(self.start, |recorded| recorded.to)
+    }
+
+    /// The turn of the last move, 0 when the agent never moved.
+    pub fn last_turn(&self) -> u32 {
+        self.moves.last().map_or(0, |recorded| recorded.turn)
+    }
+}
+
+/// Logs an agent's moves as they happen. Moves are recorded as given, so a
+/// move the game should not have allowed is kept and shows up when the
+/// recording is loaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MovementRecorder {
+    recording: MovementRecording,
+}
+
+impl MovementRecorder {
+    pub fn new(start: IVec2) -> Self {
+        Self {
+            recording: MovementRecording {
+                start,
+                moves: Vec::new(),
+            },
+        }
+    }
+
+    /// Records that the agent moved onto `to` during `turn`.
+    pub fn record(&mut self, turn: u32, to: IVec2) {
+        self.recording.moves.push(RecordedMove { turn, to });
+    }
+
+    /// Where the agent is after the recorded moves.
+    pub fn position(&self) -> IVec2 {
+        self.recording
+            .moves
+            .last()
+            .map_or(self.recording.start, |recorded| recorded.to)
+    }
+
+    pub fn recording(&self) -> &MovementRecording {
+        &self.recording
+    }
+
+    pub fn finish(self) -> MovementRecording {
+        self.recording
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::Agent;
+    use crate::map_tile::Direction;
+    use crate::open_field::OpenFieldGenerator;
+
+    #[test]
+    fn recordings_round_trip_and_replay() {
+        let map = Map::new(4, OpenFieldGenerator);
+        let mut agent = Agent::new(IVec2::new(0, 0));
+        let mut recorder = MovementRecorder::new(agent.position());
+        for (turn, direction) in [
+            (1, Direction::East),
+            (2, Direction::North),
+            (4, Direction::East),
+        ] {
+            assert!(agent.try_move(&map, direction));
+            recorder.record(turn, agent.position());
+        }
+        assert_eq!(recorder.position(), agent.position());
+
+        let recording = MovementRecording::load(&recorder.finish().to_json(), &map).unwrap();
+
+        assert_eq!(
+            recording
+                .replay()
+                .map(|(_, position)| position)
+                .collect::<Vec<_>>(),
+            agent.history()
+        );
+        assert_eq!(recording.position_at(0), IVec2::new(0, 0));
+        assert_eq!(recording.position_at(3), IVec2::new(1, 1));
+        assert_eq!(recording.position_at(9), IVec2::new(2, 1));
+        assert_eq!(recording.last_turn(), 4);
+    }
+
+    #[test]
+    fn loading_reports_the_first_invalid_move() {
+        let mut map = Map::new(4, OpenFieldGenerator);
+        map.place_obstacle(IVec2::new(2, 0));
+        let mut recorder = MovementRecorder::new(IVec2::new(0, 0));
+        recorder.record(1, IVec2::new(1, 0));
+        recorder.record(2, IVec2::new(2, 0));
+        let json = recorder.recording().to_json();
+
+        assert_eq!(
+            MovementRecording::load(&json, &map),
+            Err(ReplayError::IllegalMove {
+                index: 1,
+                turn: 2,
+                from: IVec2::new(1, 0),
+                to: IVec2::new(2, 0),
+            })
+        );
+
+        let mut recorder = MovementRecorder::new(IVec2::new(0, 0));
+        recorder.record(3, IVec2::new(0, 1));
+        recorder.record(2, IVec2::new(0, 2));
+        assert_eq!(
+            recorder.recording().validate(&map),
+            Err(ReplayError::TurnOutOfOrder { index: 1, turn: 2 })
+        );
+        assert!(matches!(
+            MovementRecording::load("{", &map),
+            Err(ReplayError::Parse(_))
+        ));
+    }
+}
@@ -0,0 +1,474 @@
+use crate::map::Map;
+use crate::map_tile::{Direction, MapTile, Tile, TileSet};
+use crate::tile_generator::TileGenerator;
+use crate::topology::Topology;
+
+use bevy::prelude::*;
+use itertools::iproduct;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+/// High-level structure of a map: connected areas of one theme and the
+/// exits joining them.
+///
+/// Built by [`Map::region_graph`]. Narrative and quest tools can read it as
+/// JSON instead of parsing the tile grid.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegionGraph {
+    pub regions: Vec<Region>,
+    pub connections: Vec<RegionConnection>,
+}
+
+/// What part of the layout a [`Region`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RegionKind {
+    Room,
+    Corridor,
+    Field,
+    /// A single corridor tile where three or four ways meet, only found by
+    /// [`Map::label_regions`].
+    Junction,
+}
+
+impl RegionKind {
+    fn label(self) -> &'static str {
+        match self {
+            RegionKind::Room => "Room",
+            RegionKind::Corridor => "Corridor",
+            RegionKind::Field => "Field",
+            RegionKind::Junction => "Junction",
+        }
+    }
+}
+
+/// A connected area of walkable tiles that share a tile set.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Region {
+    /// Index of the region in [`RegionGraph::regions`].
+    pub id: usize,
+    pub kind: RegionKind,
+    /// Display name such as "Room 3", numbering each kind from 1 in id
+    /// order.
+    pub name: String,
+    pub theme: TileSet,
+    /// Smallest rectangle containing every tile, both corners inclusive.
+    #[serde(with = "rect_json")]
+    pub bounds: IRect,
+    pub tile_count: usize,
+}
+
+/// The exits between two regions, `from` being the lower id.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegionConnection {
+    pub from: usize,
+    pub to: usize,
+    pub exits: Vec<RegionExit>,
+}
+
+/// An exit from a tile of the `from` region into the `to` region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegionExit {
+    #[serde(with = "position_json")]
+    pub position: IVec2,
+    pub direction: Direction,
+}
+
+impl RegionGraph {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("region graphs always serialize")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Ids of the regions connected to `id`.
+    pub fn neighbors(&self, id: usize) -> impl Iterator<Item = usize> + '_ {
+        self.connections.iter().filter_map(move |connection| {
+            if connection.from == id {
+                Some(connection.to)
+            } else if connection.to == id {
+                Some(connection.from)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+/// A [`RegionGraph`] together with the region of every tile, from
+/// [`Map::label_regions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegionLabels {
+    pub graph: RegionGraph,
+    region_of: HashMap<IVec2, usize>,
+}
+
+impl RegionLabels {
+    /// The region `position` lies in, e.g. to tell the player they are in
+    /// "Room 3". None for walls and positions off the map.
+    pub fn region_at(&self, position: IVec2) -> Option<&Region> {
+        self.region_of
+            .get(&position)
+            .map(|id| &self.graph.regions[*id])
+    }
+}
+
+impl<G: TileGenerator> Map<G> {
+    /// Groups walkable tiles into regions and records the exits between
+    /// them. Two neighboring tiles belong to the same region when they share
+    /// a tile set and [`Map::can_move`] allows walking between them. Tiles
+    /// without exits are walls and belong to no region.
+    ///
+    /// Regions are numbered in x-major order of their first tile, so the
+    /// same map always gives the same graph.
+    pub fn region_graph(&self) -> RegionGraph {
+        self.build_regions(false).graph
+    }
+
+    /// Like [`Map::region_graph`], but corridors are split into segments at
+    /// junctions: corridor tiles with three or four walkable neighbors, each
+    /// a [`RegionKind::Junction`] region of its own. Also records which
+    /// region each tile is in.
+    pub fn label_regions(&self) -> RegionLabels {
+        self.build_regions(true)
+    }
+
+    fn build_regions(&self, split_junctions: bool) -> RegionLabels {
+        let kind_of = |position: IVec2| {
+            let tile = self.tiles[&position];
+            match tile.tile_set {
+                _ if tile.map_tile == MapTile::ZERO => None,
+                TileSet::Room => Some(RegionKind::Room),
+                TileSet::Corridor if split_junctions && self.neighbors(position).count() >= 3 => {
+                    Some(RegionKind::Junction)
+                }
+                TileSet::Corridor => Some(RegionKind::Corridor),
+                TileSet::Field => Some(RegionKind::Field),
+            }
+        };
+
+        let mut region_of: HashMap<IVec2, usize> = HashMap::new();
+        let mut regions = Vec::new();
+        let mut kind_counts: HashMap<RegionKind, usize> = HashMap::new();
+
+        for (x, y) in iproduct!(0..self.x as i32, 0..self.y as i32) {
+            let start = IVec2::new(x, y);
+            let Some(kind) = kind_of(start) else {
+                continue;
+            };
+            if region_of.contains_key(&start) {
+                continue;
+            }
+
+            let id = regions.len();
+            let mut bounds = IRect::from_corners(start, start);
+            let mut tile_count = 0;
+            let mut queue = VecDeque::from([start]);
+            region_of.insert(start, id);
+            while let Some(position) = queue.pop_front() {
+                bounds = bounds.union_point(position);
+                tile_count += 1;
+                if kind == RegionKind::Junction {
+                    continue;
+                }
+                for neighbor in self.neighbors(position) {
+                    if kind_of(neighbor) == Some(kind) && !region_of.contains_key(&neighbor) {
+                        region_of.insert(neighbor, id);
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+            let number = kind_counts.entry(kind).or_default();
+            *number += 1;
+            regions.push(Region {
+                id,
+                kind,
+                name: format!("{} {}", kind.label(), number),
+                theme: self.tiles[&start].tile_set,
+                bounds,
+                tile_count,
+            });
+        }
+
+        let mut exits: BTreeMap<(usize, usize), Vec<RegionExit>> = BTreeMap::new();
+        for (x, y) in iproduct!(0..self.x as i32, 0..self.y as i32) {
+            let position = IVec2::new(x, y);
+            let Some(&from) = region_of.get(&position) else {
+                continue;
+            };
+            for direction in Direction::all() {
+                let neighbor = self.adjacent(position, direction);
+                if !self.can_move(position, neighbor) {
+                    continue;
+                }
+                let to = region_of[&neighbor];
+                if from < to {
+                    exits.entry((from, to)).or_default().push(RegionExit {
+                        position,
+                        direction,
+                    });
+                }
+            }
+        }
+
+        RegionLabels {
+            graph: RegionGraph {
+                regions,
+                connections: exits
+                    .into_iter()
+                    .map(|((from, to), exits)| RegionConnection { from, to, exits })
+                    .collect(),
+            },
+            region_of,
+        }
+    }
+
+    /// [`Map::region_graph`] as pretty-printed JSON.
+    pub fn regions_to_json(&self) -> String {
+        self.region_graph().to_json()
+    }
+
+    /// The map as JSON, as the CLI and servers return it: its width, height
+    /// and topology, and every tile in x-then-y order as written by
+    /// [`tile_to_json`].
+    pub fn to_json(&self) -> Value {
+        let tiles: Vec<Value> = self
+            .positions()
+            .filter_map(|position| Some(tile_to_json(position, self.tiles.get(&position)?)))
+            .collect();
+        json!({
+            "width": self.x,
+            "height": self.y,
+            "topology": match self.topology {
+                Topology::Bounded => "bounded",
+                Topology::Toroidal => "toroidal",
+            },
+            "tiles": tiles,
+        })
+    }
+
+    /// [`Map::find_path`] as JSON: `reachable`, the number of `steps`, the
+    /// total movement `cost` and the `path` as `[x, y]` pairs from `from` to
+    /// `to`. `None` when there is no path.
+    pub fn path_report(&self, from: IVec2, to: IVec2) -> Option<Value> {
+        let path = self.find_path(from, to)?;
+        let cost: u32 = path
+            .windows(2)
+            .filter_map(|step| self.move_cost(step[0], step[1]))
+            .sum();
+        Some(json!({
+            "reachable": true,
+            "steps": path.len() - 1,
+            "cost": cost,
+            "path": path.iter().map(|position| position.to_array()).collect::<Vec<_>>(),
+        }))
+    }
+}
+
+/// A tile as JSON: its position, tile set, biome and exits, each written
+/// the way [`std::str::FromStr`] reads them back.
+pub fn tile_to_json(position: IVec2, tile: &Tile) -> Value {
+    json!({
+        "x": position.x,
+        "y": position.y,
+        "tile_set": tile.tile_set.to_string(),
+        "biome": tile.biome.to_string(),
+        "exits": tile.map_tile.to_string(),
+    })
+}
+
+/// Writes positions as `[x, y]`.
+pub(crate) mod position_json {
+    use bevy::prelude::IVec2;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(position: &IVec2, serializer: S) -> Result<S::Ok, S::Error> {
+        position.to_array().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<IVec2, D::Error> {
+        <[i32; 2]>::deserialize(deserializer).map(IVec2::from_array)
+    }
+}
+
+/// Writes rectangles as `{"min": [x, y], "max": [x, y]}`.
+mod rect_json {
+    use bevy::prelude::IRect;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Corners {
+        min: [i32; 2],
+        max: [i32; 2],
+    }
+
+    pub fn serialize<S: Serializer>(rect: &IRect, serializer: S) -> Result<S::Ok, S::Error> {
+        Corners {
+            min: rect.min.to_array(),
+            max: rect.max.to_array(),
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<IRect, D::Error> {
+        let corners = Corners::deserialize(deserializer)?;
+        Ok(IRect::from_corners(corners.min.into(), corners.max.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::open_field::OpenFieldGenerator;
+    use crate::testing::GridBuilder;
+    use crate::tile_generator::TileGeneratorDefault;
+
+    /// A 2x2 room in the bottom left, a corridor running east from it along
+    /// y = 0 and a wall everywhere else.
+    fn room_and_corridor() -> Map<OpenFieldGenerator> {
+        GridBuilder::new("     \n     \n     \n┏┓   \n┗┻──╴").build(OpenFieldGenerator)
+    }
+
+    /// Corridors along x = 2 and y = 2 crossing in the middle.
+    fn crossing() -> Map<OpenFieldGenerator> {
+        GridBuilder::new("  ╷  \n  │  \n╶─┼─╴\n  │  \n  ╵  ").build(OpenFieldGenerator)
+    }
+
+    #[test]
+    fn rooms_and_corridors_become_connected_regions() {
+        let map = room_and_corridor();
+
+        let graph = map.region_graph();
+
+        assert_eq!(
+            graph.regions,
+            vec![
+                Region {
+                    id: 0,
+                    kind: RegionKind::Room,
+                    name: "Room 1".to_string(),
+                    theme: TileSet::Room,
+                    bounds: IRect::new(0, 0, 1, 1),
+                    tile_count: 4,
+                },
+                Region {
+                    id: 1,
+                    kind: RegionKind::Corridor,
+                    name: "Corridor 1".to_string(),
+                    theme: TileSet::Corridor,
+                    bounds: IRect::new(2, 0, 4, 0),
+                    tile_count: 3,
+                },
+            ]
+        );
+        assert_eq!(
+            graph.connections,
+            vec![RegionConnection {
+                from: 0,
+                to: 1,
+                exits: vec![RegionExit {
+                    position: IVec2::new(1, 0),
+                    direction: Direction::East,
+                }],
+            }]
+        );
+        assert_eq!(graph.neighbors(1).collect::<Vec<_>>(), vec![0]);
+    }
+
+    #[test]
+    fn labels_split_corridors_at_junctions() {
+        let map = crossing();
+        assert_eq!(map.region_graph().regions.len(), 1);
+
+        let labels = map.label_regions();
+
+        let names: Vec<&str> = labels
+            .graph
+            .regions
+            .iter()
+            .map(|region| region.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "Corridor 1",
+                "Corridor 2",
+                "Junction 1",
+                "Corridor 3",
+                "Corridor 4"
+            ]
+        );
+        let junction = labels.region_at(IVec2::new(2, 2)).unwrap();
+        assert_eq!(junction.kind, RegionKind::Junction);
+        assert_eq!(labels.graph.neighbors(junction.id).count(), 4);
+        assert_eq!(
+            labels.region_at(IVec2::new(2, 0)).unwrap().name,
+            "Corridor 2"
+        );
+        assert_eq!(labels.region_at(IVec2::new(0, 0)), None);
+    }
+
+    #[test]
+    fn rooms_are_named_by_position() {
+        let labels = room_and_corridor().label_regions();
+
+        assert_eq!(labels.region_at(IVec2::new(1, 1)).unwrap().name, "Room 1");
+        assert_eq!(
+            labels.region_at(IVec2::new(4, 0)).unwrap().name,
+            "Corridor 1"
+        );
+    }
+
+    #[test]
+    fn json_uses_plain_arrays_and_lowercase_names() {
+        let json = room_and_corridor().regions_to_json();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(value["regions"][0]["theme"], "room");
+        assert_eq!(value["regions"][1]["kind"], "corridor");
+        assert_eq!(
+            value["regions"][1]["bounds"]["max"],
+            serde_json::json!([4, 0])
+        );
+        assert_eq!(
+            value["connections"][0]["exits"][0]["position"],
+            serde_json::json!([1, 0])
+        );
+        assert_eq!(value["connections"][0]["exits"][0]["direction"], "east");
+    }
+
+    #[test]
+    fn maps_and_paths_as_json() {
+        let map = GridBuilder::new("   \n┏┓ \n┗┻╴")
+            .toroidal()
+            .build(OpenFieldGenerator);
+        let value = map.to_json();
+
+        assert_eq!(value["topology"], "toroidal");
+        assert_eq!(value["width"], 3);
+        assert_eq!(value["tiles"].as_array().unwrap().len(), 9);
+        assert_eq!(
+            value["tiles"][1],
+            tile_to_json(IVec2::new(0, 1), &map.tiles[&IVec2::new(0, 1)])
+        );
+        assert_eq!(value["tiles"][1]["y"], 1);
+
+        let report = map.path_report(IVec2::ZERO, IVec2::ZERO).unwrap();
+        assert_eq!(report["steps"], 0);
+        assert_eq!(report["path"], serde_json::json!([[0, 0]]));
+    }
+
+    #[test]
+    fn json_round_trips() {
+        let map = Map::new(12, TileGeneratorDefault::with_seed(3));
+        let graph = map.region_graph();
+
+        let parsed = RegionGraph::from_json(&graph.to_json()).unwrap();
+
+        assert_eq!(parsed, graph);
+        assert!(RegionGraph::from_json("{\"regions\": 3}").is_err());
+    }
+}
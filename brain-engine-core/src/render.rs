@@ -0,0 +1,306 @@
+use crate::map::Map;
+use crate::map_tile::{MapTile, TileSet};
+use crate::tile_generator::TileGenerator;
+use crate::wall_geometry::WallGeometry;
+
+use bevy::prelude::*;
+use itertools::iproduct;
+use std::fmt::{self, Write};
+
+/// An opaque 8-bit RGB colour used by the map renderers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RenderColor {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl RenderColor {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+}
+
+impl fmt::Display for RenderColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+/// Optional grid lines drawn along every tile edge.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridStyle {
+    pub color: RenderColor,
+    pub width: f32,
+}
+
+/// Styling shared by every map renderer, so a map looks the same whichever
+/// output format it is exported to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderStyle {
+    /// Size of one tile in output pixels.
+    pub tile_size: f32,
+    /// Wall thickness in output pixels.
+    pub wall_thickness: f32,
+    pub background: RenderColor,
+    pub room_color: RenderColor,
+    pub corridor_color: RenderColor,
+    pub field_color: RenderColor,
+    /// Fill for tiles without any exits, whatever their tile set.
+    pub closed_color: RenderColor,
+    pub wall_color: RenderColor,
+    pub text_color: RenderColor,
+    pub grid: Option<GridStyle>,
+}
+
+impl Default for RenderStyle {
+    fn default() -> Self {
+        Self {
+            tile_size: 32.0,
+            wall_thickness: 3.0,
+            background: RenderColor::new(0x1e, 0x1e, 0x1e),
+            room_color: RenderColor::new(0xd8, 0xc8, 0xa8),
+            corridor_color: RenderColor::new(0x9a, 0x9a, 0x9a),
+            field_color: RenderColor::new(0x7a, 0x9a, 0x5a),
+            closed_color: RenderColor::new(0x3a, 0x3a, 0x3a),
+            wall_color: RenderColor::new(0x10, 0x10, 0x10),
+            text_color: RenderColor::new(0x80, 0x10, 0x10),
+            grid: None,
+        }
+    }
+}
+
+impl RenderStyle {
+    /// A black-on-white style with grid lines, intended for printing.
+    pub fn print() -> Self {
+        Self {
+            background: RenderColor::new(0xff, 0xff, 0xff),
+            room_color: RenderColor::new(0xff, 0xff, 0xff),
+            corridor_color: RenderColor::new(0xf0, 0xf0, 0xf0),
+            field_color: RenderColor::new(0xff, 0xff, 0xff),
+            closed_color: RenderColor::new(0xc0, 0xc0, 0xc0),
+            wall_color: RenderColor::new(0x00, 0x00, 0x00),
+            text_color: RenderColor::new(0x00, 0x00, 0x00),
+            grid: Some(GridStyle {
+                color: RenderColor::new(0xd0, 0xd0, 0xd0),
+                width: 0.5,
+            }),
+            ..Self::default()
+        }
+    }
+
+    /// Fill colour for a tile, given its tile set and exits.
+    pub fn tile_color(&self, tile_set: TileSet, map_tile: MapTile) -> RenderColor {
+        if map_tile == MapTile::ZERO {
+            return self.closed_color;
+        }
+        match tile_set {
+            TileSet::Room => self.room_color,
+            TileSet::Corridor => self.corridor_color,
+            TileSet::Field => self.field_color,
+        }
+    }
+}
+
+/// A text label drawn centred on a tile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Annotation {
+    pub position: IVec2,
+    pub text: String,
+}
+
+impl Annotation {
+    pub fn new(position: IVec2, text: impl Into<String>) -> Self {
+        Self {
+            position,
+            text: text.into(),
+        }
+    }
+}
+
+impl<G: TileGenerator> Map<G> {
+    /// Renders the map as a standalone SVG document.
+    pub fn render_svg(&self, style: &RenderStyle) -> String {
+        self.render_svg_with_annotations(style, &[])
+    }
+
+    /// Renders the map as a standalone SVG document with text labels on top.
+    ///
+    /// Tile `(0, 0)` is drawn in the bottom-left corner so the picture matches
+    /// the in-game orientation where North points up.
+    pub fn render_svg_with_annotations(
+        &self,
+        style: &RenderStyle,
+        annotations: &[Annotation],
+    ) -> String {
+        let tile_size = style.tile_size;
+        let width = self.x as f32 * tile_size;
+        let height = self.y as f32 * tile_size;
+        // Converts a point in tile units (y up) into SVG pixels (y down).
+        let to_svg = |point: Vec2| Vec2::new(point.x * tile_size, height - point.y * tile_size);
+
+        let mut svg = String::new();
+        // Writing into a String cannot fail.
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+        );
+        let _ = writeln!(
+            svg,
+            r#"<rect width="{width}" height="{height}" fill="{}"/>"#,
+            style.background
+        );
+
+        let _ = writeln!(svg, r#"<g class="tiles">"#);
+        for (x, y) in iproduct!(0..self.x, 0..self.y) {
+            let position = IVec2::new(x as i32, y as i32);
+            let Some(tile) = self.tiles.get(&position) else {
+                continue;
+            };
+            let corner = to_svg(Vec2::new(x as f32, y as f32 + 1.0));
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{tile_size}" height="{tile_size}" fill="{}"/>"#,
+                corner.x,
+                corner.y,
+                style.tile_color(tile.tile_set, tile.map_tile)
+            );
+        }
+        let _ = writeln!(svg, "</g>");
+
+        if let Some(grid) = style.grid {
+            let _ = writeln!(
+                svg,
+                r#"<g class="grid" stroke="{}" stroke-width="{}">"#,
+                grid.color, grid.width
+            );
+            for x in 0..=self.x {
+                let x = x as f32 * tile_size;
+                let _ = writeln!(svg, r#"<line x1="{x}" y1="0" x2="{x}" y2="{height}"/>"#);
+            }
+            for y in 0..=self.y {
+                let y = y as f32 * tile_size;
+                let _ = writeln!(svg, r#"<line x1="0" y1="{y}" x2="{width}" y2="{y}"/>"#);
+            }
+            let _ = writeln!(svg, "</g>");
+        }
+
+        let _ = writeln!(
+            svg,
+            r#"<g class="walls" stroke="{}" stroke-width="{}" stroke-linecap="square">"#,
+            style.wall_color, style.wall_thickness
+        );
+        for segment in WallGeometry::from_map(self).segments() {
+            let start = to_svg(segment.start);
+            let end = to_svg(segment.end);
+            let _ = writeln!(
+                svg,
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}"/>"#,
+                start.x, start.y, end.x, end.y
+            );
+        }
+        let _ = writeln!(svg, "</g>");
+
+        if !annotations.is_empty() {
+            let _ = writeln!(
+                svg,
+                r#"<g class="annotations" fill="{}" font-family="sans-serif" font-size="{}" text-anchor="middle" dominant-baseline="central">"#,
+                style.text_color,
+                tile_size / 2.0
+            );
+            for annotation in annotations {
+                let center = to_svg(annotation.position.as_vec2() + Vec2::splat(0.5));
+                let _ = writeln!(
+                    svg,
+                    r#"<text x="{}" y="{}">{}</text>"#,
+                    center.x,
+                    center.y,
+                    escape_xml(&annotation.text)
+                );
+            }
+            let _ = writeln!(svg, "</g>");
+        }
+
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::open_field::OpenFieldGenerator;
+    use crate::testing::GridBuilder;
+
+    #[test]
+    fn svg_has_a_rect_per_tile_and_a_line_per_wall() {
+        let map = GridBuilder::new("   \n   \n   ").build(OpenFieldGenerator);
+        let svg = map.render_svg(&RenderStyle::default());
+
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        // Background plus nine tiles.
+        assert_eq!(svg.matches("<rect ").count(), 10);
+        assert_eq!(
+            svg.matches("<line ").count(),
+            WallGeometry::from_map(&map).segments().len()
+        );
+    }
+
+    #[test]
+    fn svg_flips_the_y_axis() {
+        let map = GridBuilder::new("╷ \n╹ ").build(OpenFieldGenerator);
+        let style = RenderStyle::default();
+        let svg = map.render_svg(&style);
+
+        // Tile (0, 0) is the bottom-left tile, so its top edge sits at y = 32.
+        assert!(svg.contains(&format!(
+            r#"<rect x="0" y="32" width="32" height="32" fill="{}"/>"#,
+            style.room_color
+        )));
+    }
+
+    #[test]
+    fn grid_lines_are_optional() {
+        let map = GridBuilder::new("┼┼\n┼┼").build(OpenFieldGenerator);
+
+        let plain = map.render_svg(&RenderStyle::default());
+        let printed = map.render_svg(&RenderStyle::print());
+
+        assert!(!plain.contains(r#"class="grid""#));
+        assert!(printed.contains(r#"class="grid""#));
+        // Three vertical and three horizontal grid lines for a 2x2 map.
+        assert_eq!(printed.matches("<line ").count(), 6);
+    }
+
+    #[test]
+    fn annotations_are_escaped() {
+        let map = GridBuilder::new(" ").build(OpenFieldGenerator);
+        let svg = map.render_svg_with_annotations(
+            &RenderStyle::default(),
+            &[Annotation::new(IVec2::ZERO, "Boss <& loot>")],
+        );
+
+        assert!(svg.contains(">Boss &lt;&amp; loot&gt;</text>"));
+    }
+
+    #[test]
+    fn render_color_formats_as_hex() {
+        assert_eq!(RenderColor::new(255, 16, 0).to_string(), "#ff1000");
+    }
+}
@@ -0,0 +1,241 @@
+//! Growing, shrinking and cropping maps after they are generated, for
+//! dungeons that expand as the players progress.
+//!
+//! ```
+//! use bevy::prelude::*;
+//! use brain_engine_core::{Map, TileGeneratorDefault};
+//!
+//! let mut map = Map::new(8, TileGeneratorDefault::with_seed(2));
+//! let start = map.tiles[&IVec2::new(3, 3)];
+//!
+//! map.resize(12, 10);
+//! assert_eq!(map.tiles[&IVec2::new(3, 3)], start);
+//! assert_eq!(map.validate(), Ok(()));
+//! ```
+
+use crate::generation_observer::NoObserver;
+use crate::map::Map;
+use crate::map_tile::Direction;
+use crate::tile_generator::TileGenerator;
+use crate::tile_grid::TileGrid;
+use crate::topology::Topology;
+
+use bevy::prelude::*;
+use itertools::iproduct;
+
+impl<G: TileGenerator> Map<G> {
+    /// Grows or shrinks the map to `width` by `height` tiles, keeping every
+    /// tile that still fits.
+    ///
+    /// New tiles along the east and north are drawn from the map's generator,
+    /// which sees the existing tiles as neighbors, so they follow any exits
+    /// leading off the old edges, as generators with edge exits allowed
+    /// leave. New tiles beside the old ones are then matched to them, for
+    /// generators that plan the new area as a whole, such as a maze. Exits
+    /// leading off a new, shrunk edge are sealed.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        let (old_x, old_y) = (self.x as i32, self.y as i32);
+        let (x, y) = (width as i32, height as i32);
+        let before = self.tiles_before(
+            iproduct!(0..old_x.max(x), 0..old_y.max(y)).map(|(x, y)| IVec2::new(x, y)),
+        );
+        self.set_grid(IVec2::ZERO, width, height);
+
+        if x < old_x {
+            self.seal_edge(Direction::East);
+        }
+        if y < old_y {
+            self.seal_edge(Direction::North);
+        }
+        // The new columns beside the old tiles, then the new rows above.
+        self.generate_tiles(old_x.min(x)..x, 0..old_y.min(y), &mut NoObserver);
+        self.generate_tiles(0..x, old_y.min(y)..y, &mut NoObserver);
+        self.match_kept_tiles(IVec2::new(old_x.min(x), old_y.min(y)));
+        self.publish_changes(before);
+    }
+
+    /// Cuts the map down to `rect` (both corners inclusive, clipped to the
+    /// map), moving its bottom-left corner to the origin. Exits leading off
+    /// the cropped map are sealed, and the cropped map is bounded.
+    pub fn crop(&mut self, rect: IRect) {
+        let min = rect.min.max(IVec2::ZERO);
+        let max = rect.max.min(IVec2::new(self.x as i32, self.y as i32) - 1);
+        let size = (max - min + 1).max(IVec2::ZERO);
+        let before = self.tiles_before(self.positions());
+        self.set_grid(min, size.x as usize, size.y as usize);
+        self.topology = Topology::Bounded;
+        for direction in Direction::all() {
+            self.seal_edge(direction);
+        }
+        self.publish_changes(before);
+    }
+
+    /// Replaces the grid with the `width` by `height` tiles from `origin`,
+    /// moved so `origin` is the new origin.
+    fn set_grid(&mut self, origin: IVec2, width: usize, height: usize) {
+        let end = origin + IVec2::new(width as i32, height as i32);
+        let mut tiles = TileGrid::with_size(UVec2::new(width as u32, height as u32));
+        tiles.extend(
+            std::mem::take(&mut self.tiles)
+                .into_iter()
+                .filter(|(position, _)| position.cmpge(origin).all() && position.cmplt(end).all())
+                .map(|(position, tile)| (position - origin, tile)),
+        );
+        self.tiles = tiles;
+        let size = IVec2::new(width as i32, height as i32);
+        let inside =
+            |position: IVec2| position.cmpge(IVec2::ZERO).all() && position.cmplt(size).all();
+        self.exploration.shift(-origin, inside);
+        self.occupancy.shift(-origin, inside);
+        self.size = width;
+        self.x = width;
+        self.y = height;
+    }
+
+    /// Opens or closes the exits of new tiles towards the kept tiles, those
+    /// below `kept`, to match the exits the kept tiles have back.
+    fn match_kept_tiles(&mut self, kept: IVec2) {
+        let is_kept =
+            |position: IVec2| position.cmpge(IVec2::ZERO).all() && position.cmplt(kept).all();
+        let seam: Vec<(IVec2, Direction)> = self
+            .positions()
+            .filter(|position| !is_kept(*position))
+            .flat_map(|position| {
+                Direction::all()
+                    .into_iter()
+                    .filter(move |direction| is_kept(position + direction.offset()))
+                    .map(move |direction| (position, direction))
+            })
+            .collect();
+        for (position, direction) in seam {
+            let joined = self.tiles[&(position + direction.offset())]
+                .map_tile
+                .contains(direction.opposite());
+            let tile = self.tiles.get_mut(&position).unwrap();
+            tile.map_tile = if joined {
+                tile.map_tile.with(direction)
+            } else {
+                tile.map_tile.without(direction)
+            };
+        }
+    }
+
+    /// Closes the exits towards `direction` on that edge of the map that
+    /// lead off it or, across the seam of a toroidal map, to a tile without
+    /// the matching exit. The tile across the seam is sealed too.
+    fn seal_edge(&mut self, direction: Direction) {
+        let edge: Vec<IVec2> = self
+            .positions()
+            .filter(|position| !self.in_bounds(*position + direction.offset()))
+            .collect();
+        for position in edge {
+            let neighbor = self.adjacent(position, direction);
+            let exit = |position: IVec2, direction: Direction| {
+                self.tiles
+                    .get(&position)
+                    .is_some_and(|tile| tile.map_tile.contains(direction))
+            };
+            let joined = self.in_bounds(neighbor)
+                && exit(position, direction)
+                && exit(neighbor, direction.opposite());
+            if joined {
+                continue;
+            }
+            if let Some(tile) = self.tiles.get_mut(&position) {
+                tile.map_tile = tile.map_tile.without(direction);
+            }
+            if self.in_bounds(neighbor)
+                && let Some(tile) = self.tiles.get_mut(&neighbor)
+            {
+                tile.map_tile = tile.map_tile.without(direction.opposite());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_tile::MapTile;
+    use crate::maze::TileGeneratorMaze;
+    use crate::open_field::OpenFieldGenerator;
+    use crate::tile_generator::TileGeneratorDefault;
+
+    #[test]
+    fn growing_keeps_the_old_tiles() {
+        let mut map = Map::new(6, TileGeneratorDefault::with_seed(3));
+        let old = map.tiles.clone();
+
+        map.resize(9, 7);
+
+        assert_eq!((map.x, map.y), (9, 7));
+        for (position, tile) in &old {
+            assert_eq!(map.tiles[position], *tile);
+        }
+        assert_eq!(map.validate(), Ok(()));
+    }
+
+    #[test]
+    fn new_tiles_follow_exits_off_the_old_edge() {
+        let mut map = Map::new(4, TileGeneratorDefault::with_seed(8));
+        let edge = IVec2::new(3, 1);
+        let tile = map.tiles.get_mut(&edge).unwrap();
+        tile.map_tile = tile.map_tile.with(Direction::East);
+
+        map.resize(6, 4);
+
+        assert!(map.can_move(edge, IVec2::new(4, 1)));
+        assert_eq!(map.validate(), Ok(()));
+    }
+
+    #[test]
+    fn grown_mazes_match_the_old_tiles() {
+        let mut map = Map::new(6, TileGeneratorMaze::new(4));
+        let old = map.tiles.clone();
+
+        map.resize(9, 8);
+
+        for (position, tile) in &old {
+            assert_eq!(map.tiles[position], *tile);
+        }
+        assert_eq!(map.validate(), Ok(()));
+    }
+
+    #[test]
+    fn shrinking_seals_the_new_edges() {
+        let mut map = Map::new(8, OpenFieldGenerator);
+
+        map.resize(5, 3);
+
+        assert_eq!(map.tiles.len(), 15);
+        assert_eq!(map.tiles[&IVec2::new(4, 2)].map_tile, MapTile::SW);
+        assert_eq!(map.validate(), Ok(()));
+    }
+
+    #[test]
+    fn toroidal_maps_line_up_across_the_new_seams() {
+        for (width, height) in [(9, 8), (4, 3)] {
+            let mut map = Map::new_toroidal(6, TileGeneratorDefault::with_seed(5));
+
+            map.resize(width, height);
+
+            assert_eq!(map.validate(), Ok(()), "{width}x{height}");
+        }
+    }
+
+    #[test]
+    fn crops_move_to_the_origin_and_seal_every_edge() {
+        let mut map = Map::new(10, OpenFieldGenerator);
+
+        map.crop(IRect::new(2, 3, 20, 5));
+
+        assert_eq!((map.x, map.y), (8, 3));
+        assert_eq!(map.tiles[&IVec2::new(0, 0)].map_tile, MapTile::NE);
+        assert_eq!(map.tiles[&IVec2::new(4, 1)].map_tile, MapTile::NESW);
+        assert_eq!(map.validate(), Ok(()));
+
+        map.crop(IRect::new(5, 5, 9, 9));
+        assert_eq!((map.x, map.y), (3, 0));
+        assert!(map.tiles.is_empty());
+    }
+}
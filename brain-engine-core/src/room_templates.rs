@@ -0,0 +1,258 @@
+//! Decorated rooms: [`Prefab`]s with named markers, such as an altar or a
+//! chest, stamped into the rooms of a generated map.
+//!
+//! ```
+//! use bevy::prelude::*;
+//! use brain_engine_core::{FeaturePlacer, Map, OpenFieldGenerator, RoomTemplate, RoomTemplates};
+//!
+//! let mut map = Map::from_ascii("┏┳┓ \n┣╋╋╴\n┗┻┛ ", OpenFieldGenerator).unwrap();
+//! let shrine = RoomTemplate::new("┏┳┓\n┣╋╋\n┗┻┛".parse().unwrap())
+//!     .with_marker("altar", IVec2::new(1, 1));
+//!
+//! let markers = RoomTemplates::new()
+//!     .with_template(shrine)
+//!     .decorate(&mut map, &FeaturePlacer::new(7));
+//! assert_eq!(markers.get(IVec2::new(1, 1)).unwrap().0, "altar");
+//! ```
+
+use crate::features::{FeatureLayer, FeaturePlacer};
+use crate::map::Map;
+use crate::map_file::TileExtension;
+use crate::prefab::Prefab;
+use crate::rooms::RoomArea;
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use rand::Rng;
+
+/// A named spot in a decorated room, such as `"altar"` or `"chest"`, for
+/// the game to put an entity on. Saved in map files under `room_marker`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RoomMarker(pub String);
+
+impl TileExtension for RoomMarker {
+    const KEY: &'static str = "room_marker";
+
+    fn encode(&self) -> Vec<u8> {
+        self.0.as_bytes().to_vec()
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok().map(RoomMarker)
+    }
+}
+
+/// A room layout for [`RoomTemplates`]: the prefab decides the room's
+/// tiles and exits, and the markers name positions in it, relative to its
+/// bottom-left cell like the prefab's own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoomTemplate {
+    prefab: Prefab,
+    markers: Vec<(IVec2, String)>,
+    weight: f32,
+}
+
+impl RoomTemplate {
+    /// A template without markers and with a weight of 1.
+    pub fn new(prefab: Prefab) -> Self {
+        Self {
+            prefab,
+            markers: Vec::new(),
+            weight: 1.0,
+        }
+    }
+
+    /// Adds a marker called `name` at `position` of the prefab.
+    pub fn with_marker(mut self, name: impl Into<String>, position: IVec2) -> Self {
+        self.markers.push((position, name.into()));
+        self
+    }
+
+    /// Sets how likely the template is picked relative to the others that
+    /// fit the same room. A weight of 0 never picks it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `weight` is negative or not finite.
+    pub fn with_weight(mut self, weight: f32) -> Self {
+        assert!(
+            weight.is_finite() && weight >= 0.0,
+            "invalid template weight {weight}"
+        );
+        self.weight = weight;
+        self
+    }
+
+    pub fn prefab(&self) -> &Prefab {
+        &self.prefab
+    }
+
+    pub fn markers(&self) -> &[(IVec2, String)] {
+        &self.markers
+    }
+
+    pub fn weight(&self) -> f32 {
+        self.weight
+    }
+
+    /// Returns true when the prefab is exactly the size of `room`, the room
+    /// is rectangular and the prefab keeps every doorway of the room open,
+    /// so stamping it leaves the room connected as before.
+    pub fn fits(&self, room: &RoomArea) -> bool {
+        let size = (room.bounds.size() + 1).as_uvec2();
+        room.is_rectangular()
+            && self.prefab.size() == size
+            && room.exits.iter().all(|(tile, direction)| {
+                self.prefab
+                    .get(*tile - room.bounds.min)
+                    .is_none_or(|prefab_tile| prefab_tile.map_tile.contains(*direction))
+            })
+    }
+}
+
+/// A catalog of [`RoomTemplate`]s to decorate the rooms of a map with.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RoomTemplates {
+    templates: Vec<RoomTemplate>,
+}
+
+impl RoomTemplates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_template(mut self, template: RoomTemplate) -> Self {
+        self.templates.push(template);
+        self
+    }
+
+    pub fn templates(&self) -> &[RoomTemplate] {
+        &self.templates
+    }
+
+    /// Stamps a template into every room of [`Map::detect_rooms`] that one
+    /// [fits](RoomTemplate::fits), picked at random by weight, and returns
+    /// the markers of the stamped templates at their map positions. Rooms
+    /// that no template fits are left as they are.
+    ///
+    /// Picks draw from the `room_templates` stream of `placer`, so the same
+    /// seed and map decorate the same rooms the same way.
+    pub fn decorate<G: TileGenerator>(
+        &self,
+        map: &mut Map<G>,
+        placer: &FeaturePlacer,
+    ) -> FeatureLayer<RoomMarker> {
+        let mut random = placer.stream("room_templates");
+        let mut markers = FeatureLayer::new();
+        for room in map.detect_rooms() {
+            let candidates: Vec<&RoomTemplate> = self
+                .templates
+                .iter()
+                .filter(|template| template.weight > 0.0 && template.fits(&room))
+                .collect();
+            let total: f32 = candidates.iter().map(|template| template.weight).sum();
+            let Some(last) = candidates.last() else {
+                continue;
+            };
+            let mut roll = random.random_range(0.0..total);
+            let template = candidates
+                .iter()
+                .find(|template| {
+                    roll -= template.weight;
+                    roll < 0.0
+                })
+                .unwrap_or(last);
+
+            let origin = room.bounds.min;
+            map.stamp(&template.prefab, origin);
+            for (position, name) in &template.markers {
+                markers.insert(origin + *position, RoomMarker(name.clone()));
+            }
+        }
+        markers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_file::TileExtensions;
+    use crate::map_tile::{MapTile, Tile, TileSet};
+    use crate::open_field::OpenFieldGenerator;
+
+    /// A 3x3 room with a doorway east onto a dead-end corridor.
+    const ROOM: &str = "┏┳┓ \n┣╋╋╴\n┗┻┛ ";
+
+    fn shrine() -> RoomTemplate {
+        RoomTemplate::new("┏┳┓\n┣╋╋\n┗┻┛".parse().unwrap()).with_marker("altar", IVec2::new(1, 1))
+    }
+
+    #[test]
+    fn templates_are_stamped_with_their_markers() {
+        let mut map = Map::from_ascii(ROOM, OpenFieldGenerator).unwrap();
+        let templates = RoomTemplates::new().with_template(shrine());
+
+        let markers = templates.decorate(&mut map, &FeaturePlacer::new(3));
+
+        assert_eq!(
+            markers.iter().collect::<Vec<_>>(),
+            vec![(IVec2::new(1, 1), &RoomMarker("altar".into()))]
+        );
+        assert!(map.can_move(IVec2::new(2, 1), IVec2::new(3, 1)));
+        assert_eq!(map.validate(), Ok(()));
+    }
+
+    #[test]
+    fn templates_must_keep_the_doorways_open() {
+        let mut map = Map::from_ascii(ROOM, OpenFieldGenerator).unwrap();
+        let sealed =
+            RoomTemplate::new("┏┳┓\n┣╋┫\n┗┻┛".parse().unwrap()).with_marker("chest", IVec2::ONE);
+        let small = RoomTemplate::new("┏┓\n┗┛".parse().unwrap());
+        let before = map.tiles.clone();
+
+        let markers = RoomTemplates::new()
+            .with_template(sealed)
+            .with_template(small)
+            .decorate(&mut map, &FeaturePlacer::new(3));
+
+        assert!(markers.is_empty());
+        assert_eq!(map.tiles, before);
+    }
+
+    #[test]
+    fn templates_are_picked_by_weight() {
+        let templates = RoomTemplates::new()
+            .with_template(shrine().with_weight(0.0))
+            .with_template(
+                RoomTemplate::new("┏━┓\n┃▪┣\n┗━┛".parse().unwrap())
+                    .with_marker("statue", IVec2::new(1, 1)),
+            );
+
+        for seed in 0..8 {
+            let mut map = Map::from_ascii(ROOM, OpenFieldGenerator).unwrap();
+            let markers = templates.decorate(&mut map, &FeaturePlacer::new(seed));
+            assert_eq!(
+                markers.get(IVec2::new(1, 1)),
+                Some(&RoomMarker("statue".into()))
+            );
+            assert_eq!(
+                map.tiles[&IVec2::new(1, 1)],
+                Tile::new(TileSet::Room, MapTile::ZERO)
+            );
+        }
+    }
+
+    #[test]
+    fn markers_are_saved_as_tile_extensions() {
+        let mut layer = FeatureLayer::new();
+        layer.insert(IVec2::new(4, 2), RoomMarker("altar".into()));
+        let mut extensions = TileExtensions::new();
+
+        layer.write_to(&mut extensions);
+
+        assert_eq!(
+            extensions.get::<RoomMarker>(IVec2::new(4, 2)),
+            Some(RoomMarker("altar".into()))
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use crate::map::Map;
+use crate::map_tile::{Direction, TileSet};
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use itertools::iproduct;
+use std::collections::{HashSet, VecDeque};
+
+/// A connected area of room tiles found by [`Map::detect_rooms`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoomArea {
+    /// The room's tiles, sorted by x then y.
+    pub tiles: Vec<IVec2>,
+    /// Smallest rectangle containing every tile, both corners inclusive.
+    pub bounds: IRect,
+    /// Doorways out of the room: a room tile and the direction of an exit
+    /// that leads to a walkable tile outside the room.
+    pub exits: Vec<(IVec2, Direction)>,
+}
+
+impl RoomArea {
+    /// Returns true when the room fills its bounding rectangle.
+    pub fn is_rectangular(&self) -> bool {
+        let size = self.bounds.size() + 1;
+        self.tiles.len() == (size.x * size.y) as usize
+    }
+
+    pub fn contains(&self, position: IVec2) -> bool {
+        self.tiles
+            .binary_search_by_key(&(position.x, position.y), |tile| (tile.x, tile.y))
+            .is_ok()
+    }
+}
+
+impl<G: TileGenerator> Map<G> {
+    /// Groups [`TileSet::Room`] tiles into rooms. Two neighboring room tiles
+    /// belong to the same room when [`Map::can_move`] allows walking between
+    /// them, so rooms can be any shape; use [`RoomArea::is_rectangular`] to
+    /// pick out the rectangular ones.
+    ///
+    /// Works on any tile layout, whichever generator or file it came from.
+    /// Rooms are ordered by their first tile in x-then-y order.
+    pub fn detect_rooms(&self) -> Vec<RoomArea> {
+        let is_room = |position: &IVec2| {
+            self.tiles
+                .get(position)
+                .is_some_and(|tile| tile.tile_set == TileSet::Room)
+        };
+
+        let mut visited = HashSet::new();
+        let mut rooms = Vec::new();
+        for (x, y) in iproduct!(0..self.x as i32, 0..self.y as i32) {
+            let start = IVec2::new(x, y);
+            if !is_room(&start) || !visited.insert(start) {
+                continue;
+            }
+
+            let mut tiles = Vec::new();
+            let mut queue = VecDeque::from([start]);
+            while let Some(current) = queue.pop_front() {
+                tiles.push(current);
+                for direction in Direction::all() {
+                    let next = self.adjacent(current, direction);
+                    if is_room(&next) && self.can_move(current, next) && visited.insert(next) {
+                        queue.push_back(next);
+                    }
+                }
+            }
+            tiles.sort_by_key(|tile| (tile.x, tile.y));
+
+            let bounds = tiles
+                .iter()
+                .fold(IRect::from_corners(start, start), |bounds, tile| {
+                    bounds.union_point(*tile)
+                });
+            let mut room = RoomArea {
+                tiles,
+                bounds,
+                exits: Vec::new(),
+            };
+            room.exits = room
+                .tiles
+                .iter()
+                .flat_map(|tile| Direction::all().map(|direction| (*tile, direction)))
+                .filter(|(tile, direction)| {
+                    let next = self.adjacent(*tile, *direction);
+                    !room.contains(next) && self.can_move(*tile, next)
+                })
+                .collect();
+            rooms.push(room);
+        }
+        rooms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::open_field::OpenFieldGenerator;
+    use crate::testing::GridBuilder;
+    use crate::tile_generator::TileGeneratorDefault;
+
+    #[test]
+    fn detects_a_rectangular_room_and_its_doorway() {
+        // A 2x2 room in the bottom left with a corridor leading east.
+        let map = GridBuilder::new("   \n┏┓ \n┗┻╴").build(OpenFieldGenerator);
+
+        let rooms = map.detect_rooms();
+
+        assert_eq!(rooms.len(), 1);
+        let room = &rooms[0];
+        assert_eq!(room.bounds, IRect::new(0, 0, 1, 1));
+        assert!(room.is_rectangular());
+        assert_eq!(room.exits, vec![(IVec2::new(1, 0), Direction::East)]);
+    }
+
+    #[test]
+    fn walls_split_adjacent_room_tiles() {
+        // An L-shaped room next to a single-tile room it has no exit into.
+        let map = GridBuilder::new("╻▪\n┗╸").build(OpenFieldGenerator);
+
+        let rooms = map.detect_rooms();
+
+        assert_eq!(rooms.len(), 2);
+        assert_eq!(
+            rooms[0].tiles,
+            vec![IVec2::new(0, 0), IVec2::new(0, 1), IVec2::new(1, 0)]
+        );
+        assert!(!rooms[0].is_rectangular());
+        assert_eq!(rooms[1].tiles, vec![IVec2::new(1, 1)]);
+        assert!(rooms[1].exits.is_empty());
+    }
+
+    #[test]
+    fn every_room_tile_of_a_generated_map_is_in_exactly_one_room() {
+        let map = Map::new(
+            16,
+            TileGeneratorDefault::with_seed_and_probabilities(21, 0.6, 0.5),
+        );
+
+        let rooms = map.detect_rooms();
+        let mut seen = HashSet::new();
+        for room in &rooms {
+            for tile in &room.tiles {
+                assert_eq!(map.tiles[tile].tile_set, TileSet::Room);
+                assert!(room.bounds.contains(*tile));
+                assert!(seen.insert(*tile));
+            }
+        }
+        let room_tiles = map
+            .tiles
+            .values()
+            .filter(|tile| tile.tile_set == TileSet::Room)
+            .count();
+        assert_eq!(seen.len(), room_tiles);
+    }
+}
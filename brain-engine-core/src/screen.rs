@@ -1,39 +1,415 @@
 use bevy::prelude::*;
 
+/// Where [`Screen`] places the grid in the window, inside the padding, when
+/// the grid is smaller than the space left for it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ScreenAnchor {
+    #[default]
+    Center,
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl ScreenAnchor {
+    /// The side the anchor pulls the grid towards on each axis, -1, 0 or 1,
+    /// with y pointing up as in Bevy's world space.
+    pub fn direction(self) -> Vec2 {
+        match self {
+            ScreenAnchor::Center => Vec2::ZERO,
+            ScreenAnchor::Top => Vec2::Y,
+            ScreenAnchor::Bottom => Vec2::NEG_Y,
+            ScreenAnchor::Left => Vec2::NEG_X,
+            ScreenAnchor::Right => Vec2::X,
+            ScreenAnchor::TopLeft => Vec2::new(-1.0, 1.0),
+            ScreenAnchor::TopRight => Vec2::ONE,
+            ScreenAnchor::BottomLeft => Vec2::NEG_ONE,
+            ScreenAnchor::BottomRight => Vec2::new(1.0, -1.0),
+        }
+    }
+}
+
+/// Pixels at each edge of the window that [`Screen`] keeps free of the
+/// grid, e.g. for a UI sidebar.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ScreenPadding {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
+impl ScreenPadding {
+    pub const ZERO: Self = Self::all(0.0);
+
+    /// The same padding at every edge.
+    pub const fn all(pixels: f32) -> Self {
+        Self {
+            left: pixels,
+            right: pixels,
+            top: pixels,
+            bottom: pixels,
+        }
+    }
+
+    /// The padding along each axis: left plus right, and top plus bottom.
+    pub fn size(&self) -> Vec2 {
+        Vec2::new(self.left + self.right, self.top + self.bottom)
+    }
+}
+
+const SQRT_3: f32 = 1.732_050_8;
+
+/// How [`Screen`] lays the tiles of the grid out in the window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ScreenProjection {
+    /// Square tiles in rows and columns.
+    #[default]
+    Orthogonal,
+    /// Diamond tiles twice as wide as high, with the x axis running up to
+    /// the right and the y axis up to the left.
+    Isometric,
+    /// Hexagons with a corner at the top, in rows with every odd row moved
+    /// half a tile to the right. The tile size is the width of a hexagon.
+    HexPointyTop,
+    /// Hexagons with a flat top, in columns with every odd column moved
+    /// half a tile up. The tile size is the height of a hexagon.
+    HexFlatTop,
+}
+
+impl ScreenProjection {
+    /// Where the center of `tile` is drawn relative to `camera`, in tiles.
+    pub fn offset(self, tile: IVec2, camera: Vec2) -> Vec2 {
+        let offset = tile.as_vec2() - camera;
+        match self {
+            ScreenProjection::Orthogonal => offset,
+            ScreenProjection::Isometric => {
+                Vec2::new(offset.x - offset.y, (offset.x + offset.y) / 2.0) / 2.0
+            }
+            ScreenProjection::HexPointyTop => Vec2::new(
+                offset.x + tile.y.rem_euclid(2) as f32 / 2.0,
+                offset.y * SQRT_3 / 2.0,
+            ),
+            ScreenProjection::HexFlatTop => Vec2::new(
+                offset.x * SQRT_3 / 2.0,
+                offset.y + tile.x.rem_euclid(2) as f32 / 2.0,
+            ),
+        }
+    }
+
+    /// The tile whose shape covers `offset`, in tiles from `camera`: the
+    /// inverse of [`ScreenProjection::offset`].
+    pub fn nearest_tile(self, offset: Vec2, camera: Vec2) -> IVec2 {
+        match self {
+            ScreenProjection::Orthogonal => (offset + camera + 0.5).floor().as_ivec2(),
+            ScreenProjection::Isometric => {
+                let (across, up) = (offset.x * 2.0, offset.y * 4.0);
+                let tile = Vec2::new(up + across, up - across) / 2.0;
+                (tile + camera + 0.5).floor().as_ivec2()
+            }
+            ScreenProjection::HexPointyTop => {
+                let row = offset.y * 2.0 / SQRT_3 + camera.y;
+                let axial = round_axial(Vec2::new(offset.x + camera.x - row / 2.0, row));
+                IVec2::new(axial.x + axial.y.div_euclid(2), axial.y)
+            }
+            ScreenProjection::HexFlatTop => {
+                let column = offset.x * 2.0 / SQRT_3 + camera.x;
+                let axial = round_axial(Vec2::new(offset.y + camera.y - column / 2.0, column));
+                IVec2::new(axial.y, axial.x + axial.y.div_euclid(2))
+            }
+        }
+    }
+
+    /// The width and height of the box around a single tile, in tiles.
+    pub fn tile_extent(self) -> Vec2 {
+        match self {
+            ScreenProjection::Orthogonal => Vec2::ONE,
+            ScreenProjection::Isometric => Vec2::new(1.0, 0.5),
+            ScreenProjection::HexPointyTop => Vec2::new(1.0, 2.0 / SQRT_3),
+            ScreenProjection::HexFlatTop => Vec2::new(2.0 / SQRT_3, 1.0),
+        }
+    }
+
+    /// The width and height of the box around a grid of `tiles`, in tiles.
+    pub fn grid_extent(self, tiles: UVec2) -> Vec2 {
+        if tiles.cmpeq(UVec2::ZERO).any() {
+            return Vec2::ZERO;
+        }
+        let tiles = tiles.as_vec2();
+        let shifted = |count: f32| if count > 1.0 { 0.5 } else { 0.0 };
+        match self {
+            ScreenProjection::Orthogonal => tiles,
+            ScreenProjection::Isometric => Vec2::new(2.0, 1.0) * (tiles.x + tiles.y) / 4.0,
+            ScreenProjection::HexPointyTop => Vec2::new(
+                tiles.x + shifted(tiles.y),
+                (tiles.y - 1.0) * SQRT_3 / 2.0 + 2.0 / SQRT_3,
+            ),
+            ScreenProjection::HexFlatTop => Vec2::new(
+                (tiles.x - 1.0) * SQRT_3 / 2.0 + 2.0 / SQRT_3,
+                tiles.y + shifted(tiles.x),
+            ),
+        }
+    }
+}
+
+/// Rounds fractional axial hex coordinates to the hexagon containing them.
+fn round_axial(axial: Vec2) -> IVec2 {
+    let cube = axial.extend(-axial.x - axial.y);
+    let rounded = cube.round();
+    let error = (rounded - cube).abs();
+    let (mut q, mut r) = (rounded.x, rounded.y);
+    if error.x > error.y && error.x > error.z {
+        q = -r - rounded.z;
+    } else if error.y > error.z {
+        r = -q - rounded.z;
+    }
+    IVec2::new(q as i32, r as i32)
+}
+
 /// Describes the screen dimensions and tile sizing, providing helpers for
 /// converting tile coordinates into pixel positions.
+///
+/// A map larger than the window is shown through a viewport: pixel
+/// positions are relative to the camera, which scrolls over the map and
+/// stays within it. Zooming scales tiles around the camera, so the viewport
+/// shows fewer tiles when zoomed in.
+///
+/// The grid is centered in the window unless a [`ScreenAnchor`] and
+/// [`ScreenPadding`] say otherwise, see [`Screen::new_anchored`]. Tiles are
+/// square unless [`Screen::with_projection`] picks isometric or hex tiles.
 #[derive(Debug, Clone, Resource)]
 pub struct Screen {
     dimensions: UVec2,
     tile_size: f32,
-    center_offset: Vec2,
+    zoom: f32,
+    viewport: UVec2,
+    /// The tile coordinate drawn at the origin, fractional when the viewport
+    /// or the map has an even number of tiles.
+    camera_center: Vec2,
+    anchor: ScreenAnchor,
+    padding: ScreenPadding,
+    /// The window size in pixels, when set rather than fitted to the
+    /// viewport and padding.
+    window_size: Option<Vec2>,
+    projection: ScreenProjection,
 }
 
 impl Screen {
-    /// Creates a new [`Screen`].
+    /// The smallest zoom [`Screen::set_zoom`] accepts.
+    pub const MIN_ZOOM: f32 = 0.1;
+    /// The largest zoom [`Screen::set_zoom`] accepts.
+    pub const MAX_ZOOM: f32 = 10.0;
+
+    /// Creates a new [`Screen`] showing the whole grid, centered.
     ///
     /// * `dimensions` - The number of tiles that fit horizontally and vertically.
     /// * `tile_size` - The size in pixels of a single tile.
     pub fn new(dimensions: UVec2, tile_size: f32) -> Self {
-        let center_offset = Vec2::new(
-            (dimensions.x as f32 - 1.0) / 2.0 * tile_size,
-            (dimensions.y as f32 - 1.0) / 2.0 * tile_size,
-        );
-
         Self {
             dimensions,
             tile_size,
-            center_offset,
+            zoom: 1.0,
+            viewport: dimensions,
+            camera_center: (dimensions.as_vec2() - 1.0) / 2.0,
+            anchor: ScreenAnchor::Center,
+            padding: ScreenPadding::ZERO,
+            window_size: None,
+            projection: ScreenProjection::Orthogonal,
+        }
+    }
+
+    /// Creates a [`Screen`] that keeps `padding` pixels free at the edges of
+    /// the window and places the grid at `anchor` in the space left, e.g.
+    /// [`ScreenAnchor::TopLeft`] with 200 pixels of right padding for a
+    /// sidebar. The window fits the grid and the padding until
+    /// [`Screen::set_window_size`] says otherwise.
+    pub fn new_anchored(
+        dimensions: UVec2,
+        tile_size: f32,
+        anchor: ScreenAnchor,
+        padding: ScreenPadding,
+    ) -> Self {
+        Self {
+            anchor,
+            padding,
+            ..Self::new(dimensions, tile_size)
         }
     }
 
-    /// Converts a tile coordinate into the centered pixel position on screen.
+    pub fn with_window_size(mut self, window_size: Vec2) -> Self {
+        self.set_window_size(window_size);
+        self
+    }
+
+    /// Sets the size of the window in pixels, e.g. when it is resized, so
+    /// anchoring and padding follow its edges.
+    pub fn set_window_size(&mut self, window_size: Vec2) {
+        self.window_size = Some(window_size);
+    }
+
+    /// The size of the window in pixels: as set, or else the viewport at
+    /// zoom 1 plus the padding.
+    pub fn window_size(&self) -> Vec2 {
+        self.window_size
+            .unwrap_or(self.viewport_size() + self.padding.size())
+    }
+
+    /// Lays the tiles out with `projection`, e.g. [`ScreenProjection::Isometric`]
+    /// for diamond tiles. Scrolling limits are still worked out in tiles, so
+    /// they match the window exactly only for [`ScreenProjection::Orthogonal`].
+    pub fn with_projection(mut self, projection: ScreenProjection) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    pub fn projection(&self) -> ScreenProjection {
+        self.projection
+    }
+
+    pub fn anchor(&self) -> ScreenAnchor {
+        self.anchor
+    }
+
+    pub fn padding(&self) -> ScreenPadding {
+        self.padding
+    }
+
+    /// Shows only `viewport_tiles` tiles at a time, e.g. the tiles that fit
+    /// in the window at zoom 1, with the camera on the center of the grid.
+    pub fn with_viewport(mut self, viewport_tiles: UVec2) -> Self {
+        self.viewport = viewport_tiles;
+        self.camera_center = self.clamp_camera((self.dimensions.as_vec2() - 1.0) / 2.0);
+        self
+    }
+
+    /// Scrolls the camera to `tile`, or as close to it as the viewport can
+    /// get without showing space beyond the grid. Along an axis where the
+    /// whole grid fits, the grid stays centered.
+    pub fn set_camera_center(&mut self, tile: IVec2) {
+        self.camera_center = self.clamp_camera(tile.as_vec2());
+    }
+
+    /// The tile coordinate drawn at the origin.
+    pub fn camera_center(&self) -> Vec2 {
+        self.camera_center
+    }
+
+    /// Returns the number of tiles the viewport shows across at zoom 1.
+    pub fn viewport(&self) -> UVec2 {
+        self.viewport
+    }
+
+    pub fn with_zoom(mut self, zoom: f32) -> Self {
+        self.set_zoom(zoom);
+        self
+    }
+
+    /// Scales tiles by `zoom`, clamped between [`Screen::MIN_ZOOM`] and
+    /// [`Screen::MAX_ZOOM`], keeping the camera within the grid.
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.clamp(Self::MIN_ZOOM, Self::MAX_ZOOM);
+        self.camera_center = self.clamp_camera(self.camera_center);
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Moves the zoom smoothly towards `target`, e.g. once per frame after a
+    /// mouse-wheel scroll. `rate` is how quickly it closes the gap: after
+    /// `1 / rate` seconds about two thirds of the way is covered. Snaps to
+    /// `target` once within 0.1%.
+    pub fn zoom_towards(&mut self, target: f32, rate: f32, delta_seconds: f32) {
+        let progress = 1.0 - (-rate * delta_seconds).exp();
+        let zoom = Self::interpolate_zoom(self.zoom, target, progress);
+        if (zoom / target - 1.0).abs() < 0.001 {
+            self.set_zoom(target);
+        } else {
+            self.set_zoom(zoom);
+        }
+    }
+
+    /// The zoom `progress` of the way from `from` to `to`, both positive.
+    /// Interpolates geometrically, so zooming from 1 to 4 passes 2 half way
+    /// and every step looks equally large.
+    pub fn interpolate_zoom(from: f32, to: f32, progress: f32) -> f32 {
+        from * (to / from).powf(progress.clamp(0.0, 1.0))
+    }
+
+    /// Converts a tile coordinate into the centered pixel position on screen,
+    /// relative to the camera and moved by [`Screen::grid_offset`].
     pub fn pixel_position(&self, tile_position: IVec2) -> Vec3 {
-        Vec3::new(
-            tile_position.x as f32 * self.tile_size - self.center_offset.x,
-            tile_position.y as f32 * self.tile_size - self.center_offset.y,
-            0.0,
-        )
+        (self.projection.offset(tile_position, self.camera_center) * self.tile_size()
+            + self.grid_offset())
+        .extend(0.0)
+    }
+
+    /// Where the anchor and padding move the grid from the center of the
+    /// window, in pixels. Zero for a centered grid without padding.
+    pub fn grid_offset(&self) -> Vec2 {
+        let half_window = self.window_size() / 2.0;
+        let min = -half_window + Vec2::new(self.padding.left, self.padding.bottom);
+        let max = half_window - Vec2::new(self.padding.right, self.padding.top);
+        let viewport = self.viewport_rect();
+        let shown = self.grid_rect().size().min(viewport.size());
+        let slack = (max - min - shown) / 2.0;
+        (min + max) / 2.0 + self.anchor.direction() * slack - viewport.center()
+    }
+
+    /// Converts a world position into the tile under it, e.g. for mouse
+    /// picking. Returns `None` when the position is off the grid.
+    pub fn tile_position(&self, world_position: Vec2) -> Option<IVec2> {
+        let offset = (world_position - self.grid_offset()) / self.tile_size();
+        let tile_position = self.projection.nearest_tile(offset, self.camera_center);
+        self.in_bounds(tile_position).then_some(tile_position)
+    }
+
+    /// The grid tiles at least partly inside the viewport, in x-then-y
+    /// order, e.g. to spawn or draw only what can be seen.
+    pub fn visible_tiles(&self) -> impl Iterator<Item = IVec2> + use<> {
+        let (projection, camera) = (self.projection, self.camera_center);
+        let tile_size = self.tile_size();
+        let viewport = self.viewport_rect();
+        // The tiles under the corners of the viewport span all it shows, give
+        // or take the tiles the corners only just miss.
+        let corners = [
+            viewport.min,
+            viewport.max,
+            Vec2::new(viewport.min.x, viewport.max.y),
+            Vec2::new(viewport.max.x, viewport.min.y),
+        ]
+        .map(|corner| projection.nearest_tile(corner / tile_size, camera));
+        let min = (corners.into_iter().reduce(IVec2::min).unwrap() - 1).max(IVec2::ZERO);
+        let max = (corners.into_iter().reduce(IVec2::max).unwrap() + 1)
+            .min(self.dimensions.as_ivec2() - 1);
+        let extent = projection.tile_extent() * tile_size;
+        (min.x..=max.x)
+            .flat_map(move |x| (min.y..=max.y).map(move |y| IVec2::new(x, y)))
+            .filter(move |&tile| {
+                let center = projection.offset(tile, camera) * tile_size;
+                !Rect::from_center_size(center, extent)
+                    .intersect(viewport)
+                    .is_empty()
+            })
+    }
+
+    /// Returns true when `tile_position` lies on the grid.
+    pub fn in_bounds(&self, tile_position: IVec2) -> bool {
+        tile_position.cmpge(IVec2::ZERO).all()
+            && tile_position.cmplt(self.dimensions.as_ivec2()).all()
+    }
+
+    /// Returns the world-space rectangle covered by the grid, from the outer
+    /// edges of the outermost tiles.
+    pub fn bounds(&self) -> Rect {
+        let grid = self.grid_rect();
+        let offset = self.grid_offset();
+        Rect::from_corners(grid.min + offset, grid.max + offset)
     }
 
     /// Returns the number of tiles across the screen.
@@ -41,9 +417,59 @@ impl Screen {
         self.dimensions
     }
 
-    /// Returns the size of a tile in pixels.
+    /// Returns the size of a tile in pixels at the current zoom.
     pub fn tile_size(&self) -> f32 {
-        self.tile_size
+        self.tile_size * self.zoom
+    }
+
+    /// The rectangle covered by the grid relative to the camera, in pixels.
+    fn grid_rect(&self) -> Rect {
+        let last = (self.dimensions.as_ivec2() - 1).max(IVec2::ZERO);
+        let extent = self.projection.tile_extent() * self.tile_size();
+        // The outermost tiles are at the corners, or next to them where hex
+        // rows and columns are shifted.
+        let edges = |last: i32| [0, 1, last - 1, last].map(|index| index.clamp(0, last));
+        edges(last.x)
+            .into_iter()
+            .flat_map(|x| edges(last.y).map(|y| IVec2::new(x, y)))
+            .map(|tile| {
+                let center = self.projection.offset(tile, self.camera_center) * self.tile_size();
+                Rect::from_center_size(center, extent)
+            })
+            .reduce(|bounds, tile| bounds.union(tile))
+            .unwrap()
+    }
+
+    /// What the viewport shows relative to the camera, in pixels: centered
+    /// on the camera, or on the grid along an axis where the grid fits.
+    fn viewport_rect(&self) -> Rect {
+        let grid = self.grid_rect();
+        let size = self.viewport_size();
+        let center = Vec2::select(grid.size().cmple(size), grid.center(), Vec2::ZERO);
+        Rect::from_center_size(center, size)
+    }
+
+    /// The size of the viewport in pixels, the same at every zoom.
+    fn viewport_size(&self) -> Vec2 {
+        self.projection.grid_extent(self.viewport) * self.tile_size
+    }
+
+    /// The number of tiles the viewport shows across at the current zoom.
+    fn viewport_extent(&self) -> Vec2 {
+        self.viewport.as_vec2() / self.zoom
+    }
+
+    fn clamp_camera(&self, center: Vec2) -> Vec2 {
+        let dimensions = self.dimensions.as_vec2();
+        let viewport = self.viewport_extent();
+        let low = (viewport - 1.0) / 2.0;
+        let high = dimensions - 1.0 - low;
+        let centered = (dimensions - 1.0) / 2.0;
+        Vec2::select(
+            dimensions.cmpgt(viewport),
+            center.clamp(low, high),
+            centered,
+        )
     }
 }
 
@@ -55,16 +481,257 @@ mod tests {
     fn pixel_position_centers_square_grid() {
         let screen = Screen::new(UVec2::new(5, 5), 64.0);
 
-        assert_eq!(screen.pixel_position(IVec2::new(0, 0)), Vec3::new(-128.0, -128.0, 0.0));
-        assert_eq!(screen.pixel_position(IVec2::new(2, 2)), Vec3::new(0.0, 0.0, 0.0));
-        assert_eq!(screen.pixel_position(IVec2::new(4, 4)), Vec3::new(128.0, 128.0, 0.0));
+        assert_eq!(
+            screen.pixel_position(IVec2::new(0, 0)),
+            Vec3::new(-128.0, -128.0, 0.0)
+        );
+        assert_eq!(
+            screen.pixel_position(IVec2::new(2, 2)),
+            Vec3::new(0.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            screen.pixel_position(IVec2::new(4, 4)),
+            Vec3::new(128.0, 128.0, 0.0)
+        );
     }
 
     #[test]
     fn pixel_position_handles_rectangular_grid() {
         let screen = Screen::new(UVec2::new(4, 6), 32.0);
 
-        assert_eq!(screen.pixel_position(IVec2::new(0, 0)), Vec3::new(-48.0, -80.0, 0.0));
-        assert_eq!(screen.pixel_position(IVec2::new(3, 5)), Vec3::new(48.0, 80.0, 0.0));
+        assert_eq!(
+            screen.pixel_position(IVec2::new(0, 0)),
+            Vec3::new(-48.0, -80.0, 0.0)
+        );
+        assert_eq!(
+            screen.pixel_position(IVec2::new(3, 5)),
+            Vec3::new(48.0, 80.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn tile_position_inverts_pixel_position() {
+        let screen = Screen::new(UVec2::new(4, 6), 32.0);
+
+        for (x, y) in [(0, 0), (3, 5), (1, 4)] {
+            let tile_position = IVec2::new(x, y);
+            let center = screen.pixel_position(tile_position).truncate();
+            assert_eq!(screen.tile_position(center), Some(tile_position));
+            assert_eq!(
+                screen.tile_position(center + Vec2::splat(15.9)),
+                Some(tile_position)
+            );
+            assert_eq!(
+                screen.tile_position(center - Vec2::splat(16.0)),
+                Some(tile_position)
+            );
+        }
+    }
+
+    #[test]
+    fn tile_position_is_none_off_the_grid() {
+        let screen = Screen::new(UVec2::new(4, 6), 32.0);
+        let bounds = screen.bounds();
+
+        assert_eq!(bounds, Rect::new(-64.0, -96.0, 64.0, 96.0));
+        assert_eq!(screen.tile_position(bounds.min), Some(IVec2::new(0, 0)));
+        assert_eq!(screen.tile_position(bounds.max), None);
+        assert_eq!(screen.tile_position(bounds.min - Vec2::new(0.1, 0.0)), None);
+        assert!(!screen.in_bounds(IVec2::new(4, 0)));
+    }
+
+    #[test]
+    fn viewports_scroll_within_the_grid() {
+        let mut screen = Screen::new(UVec2::new(20, 10), 32.0).with_viewport(UVec2::new(5, 12));
+
+        assert_eq!(screen.camera_center(), Vec2::new(9.5, 4.5));
+        screen.set_camera_center(IVec2::new(8, 1));
+        assert_eq!(
+            screen.pixel_position(IVec2::new(8, 4)),
+            Vec3::new(0.0, -16.0, 0.0)
+        );
+        assert_eq!(
+            screen.tile_position(Vec2::new(40.0, 0.0)),
+            Some(IVec2::new(9, 5))
+        );
+
+        screen.set_camera_center(IVec2::new(0, 0));
+        assert_eq!(screen.camera_center(), Vec2::new(2.0, 4.5));
+        assert_eq!(screen.bounds().min, Vec2::new(-80.0, -160.0));
+    }
+
+    #[test]
+    fn visible_tiles_cover_the_viewport() {
+        let mut screen = Screen::new(UVec2::new(20, 20), 16.0).with_viewport(UVec2::new(4, 3));
+        screen.set_camera_center(IVec2::new(10, 10));
+
+        let visible: Vec<IVec2> = screen.visible_tiles().collect();
+
+        // An even viewport shows half tiles at both sides.
+        assert_eq!(visible.len(), 5 * 3);
+        assert_eq!(visible[0], IVec2::new(8, 9));
+        assert_eq!(visible[14], IVec2::new(12, 11));
+        assert_eq!(
+            Screen::new(UVec2::new(3, 2), 16.0).visible_tiles().count(),
+            6
+        );
+    }
+
+    #[test]
+    fn zoom_scales_tiles_around_the_camera() {
+        let mut screen = Screen::new(UVec2::new(10, 10), 16.0).with_zoom(2.0);
+        screen.set_camera_center(IVec2::new(1, 8));
+
+        assert_eq!(screen.tile_size(), 32.0);
+        // Zoomed in twice, the viewport shows 5 of the 10 tiles across.
+        assert_eq!(screen.camera_center(), Vec2::new(2.0, 7.0));
+        assert_eq!(
+            screen.pixel_position(IVec2::new(3, 7)),
+            Vec3::new(32.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            screen.tile_position(Vec2::new(-40.0, 0.0)),
+            Some(IVec2::new(1, 7))
+        );
+        assert_eq!(screen.visible_tiles().count(), 5 * 5);
+
+        screen.set_zoom(0.0);
+        assert_eq!(screen.zoom(), Screen::MIN_ZOOM);
+        assert_eq!(screen.camera_center(), Vec2::splat(4.5));
+    }
+
+    #[test]
+    fn anchored_grids_sit_in_the_corner_inside_the_padding() {
+        let padding = ScreenPadding {
+            left: 200.0,
+            ..ScreenPadding::ZERO
+        };
+        let screen = Screen::new_anchored(UVec2::new(10, 10), 32.0, ScreenAnchor::TopLeft, padding)
+            .with_window_size(Vec2::new(800.0, 600.0));
+
+        assert_eq!(screen.bounds(), Rect::new(-200.0, -20.0, 120.0, 300.0));
+        assert_eq!(
+            screen.pixel_position(IVec2::new(0, 9)),
+            Vec3::new(-184.0, 284.0, 0.0)
+        );
+        assert_eq!(
+            screen.tile_position(Vec2::new(-190.0, 290.0)),
+            Some(IVec2::new(0, 9))
+        );
+        assert_eq!(screen.tile_position(Vec2::new(-210.0, 290.0)), None);
+    }
+
+    #[test]
+    fn padding_shifts_the_centered_grid() {
+        let padding = ScreenPadding {
+            right: 200.0,
+            ..ScreenPadding::all(10.0)
+        };
+        let screen = Screen::new_anchored(UVec2::new(10, 5), 32.0, ScreenAnchor::Center, padding);
+
+        assert_eq!(screen.window_size(), Vec2::new(530.0, 180.0));
+        assert_eq!(screen.grid_offset(), Vec2::new(-95.0, 0.0));
+        assert_eq!(screen.bounds(), Rect::new(-255.0, -80.0, 65.0, 80.0));
+        assert_eq!(
+            Screen::new(UVec2::new(10, 5), 32.0).grid_offset(),
+            Vec2::ZERO
+        );
+    }
+
+    #[test]
+    fn isometric_tiles_are_diamonds() {
+        let screen =
+            Screen::new(UVec2::new(3, 3), 64.0).with_projection(ScreenProjection::Isometric);
+
+        assert_eq!(screen.pixel_position(IVec2::new(1, 1)), Vec3::ZERO);
+        assert_eq!(
+            screen.pixel_position(IVec2::new(2, 1)),
+            Vec3::new(32.0, 16.0, 0.0)
+        );
+        assert_eq!(
+            screen.pixel_position(IVec2::new(1, 2)),
+            Vec3::new(-32.0, 16.0, 0.0)
+        );
+        assert_eq!(screen.bounds(), Rect::new(-96.0, -48.0, 96.0, 48.0));
+        // Just inside and just outside the right corner of the bottom diamond.
+        assert_eq!(
+            screen.tile_position(Vec2::new(15.0, -25.0)),
+            Some(IVec2::new(0, 0))
+        );
+        assert_eq!(
+            screen.tile_position(Vec2::new(17.0, -23.0)),
+            Some(IVec2::new(1, 0))
+        );
+        assert_eq!(screen.tile_position(Vec2::new(-80.0, -40.0)), None);
+        assert_eq!(screen.visible_tiles().count(), 9);
+    }
+
+    #[test]
+    fn hex_picking_finds_the_nearest_center() {
+        for projection in [ScreenProjection::HexPointyTop, ScreenProjection::HexFlatTop] {
+            let screen = Screen::new(UVec2::new(5, 4), 32.0).with_projection(projection);
+            let centers: Vec<(IVec2, Vec2)> = (-1..=5)
+                .flat_map(|x| (-1..=4).map(move |y| IVec2::new(x, y)))
+                .map(|tile| (tile, screen.pixel_position(tile).truncate()))
+                .collect();
+
+            for x in -100..100 {
+                for y in -80..80 {
+                    let point = Vec2::new(x as f32, y as f32) * 1.3;
+                    let mut distances: Vec<(f32, IVec2)> = centers
+                        .iter()
+                        .map(|(tile, center)| (center.distance(point), *tile))
+                        .collect();
+                    distances.sort_by(|a, b| a.0.total_cmp(&b.0));
+                    if distances[1].0 - distances[0].0 < 0.01 {
+                        continue;
+                    }
+                    let nearest = distances[0].1;
+                    assert_eq!(
+                        screen.tile_position(point),
+                        screen.in_bounds(nearest).then_some(nearest),
+                        "{projection:?} at {point}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hex_rows_and_columns_are_shifted() {
+        let pointy =
+            Screen::new(UVec2::new(4, 3), 32.0).with_projection(ScreenProjection::HexPointyTop);
+        let flat =
+            Screen::new(UVec2::new(3, 4), 32.0).with_projection(ScreenProjection::HexFlatTop);
+
+        let step = |screen: &Screen, from: IVec2, to: IVec2| {
+            screen.pixel_position(to) - screen.pixel_position(from)
+        };
+        assert_eq!(
+            step(&pointy, IVec2::ZERO, IVec2::X),
+            Vec3::new(32.0, 0.0, 0.0)
+        );
+        assert_eq!(step(&pointy, IVec2::ZERO, IVec2::Y).x, 16.0);
+        assert_eq!(
+            step(&flat, IVec2::ZERO, IVec2::Y),
+            Vec3::new(0.0, 32.0, 0.0)
+        );
+        assert_eq!(step(&flat, IVec2::ZERO, IVec2::X).y, 16.0);
+        assert_eq!(pointy.bounds().width(), 4.5 * 32.0);
+        assert_eq!(pointy.visible_tiles().count(), 12);
+    }
+
+    #[test]
+    fn zoom_interpolates_geometrically() {
+        assert_eq!(Screen::interpolate_zoom(1.0, 4.0, 0.5), 2.0);
+        assert_eq!(Screen::interpolate_zoom(1.0, 4.0, 2.0), 4.0);
+
+        let mut screen = Screen::new(UVec2::new(4, 4), 16.0);
+        screen.zoom_towards(2.0, 10.0, 0.05);
+        assert!(screen.zoom() > 1.0 && screen.zoom() < 2.0);
+        for _ in 0..100 {
+            screen.zoom_towards(2.0, 10.0, 0.05);
+        }
+        assert_eq!(screen.zoom(), 2.0);
     }
 }
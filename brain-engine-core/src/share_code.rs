@@ -0,0 +1,391 @@
+use crate::map::Map;
+use crate::map_file::{MapFile, TileExtensions};
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Crockford base32: no I, L, O or U, so codes survive being read aloud.
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+/// Prefix of codes that regenerate a map from its seed and config.
+const SEEDED_PREFIX: char = 'S';
+/// Prefix of codes that name a map's content in a [`ShareRegistry`].
+const CONTENT_PREFIX: char = 'C';
+/// Largest map a seed code may name. Codes are untrusted input, and a few
+/// characters could otherwise ask for a map too big to generate.
+pub const MAX_SHARE_CODE_SIZE: usize = 256;
+
+/// A generator whose output can be reproduced from a few bytes of config,
+/// so maps it generated can be shared as a seed code.
+pub trait ShareableGenerator: TileGenerator + Sized {
+    /// Returns the seed and settings needed to rebuild this generator in its
+    /// initial state, or `None` when it can't be rebuilt (e.g. unseeded).
+    fn share_config(&self) -> Option<Vec<u8>>;
+
+    /// Rebuilds a generator from [`ShareableGenerator::share_config`] bytes.
+    fn from_share_config(config: &[u8]) -> Option<Self>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShareCodeError {
+    /// The code is not a valid share code.
+    Malformed,
+    /// The code names map content that is not in the registry.
+    UnknownContent(String),
+    /// The code's config can't be loaded by this generator type.
+    UnsupportedConfig,
+}
+
+impl fmt::Display for ShareCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShareCodeError::Malformed => write!(f, "not a share code"),
+            ShareCodeError::UnknownContent(code) => write!(f, "no map is registered as {code}"),
+            ShareCodeError::UnsupportedConfig => {
+                write!(f, "the share code's generator config is not supported")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ShareCodeError {}
+
+/// Content-addressed store of maps (or any block of tiles, such as a
+/// prefab) that can't be reproduced from a seed, e.g. hand-edited maps.
+///
+/// Every entry is keyed by the content code of its tiles, so registering the
+/// same tiles twice stores them once.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct ShareRegistry {
+    files: HashMap<String, MapFile>,
+}
+
+impl ShareRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores the map's tiles and returns their content code.
+    pub fn register<G: TileGenerator>(&mut self, map: &Map<G>) -> String {
+        self.register_file(MapFile::from_map(map, TileExtensions::new()))
+    }
+
+    /// Stores a map file, extensions included, and returns its content code.
+    pub fn register_file(&mut self, file: MapFile) -> String {
+        let code = content_code(&file);
+        self.files.insert(code.clone(), file);
+        code
+    }
+
+    pub fn get(&self, code: &str) -> Option<&MapFile> {
+        self.files.get(&normalize(code)?)
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+impl<G: ShareableGenerator> Map<G> {
+    /// Returns a short code that identifies this exact map.
+    ///
+    /// Maps that are still exactly what their seeded generator produced get a
+    /// seed code (prefix `S`) that rebuilds them anywhere. Anything else,
+    /// such as a map edited after generation or one larger than
+    /// [`MAX_SHARE_CODE_SIZE`], gets a content code (prefix
+    /// `C`) that only resolves through a [`ShareRegistry`] holding the map.
+    ///
+    /// Checking for edits regenerates the map once, so this costs about as
+    /// much as [`Map::new`].
+    pub fn share_code(&self) -> String {
+        if let Some(code) = self.seeded_share_code() {
+            return code;
+        }
+        content_code(&MapFile::from_map(self, TileExtensions::new()))
+    }
+
+    /// Rebuilds the map a [`Map::share_code`] refers to. Content codes are
+    /// looked up in `registry` and get a default generator. Seed codes for
+    /// maps larger than [`MAX_SHARE_CODE_SIZE`] are rejected as malformed.
+    pub fn from_share_code(code: &str, registry: &ShareRegistry) -> Result<Self, ShareCodeError>
+    where
+        G: Default,
+    {
+        let code = normalize(code).ok_or(ShareCodeError::Malformed)?;
+        let payload = decode_base32(&code[1..]).ok_or(ShareCodeError::Malformed)?;
+        match code.chars().next() {
+            Some(SEEDED_PREFIX) => {
+                let mut bytes = payload.as_slice();
+                let size = read_varint(&mut bytes)
+                    .filter(|&size| size <= MAX_SHARE_CODE_SIZE as u64)
+                    .ok_or(ShareCodeError::Malformed)?;
+                let generator =
+                    G::from_share_config(bytes).ok_or(ShareCodeError::UnsupportedConfig)?;
+                Ok(Map::new(size as usize, generator))
+            }
+            Some(CONTENT_PREFIX) => {
+                let file = registry
+                    .get(&code)
+                    .ok_or_else(|| ShareCodeError::UnknownContent(code.clone()))?;
+                Ok(file.clone().into_map(G::default()).0)
+            }
+            _ => Err(ShareCodeError::Malformed),
+        }
+    }
+
+    fn seeded_share_code(&self) -> Option<String> {
+        if self.x != self.y || self.size > MAX_SHARE_CODE_SIZE {
+            return None;
+        }
+        let config = self.generator.share_config()?;
+        let regenerated = Map::new(self.size, G::from_share_config(&config)?);
+        if regenerated.tiles != self.tiles {
+            return None;
+        }
+
+        let mut payload = Vec::new();
+        write_varint(&mut payload, self.size as u64);
+        payload.extend(config);
+        Some(format!("{SEEDED_PREFIX}{}", encode_base32(&payload)))
+    }
+}
+
+/// Appends `value` as a LEB128 varint, so small numbers take one byte.
+pub(crate) fn write_varint(bytes: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+/// Reads a LEB128 varint from the front of `bytes`, advancing past it.
+pub(crate) fn read_varint(bytes: &mut &[u8]) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first()?;
+        *bytes = rest;
+        value |= u64::from(byte & 0x7f).checked_shl(shift)?;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Maps signed values to unsigned ones so small magnitudes of either sign
+/// stay short as varints.
+pub(crate) fn zigzag(value: i32) -> u64 {
+    ((value << 1) ^ (value >> 31)) as u32 as u64
+}
+
+/// Reverses [`zigzag`]. Returns None for values no `i32` encodes to.
+pub(crate) fn unzigzag(value: u64) -> Option<i32> {
+    let value = u32::try_from(value).ok()?;
+    Some(((value >> 1) as i32) ^ -((value & 1) as i32))
+}
+
+/// 64-bit FNV-1a hash. Stable across platforms and releases, unlike
+/// `std::hash`.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Hash of the canonical map file text.
+fn content_code(file: &MapFile) -> String {
+    let hash = fnv1a(file.write().as_bytes());
+    format!("{CONTENT_PREFIX}{}", encode_base32(&hash.to_be_bytes()))
+}
+
+/// Upper-cases a code and drops separators, so `s-1a2b` matches `S1A2B`.
+fn normalize(code: &str) -> Option<String> {
+    let code: String = code
+        .chars()
+        .filter(|character| *character != '-')
+        .map(|character| character.to_ascii_uppercase())
+        .collect();
+    (code.len() > 1 && code.is_ascii()).then_some(code)
+}
+
+fn encode_base32(bytes: &[u8]) -> String {
+    let mut text = String::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &byte in bytes {
+        buffer = buffer << 8 | u32::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            text.push(ALPHABET[(buffer >> bits) as usize & 31] as char);
+        }
+    }
+    if bits > 0 {
+        text.push(ALPHABET[(buffer << (5 - bits)) as usize & 31] as char);
+    }
+    text
+}
+
+fn decode_base32(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for character in text.chars() {
+        // Crockford decoding forgives the letters most often misread.
+        let character = match character {
+            'O' => '0',
+            'I' | 'L' => '1',
+            other => other,
+        };
+        let value = ALPHABET
+            .iter()
+            .position(|symbol| *symbol as char == character)?;
+        buffer = buffer << 5 | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_tile::TileSet;
+    use crate::tile_generator::{RandomDrawMode, TileGeneratorDefault};
+
+    #[test]
+    fn base32_varints_and_zigzag_round_trip() {
+        for bytes in [
+            vec![],
+            vec![0],
+            vec![255, 1, 2],
+            (0..=255).collect::<Vec<u8>>(),
+        ] {
+            assert_eq!(decode_base32(&encode_base32(&bytes)).unwrap(), bytes);
+        }
+        let mut bytes = Vec::new();
+        for value in [0, 127, 128, 300, u64::MAX] {
+            write_varint(&mut bytes, value);
+        }
+        let mut reader = bytes.as_slice();
+        for value in [0, 127, 128, 300, u64::MAX] {
+            assert_eq!(read_varint(&mut reader), Some(value));
+        }
+        assert!(reader.is_empty());
+        for value in [0, 1, -1, 64, -65, i32::MAX, i32::MIN] {
+            assert_eq!(unzigzag(zigzag(value)), Some(value));
+        }
+        assert_eq!(zigzag(-1), 1);
+        assert_eq!(unzigzag(u64::from(u32::MAX) + 1), None);
+    }
+
+    #[test]
+    fn seeded_maps_share_a_short_code() {
+        let map = Map::new(
+            24,
+            TileGeneratorDefault::with_seed_and_probabilities(42, 0.5, 0.25),
+        );
+
+        let code = map.share_code();
+        assert!(code.starts_with('S'));
+        assert!(code.len() <= 12, "{code} is too long");
+
+        let shared = Map::<TileGeneratorDefault>::from_share_code(
+            &code.to_lowercase(),
+            &ShareRegistry::new(),
+        )
+        .unwrap();
+        assert_eq!(shared.tiles, map.tiles);
+        assert_eq!(shared.generator.seed(), Some(42));
+    }
+
+    #[test]
+    fn seed_codes_keep_the_draw_mode() {
+        for draw_mode in [RandomDrawMode::Legacy, RandomDrawMode::PositionHashed] {
+            let generator = TileGeneratorDefault::builder()
+                .seed(9)
+                .draw_mode(draw_mode)
+                .build();
+            let map = Map::new(10, generator);
+
+            let shared = Map::<TileGeneratorDefault>::from_share_code(
+                &map.share_code(),
+                &ShareRegistry::new(),
+            )
+            .unwrap();
+            assert_eq!(shared.generator.draw_mode, draw_mode);
+            assert_eq!(shared.tiles, map.tiles);
+        }
+    }
+
+    #[test]
+    fn seed_codes_keep_edge_exits() {
+        let generator = TileGeneratorDefault::builder()
+            .seed(9)
+            .allow_edge_exits(true)
+            .build();
+        let map = Map::new(10, generator);
+
+        let shared =
+            Map::<TileGeneratorDefault>::from_share_code(&map.share_code(), &ShareRegistry::new())
+                .unwrap();
+        assert!(shared.generator.allow_edge_exits);
+        assert_eq!(shared.generator.draw_mode, RandomDrawMode::Fixed);
+        assert_eq!(shared.tiles, map.tiles);
+    }
+
+    #[test]
+    fn edited_maps_share_their_content_through_a_registry() {
+        let mut map = Map::new(8, TileGeneratorDefault::with_seed(3));
+        let edited = map.tiles.get_mut(&IVec2::new(2, 2)).unwrap();
+        edited.tile_set = match edited.tile_set {
+            TileSet::Room => TileSet::Corridor,
+            TileSet::Corridor | TileSet::Field => TileSet::Room,
+        };
+
+        let code = map.share_code();
+        assert!(code.starts_with('C'));
+        assert_eq!(
+            Map::<TileGeneratorDefault>::from_share_code(&code, &ShareRegistry::new()).err(),
+            Some(ShareCodeError::UnknownContent(code.clone()))
+        );
+
+        let mut registry = ShareRegistry::new();
+        assert_eq!(registry.register(&map), code);
+        assert_eq!(registry.register(&map), code);
+        assert_eq!(registry.len(), 1);
+        let shared = Map::<TileGeneratorDefault>::from_share_code(&code, &registry).unwrap();
+        assert_eq!(shared.tiles, map.tiles);
+    }
+
+    #[test]
+    fn unseeded_maps_fall_back_to_content_codes() {
+        let map = Map::new(4, TileGeneratorDefault::new());
+
+        assert!(map.share_code().starts_with('C'));
+    }
+
+    #[test]
+    fn malformed_codes_are_rejected() {
+        let registry = ShareRegistry::new();
+        let mut oversized = Vec::new();
+        write_varint(&mut oversized, MAX_SHARE_CODE_SIZE as u64 + 1);
+        let oversized = format!("{SEEDED_PREFIX}{}", encode_base32(&oversized));
+        for code in ["", "S", "X1234", "S!!", &oversized] {
+            assert_eq!(
+                Map::<TileGeneratorDefault>::from_share_code(code, &registry).err(),
+                Some(ShareCodeError::Malformed),
+                "{code:?}"
+            );
+        }
+    }
+}
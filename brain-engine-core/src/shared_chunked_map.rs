@@ -0,0 +1,281 @@
+use crate::generation_context::GenerationContext;
+use crate::map_tile::{Direction, Tile};
+use crate::tile_generator::TileGenerator;
+use crate::tile_grid::TileGrid;
+
+use bevy::prelude::*;
+use itertools::iproduct;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+
+/// Tiles of one chunk; `None` until the chunk has been generated.
+type ChunkSlot = Arc<RwLock<Option<HashMap<IVec2, Tile>>>>;
+
+/// A [`crate::ChunkedMap`] that can be shared between threads.
+///
+/// Every chunk has its own read/write lock, so a chunk can be generated while
+/// queries and generation proceed on other chunks. Generating a chunk holds
+/// its write lock plus read locks on the eight chunks around it, so the
+/// generator sees the ring of tiles around the chunk and seams stitch
+/// exactly as in [`crate::ChunkedMap`]. Tiles further out are not visible
+/// to the generator, see [`GenerationContext::visible_within`].
+///
+/// Chunk locks are always taken in ascending `(y, x)` chunk order, and the
+/// chunk directory lock is never held while waiting for a chunk, so
+/// concurrent callers cannot deadlock.
+///
+/// Generation order between threads is not deterministic, so a seeded
+/// generator produces different maps when chunks are generated concurrently.
+#[derive(Resource)]
+pub struct SharedChunkedMap<G: TileGenerator> {
+    chunk_size: usize,
+    chunks: RwLock<HashMap<IVec2, ChunkSlot>>,
+    generator: G,
+}
+
+impl<G: TileGenerator + Sync> SharedChunkedMap<G> {
+    /// Creates an empty map. No tiles exist until a chunk is requested.
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn new(chunk_size: usize, generator: G) -> Self {
+        assert!(chunk_size > 0, "chunk size must be at least one tile");
+        Self {
+            chunk_size,
+            chunks: RwLock::new(HashMap::new()),
+            generator,
+        }
+    }
+
+    /// Returns the side length of a chunk, in tiles.
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Returns the chunk coordinate containing a tile position.
+    pub fn chunk_of(&self, position: IVec2) -> IVec2 {
+        position.div_euclid(IVec2::splat(self.chunk_size as i32))
+    }
+
+    pub fn is_chunk_generated(&self, chunk: IVec2) -> bool {
+        self.existing_slot(chunk)
+            .is_some_and(|slot| slot.read().unwrap().is_some())
+    }
+
+    /// Generates `chunk` if it does not exist yet, blocking only callers that
+    /// touch the same chunk or its seams.
+    ///
+    /// Returns `true` when the chunk was generated by this call.
+    pub fn ensure_chunk(&self, chunk: IVec2) -> bool {
+        // Ascending `(y, x)` order, as the locks must be taken.
+        let ordered = iproduct!(-1..=1, -1..=1).map(|(y, x)| chunk + IVec2::new(x, y));
+        let slots: Vec<(IVec2, ChunkSlot)> =
+            ordered.map(|chunk| (chunk, self.slot(chunk))).collect();
+
+        // Take every lock in the global order before touching any tiles.
+        let mut own = None;
+        let mut neighbor_guards = Vec::new();
+        for (slot_chunk, slot) in &slots {
+            if *slot_chunk == chunk {
+                own = Some(slot.write().unwrap());
+            } else {
+                neighbor_guards.push(slot.read().unwrap());
+            }
+        }
+        let mut own = own.expect("own chunk is always locked");
+        if own.is_some() {
+            return false;
+        }
+
+        let origin = chunk * self.chunk_size as i32;
+        let size = self.chunk_size as i32;
+        let visible_area = IRect::from_corners(origin - 1, origin + size);
+        let mut seam_tiles = TileGrid::new();
+        for tiles in neighbor_guards.iter().filter_map(|guard| guard.as_ref()) {
+            seam_tiles.extend(
+                tiles
+                    .iter()
+                    .filter(|(position, _)| visible_area.contains(**position))
+                    .map(|(position, tile)| (*position, *tile)),
+            );
+        }
+
+        let mut generated = HashMap::new();
+        for (index, (x, y)) in iproduct!(0..size, 0..size).enumerate() {
+            let position = origin + IVec2::new(x, y);
+            let context = GenerationContext::new(&seam_tiles, position)
+                .visible_within(visible_area)
+                .with_progress(index, (size * size) as usize);
+            let tile = self.generator.generate(&context);
+            seam_tiles.insert(position, tile);
+            generated.insert(position, tile);
+        }
+        *own = Some(generated);
+        true
+    }
+
+    /// Returns the tile at `position`, if its chunk has been generated.
+    pub fn tile(&self, position: IVec2) -> Option<Tile> {
+        let slot = self.existing_slot(self.chunk_of(position))?;
+        let chunk = slot.read().unwrap();
+        chunk.as_ref()?.get(&position).copied()
+    }
+
+    /// Same rules as [`crate::ChunkedMap::can_move`].
+    pub fn can_move(&self, from: IVec2, to: IVec2) -> bool {
+        let Some(direction) = Direction::from_offset(to - from) else {
+            return false;
+        };
+        // Generated tiles never change, so the two reads need not be atomic.
+        let (Some(from_tile), Some(to_tile)) = (self.tile(from), self.tile(to)) else {
+            return false;
+        };
+
+        from_tile.map_tile.contains(direction) && to_tile.map_tile.contains(direction.opposite())
+    }
+
+    /// Finds a shortest path through already generated chunks, or `None` when
+    /// `to` cannot be reached without generating more of the map.
+    pub fn find_path(&self, from: IVec2, to: IVec2) -> Option<Vec<IVec2>> {
+        self.tile(from)?;
+        let mut came_from = HashMap::from([(from, from)]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(current) = queue.pop_front() {
+            if current == to {
+                let mut path = vec![to];
+                let mut step = to;
+                while step != from {
+                    step = came_from[&step];
+                    path.push(step);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for direction in Direction::all() {
+                let next = current + direction.offset();
+                if !came_from.contains_key(&next) && self.can_move(current, next) {
+                    came_from.insert(next, current);
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    fn existing_slot(&self, chunk: IVec2) -> Option<ChunkSlot> {
+        self.chunks.read().unwrap().get(&chunk).cloned()
+    }
+
+    fn slot(&self, chunk: IVec2) -> ChunkSlot {
+        if let Some(slot) = self.existing_slot(chunk) {
+            return slot;
+        }
+        self.chunks
+            .write()
+            .unwrap()
+            .entry(chunk)
+            .or_default()
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile_generator::TileGeneratorDefault;
+    use std::thread;
+
+    fn assert_seams_match(map: &SharedChunkedMap<TileGeneratorDefault>, extent: i32) {
+        for (x, y) in iproduct!(0..extent, 0..extent) {
+            let position = IVec2::new(x, y);
+            let tile = map.tile(position).unwrap();
+            for direction in [Direction::North, Direction::East] {
+                let Some(neighbor) = map.tile(position + direction.offset()) else {
+                    continue;
+                };
+                assert_eq!(
+                    tile.map_tile.contains(direction),
+                    neighbor.map_tile.contains(direction.opposite()),
+                    "mismatched exits at {position} towards {direction}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn chunks_are_generated_once() {
+        let map = SharedChunkedMap::new(4, TileGeneratorDefault::with_seed(1));
+
+        assert!(map.ensure_chunk(IVec2::new(-1, 2)));
+        assert!(!map.ensure_chunk(IVec2::new(-1, 2)));
+        assert!(map.is_chunk_generated(IVec2::new(-1, 2)));
+        assert!(map.tile(IVec2::new(-4, 8)).is_some());
+        assert!(map.tile(IVec2::new(0, 0)).is_none());
+    }
+
+    #[test]
+    fn concurrent_generation_stitches_every_seam() {
+        let map = SharedChunkedMap::new(
+            4,
+            TileGeneratorDefault::with_seed_and_probabilities(3, 0.6, 0.3),
+        );
+        let chunks: Vec<IVec2> = iproduct!(0..6, 0..6)
+            .map(|(x, y)| IVec2::new(x, y))
+            .collect();
+
+        thread::scope(|scope| {
+            for worker in 0..4 {
+                let map = &map;
+                let chunks = &chunks;
+                scope.spawn(move || {
+                    // Each worker walks the chunks in a different order so
+                    // that neighbors are regularly generated at the same time.
+                    for index in 0..chunks.len() {
+                        map.ensure_chunk(
+                            chunks[(index * (worker * 2 + 1) + worker) % chunks.len()],
+                        );
+                    }
+                });
+            }
+        });
+
+        assert!(chunks.iter().all(|chunk| map.is_chunk_generated(*chunk)));
+        assert_seams_match(&map, 24);
+    }
+
+    #[test]
+    fn pathfinding_proceeds_while_other_chunks_generate() {
+        let map = SharedChunkedMap::new(8, TileGeneratorDefault::with_probabilities(1.0, 0.5));
+        for (x, y) in iproduct!(0..2, 0..2) {
+            map.ensure_chunk(IVec2::new(x, y));
+        }
+
+        thread::scope(|scope| {
+            for worker in 0..3 {
+                let map = &map;
+                scope.spawn(move || {
+                    for x in 2..8 {
+                        map.ensure_chunk(IVec2::new(x, worker));
+                    }
+                });
+            }
+            for _ in 0..3 {
+                let map = &map;
+                scope.spawn(move || {
+                    for _ in 0..20 {
+                        let path = map.find_path(IVec2::new(0, 0), IVec2::new(15, 15)).unwrap();
+                        assert_eq!(path.len(), 31);
+                        for step in path.windows(2) {
+                            assert!(map.can_move(step[0], step[1]));
+                        }
+                    }
+                });
+            }
+        });
+
+        assert_seams_match(&map, 16);
+        assert!(
+            map.find_path(IVec2::new(0, 0), IVec2::new(100, 0))
+                .is_none()
+        );
+    }
+}
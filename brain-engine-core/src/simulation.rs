@@ -0,0 +1,255 @@
+//! A turn-based loop for headless games and servers.
+//!
+//! Every [`Simulation::step`] is one turn: all agents act at once, and
+//! conflicts are resolved the same way every time, so replaying the same
+//! actions gives the same game.
+
+use crate::agent::Agent;
+use crate::map::Map;
+use crate::map_tile::Direction;
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+/// What an agent does in one turn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Action {
+    #[default]
+    Wait,
+    Move(Direction),
+}
+
+/// What happened to an agent in one turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MoveOutcome {
+    Waited,
+    Moved(IVec2),
+    /// The map has no exit that way, or an occupant blocks the tile.
+    Blocked,
+    /// Another agent holds or wins the target tile.
+    Contested,
+}
+
+/// A map and the agents on it, advanced one turn at a time. No two agents
+/// ever share a tile.
+pub struct Simulation<G: TileGenerator> {
+    map: Map<G>,
+    agents: Vec<Agent>,
+    turn: u64,
+}
+
+impl<G: TileGenerator> Simulation<G> {
+    pub fn new(map: Map<G>) -> Self {
+        Self {
+            map,
+            agents: Vec::new(),
+            turn: 0,
+        }
+    }
+
+    /// Adds an agent and returns its id, the index of its action in
+    /// [`Simulation::step`]. Returns `None` when its tile is off the map or
+    /// taken.
+    pub fn add_agent(&mut self, agent: Agent) -> Option<usize> {
+        let position = agent.position();
+        if !self.map.in_bounds(position) || self.agent_at(position).is_some() {
+            return None;
+        }
+        self.agents.push(agent);
+        Some(self.agents.len() - 1)
+    }
+
+    pub fn agent(&self, id: usize) -> Option<&Agent> {
+        self.agents.get(id)
+    }
+
+    pub fn agents(&self) -> &[Agent] {
+        &self.agents
+    }
+
+    /// The id of the agent standing on `position`.
+    pub fn agent_at(&self, position: IVec2) -> Option<usize> {
+        self.agents
+            .iter()
+            .position(|agent| agent.position() == position)
+    }
+
+    pub fn map(&self) -> &Map<G> {
+        &self.map
+    }
+
+    /// The map, e.g. for opening a door between turns.
+    pub fn map_mut(&mut self) -> &mut Map<G> {
+        &mut self.map
+    }
+
+    /// Number of turns stepped so far.
+    pub fn turn(&self) -> u64 {
+        self.turn
+    }
+
+    /// Plays one turn: `actions[id]` is the action of agent `id`, and agents
+    /// past the end of `actions` wait. Returns every agent's outcome, by id.
+    ///
+    /// All moves happen at once. Agents moving onto the same tile are
+    /// resolved in favour of the lowest id, two agents can't swap tiles, and
+    /// an agent can't move onto the tile of one that stays; an agent may
+    /// follow one that moves away.
+    pub fn step(&mut self, actions: &[Action]) -> Vec<MoveOutcome> {
+        self.turn += 1;
+        let mut outcomes = vec![MoveOutcome::Waited; self.agents.len()];
+        let mut targets: Vec<Option<IVec2>> = vec![None; self.agents.len()];
+        for (id, action) in actions.iter().enumerate().take(self.agents.len()) {
+            let Action::Move(direction) = *action else {
+                continue;
+            };
+            let agent = &mut self.agents[id];
+            agent.face(direction);
+            let target = self.map.adjacent(agent.position(), direction);
+            if self.map.can_move(agent.position(), target)
+                && !self.map.is_blocked_by_occupant(target)
+            {
+                targets[id] = Some(target);
+            } else {
+                outcomes[id] = MoveOutcome::Blocked;
+            }
+        }
+
+        let mut winners = HashMap::new();
+        for (id, target) in targets.iter().enumerate() {
+            if let Some(position) = target {
+                winners.entry(*position).or_insert(id);
+            }
+        }
+        for (id, target) in targets.iter_mut().enumerate() {
+            if target.is_some_and(|position| winners[&position] != id) {
+                *target = None;
+                outcomes[id] = MoveOutcome::Contested;
+            }
+        }
+
+        let occupants: HashMap<IVec2, usize> = self
+            .agents
+            .iter()
+            .enumerate()
+            .map(|(id, agent)| (agent.position(), id))
+            .collect();
+        loop {
+            let mut changed = false;
+            for id in 0..targets.len() {
+                let Some(target) = targets[id] else {
+                    continue;
+                };
+                let Some(&occupant) = occupants.get(&target) else {
+                    continue;
+                };
+                let swapping = targets[occupant] == Some(self.agents[id].position());
+                if targets[occupant].is_none() || swapping {
+                    targets[id] = None;
+                    outcomes[id] = MoveOutcome::Contested;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        for (id, target) in targets.into_iter().enumerate() {
+            if let Some(target) = target {
+                let direction = self.agents[id].facing();
+                self.agents[id].try_move(&self.map, direction);
+                outcomes[id] = MoveOutcome::Moved(target);
+            }
+        }
+        outcomes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::open_field::OpenFieldGenerator;
+
+    fn simulation(starts: &[(i32, i32)]) -> Simulation<OpenFieldGenerator> {
+        let mut simulation = Simulation::new(Map::new(4, OpenFieldGenerator));
+        for (x, y) in starts {
+            simulation
+                .add_agent(Agent::new(IVec2::new(*x, *y)))
+                .unwrap();
+        }
+        simulation
+    }
+
+    fn positions(simulation: &Simulation<OpenFieldGenerator>) -> Vec<IVec2> {
+        simulation.agents().iter().map(Agent::position).collect()
+    }
+
+    #[test]
+    fn agents_move_at_once_and_missing_actions_wait() {
+        let mut simulation = simulation(&[(0, 0), (3, 3), (1, 1)]);
+        simulation.map_mut().place_obstacle(IVec2::new(3, 2));
+
+        let outcomes = simulation.step(&[
+            Action::Move(Direction::North),
+            Action::Move(Direction::South),
+        ]);
+
+        assert_eq!(
+            outcomes,
+            [
+                MoveOutcome::Moved(IVec2::new(0, 1)),
+                MoveOutcome::Blocked,
+                MoveOutcome::Waited
+            ]
+        );
+        assert_eq!(simulation.agent(1).unwrap().facing(), Direction::South);
+        assert_eq!(simulation.turn(), 1);
+    }
+
+    #[test]
+    fn the_lowest_id_wins_a_contested_tile() {
+        let mut simulation = simulation(&[(2, 1), (0, 1)]);
+
+        let outcomes =
+            simulation.step(&[Action::Move(Direction::West), Action::Move(Direction::East)]);
+
+        assert_eq!(
+            outcomes,
+            [MoveOutcome::Moved(IVec2::new(1, 1)), MoveOutcome::Contested]
+        );
+        assert_eq!(positions(&simulation), [IVec2::new(1, 1), IVec2::new(0, 1)]);
+    }
+
+    #[test]
+    fn agents_follow_but_never_swap_or_stack() {
+        let mut simulation = simulation(&[(0, 0), (1, 0), (2, 0), (3, 0)]);
+
+        let outcomes = simulation.step(&[
+            Action::Move(Direction::East),
+            Action::Move(Direction::East),
+            Action::Move(Direction::East),
+            Action::Move(Direction::West),
+        ]);
+
+        assert_eq!(outcomes, [MoveOutcome::Contested; 4]);
+
+        let outcomes = simulation.step(&[
+            Action::Move(Direction::East),
+            Action::Move(Direction::East),
+            Action::Move(Direction::North),
+        ]);
+
+        assert_eq!(
+            outcomes,
+            [
+                MoveOutcome::Moved(IVec2::new(1, 0)),
+                MoveOutcome::Moved(IVec2::new(2, 0)),
+                MoveOutcome::Moved(IVec2::new(2, 1)),
+                MoveOutcome::Waited
+            ]
+        );
+        assert!(simulation.add_agent(Agent::new(IVec2::new(3, 0))).is_none());
+    }
+}
@@ -0,0 +1,349 @@
+use crate::map::Map;
+use crate::map_tile::TileSet;
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use itertools::iproduct;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// How many spawns of one kind to place, and where they may go. Distances
+/// are walking distances, so a spawn behind a wall counts as far away.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpawnRule<K> {
+    pub kind: K,
+    pub count: usize,
+    /// Only place on [`TileSet::Room`] tiles.
+    pub rooms_only: bool,
+    /// Fewest steps between this spawn and every spawn placed before it.
+    pub min_spacing: u32,
+    /// Fewest steps from the player start.
+    pub min_distance_from_start: u32,
+    /// Pick the allowed positions farthest from the start instead of random
+    /// ones, e.g. for the exit stairs.
+    pub farthest_from_start: bool,
+    /// Tiles the entity covers, extending north and east from its position.
+    pub footprint: UVec2,
+    /// How much bigger than the footprint, along each axis, the open area
+    /// around it must be, so a large entity has room to move: a 2x2 boss
+    /// with a clearance of 1 needs a 3x3 area that contains it.
+    pub clearance: u32,
+}
+
+impl<K> SpawnRule<K> {
+    pub fn new(kind: K, count: usize) -> Self {
+        Self {
+            kind,
+            count,
+            rooms_only: false,
+            min_spacing: 1,
+            min_distance_from_start: 1,
+            farthest_from_start: false,
+            footprint: UVec2::ONE,
+            clearance: 0,
+        }
+    }
+
+    pub fn with_rooms_only(mut self) -> Self {
+        self.rooms_only = true;
+        self
+    }
+
+    pub fn with_min_spacing(mut self, steps: u32) -> Self {
+        self.min_spacing = steps;
+        self
+    }
+
+    pub fn with_min_distance_from_start(mut self, steps: u32) -> Self {
+        self.min_distance_from_start = steps;
+        self
+    }
+
+    pub fn with_farthest_from_start(mut self) -> Self {
+        self.farthest_from_start = true;
+        self
+    }
+
+    pub fn with_footprint(mut self, footprint: UVec2, clearance: u32) -> Self {
+        self.footprint = footprint.max(UVec2::ONE);
+        self.clearance = clearance;
+        self
+    }
+
+    fn footprint_tiles(&self, position: IVec2) -> impl Iterator<Item = IVec2> + use<K> {
+        let size = self.footprint.as_ivec2();
+        iproduct!(0..size.x, 0..size.y).map(move |(x, y)| position + IVec2::new(x, y))
+    }
+
+    /// Whether an entity at `position` fits: some area of the footprint plus
+    /// the clearance that contains the footprint lies on the map, is open
+    /// between every pair of neighboring tiles, and follows `rooms_only`.
+    fn fits<G: TileGenerator>(&self, map: &Map<G>, position: IVec2) -> bool {
+        let clearance = self.clearance as i32;
+        let size = self.footprint.as_ivec2() + clearance;
+        iproduct!(0..=clearance, 0..=clearance).any(|(x, y)| {
+            let corner = position - IVec2::new(x, y);
+            iproduct!(0..size.x, 0..size.y).all(|(x, y)| {
+                let tile = corner + IVec2::new(x, y);
+                let in_room = !self.rooms_only
+                    || map
+                        .tiles
+                        .get(&tile)
+                        .is_some_and(|tile| tile.tile_set == TileSet::Room);
+                let east = (x + 1 == size.x) || map.can_move(tile, tile + IVec2::X);
+                let north = (y + 1 == size.y) || map.can_move(tile, tile + IVec2::Y);
+                map.in_bounds(tile) && in_room && east && north
+            })
+        })
+    }
+}
+
+/// A placed entity, from [`SpawnPlanner::plan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spawn<K> {
+    pub kind: K,
+    /// The south-west tile of the footprint.
+    pub position: IVec2,
+    pub footprint: UVec2,
+}
+
+/// Picks spawn positions for enemies, chests, stairs and the like.
+///
+/// Rules are applied in order, so earlier rules get first pick and later
+/// ones keep their spacing from everything already placed. Only positions
+/// the player can walk to from the start are used. The same seed, map and
+/// rules always give the same plan. Footprints of different spawns never
+/// overlap.
+#[derive(Debug, Clone)]
+pub struct SpawnPlanner<K> {
+    seed: u64,
+    start: IVec2,
+    rules: Vec<SpawnRule<K>>,
+}
+
+impl<K: Clone> SpawnPlanner<K> {
+    pub fn new(seed: u64, start: IVec2) -> Self {
+        Self {
+            seed,
+            start,
+            rules: Vec::new(),
+        }
+    }
+
+    pub fn with_rule(mut self, rule: SpawnRule<K>) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Places every rule's spawns, in rule order. A rule gets fewer than
+    /// its `count` when not enough positions satisfy it.
+    pub fn plan<G: TileGenerator>(&self, map: &Map<G>) -> Vec<Spawn<K>> {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let from_start = walking_distances(map, self.start);
+        let mut reachable: Vec<IVec2> = map
+            .positions()
+            .filter(|position| from_start.contains_key(position))
+            .collect();
+
+        let mut spawns: Vec<Spawn<K>> = Vec::new();
+        let mut from_spawns: Vec<HashMap<IVec2, u32>> = Vec::new();
+        let mut occupied: HashSet<IVec2> = HashSet::new();
+        for rule in &self.rules {
+            reachable.shuffle(&mut rng);
+            let mut candidates: Vec<IVec2> = reachable
+                .iter()
+                .copied()
+                .filter(|position| {
+                    from_start[position] >= rule.min_distance_from_start
+                        && (!rule.rooms_only || map.tiles[position].tile_set == TileSet::Room)
+                        && rule.fits(map, *position)
+                })
+                .collect();
+            if rule.farthest_from_start {
+                candidates.sort_by_key(|position| std::cmp::Reverse(from_start[position]));
+            }
+
+            let mut placed = 0;
+            for position in candidates {
+                if placed == rule.count {
+                    break;
+                }
+                let spaced = from_spawns.iter().all(|distances| {
+                    distances
+                        .get(&position)
+                        .is_none_or(|steps| *steps >= rule.min_spacing)
+                });
+                if !spaced
+                    || rule
+                        .footprint_tiles(position)
+                        .any(|tile| occupied.contains(&tile))
+                {
+                    continue;
+                }
+                occupied.extend(rule.footprint_tiles(position));
+                spawns.push(Spawn {
+                    kind: rule.kind.clone(),
+                    position,
+                    footprint: rule.footprint,
+                });
+                from_spawns.push(walking_distances(map, position));
+                placed += 1;
+            }
+        }
+        spawns
+    }
+}
+
+/// Steps from `from` to every position reachable from it.
+fn walking_distances<G: TileGenerator>(map: &Map<G>, from: IVec2) -> HashMap<IVec2, u32> {
+    if !map.in_bounds(from) {
+        return HashMap::new();
+    }
+    let mut distances = HashMap::from([(from, 0)]);
+    let mut queue = VecDeque::from([from]);
+    while let Some(current) = queue.pop_front() {
+        let steps = distances[&current] + 1;
+        for neighbor in map.neighbors(current) {
+            distances.entry(neighbor).or_insert_with(|| {
+                queue.push_back(neighbor);
+                steps
+            });
+        }
+    }
+    distances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile_generator::TileGeneratorDefault;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Entity {
+        Boss,
+        Stairs,
+        Chest,
+        Enemy,
+    }
+
+    fn planner() -> SpawnPlanner<Entity> {
+        SpawnPlanner::new(11, IVec2::ZERO)
+            .with_rule(SpawnRule::new(Entity::Stairs, 1).with_farthest_from_start())
+            .with_rule(SpawnRule::new(Entity::Chest, 3).with_rooms_only())
+            .with_rule(
+                SpawnRule::new(Entity::Enemy, 6)
+                    .with_min_spacing(3)
+                    .with_min_distance_from_start(4),
+            )
+    }
+
+    fn open_map() -> Map<TileGeneratorDefault> {
+        let generator = TileGeneratorDefault::builder()
+            .seed(2)
+            .tile_exit_probability(0.9)
+            .build();
+        Map::new(12, generator)
+    }
+
+    #[test]
+    fn plans_follow_their_rules() {
+        let map = open_map();
+        let from_start = walking_distances(&map, IVec2::ZERO);
+
+        let spawns = planner().plan(&map);
+
+        let stairs = &spawns[0];
+        assert_eq!(stairs.kind, Entity::Stairs);
+        assert_eq!(
+            from_start[&stairs.position],
+            *from_start.values().max().unwrap()
+        );
+        let enemies: Vec<_> = spawns
+            .iter()
+            .filter(|spawn| spawn.kind == Entity::Enemy)
+            .collect();
+        assert_eq!(enemies.len(), 6);
+        for spawn in &spawns {
+            assert!(from_start.contains_key(&spawn.position));
+            if spawn.kind == Entity::Chest {
+                assert_eq!(map.tiles[&spawn.position].tile_set, TileSet::Room);
+            }
+        }
+        for enemy in &enemies {
+            assert!(from_start[&enemy.position] >= 4);
+            let distances = walking_distances(&map, enemy.position);
+            for other in spawns
+                .iter()
+                .filter(|other| other.position != enemy.position)
+            {
+                assert!(distances[&other.position] >= 3, "{other:?} near {enemy:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn plans_are_reproducible() {
+        let map = open_map();
+
+        assert_eq!(planner().plan(&map), planner().plan(&map));
+    }
+
+    #[test]
+    fn rules_place_fewer_when_space_runs_out() {
+        let map = open_map();
+        let planner = SpawnPlanner::new(1, IVec2::ZERO)
+            .with_rule(SpawnRule::new(Entity::Enemy, 100).with_min_spacing(6));
+
+        let spawns = planner.plan(&map);
+
+        assert!(!spawns.is_empty() && spawns.len() < 100);
+    }
+
+    #[test]
+    fn large_entities_get_a_clear_area() {
+        let map = open_map();
+        let planner = SpawnPlanner::new(4, IVec2::ZERO)
+            .with_rule(SpawnRule::new(Entity::Boss, 3).with_footprint(UVec2::splat(2), 1))
+            .with_rule(SpawnRule::new(Entity::Enemy, 20));
+
+        let spawns = planner.plan(&map);
+
+        let bosses: Vec<_> = spawns
+            .iter()
+            .filter(|spawn| spawn.kind == Entity::Boss)
+            .collect();
+        assert!(!bosses.is_empty());
+        let mut occupied = HashSet::new();
+        for spawn in &spawns {
+            let rule = SpawnRule::new(spawn.kind, 1).with_footprint(spawn.footprint, 0);
+            for tile in rule.footprint_tiles(spawn.position) {
+                assert!(occupied.insert(tile), "{spawn:?} overlaps at {tile}");
+            }
+        }
+        for boss in bosses {
+            assert_eq!(boss.footprint, UVec2::splat(2));
+            let mut clear_area = (-1..=0).flat_map(|x| (-1..=0).map(move |y| IVec2::new(x, y)));
+            let has_room = clear_area.any(|offset| {
+                let corner = boss.position + offset;
+                iproduct!(0..3, 0..3).all(|(x, y)| {
+                    let tile = corner + IVec2::new(x, y);
+                    map.in_bounds(tile)
+                        && (x == 2 || map.can_move(tile, tile + IVec2::X))
+                        && (y == 2 || map.can_move(tile, tile + IVec2::Y))
+                })
+            });
+            assert!(has_room, "{boss:?}");
+        }
+    }
+
+    #[test]
+    fn footprints_that_never_fit_are_not_placed() {
+        let map = open_map();
+        let planner = SpawnPlanner::new(4, IVec2::ZERO)
+            .with_rule(SpawnRule::new(Entity::Boss, 1).with_footprint(UVec2::splat(13), 0));
+
+        assert!(planner.plan(&map).is_empty());
+    }
+}
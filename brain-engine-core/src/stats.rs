@@ -0,0 +1,146 @@
+use crate::map::Map;
+use crate::map_tile::{Direction, MapTile, TileSet};
+use crate::tile_generator::TileGenerator;
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+
+/// Summary numbers for judging a generated map, from [`Map::stats`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MapStats {
+    pub tile_count: usize,
+    pub room_tiles: usize,
+    pub corridor_tiles: usize,
+    pub field_tiles: usize,
+    /// Tiles without any exits, whatever their tile set.
+    pub closed_tiles: usize,
+    /// Walkable connections between neighboring tiles, each counted once.
+    pub connections: usize,
+    pub dead_ends: usize,
+    /// Rooms found by [`Map::detect_rooms`].
+    pub rooms: usize,
+    /// Number of separate areas of walkable tiles.
+    pub connected_areas: usize,
+    /// Tile count of the biggest connected area.
+    pub largest_area: usize,
+    /// Number of tiles with 0, 1, 2, 3 and 4 exits.
+    pub exit_histogram: [usize; 5],
+    /// Share of tile sides that are open exits in each row, south first.
+    pub row_openness: Vec<f64>,
+    /// Share of tile sides that are open exits in each column, west first.
+    pub column_openness: Vec<f64>,
+    /// Number of rooms of each size in tiles.
+    pub room_sizes: BTreeMap<usize, usize>,
+}
+
+impl MapStats {
+    /// Share of the walkable tiles that lie in the largest connected area,
+    /// 1 when every walkable tile can reach every other.
+    pub fn connectivity(&self) -> f64 {
+        let walkable = self.tile_count - self.closed_tiles;
+        if walkable == 0 {
+            return 1.0;
+        }
+        self.largest_area as f64 / walkable as f64
+    }
+}
+
+impl<G: TileGenerator> Map<G> {
+    pub fn stats(&self) -> MapStats {
+        let rooms = self.detect_rooms();
+        let mut stats = MapStats {
+            dead_ends: self.dead_ends().len(),
+            rooms: rooms.len(),
+            ..MapStats::default()
+        };
+        for room in &rooms {
+            *stats.room_sizes.entry(room.tiles.len()).or_default() += 1;
+        }
+
+        let mut row_exits = vec![(0, 0); self.y];
+        let mut column_exits = vec![(0, 0); self.x];
+        let mut visited = HashSet::new();
+        for position in self.positions() {
+            let Some(tile) = self.tiles.get(&position) else {
+                continue;
+            };
+            stats.tile_count += 1;
+            let exits = tile.directions().len();
+            stats.exit_histogram[exits] += 1;
+            for (open, sides) in [
+                &mut row_exits[position.y as usize],
+                &mut column_exits[position.x as usize],
+            ] {
+                *open += exits;
+                *sides += 4;
+            }
+            match tile.tile_set {
+                TileSet::Room => stats.room_tiles += 1,
+                TileSet::Corridor => stats.corridor_tiles += 1,
+                TileSet::Field => stats.field_tiles += 1,
+            }
+            if tile.map_tile == MapTile::ZERO {
+                stats.closed_tiles += 1;
+                continue;
+            }
+            for direction in [Direction::North, Direction::East] {
+                if self.can_move(position, self.adjacent(position, direction)) {
+                    stats.connections += 1;
+                }
+            }
+            if !visited.contains(&position) {
+                let area = self.reachable_from(position);
+                stats.connected_areas += 1;
+                stats.largest_area = stats.largest_area.max(area.len());
+                visited.extend(area);
+            }
+        }
+        let openness = |(open, sides): (usize, usize)| {
+            if sides == 0 {
+                0.0
+            } else {
+                open as f64 / sides as f64
+            }
+        };
+        stats.row_openness = row_exits.into_iter().map(openness).collect();
+        stats.column_openness = column_exits.into_iter().map(openness).collect();
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::open_field::OpenFieldGenerator;
+    use crate::testing::GridBuilder;
+
+    #[test]
+    fn stats_count_tiles_connections_and_areas() {
+        // Two east-west corridors on rows 0 and 2, the middle row closed,
+        // and the top-left tile a room.
+        let map = GridBuilder::new("╺─╴\n   \n╶─╴").build(OpenFieldGenerator);
+
+        let stats = map.stats();
+
+        assert_eq!(
+            stats,
+            MapStats {
+                tile_count: 9,
+                room_tiles: 1,
+                corridor_tiles: 8,
+                field_tiles: 0,
+                closed_tiles: 3,
+                connections: 4,
+                dead_ends: 3,
+                rooms: 1,
+                connected_areas: 2,
+                largest_area: 3,
+                exit_histogram: [3, 4, 2, 0, 0],
+                row_openness: vec![4.0 / 12.0, 0.0, 4.0 / 12.0],
+                column_openness: vec![2.0 / 12.0, 4.0 / 12.0, 2.0 / 12.0],
+                room_sizes: BTreeMap::from([(1, 1)]),
+            }
+        );
+        assert_eq!(stats.connectivity(), 0.5);
+    }
+}
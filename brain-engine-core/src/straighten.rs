@@ -0,0 +1,228 @@
+use crate::direction_set::DirectionSet;
+use crate::map::Map;
+use crate::map_tile::{Direction, MapTile, Tile, TileSet};
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+
+/// Longest detour, in steps, that [`Map::straighten_corridors`] looks for.
+const MAX_DETOUR_STEPS: usize = 8;
+
+/// A corridor winding from `start` to `end` through `detour`, where a
+/// straight corridor through `gap` would be shorter.
+struct Detour {
+    start: IVec2,
+    end: IVec2,
+    detour: Vec<IVec2>,
+    gap: Vec<IVec2>,
+}
+
+impl<G: TileGenerator> Map<G> {
+    /// Replaces winding corridors with straight ones: where a corridor
+    /// leaves a tile and comes back into line with it through corridor tiles
+    /// with no side exits, such as a bump or a U-turn, and only walls lie
+    /// straight between the two ends, the winding tiles are sealed and a
+    /// straight corridor is carved through the walls instead.
+    ///
+    /// Every tile that could be reached before stays reachable, apart from
+    /// the sealed ones, and no path gets longer. Returns the number of
+    /// corridors straightened.
+    pub fn straighten_corridors(&mut self) -> usize {
+        let before = self.tiles_before(self.positions());
+        let mut straightened = 0;
+        while let Some(detour) = self.find_detour() {
+            self.reroute(detour);
+            straightened += 1;
+        }
+        self.publish_changes(before);
+        straightened
+    }
+
+    fn find_detour(&self) -> Option<Detour> {
+        self.positions()
+            .filter(|position| self.is_corridor(*position))
+            .find_map(|start| {
+                Direction::all()
+                    .into_iter()
+                    .find_map(|direction| self.detour_from(start, direction))
+            })
+    }
+
+    /// Follows the corridor leaving `start` towards `direction` while its
+    /// tiles have no side exits, stopping at the first tile in line with
+    /// `start` that a straight corridor would reach in fewer steps.
+    fn detour_from(&self, start: IVec2, direction: Direction) -> Option<Detour> {
+        let mut detour = Vec::new();
+        let mut current = start;
+        let mut heading = direction;
+        for steps in 1..=MAX_DETOUR_STEPS {
+            current = self.joined(current, heading)?;
+            if current == start || !self.is_corridor(current) {
+                return None;
+            }
+            if let Some(gap) = self.straight_gap(start, current, steps) {
+                return Some(Detour {
+                    start,
+                    end: current,
+                    detour,
+                    gap,
+                });
+            }
+            let exits = self.tiles[&current].map_tile.exits();
+            if exits.len() != 2 {
+                return None;
+            }
+            heading = (exits - heading.opposite()).into_iter().next()?;
+            detour.push(current);
+        }
+        None
+    }
+
+    /// The walls strictly between `start` and `end` when they are in line,
+    /// fewer than `steps` apart and nothing but walls lies between them.
+    fn straight_gap(&self, start: IVec2, end: IVec2, steps: usize) -> Option<Vec<IVec2>> {
+        let difference = end - start;
+        if difference.x != 0 && difference.y != 0 {
+            return None;
+        }
+        let distance = difference.abs().element_sum();
+        if distance as usize >= steps {
+            return None;
+        }
+        let step = difference / distance;
+        let gap: Vec<IVec2> = (1..distance).map(|index| start + step * index).collect();
+        gap.iter()
+            .all(|position| {
+                self.tiles.get(position).is_some_and(|tile| {
+                    tile.map_tile == MapTile::ZERO && tile.tile_set != TileSet::Field
+                })
+            })
+            .then_some(gap)
+    }
+
+    fn reroute(&mut self, detour: Detour) {
+        let first = detour.detour.first().copied().unwrap_or(detour.end);
+        let last = detour.detour.last().copied().unwrap_or(detour.start);
+        self.remove_exit_towards(detour.start, first);
+        self.remove_exit_towards(detour.end, last);
+        for position in &detour.detour {
+            self.tiles.get_mut(position).unwrap().map_tile = MapTile::ZERO;
+        }
+
+        let direction = Direction::from_offset((detour.end - detour.start).signum()).unwrap();
+        let through = DirectionSet::from(direction) | direction.opposite();
+        for position in &detour.gap {
+            self.tiles
+                .insert(*position, Tile::new(TileSet::Corridor, through.into()));
+        }
+        self.add_exit(detour.start, direction);
+        self.add_exit(detour.end, direction.opposite());
+    }
+
+    fn is_corridor(&self, position: IVec2) -> bool {
+        self.tiles
+            .get(&position)
+            .is_some_and(|tile| tile.tile_set == TileSet::Corridor)
+    }
+
+    /// The tile next to `position` towards `direction` when both have exits
+    /// towards each other. Doesn't cross the seams of toroidal maps.
+    fn joined(&self, position: IVec2, direction: Direction) -> Option<IVec2> {
+        let neighbor = position + direction.offset();
+        let tile = self.tiles.get(&position)?;
+        let other = self.tiles.get(&neighbor)?;
+        (tile.map_tile.contains(direction) && other.map_tile.contains(direction.opposite()))
+            .then_some(neighbor)
+    }
+
+    fn remove_exit_towards(&mut self, position: IVec2, neighbor: IVec2) {
+        let direction = Direction::from_offset(neighbor - position).unwrap();
+        let tile = self.tiles.get_mut(&position).unwrap();
+        tile.map_tile = tile.map_tile.without(direction);
+    }
+
+    fn add_exit(&mut self, position: IVec2, direction: Direction) {
+        let tile = self.tiles.get_mut(&position).unwrap();
+        tile.map_tile = tile.map_tile.with(direction);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::open_field::OpenFieldGenerator;
+    use crate::tile_generator::TileGeneratorDefault;
+
+    #[test]
+    fn bumps_are_flattened() {
+        let mut map = Map::from_ascii("┌┐ \n╵└╴", OpenFieldGenerator).unwrap();
+
+        assert_eq!(map.straighten_corridors(), 1);
+
+        assert_eq!(map.render_ascii(), "   \n╶─╴\n");
+        assert_eq!(map.straighten_corridors(), 0);
+    }
+
+    #[test]
+    fn u_turns_are_cut_through_walls() {
+        let mut map = Map::from_ascii("┌─┐\n│ │\n╵ ╵", OpenFieldGenerator).unwrap();
+
+        assert_eq!(map.straighten_corridors(), 1);
+
+        assert_eq!(map.render_ascii(), "   \n   \n╶─╴\n");
+    }
+
+    #[test]
+    fn rooms_and_side_exits_stay() {
+        let mut map = Map::from_ascii("┏┓ \n╹┗╸", OpenFieldGenerator).unwrap();
+        assert_eq!(map.straighten_corridors(), 0);
+
+        let mut map = Map::from_ascii("┌┬╴\n╵└╴", OpenFieldGenerator).unwrap();
+        assert_eq!(map.straighten_corridors(), 0);
+    }
+
+    #[test]
+    fn straightening_keeps_generated_maps_connected() {
+        let mut map = Map::new(
+            24,
+            TileGeneratorDefault::with_seed_and_probabilities(5, 0.5, 0.2),
+        );
+        let start = map
+            .positions()
+            .find(|position| {
+                map.tiles[position].tile_set == TileSet::Room
+                    && map.neighbors(*position).count() > 0
+            })
+            .unwrap();
+        let reachable = map.reachable_from(start);
+        let walkable = |map: &Map<TileGeneratorDefault>| {
+            map.tiles
+                .values()
+                .filter(|tile| tile.map_tile != MapTile::ZERO)
+                .count()
+        };
+        let walkable_before = walkable(&map);
+
+        assert!(map.straighten_corridors() > 0);
+
+        assert!(walkable(&map) < walkable_before);
+        let still_reachable = map.reachable_from(start);
+        for position in reachable {
+            if map.tiles[&position].map_tile != MapTile::ZERO {
+                assert!(still_reachable.contains(&position), "cut off {position}");
+            }
+        }
+        for position in map.positions() {
+            for direction in [Direction::North, Direction::East] {
+                let neighbor = position + direction.offset();
+                if map.in_bounds(neighbor) {
+                    assert_eq!(
+                        map.tiles[&position].map_tile.contains(direction),
+                        map.tiles[&neighbor].map_tile.contains(direction.opposite()),
+                        "mismatched exits at {position} towards {direction}"
+                    );
+                }
+            }
+        }
+    }
+}
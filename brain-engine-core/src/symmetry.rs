@@ -0,0 +1,191 @@
+//! Symmetric maps for competitive play, where every side must get the same
+//! layout.
+//!
+//! A [`SymmetricGenerator`] lets its inner generator fill one part of the
+//! map, e.g. the bottom-left quadrant, and copies every other tile from its
+//! reflection there with the exits reflected to match. Tiles next to a copy
+//! that already exists take their exit towards it from the copy, so the
+//! seams between the parts stay consistent.
+
+use crate::generation_context::GenerationContext;
+use crate::map_tile::{Direction, Tile};
+use crate::tile_generator::TileGenerator;
+use crate::tile_grid::TileGrid;
+
+use bevy::prelude::*;
+
+/// Which copies of the generated part make up the map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+    /// The right half mirrors the left half.
+    MirrorHorizontal,
+    /// The top half mirrors the bottom half.
+    MirrorVertical,
+    /// Every quadrant mirrors the bottom-left one.
+    MirrorBoth,
+    /// The map looks the same turned half way round, e.g. for two players
+    /// starting in opposite corners.
+    Rotate180,
+}
+
+impl Symmetry {
+    /// The tile `location` copies within `bounds`, and whether the copy is
+    /// reflected left to right and top to bottom. A tile of the generated
+    /// part is its own source.
+    fn source(self, location: IVec2, bounds: IRect) -> (IVec2, BVec2) {
+        let reflected = bounds.min + bounds.max - location;
+        let (flip_x, flip_y) = match self {
+            Symmetry::MirrorHorizontal => (reflected.x < location.x, false),
+            Symmetry::MirrorVertical => (false, reflected.y < location.y),
+            Symmetry::MirrorBoth => (reflected.x < location.x, reflected.y < location.y),
+            Symmetry::Rotate180 => {
+                let flip = (reflected.x, reflected.y) < (location.x, location.y);
+                (flip, flip)
+            }
+        };
+        let flip = BVec2::new(flip_x, flip_y);
+        (IVec2::select(flip, reflected, location), flip)
+    }
+}
+
+/// Wraps a generator so bounded maps come out symmetric, see
+/// [`Symmetry`].
+///
+/// The generated part is the first in x-then-y order, so [`crate::Map`]
+/// places every source tile before its copies. Maps without bounds, such as
+/// toroidal maps and [`crate::ChunkedMap`], use `tile_at` and are not
+/// mirrored.
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct SymmetricGenerator<G> {
+    pub generator: G,
+    pub symmetry: Symmetry,
+}
+
+impl<G> SymmetricGenerator<G> {
+    pub fn new(generator: G, symmetry: Symmetry) -> Self {
+        Self {
+            generator,
+            symmetry,
+        }
+    }
+}
+
+impl<G: TileGenerator> TileGenerator for SymmetricGenerator<G> {
+    fn tile_at(&self, tiles: &TileGrid, location: IVec2) -> Tile {
+        self.generate(&GenerationContext::new(tiles, location))
+    }
+
+    fn tile_at_within(&self, tiles: &TileGrid, location: IVec2, bounds: IRect) -> Tile {
+        self.generate(&GenerationContext::new(tiles, location).within(bounds))
+    }
+
+    fn generate(&self, context: &GenerationContext) -> Tile {
+        let Some(bounds) = context.bounds() else {
+            return self.generator.generate(context);
+        };
+        let (tiles, location) = (context.tiles(), context.location());
+        let (source, flip) = self.symmetry.source(location, bounds);
+        if let Some(tile) = tiles.get(&source).filter(|_| source != location) {
+            return reflect(*tile, flip);
+        }
+
+        let mut tile = self.generator.generate(context);
+        for direction in Direction::all() {
+            let neighbor = location + direction.offset();
+            if tiles.contains_key(&neighbor) || !bounds.contains(neighbor) {
+                continue;
+            }
+            let (neighbor_source, neighbor_flip) = self.symmetry.source(neighbor, bounds);
+            let Some(copied) = tiles.get(&neighbor_source) else {
+                continue;
+            };
+            let open = reflect(*copied, neighbor_flip)
+                .map_tile
+                .contains(direction.opposite());
+            tile.map_tile = if open {
+                tile.map_tile.with(direction)
+            } else {
+                tile.map_tile.without(direction)
+            };
+        }
+        tile
+    }
+}
+
+fn reflect(mut tile: Tile, flip: BVec2) -> Tile {
+    if flip.x {
+        tile.map_tile = tile.map_tile.mirror_horizontal();
+    }
+    if flip.y {
+        tile.map_tile = tile.map_tile.mirror_vertical();
+    }
+    tile
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::Map;
+    use crate::tile_generator::TileGeneratorDefault;
+
+    const SYMMETRIES: [Symmetry; 4] = [
+        Symmetry::MirrorHorizontal,
+        Symmetry::MirrorVertical,
+        Symmetry::MirrorBoth,
+        Symmetry::Rotate180,
+    ];
+
+    fn symmetric_map(size: usize, seed: u64, symmetry: Symmetry) -> Map<impl TileGenerator> {
+        let generator = TileGeneratorDefault::with_seed_and_probabilities(seed, 0.6, 0.2);
+        Map::new(size, SymmetricGenerator::new(generator, symmetry))
+    }
+
+    #[test]
+    fn maps_match_their_reflections() {
+        for (symmetry, size, seed) in itertools::iproduct!(SYMMETRIES, [6, 7], 0..4) {
+            let map = symmetric_map(size, seed, symmetry);
+            let bounds = map.bounds();
+
+            assert_eq!(map.validate(), Ok(()), "{symmetry:?} {size} {seed}");
+            for position in map.positions() {
+                let (source, flip) = symmetry.source(position, bounds);
+                assert_eq!(
+                    map.tiles[&position],
+                    reflect(map.tiles[&source], flip),
+                    "{symmetry:?} {size} {seed} at {position}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn quadrants_come_from_the_bottom_left() {
+        let bounds = IRect::new(0, 0, 5, 5);
+
+        let source = |location| Symmetry::MirrorBoth.source(location, bounds);
+
+        assert_eq!(source(IVec2::new(1, 2)), (IVec2::new(1, 2), BVec2::FALSE));
+        assert_eq!(
+            source(IVec2::new(4, 1)),
+            (IVec2::new(1, 1), BVec2::new(true, false))
+        );
+        assert_eq!(source(IVec2::new(5, 5)), (IVec2::new(0, 0), BVec2::TRUE));
+        assert_eq!(
+            Symmetry::Rotate180.source(IVec2::new(3, 1), bounds),
+            (IVec2::new(2, 4), BVec2::TRUE)
+        );
+    }
+
+    #[test]
+    fn unbounded_maps_are_not_mirrored() {
+        let generator =
+            SymmetricGenerator::new(TileGeneratorDefault::with_seed(3), Symmetry::Rotate180);
+
+        let tiles = TileGrid::new();
+
+        assert_eq!(
+            generator.tile_at(&tiles, IVec2::new(9, 9)),
+            TileGeneratorDefault::with_seed(3).tile_at(&tiles, IVec2::new(9, 9))
+        );
+    }
+}
@@ -0,0 +1,287 @@
+//! Helpers for tests of code that builds or changes maps: [`GridBuilder`]
+//! draws maps with the glyphs of [`Map::render_ascii`],
+//! [`assert_maps_equal`] reports which tiles differ and [`assert_golden`]
+//! compares maps with snapshot files.
+//!
+//! ```
+//! use bevy::prelude::*;
+//! use brain_engine_core::testing::{GridBuilder, assert_maps_equal};
+//! use brain_engine_core::OpenFieldGenerator;
+//!
+//! let mut map = GridBuilder::new("┌─┐\n└─┘").build(OpenFieldGenerator);
+//! assert_eq!(map.find_path(IVec2::ZERO, IVec2::new(2, 1)).unwrap().len(), 4);
+//!
+//! map.place_obstacle(IVec2::new(1, 1));
+//! let expected = GridBuilder::new("╷#╷\n└─┘").build(OpenFieldGenerator);
+//! assert_maps_equal(&map, &expected);
+//! ```
+
+use crate::map::Map;
+use crate::map_tile::{Biome, Tile};
+use crate::prefab::Prefab;
+use crate::tile_generator::TileGenerator;
+use crate::topology::Topology;
+
+use bevy::prelude::*;
+use std::fs;
+use std::path::Path;
+
+/// Maximum number of differing tiles listed by [`map_differences`].
+const MAX_REPORTED_TILES: usize = 10;
+
+/// Environment variable that makes [`assert_golden`] rewrite the snapshot
+/// files, shared with the golden map fixtures.
+pub const BLESS_VARIABLE: &str = "BLESS_GOLDEN_MAPS";
+
+/// Builds maps from ASCII drawings with [`Map::from_ascii`], panicking on
+/// invalid drawings instead of returning an error.
+#[derive(Debug, Clone)]
+pub struct GridBuilder {
+    ascii: String,
+    topology: Topology,
+    biomes: Vec<(IVec2, Biome)>,
+}
+
+impl GridBuilder {
+    /// Panics with the line and column of an invalid glyph, or the position
+    /// of a tile whose exits don't match its neighbors.
+    #[track_caller]
+    pub fn new(ascii: &str) -> Self {
+        if let Err(error) = ascii.parse::<Prefab>() {
+            panic!("invalid test map: {error}\n{ascii}");
+        }
+        Self {
+            ascii: ascii.to_string(),
+            topology: Topology::Bounded,
+            biomes: Vec::new(),
+        }
+    }
+
+    /// Wraps the edges around like [`Map::new_toroidal`].
+    pub fn toroidal(mut self) -> Self {
+        self.topology = Topology::Toroidal;
+        self
+    }
+
+    pub fn with_biome(mut self, position: IVec2, biome: Biome) -> Self {
+        self.biomes.push((position, biome));
+        self
+    }
+
+    /// The drawn map, see [`Map::from_ascii`].
+    pub fn build<G: TileGenerator>(self, generator: G) -> Map<G> {
+        let mut map = Map::from_ascii(&self.ascii, generator).expect("checked by GridBuilder::new");
+        map.topology = self.topology;
+        for (position, biome) in self.biomes {
+            if let Some(tile) = map.tiles.get_mut(&position) {
+                tile.biome = biome;
+            }
+        }
+        map
+    }
+}
+
+/// Describes how `actual` differs from `expected` in size, topology or
+/// tiles, or `None` when they match. Generators are not compared.
+pub fn map_differences<G: TileGenerator, H: TileGenerator>(
+    actual: &Map<G>,
+    expected: &Map<H>,
+) -> Option<String> {
+    if (actual.x, actual.y) != (expected.x, expected.y) {
+        return Some(format!(
+            "size is {}x{}, expected {}x{}",
+            actual.x, actual.y, expected.x, expected.y
+        ));
+    }
+    if actual.topology != expected.topology {
+        return Some(format!(
+            "topology is {:?}, expected {:?}",
+            actual.topology, expected.topology
+        ));
+    }
+
+    let changed: Vec<IVec2> = actual
+        .positions()
+        .filter(|position| actual.tiles.get(position) != expected.tiles.get(position))
+        .collect();
+    if changed.is_empty() {
+        return None;
+    }
+    let mut report = format!("{} of {} tiles differ", changed.len(), actual.x * actual.y);
+    for position in changed.iter().take(MAX_REPORTED_TILES) {
+        report.push_str(&format!(
+            "\n  {position}: {}, expected {}",
+            describe(actual.tiles.get(position)),
+            describe(expected.tiles.get(position))
+        ));
+    }
+    if changed.len() > MAX_REPORTED_TILES {
+        report.push_str(&format!(
+            "\n  ... and {} more",
+            changed.len() - MAX_REPORTED_TILES
+        ));
+    }
+    Some(report)
+}
+
+fn describe(tile: Option<&Tile>) -> String {
+    match tile {
+        None => "missing".to_string(),
+        Some(tile) if tile.biome == Biome::Dungeon => {
+            format!("{} {}", tile.tile_set, tile.map_tile)
+        }
+        Some(tile) => format!("{} {} {}", tile.tile_set, tile.map_tile, tile.biome),
+    }
+}
+
+/// Panics with the differing tiles and both maps drawn as ASCII unless the
+/// maps have the same size, topology and tiles.
+#[track_caller]
+pub fn assert_maps_equal<G: TileGenerator, H: TileGenerator>(actual: &Map<G>, expected: &Map<H>) {
+    if let Some(report) = map_differences(actual, expected) {
+        panic!(
+            "maps differ: {report}\n\nactual:\n{}\nexpected:\n{}",
+            actual.render_ascii(),
+            expected.render_ascii()
+        );
+    }
+}
+
+/// How [`assert_golden`] writes a map.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    /// [`Map::render_ascii`], easy to review in diffs.
+    Ascii,
+    /// The size, topology and every tile with its tile set, exits and biome,
+    /// for tiles the ASCII glyphs can't tell apart.
+    Json,
+}
+
+/// The text [`assert_golden`] compares.
+pub fn snapshot<G: TileGenerator>(map: &Map<G>, format: SnapshotFormat) -> String {
+    match format {
+        SnapshotFormat::Ascii => map.render_ascii(),
+        SnapshotFormat::Json => serde_json::to_string_pretty(&map.to_json()).unwrap() + "\n",
+    }
+}
+
+/// Compares the [`snapshot`] of `map` with the file at `path`, relative to
+/// the directory `cargo test` runs in, and panics when they differ. Run
+/// with [`BLESS_VARIABLE`] set to write the file instead, e.g. for a new
+/// test or after an intended change.
+#[track_caller]
+pub fn assert_golden<G: TileGenerator>(
+    map: &Map<G>,
+    path: impl AsRef<Path>,
+    format: SnapshotFormat,
+) {
+    let path = path.as_ref();
+    let actual = snapshot(map, format);
+    if std::env::var_os(BLESS_VARIABLE).is_some() {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)
+                .unwrap_or_else(|error| panic!("cannot create {}: {error}", directory.display()));
+        }
+        fs::write(path, &actual)
+            .unwrap_or_else(|error| panic!("cannot write {}: {error}", path.display()));
+        return;
+    }
+    let expected = fs::read_to_string(path).unwrap_or_else(|error| {
+        panic!(
+            "cannot read {}: {error}; run with {BLESS_VARIABLE}=1 to create it",
+            path.display()
+        )
+    });
+    if actual != expected {
+        panic!(
+            "map differs from {}; re-run with {BLESS_VARIABLE}=1 if the change is \
+             intended\n\nactual:\n{actual}\nexpected:\n{expected}",
+            path.display()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_tile::{MapTile, TileSet};
+    use crate::open_field::OpenFieldGenerator;
+
+    #[test]
+    fn grids_are_drawn_north_up() {
+        let map = GridBuilder::new("┌─?\n└─.")
+            .with_biome(IVec2::new(2, 0), Biome::Grass)
+            .build(OpenFieldGenerator);
+
+        assert_eq!((map.x, map.y), (3, 2));
+        assert_eq!(
+            map.tiles[&IVec2::ZERO],
+            Tile::new(TileSet::Corridor, MapTile::NE)
+        );
+        assert_eq!(
+            map.tiles[&IVec2::new(1, 1)],
+            Tile::new(TileSet::Corridor, MapTile::EW)
+        );
+        assert!(!map.tiles.contains_key(&IVec2::new(2, 1)));
+        assert_eq!(map.tiles[&IVec2::new(2, 0)].biome, Biome::Grass);
+        assert_eq!(map.render_ascii(), "┌─?\n└─.\n");
+    }
+
+    #[test]
+    #[should_panic(expected = "line 2, column 1: invalid glyph 'x'")]
+    fn invalid_drawings_panic() {
+        GridBuilder::new("..\nx.");
+    }
+
+    #[test]
+    fn differences_list_the_changed_tiles() {
+        let expected = GridBuilder::new("┏━┓\n┗━┛").build(OpenFieldGenerator);
+        let mut actual = GridBuilder::new("┏━┓\n┗━┛").build(OpenFieldGenerator);
+        assert_eq!(map_differences(&actual, &expected), None);
+
+        actual.tiles.remove(&IVec2::new(1, 0));
+        assert_eq!(
+            map_differences(&actual, &expected).unwrap(),
+            "1 of 6 tiles differ\n  [1, 0]: missing, expected room EW"
+        );
+        let toroidal = GridBuilder::new("┏━┓\n┗━┛")
+            .toroidal()
+            .build(OpenFieldGenerator);
+        assert!(
+            map_differences(&toroidal, &expected)
+                .unwrap()
+                .starts_with("topology")
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "maps differ: 3 of 4 tiles differ")]
+    fn unequal_maps_fail_the_assertion() {
+        let mut map = Map::new(2, OpenFieldGenerator);
+        let expected = Map::new(2, OpenFieldGenerator);
+        map.place_obstacle(IVec2::ZERO);
+
+        assert_maps_equal(&map, &expected);
+    }
+
+    #[test]
+    fn snapshots_match_their_files() {
+        let map = GridBuilder::new("╻.\n╹#").build(OpenFieldGenerator);
+        let directory = std::env::temp_dir().join(format!("golden-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        for (name, format) in [
+            ("map.txt", SnapshotFormat::Ascii),
+            ("map.json", SnapshotFormat::Json),
+        ] {
+            let path = directory.join(name);
+            fs::write(&path, snapshot(&map, format)).unwrap();
+            assert_golden(&map, &path, format);
+        }
+
+        let json: serde_json::Value =
+            serde_json::from_str(&snapshot(&map, SnapshotFormat::Json)).unwrap();
+        assert_eq!(json["tiles"][0]["exits"], "N");
+        assert_eq!(json["tiles"][2]["exits"], "ZERO");
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,226 @@
+use crate::map_tile::{Biome, MapTile, Tile, TileSet};
+
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+
+/// Decides which texture a map tile is drawn with.
+///
+/// Used by [`crate::Map::iterate_tiles_with`]. The texture can be anything:
+/// an asset path, an atlas index, a handle. Closures taking
+/// `(IVec2, Tile)` are resolvers too.
+pub trait TextureResolver {
+    type Texture;
+
+    fn texture(&self, position: IVec2, tile: Tile) -> Self::Texture;
+}
+
+impl<F, T> TextureResolver for F
+where
+    F: Fn(IVec2, Tile) -> T,
+{
+    type Texture = T;
+
+    fn texture(&self, position: IVec2, tile: Tile) -> T {
+        self(position, tile)
+    }
+}
+
+/// Resolves tiles to the file names of the bundled tile assets, e.g.
+/// `room-5-NS.png`: the tile set, the exit mask as a number and the exit
+/// codes. Tiles outside the [`Biome::Dungeon`] are prefixed with their
+/// biome, e.g. `water-room-5-NS.png`. This is what
+/// [`crate::Map::iterate_tiles`] uses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FileNameResolver;
+
+impl TextureResolver for FileNameResolver {
+    type Texture = String;
+
+    fn texture(&self, _position: IVec2, tile: Tile) -> String {
+        let name = format!(
+            "{}-{}-{}.png",
+            tile.tile_set, tile.map_tile as u8, tile.map_tile
+        );
+        match tile.biome {
+            Biome::Dungeon => name,
+            biome => format!("{biome}-{name}"),
+        }
+    }
+}
+
+/// Picks one of several textures for tiles that have variants, such as
+/// three cracked floors for `corridor-5-NS.png`, so repeated tiles don't
+/// look identical. The variant depends only on the seed and the tile
+/// position, so a tile keeps its look when the map is redrawn.
+///
+/// Variant 0 is the texture `resolver` gives; variant `n` adds `-n` before
+/// the extension, e.g. `corridor-5-NS-2.png`. Tiles without variants keep
+/// their one texture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VariantResolver<R> {
+    resolver: R,
+    seed: u64,
+    variants: HashMap<(TileSet, MapTile), u32>,
+}
+
+impl<R: TextureResolver<Texture = String>> VariantResolver<R> {
+    pub fn new(resolver: R, seed: u64) -> Self {
+        Self {
+            resolver,
+            seed,
+            variants: HashMap::new(),
+        }
+    }
+
+    /// Gives tiles of `tile_set` with the exits of `map_tile` `count`
+    /// textures to pick from, the original included.
+    pub fn with_variants(mut self, tile_set: TileSet, map_tile: MapTile, count: u32) -> Self {
+        self.variants.insert((tile_set, map_tile), count.max(1));
+        self
+    }
+
+    /// The number of textures `tile` picks from, 1 without variants.
+    pub fn variant_count(&self, tile: Tile) -> u32 {
+        self.variants
+            .get(&(tile.tile_set, tile.map_tile))
+            .copied()
+            .unwrap_or(1)
+    }
+
+    /// Which of its [`VariantResolver::variant_count`] textures the tile at
+    /// `position` uses.
+    pub fn variant(&self, position: IVec2, tile: Tile) -> u32 {
+        let count = self.variant_count(tile);
+        if count == 1 {
+            return 0;
+        }
+        let hash = (position.x as u32 as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+            ^ (position.y as u32 as u64).wrapping_mul(0xc2b2_ae3d_27d4_eb4f);
+        StdRng::seed_from_u64(self.seed ^ hash).random_range(0..count)
+    }
+
+    /// Every texture of `tile` as animation frames, starting with the one
+    /// [`TextureResolver::texture`] picks, so neighboring tiles animate out
+    /// of step.
+    pub fn frames(&self, position: IVec2, tile: Tile) -> Vec<String> {
+        let count = self.variant_count(tile);
+        let first = self.variant(position, tile);
+        let texture = self.resolver.texture(position, tile);
+        (0..count)
+            .map(|frame| variant_texture(&texture, (first + frame) % count))
+            .collect()
+    }
+}
+
+impl<R: TextureResolver<Texture = String>> TextureResolver for VariantResolver<R> {
+    type Texture = String;
+
+    fn texture(&self, position: IVec2, tile: Tile) -> String {
+        let texture = self.resolver.texture(position, tile);
+        variant_texture(&texture, self.variant(position, tile))
+    }
+}
+
+/// `texture` with `-variant` before its extension, unchanged for variant 0.
+fn variant_texture(texture: &str, variant: u32) -> String {
+    match (variant, texture.rsplit_once('.')) {
+        (0, _) => texture.to_string(),
+        (_, Some((stem, extension))) => format!("{stem}-{variant}.{extension}"),
+        (_, None) => format!("{texture}-{variant}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn file_names_encode_the_tile_set_and_exits() {
+        let texture = FileNameResolver.texture(IVec2::ZERO, Tile::new(TileSet::Room, MapTile::NES));
+
+        assert_eq!(texture, "room-7-NES.png");
+    }
+
+    #[test]
+    fn file_names_start_with_the_biome_outside_dungeons() {
+        let tile = Tile::new(TileSet::Field, MapTile::EW).with_biome(Biome::Lava);
+
+        assert_eq!(
+            FileNameResolver.texture(IVec2::ZERO, tile),
+            "lava-field-10-EW.png"
+        );
+    }
+
+    #[test]
+    fn variants_are_picked_per_position() {
+        let resolver = VariantResolver::new(FileNameResolver, 7).with_variants(
+            TileSet::Corridor,
+            MapTile::NS,
+            3,
+        );
+        let corridor = Tile::new(TileSet::Corridor, MapTile::NS);
+
+        let textures: HashSet<String> = (0..32)
+            .map(|x| resolver.texture(IVec2::new(x, 0), corridor))
+            .collect();
+        let expected: HashSet<String> = [
+            "corridor-5-NS.png",
+            "corridor-5-NS-1.png",
+            "corridor-5-NS-2.png",
+        ]
+        .map(String::from)
+        .into();
+        assert_eq!(textures, expected);
+        // The same seed picks the same variants.
+        let position = IVec2::new(4, 9);
+        let again = VariantResolver::new(FileNameResolver, 7).with_variants(
+            TileSet::Corridor,
+            MapTile::NS,
+            3,
+        );
+        assert_eq!(
+            resolver.texture(position, corridor),
+            again.texture(position, corridor)
+        );
+        assert_eq!(
+            resolver.texture(position, Tile::new(TileSet::Room, MapTile::NS)),
+            "room-5-NS.png"
+        );
+    }
+
+    #[test]
+    fn frames_start_at_the_picked_variant() {
+        let resolver =
+            VariantResolver::new(FileNameResolver, 1).with_variants(TileSet::Room, MapTile::E, 4);
+        let room = Tile::new(TileSet::Room, MapTile::E);
+
+        for x in 0..8 {
+            let position = IVec2::new(x, 3);
+            let frames = resolver.frames(position, room);
+            assert_eq!(frames.len(), 4);
+            assert_eq!(frames[0], resolver.texture(position, room));
+            assert_eq!(frames.iter().collect::<HashSet<_>>().len(), 4);
+        }
+        assert_eq!(
+            resolver
+                .frames(IVec2::ZERO, Tile::new(TileSet::Room, MapTile::W))
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn closures_resolve_textures() {
+        let atlas_index = |_: IVec2, tile: Tile| {
+            tile.map_tile as usize + 16 * (tile.tile_set == TileSet::Corridor) as usize
+        };
+
+        assert_eq!(
+            atlas_index.texture(IVec2::ZERO, Tile::new(TileSet::Corridor, MapTile::N)),
+            17
+        );
+    }
+}
@@ -1,11 +1,17 @@
+use crate::direction_set::DirectionSet;
+use crate::generation_context::{GenerationContext, position_hash};
+use crate::generation_trace::{Decision, GenerationTrace, Roll};
 use crate::map_tile::{Direction, MapTile, Tile, TileSet};
+use crate::share_code::{ShareableGenerator, read_varint, write_varint};
+use crate::tile_grid::TileGrid;
 use bevy::prelude::*;
-use rand::{rng, rngs::StdRng, Rng, SeedableRng};
+use rand::{Rng, SeedableRng, rng, rngs::StdRng};
 use std::{collections::HashMap, sync::Mutex};
 
 enum RandomSource {
     Thread,
-    Seeded(Mutex<StdRng>),
+    /// Boxed so the enum stays small, see `clippy::large_enum_variant`.
+    Seeded(Mutex<Box<StdRng>>),
 }
 
 impl RandomSource {
@@ -15,31 +21,208 @@ impl RandomSource {
             RandomSource::Seeded(rng) => rng.lock().unwrap().random_bool(probability),
         }
     }
+
+    /// Returns a value in `0.0..1.0`.
+    fn random_unit(&self) -> f64 {
+        match self {
+            RandomSource::Thread => rng().random(),
+            RandomSource::Seeded(rng) => rng.lock().unwrap().random(),
+        }
+    }
+}
+
+/// How many random values [`TileGeneratorDefault`] draws per tile.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RandomDrawMode {
+    /// Draws one value per exit that isn't forced by a neighbor, then one
+    /// for the tile set. The number of draws depends on the neighbors, so
+    /// any change to what a tile sees shifts every later tile of the seed.
+    /// Kept so that seeds recorded before [`RandomDrawMode::Fixed`] existed
+    /// still produce the same maps.
+    Legacy,
+    /// Draws the same number of values for every tile and discards the
+    /// unused ones, so a tile only affects the random values of later tiles
+    /// through the neighbors they see.
+    #[default]
+    Fixed,
+    /// Draws like [`RandomDrawMode::Fixed`], but from a stream seeded with
+    /// the seed xor a hash of the tile position, so a tile depends only on
+    /// the seed, its position and its neighbors, whatever order tiles are
+    /// generated in. Generators without a seed draw from the thread's random
+    /// number generator as usual.
+    PositionHashed,
 }
 
 #[derive(Resource)]
 pub struct TileGeneratorDefault {
     pub tile_exit_probability: f64,
     pub room_probability: f64,
+    pub draw_mode: RandomDrawMode,
+    /// Lets tiles on the map border open exits that leave the grid, for maps
+    /// whose edges wrap around. Off by default.
+    pub allow_edge_exits: bool,
     rng: RandomSource,
+    /// The seed the generator was last (re)started from, if any.
+    seed: Option<u64>,
+    weights: Option<TileWeights>,
+    /// Records every random draw once [`TileGeneratorDefault::start_trace`]
+    /// is called.
+    trace: Option<Mutex<GenerationTrace>>,
+}
+
+/// Relative weights biasing which exit combination is picked for a tile.
+#[derive(Debug, Clone, PartialEq)]
+struct TileWeights {
+    map_tiles: HashMap<MapTile, f64>,
+    /// Indexed by the number of exits, 0 to 4.
+    exit_counts: [f64; 5],
+}
+
+impl TileWeights {
+    fn weight(&self, map_tile: MapTile) -> f64 {
+        let exit_count = map_tile.exits().len();
+        self.map_tiles.get(&map_tile).copied().unwrap_or(1.0) * self.exit_counts[exit_count]
+    }
+}
+
+/// Builds a [`TileGeneratorDefault`], optionally with a weight table over
+/// exit combinations.
+///
+/// ```
+/// use brain_engine_core::{MapTile, TileGeneratorDefault};
+///
+/// // Favour straight corridors and avoid dead ends.
+/// let generator = TileGeneratorDefault::builder()
+///     .seed(7)
+///     .weight(MapTile::NS, 3.0)
+///     .weight(MapTile::EW, 3.0)
+///     .exit_count_weight(1, 0.1)
+///     .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct TileGeneratorBuilder {
+    seed: Option<u64>,
+    tile_exit_probability: f64,
+    room_probability: f64,
+    draw_mode: RandomDrawMode,
+    allow_edge_exits: bool,
+    weights: TileWeights,
+}
+
+impl TileGeneratorBuilder {
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn tile_exit_probability(mut self, tile_exit_probability: f64) -> Self {
+        self.tile_exit_probability = tile_exit_probability;
+        self
+    }
+
+    pub fn room_probability(mut self, room_probability: f64) -> Self {
+        self.room_probability = room_probability;
+        self
+    }
+
+    /// Use [`RandomDrawMode::Legacy`] to reproduce maps from old seeds.
+    pub fn draw_mode(mut self, draw_mode: RandomDrawMode) -> Self {
+        self.draw_mode = draw_mode;
+        self
+    }
+
+    /// See [`TileGeneratorDefault::allow_edge_exits`].
+    pub fn allow_edge_exits(mut self, allow_edge_exits: bool) -> Self {
+        self.allow_edge_exits = allow_edge_exits;
+        self
+    }
+
+    /// Multiplies the chance of generating `map_tile` by `weight`. Tiles
+    /// without a weight keep 1.0; 0.0 avoids the tile unless the neighbors
+    /// leave no other choice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `weight` is negative or not finite.
+    pub fn weight(mut self, map_tile: MapTile, weight: f64) -> Self {
+        assert!(
+            weight.is_finite() && weight >= 0.0,
+            "invalid weight {weight}"
+        );
+        self.weights.map_tiles.insert(map_tile, weight);
+        self
+    }
+
+    /// Multiplies the chance of generating any tile with `exit_count` exits
+    /// by `weight`, e.g. `exit_count_weight(1, 0.2)` discourages dead ends.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `exit_count` is above 4 or `weight` is negative or not finite.
+    pub fn exit_count_weight(mut self, exit_count: usize, weight: f64) -> Self {
+        assert!(exit_count <= 4, "a tile has at most 4 exits");
+        assert!(
+            weight.is_finite() && weight >= 0.0,
+            "invalid weight {weight}"
+        );
+        self.weights.exit_counts[exit_count] = weight;
+        self
+    }
+
+    pub fn build(self) -> TileGeneratorDefault {
+        let mut generator = match self.seed {
+            Some(seed) => TileGeneratorDefault::with_seed_and_probabilities(
+                seed,
+                self.tile_exit_probability,
+                self.room_probability,
+            ),
+            None => TileGeneratorDefault::with_probabilities(
+                self.tile_exit_probability,
+                self.room_probability,
+            ),
+        };
+        let unweighted = self.weights.map_tiles.values().all(|weight| *weight == 1.0)
+            && self.weights.exit_counts == [1.0; 5];
+        generator.weights = (!unweighted).then_some(self.weights);
+        generator.draw_mode = self.draw_mode;
+        generator.allow_edge_exits = self.allow_edge_exits;
+        generator
+    }
 }
 
 impl TileGeneratorDefault {
+    pub fn builder() -> TileGeneratorBuilder {
+        TileGeneratorBuilder {
+            seed: None,
+            tile_exit_probability: 0.35,
+            room_probability: 0.35,
+            draw_mode: RandomDrawMode::default(),
+            allow_edge_exits: false,
+            weights: TileWeights {
+                map_tiles: HashMap::new(),
+                exit_counts: [1.0; 5],
+            },
+        }
+    }
+
     pub fn new() -> Self {
         Self::new_with_rng(RandomSource::Thread)
     }
 
     pub fn with_seed(seed: u64) -> Self {
-        Self::new_with_rng(RandomSource::Seeded(Mutex::new(StdRng::seed_from_u64(
-            seed,
-        ))))
+        Self::with_seed_and_probabilities(seed, 0.35, 0.35)
     }
 
     pub fn with_probabilities(tile_exit_probability: f64, room_probability: f64) -> Self {
         Self {
             tile_exit_probability,
             room_probability,
+            draw_mode: RandomDrawMode::default(),
+            allow_edge_exits: false,
             rng: RandomSource::Thread,
+            seed: None,
+            weights: None,
+            trace: None,
         }
     }
 
@@ -51,7 +234,49 @@ impl TileGeneratorDefault {
         Self {
             tile_exit_probability,
             room_probability,
-            rng: RandomSource::Seeded(Mutex::new(StdRng::seed_from_u64(seed))),
+            draw_mode: RandomDrawMode::default(),
+            allow_edge_exits: false,
+            rng: RandomSource::Seeded(Mutex::new(Box::new(StdRng::seed_from_u64(seed)))),
+            seed: Some(seed),
+            weights: None,
+            trace: None,
+        }
+    }
+
+    /// Restarts the generator from `seed`, e.g. to reroll a map with
+    /// [`crate::Map::regenerate`] reproducibly.
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = RandomSource::Seeded(Mutex::new(Box::new(StdRng::seed_from_u64(seed))));
+        self.seed = Some(seed);
+    }
+
+    /// Returns the seed the generator was created or last reseeded with, or
+    /// `None` when it draws from the thread's random number generator.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Starts recording every random draw, with the tile it was for and
+    /// what it decided, e.g. to find the roll behind an unexpected tile.
+    /// Restarts the trace if one is being recorded.
+    pub fn start_trace(&mut self) {
+        self.trace = Some(Mutex::new(GenerationTrace::default()));
+    }
+
+    /// The draws recorded since [`TileGeneratorDefault::start_trace`] or the
+    /// last call, `None` when no trace was started. Recording continues.
+    pub fn take_trace(&self) -> Option<GenerationTrace> {
+        let trace = self.trace.as_ref()?;
+        Some(std::mem::take(&mut *trace.lock().unwrap()))
+    }
+
+    fn record(&self, position: IVec2, decision: Decision, used: bool) {
+        if let Some(trace) = &self.trace {
+            trace.lock().unwrap().rolls.push(Roll {
+                position,
+                decision,
+                used,
+            });
         }
     }
 
@@ -59,17 +284,130 @@ impl TileGeneratorDefault {
         Self {
             tile_exit_probability: 0.35,
             room_probability: 0.35,
+            draw_mode: RandomDrawMode::default(),
+            allow_edge_exits: false,
             rng,
+            seed: None,
+            weights: None,
+            trace: None,
         }
     }
 
-    fn random_bool(&self, probability: f64) -> bool {
-        self.rng.random_bool(probability)
+    /// The random stream for the tile at `location`: the generator's own,
+    /// or a fresh one for [`RandomDrawMode::PositionHashed`].
+    fn random_source(&self, location: IVec2) -> Option<RandomSource> {
+        let seed = self
+            .seed
+            .filter(|_| self.draw_mode == RandomDrawMode::PositionHashed)?;
+        Some(RandomSource::Seeded(Mutex::new(Box::new(
+            StdRng::seed_from_u64(seed ^ position_hash(location)),
+        ))))
+    }
+
+    /// Picks an exit combination matching the neighbors' exits. Each
+    /// candidate's chance is the chance of drawing its free exits with
+    /// `tile_exit_probability`, scaled by its weights.
+    fn weighted_map_tile(
+        &self,
+        rng: &RandomSource,
+        weights: &TileWeights,
+        tiles: &TileGrid,
+        location: IVec2,
+        on_map: impl Fn(IVec2) -> bool,
+    ) -> MapTile {
+        let mut required = DirectionSet::EMPTY;
+        let mut free = DirectionSet::EMPTY;
+        for direction in Direction::all() {
+            let neighbor = location + direction.offset();
+            match tiles.get(&neighbor) {
+                Some(tile) if tile.map_tile.contains(direction.opposite()) => {
+                    required.insert(direction);
+                }
+                Some(_) => {}
+                None if on_map(neighbor) => {
+                    free.insert(direction);
+                }
+                None => {}
+            }
+        }
+
+        let candidates: Vec<(MapTile, f64)> = free
+            .subsets()
+            .map(|opened| {
+                let map_tile = MapTile::from(required | opened);
+                let opened = opened.len() as i32;
+                let closed = free.len() as i32 - opened;
+                let probability = self.tile_exit_probability.powi(opened)
+                    * (1.0 - self.tile_exit_probability).powi(closed);
+                (map_tile, probability * weights.weight(map_tile))
+            })
+            .collect();
+
+        let fixed_draw = (self.draw_mode != RandomDrawMode::Legacy).then(|| rng.random_unit());
+        let total: f64 = candidates.iter().map(|(_, weight)| weight).sum();
+        if total <= 0.0 {
+            let map_tile = MapTile::from(required);
+            if fixed_draw.is_some() {
+                self.record(location, Decision::Exits(map_tile), false);
+            }
+            return map_tile;
+        }
+        let mut remaining = fixed_draw.unwrap_or_else(|| rng.random_unit()) * total;
+        // Rounding can leave a sliver past the last candidate.
+        let mut picked = candidates
+            .iter()
+            .rev()
+            .find(|(_, weight)| *weight > 0.0)
+            .map(|(map_tile, _)| *map_tile)
+            .unwrap();
+        for (map_tile, weight) in &candidates {
+            if remaining < *weight {
+                picked = *map_tile;
+                break;
+            }
+            remaining -= weight;
+        }
+        self.record(location, Decision::Exits(picked), true);
+        picked
+    }
+}
+
+/// Same as [`TileGeneratorDefault::new`], see `clippy::new_without_default`.
+impl Default for TileGeneratorDefault {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl TileGenerator for TileGeneratorDefault {
-    fn tile_at(&self, tiles: &HashMap<IVec2, Tile>, location: IVec2) -> Tile {
+    fn tile_at(&self, tiles: &TileGrid, location: IVec2) -> Tile {
+        self.generate_tile(tiles, location, |_| true)
+    }
+
+    fn tile_at_within(&self, tiles: &TileGrid, location: IVec2, bounds: IRect) -> Tile {
+        let allow_edge_exits = self.allow_edge_exits;
+        self.generate_tile(tiles, location, |position| {
+            allow_edge_exits || bounds.contains(position)
+        })
+    }
+}
+
+impl TileGeneratorDefault {
+    /// Exits towards positions where `on_map` is false are never opened.
+    fn generate_tile(
+        &self,
+        tiles: &TileGrid,
+        location: IVec2,
+        on_map: impl Fn(IVec2) -> bool,
+    ) -> Tile {
+        let position_rng = self.random_source(location);
+        let rng = position_rng.as_ref().unwrap_or(&self.rng);
+        if let Some(weights) = &self.weights {
+            let map_tile = self.weighted_map_tile(rng, weights, tiles, location, on_map);
+            let tile_set = self.random_tile_set(rng, location);
+            return Tile::new(tile_set, map_tile);
+        }
+
         let mut tile_exits: Vec<Direction> = Vec::new();
         for direction in [
             Direction::North,
@@ -77,41 +415,172 @@ impl TileGenerator for TileGeneratorDefault {
             Direction::South,
             Direction::West,
         ] {
-            let direction_vector = match direction {
-                Direction::North => IVec2::new(0, 1),
-                Direction::East => IVec2::new(1, 0),
-                Direction::South => IVec2::new(0, -1),
-                Direction::West => IVec2::new(-1, 0),
-            };
-            let neighbor = location + direction_vector;
+            let neighbor = location + direction.offset();
+            let fixed_draw = (self.draw_mode != RandomDrawMode::Legacy)
+                .then(|| rng.random_bool(self.tile_exit_probability));
             if let Some(tile) = tiles.get(&neighbor) {
                 if tile.map_tile.directions().contains(&direction.opposite()) {
                     tile_exits.push(direction);
                 } else {
                     // no exit on neighbouring tile - so don't open an exit into a wall !
                 }
+            } else if !on_map(neighbor) {
+                // never open an exit off the edge of the map
             } else {
                 // random chance we push direction to tile_exits based on configured probability
-                if self.random_bool(self.tile_exit_probability) {
+                let open =
+                    fixed_draw.unwrap_or_else(|| rng.random_bool(self.tile_exit_probability));
+                self.record(location, Decision::Exit { direction, open }, true);
+                if open {
                     tile_exits.push(direction);
                 }
+                continue;
+            }
+            if let Some(open) = fixed_draw {
+                self.record(location, Decision::Exit { direction, open }, false);
             }
         }
         let map_tile = MapTile::from_directions(&tile_exits).unwrap();
 
-        // Randomly select room or corridor based on room_probability
-        let tile_set = if self.random_bool(self.room_probability) {
+        let tile_set = self.random_tile_set(rng, location);
+        Tile::new(tile_set, map_tile)
+    }
+
+    /// Draws `count` exits with `tile_exit_probability` and then a tile set
+    /// from the stream for `location`, for grids whose tiles have other
+    /// neighbors than the four [`Direction`]s, such as [`crate::HexMap`].
+    /// Draws every exit whether it is used or not, like
+    /// [`RandomDrawMode::Fixed`], and ignores weights.
+    pub(crate) fn draw_exits(&self, location: IVec2, count: usize) -> (Vec<bool>, TileSet) {
+        let position_rng = self.random_source(location);
+        let rng = position_rng.as_ref().unwrap_or(&self.rng);
+        let exits = (0..count)
+            .map(|_| rng.random_bool(self.tile_exit_probability))
+            .collect();
+        (exits, self.random_tile_set(rng, location))
+    }
+
+    /// Randomly selects room or corridor based on `room_probability`.
+    fn random_tile_set(&self, rng: &RandomSource, location: IVec2) -> TileSet {
+        let tile_set = if rng.random_bool(self.room_probability) {
             TileSet::Room
         } else {
             TileSet::Corridor
         };
+        self.record(location, Decision::TileSet(tile_set), true);
+        tile_set
+    }
+}
 
-        Tile::new(tile_set, map_tile)
+/// Shares the seed and both probabilities, in thousandths. When the draw
+/// mode isn't the default or edge exits are allowed, the draw mode follows
+/// (0 for [`RandomDrawMode::Legacy`], 1 for fixed, 2 for position hashed),
+/// then a 1 if edge exits are allowed. Weighted generators and
+/// probabilities finer than a thousandth aren't shareable.
+impl ShareableGenerator for TileGeneratorDefault {
+    fn share_config(&self) -> Option<Vec<u8>> {
+        let to_thousandths = |probability: f64| {
+            let thousandths = (probability * 1000.0).round();
+            ((0.0..=1000.0).contains(&thousandths) && thousandths / 1000.0 == probability)
+                .then_some(thousandths as u64)
+        };
+        if self.weights.is_some() {
+            return None;
+        }
+        let mut config = Vec::new();
+        write_varint(&mut config, self.seed?);
+        write_varint(&mut config, to_thousandths(self.tile_exit_probability)?);
+        write_varint(&mut config, to_thousandths(self.room_probability)?);
+        if self.draw_mode != RandomDrawMode::Fixed || self.allow_edge_exits {
+            let draw_mode = match self.draw_mode {
+                RandomDrawMode::Legacy => 0,
+                RandomDrawMode::Fixed => 1,
+                RandomDrawMode::PositionHashed => 2,
+            };
+            write_varint(&mut config, draw_mode);
+        }
+        if self.allow_edge_exits {
+            write_varint(&mut config, 1);
+        }
+        Some(config)
+    }
+
+    fn from_share_config(mut config: &[u8]) -> Option<Self> {
+        let seed = read_varint(&mut config)?;
+        let tile_exit_probability = read_varint(&mut config)?;
+        let room_probability = read_varint(&mut config)?;
+        let (draw_mode, allow_edge_exits) = match config {
+            [] => (RandomDrawMode::Fixed, false),
+            [0] => (RandomDrawMode::Legacy, false),
+            [2] => (RandomDrawMode::PositionHashed, false),
+            [draw_mode @ (0..=2), 1] => {
+                let draw_mode = match draw_mode {
+                    0 => RandomDrawMode::Legacy,
+                    1 => RandomDrawMode::Fixed,
+                    _ => RandomDrawMode::PositionHashed,
+                };
+                (draw_mode, true)
+            }
+            _ => return None,
+        };
+        if tile_exit_probability > 1000 || room_probability > 1000 {
+            return None;
+        }
+        let mut generator = Self::with_seed_and_probabilities(
+            seed,
+            tile_exit_probability as f64 / 1000.0,
+            room_probability as f64 / 1000.0,
+        );
+        generator.draw_mode = draw_mode;
+        generator.allow_edge_exits = allow_edge_exits;
+        Some(generator)
     }
 }
 
 pub trait TileGenerator {
-    fn tile_at(&self, tiles: &HashMap<IVec2, Tile>, location: IVec2) -> Tile;
+    /// Generators that only use [`TileGenerator::generate`] can implement
+    /// this as `self.generate(&GenerationContext::new(tiles, location))`.
+    fn tile_at(&self, tiles: &TileGrid, location: IVec2) -> Tile;
+
+    /// Like [`TileGenerator::tile_at`], for a map whose tiles all lie within
+    /// `bounds` (both corners inclusive), so border tiles can keep their
+    /// exits on the map. [`crate::Map`] generates through this; unbounded
+    /// maps such as [`crate::ChunkedMap`] call `tile_at`. The default
+    /// ignores the bounds.
+    fn tile_at_within(&self, tiles: &TileGrid, location: IVec2, bounds: IRect) -> Tile {
+        let _ = bounds;
+        self.tile_at(tiles, location)
+    }
+
+    /// Generates the tile at [`GenerationContext::location`], with helpers
+    /// for the neighbors, bounds, a position-seeded random stream and the
+    /// progress of the run. [`crate::Map`] and [`crate::ChunkedMap`]
+    /// generate through this. The default calls
+    /// [`TileGenerator::tile_at_within`] on bounded maps and
+    /// [`TileGenerator::tile_at`] on unbounded ones.
+    fn generate(&self, context: &GenerationContext) -> Tile {
+        match context.bounds() {
+            Some(bounds) => self.tile_at_within(context.tiles(), context.location(), bounds),
+            None => self.tile_at(context.tiles(), context.location()),
+        }
+    }
+}
+
+/// A tile generator chosen at runtime, e.g. from a config file.
+pub type BoxedTileGenerator = Box<dyn TileGenerator + Send + Sync>;
+
+impl<T: TileGenerator + ?Sized> TileGenerator for Box<T> {
+    fn tile_at(&self, tiles: &TileGrid, location: IVec2) -> Tile {
+        (**self).tile_at(tiles, location)
+    }
+
+    fn tile_at_within(&self, tiles: &TileGrid, location: IVec2, bounds: IRect) -> Tile {
+        (**self).tile_at_within(tiles, location, bounds)
+    }
+
+    fn generate(&self, context: &GenerationContext) -> Tile {
+        (**self).generate(context)
+    }
 }
 
 #[cfg(test)]
@@ -129,7 +598,7 @@ mod tests {
     fn tile_generator_with_seed_is_reproducible() {
         let generator_a = TileGeneratorDefault::with_seed(42);
         let generator_b = TileGeneratorDefault::with_seed(42);
-        let tiles = HashMap::new();
+        let tiles = TileGrid::new();
 
         let sample_locations = [IVec2::new(0, 0), IVec2::new(1, 2), IVec2::new(-3, 5)];
 
@@ -147,7 +616,7 @@ mod tests {
         let mut generator = TileGeneratorDefault::with_seed(7);
         generator.tile_exit_probability = 0.5;
         generator.room_probability = 1.0;
-        let tiles = HashMap::new();
+        let tiles = TileGrid::new();
 
         let tile = generator.tile_at(&tiles, IVec2::new(0, 0));
         assert_eq!(tile.tile_set, TileSet::Room);
@@ -158,7 +627,7 @@ mod tests {
         let mut generator = TileGeneratorDefault::with_seed(11);
         generator.tile_exit_probability = 0.5;
         generator.room_probability = 0.0;
-        let tiles = HashMap::new();
+        let tiles = TileGrid::new();
 
         let tile = generator.tile_at(&tiles, IVec2::new(0, 0));
         assert_eq!(tile.tile_set, TileSet::Corridor);
@@ -169,7 +638,7 @@ mod tests {
         let mut generator = TileGeneratorDefault::with_seed(99);
         generator.tile_exit_probability = 0.0;
         generator.room_probability = 1.0;
-        let mut tiles = HashMap::new();
+        let mut tiles = TileGrid::new();
 
         // Create a tile with only an East exit at (0, 0)
         tiles.insert(IVec2::new(0, 0), Tile::new(TileSet::Room, MapTile::E));
@@ -178,4 +647,174 @@ mod tests {
         let tile = generator.tile_at(&tiles, IVec2::new(1, 0));
         assert!(tile.map_tile.directions().contains(&Direction::West));
     }
+
+    #[test]
+    fn builder_without_weights_matches_seeded_constructor() {
+        let built = TileGeneratorDefault::builder()
+            .seed(5)
+            .tile_exit_probability(0.6)
+            .room_probability(0.2)
+            .weight(MapTile::NS, 1.0)
+            .build();
+        let constructed = TileGeneratorDefault::with_seed_and_probabilities(5, 0.6, 0.2);
+        let tiles = TileGrid::new();
+
+        for x in 0..20 {
+            let location = IVec2::new(x, 0);
+            assert_eq!(
+                built.tile_at(&tiles, location),
+                constructed.tile_at(&tiles, location)
+            );
+        }
+    }
+
+    #[test]
+    fn weights_bias_the_generated_tiles() {
+        let generator = TileGeneratorDefault::builder()
+            .seed(3)
+            .tile_exit_probability(0.5)
+            .weight(MapTile::NS, 50.0)
+            .exit_count_weight(1, 0.0)
+            .build();
+        let tiles = TileGrid::new();
+
+        let map_tiles: Vec<MapTile> = (0..200)
+            .map(|x| generator.tile_at(&tiles, IVec2::new(x, 0)).map_tile)
+            .collect();
+
+        assert!(
+            map_tiles
+                .iter()
+                .all(|map_tile| map_tile.directions().len() != 1)
+        );
+        let straight = map_tiles
+            .iter()
+            .filter(|map_tile| **map_tile == MapTile::NS);
+        assert!(straight.count() > 100);
+    }
+
+    #[test]
+    fn weighted_generation_respects_neighbor_exits() {
+        let generator = TileGeneratorDefault::builder()
+            .seed(8)
+            .weight(MapTile::ZERO, 100.0)
+            .build();
+        let mut tiles = TileGrid::new();
+        tiles.insert(IVec2::new(0, 1), Tile::new(TileSet::Room, MapTile::S));
+        tiles.insert(IVec2::new(1, 0), Tile::new(TileSet::Room, MapTile::N));
+
+        for _ in 0..20 {
+            let map_tile = generator.tile_at(&tiles, IVec2::new(0, 0)).map_tile;
+            assert!(map_tile.directions().contains(&Direction::North));
+            assert!(!map_tile.directions().contains(&Direction::East));
+        }
+    }
+
+    #[test]
+    fn zero_weights_fall_back_to_the_required_exits() {
+        let generator = TileGeneratorDefault::builder()
+            .seed(1)
+            .exit_count_weight(1, 0.0)
+            .build();
+        let mut tiles = TileGrid::new();
+        for direction in [Direction::North, Direction::East, Direction::South] {
+            tiles.insert(direction.offset(), Tile::new(TileSet::Room, MapTile::ZERO));
+        }
+        tiles.insert(
+            Direction::West.offset(),
+            Tile::new(TileSet::Room, MapTile::E),
+        );
+
+        let map_tile = generator.tile_at(&tiles, IVec2::ZERO).map_tile;
+        assert_eq!(map_tile, MapTile::W);
+    }
+
+    /// Draws a tile surrounded by neighbors on one generator and an isolated
+    /// tile on the other, then compares what the two generate next.
+    fn later_tiles_after_different_neighbors(
+        generator_a: TileGeneratorDefault,
+        generator_b: TileGeneratorDefault,
+    ) -> bool {
+        let mut surrounded = TileGrid::new();
+        for direction in Direction::all() {
+            surrounded.insert(direction.offset(), Tile::new(TileSet::Room, MapTile::NESW));
+        }
+        generator_a.tile_at(&surrounded, IVec2::ZERO);
+        generator_b.tile_at(&TileGrid::new(), IVec2::ZERO);
+
+        (1..20).all(|x| {
+            let location = IVec2::new(x * 10, 0);
+            generator_a.tile_at(&TileGrid::new(), location)
+                == generator_b.tile_at(&TileGrid::new(), location)
+        })
+    }
+
+    #[test]
+    fn fixed_draw_mode_keeps_the_random_stream_aligned() {
+        assert!(later_tiles_after_different_neighbors(
+            TileGeneratorDefault::with_seed_and_probabilities(4, 0.5, 0.5),
+            TileGeneratorDefault::with_seed_and_probabilities(4, 0.5, 0.5),
+        ));
+
+        let weighted = || {
+            TileGeneratorDefault::builder()
+                .seed(4)
+                .exit_count_weight(4, 0.0)
+                .build()
+        };
+        assert!(later_tiles_after_different_neighbors(
+            weighted(),
+            weighted()
+        ));
+    }
+
+    #[test]
+    fn legacy_draw_mode_skips_draws_for_forced_exits() {
+        let legacy = || {
+            TileGeneratorDefault::builder()
+                .seed(4)
+                .tile_exit_probability(0.5)
+                .room_probability(0.5)
+                .draw_mode(RandomDrawMode::Legacy)
+                .build()
+        };
+
+        assert!(!later_tiles_after_different_neighbors(legacy(), legacy()));
+    }
+
+    #[test]
+    fn position_hashed_tiles_ignore_generation_order() {
+        let hashed = || {
+            TileGeneratorDefault::builder()
+                .seed(4)
+                .tile_exit_probability(0.5)
+                .room_probability(0.5)
+                .draw_mode(RandomDrawMode::PositionHashed)
+                .build()
+        };
+        let (forwards, backwards) = (hashed(), hashed());
+        let tiles = TileGrid::new();
+        let locations: Vec<IVec2> = (-5..5).map(|x| IVec2::new(x, x * 3)).collect();
+
+        let forward_tiles: Vec<Tile> = locations
+            .iter()
+            .map(|location| forwards.tile_at(&tiles, *location))
+            .collect();
+        let mut backward_tiles: Vec<Tile> = locations
+            .iter()
+            .rev()
+            .map(|location| backwards.tile_at(&tiles, *location))
+            .collect();
+        backward_tiles.reverse();
+
+        assert_eq!(forward_tiles, backward_tiles);
+        assert!(later_tiles_after_different_neighbors(hashed(), hashed()));
+        assert_ne!(
+            forward_tiles,
+            locations
+                .iter()
+                .map(|location| hashed().tile_at(&tiles, *location + IVec2::X))
+                .collect::<Vec<_>>()
+        );
+    }
 }
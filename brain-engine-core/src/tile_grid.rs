@@ -0,0 +1,268 @@
+use crate::map_tile::Tile;
+
+use bevy::prelude::*;
+use std::collections::HashMap;
+use std::ops::Index;
+
+/// Tile storage with the `HashMap<IVec2, Tile>` methods maps use, backed by
+/// a flat `Vec` for a dense grid.
+///
+/// Positions from `(0, 0)` up to [`TileGrid::size`] are stored in row-major
+/// order, so lookups on square maps are an index instead of a hash. Any
+/// other position, e.g. a tile across the seam of a toroidal map or the
+/// tiles of an unbounded [`crate::ChunkedMap`], falls back to a `HashMap`.
+///
+/// Iteration yields the grid in row-major order, then the other tiles in
+/// arbitrary order.
+#[derive(Debug, Clone, Default)]
+pub struct TileGrid {
+    size: UVec2,
+    /// Each tile is stored with its position, so iterators can hand out
+    /// `&IVec2` keys like a `HashMap`.
+    cells: Vec<Option<(IVec2, Tile)>>,
+    /// Tiles stored in `cells`.
+    grid_len: usize,
+    overflow: HashMap<IVec2, Tile>,
+}
+
+impl TileGrid {
+    /// An empty grid that keeps every tile in the `HashMap` fallback.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An empty grid with room for every position from `(0, 0)` up to
+    /// `size`.
+    pub fn with_size(size: UVec2) -> Self {
+        Self {
+            size,
+            cells: vec![None; size.x as usize * size.y as usize],
+            grid_len: 0,
+            overflow: HashMap::new(),
+        }
+    }
+
+    /// Width and height of the dense grid.
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    fn index(&self, position: IVec2) -> Option<usize> {
+        let in_grid =
+            position.cmpge(IVec2::ZERO).all() && position.cmplt(self.size.as_ivec2()).all();
+        in_grid.then(|| position.y as usize * self.size.x as usize + position.x as usize)
+    }
+
+    pub fn get(&self, position: &IVec2) -> Option<&Tile> {
+        match self.index(*position) {
+            Some(index) => self.cells[index].as_ref().map(|(_, tile)| tile),
+            None if self.overflow.is_empty() => None,
+            None => self.overflow.get(position),
+        }
+    }
+
+    pub fn get_mut(&mut self, position: &IVec2) -> Option<&mut Tile> {
+        match self.index(*position) {
+            Some(index) => self.cells[index].as_mut().map(|(_, tile)| tile),
+            None => self.overflow.get_mut(position),
+        }
+    }
+
+    pub fn contains_key(&self, position: &IVec2) -> bool {
+        self.get(position).is_some()
+    }
+
+    /// Stores `tile` at `position`, returning the tile it replaced.
+    pub fn insert(&mut self, position: IVec2, tile: Tile) -> Option<Tile> {
+        let Some(index) = self.index(position) else {
+            return self.overflow.insert(position, tile);
+        };
+        let previous = self.cells[index].replace((position, tile));
+        if previous.is_none() {
+            self.grid_len += 1;
+        }
+        previous.map(|(_, tile)| tile)
+    }
+
+    pub fn remove(&mut self, position: &IVec2) -> Option<Tile> {
+        let Some(index) = self.index(*position) else {
+            return self.overflow.remove(position);
+        };
+        let previous = self.cells[index].take();
+        if previous.is_some() {
+            self.grid_len -= 1;
+        }
+        previous.map(|(_, tile)| tile)
+    }
+
+    pub fn len(&self) -> usize {
+        self.grid_len + self.overflow.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes every tile, keeping the size of the grid.
+    pub fn clear(&mut self) {
+        self.cells.fill(None);
+        self.grid_len = 0;
+        self.overflow.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&IVec2, &Tile)> {
+        self.cells
+            .iter()
+            .filter_map(|cell| cell.as_ref().map(|(position, tile)| (position, tile)))
+            .chain(self.overflow.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&IVec2, &mut Tile)> {
+        self.cells
+            .iter_mut()
+            .filter_map(|cell| cell.as_mut().map(|(position, tile)| (&*position, tile)))
+            .chain(self.overflow.iter_mut())
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &IVec2> {
+        self.iter().map(|(position, _)| position)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Tile> {
+        self.iter().map(|(_, tile)| tile)
+    }
+
+    /// Keeps only the tiles for which `keep` returns true.
+    pub fn retain(&mut self, mut keep: impl FnMut(&IVec2, &mut Tile) -> bool) {
+        for cell in &mut self.cells {
+            if let Some((position, tile)) = cell
+                && !keep(position, tile)
+            {
+                *cell = None;
+                self.grid_len -= 1;
+            }
+        }
+        self.overflow.retain(|position, tile| keep(position, tile));
+    }
+}
+
+impl Index<&IVec2> for TileGrid {
+    type Output = Tile;
+
+    /// # Panics
+    ///
+    /// Panics if there is no tile at `position`.
+    fn index(&self, position: &IVec2) -> &Tile {
+        self.get(position)
+            .unwrap_or_else(|| panic!("no tile at {position}"))
+    }
+}
+
+/// Grids are equal when they hold the same tiles, whatever their sizes.
+impl PartialEq for TileGrid {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(position, tile)| other.get(position) == Some(tile))
+    }
+}
+
+impl Eq for TileGrid {}
+
+impl Extend<(IVec2, Tile)> for TileGrid {
+    fn extend<I: IntoIterator<Item = (IVec2, Tile)>>(&mut self, tiles: I) {
+        for (position, tile) in tiles {
+            self.insert(position, tile);
+        }
+    }
+}
+
+/// Collects into a grid sized to fit every tile with a non-negative
+/// position.
+impl FromIterator<(IVec2, Tile)> for TileGrid {
+    fn from_iter<I: IntoIterator<Item = (IVec2, Tile)>>(tiles: I) -> Self {
+        let tiles: Vec<(IVec2, Tile)> = tiles.into_iter().collect();
+        let size = tiles
+            .iter()
+            .filter(|(position, _)| position.cmpge(IVec2::ZERO).all())
+            .fold(UVec2::ZERO, |size, (position, _)| {
+                size.max(position.as_uvec2() + 1)
+            });
+        let mut grid = Self::with_size(size);
+        grid.extend(tiles);
+        grid
+    }
+}
+
+impl From<HashMap<IVec2, Tile>> for TileGrid {
+    fn from(tiles: HashMap<IVec2, Tile>) -> Self {
+        tiles.into_iter().collect()
+    }
+}
+
+impl IntoIterator for TileGrid {
+    type Item = (IVec2, Tile);
+    type IntoIter = Box<dyn Iterator<Item = (IVec2, Tile)>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.cells.into_iter().flatten().chain(self.overflow))
+    }
+}
+
+impl<'a> IntoIterator for &'a TileGrid {
+    type Item = (&'a IVec2, &'a Tile);
+    type IntoIter = Box<dyn Iterator<Item = (&'a IVec2, &'a Tile)> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_tile::{MapTile, TileSet};
+
+    fn tile(map_tile: MapTile) -> Tile {
+        Tile::new(TileSet::Corridor, map_tile)
+    }
+
+    #[test]
+    fn grids_behave_like_hash_maps() {
+        let mut grid = TileGrid::with_size(UVec2::new(3, 2));
+
+        assert_eq!(grid.insert(IVec2::new(2, 1), tile(MapTile::N)), None);
+        assert_eq!(grid.insert(IVec2::new(-1, 0), tile(MapTile::E)), None);
+        assert_eq!(
+            grid.insert(IVec2::new(2, 1), tile(MapTile::S)),
+            Some(tile(MapTile::N))
+        );
+
+        assert_eq!(grid.len(), 2);
+        assert_eq!(grid[&IVec2::new(2, 1)], tile(MapTile::S));
+        assert_eq!(grid.get(&IVec2::new(-1, 0)), Some(&tile(MapTile::E)));
+        assert!(!grid.contains_key(&IVec2::new(0, 0)));
+        grid.retain(|position, _| position.x >= 0);
+        assert_eq!(grid.keys().collect::<Vec<_>>(), [&IVec2::new(2, 1)]);
+        assert_eq!(grid.remove(&IVec2::new(2, 1)), Some(tile(MapTile::S)));
+        assert!(grid.is_empty());
+    }
+
+    #[test]
+    fn grids_iterate_in_row_major_order() {
+        let grid: TileGrid = [IVec2::new(1, 0), IVec2::new(0, 1), IVec2::new(0, 0)]
+            .into_iter()
+            .map(|position| (position, tile(MapTile::ZERO)))
+            .collect();
+
+        assert_eq!(grid.size(), UVec2::new(2, 2));
+        assert_eq!(
+            grid.keys().copied().collect::<Vec<_>>(),
+            [IVec2::new(0, 0), IVec2::new(1, 0), IVec2::new(0, 1)]
+        );
+        let mut sparse = TileGrid::new();
+        sparse.extend(grid.clone());
+        assert_eq!(sparse, grid);
+    }
+}
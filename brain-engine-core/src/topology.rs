@@ -0,0 +1,154 @@
+use crate::exploration::Exploration;
+use crate::map::Map;
+use crate::map_events::MapSubscribers;
+use crate::map_tile::{Direction, Tile};
+use crate::movement::MovementCosts;
+use crate::occupancy::Occupancy;
+use crate::tile_generator::TileGenerator;
+use crate::tile_grid::TileGrid;
+
+use bevy::prelude::*;
+
+/// How the edges of a [`Map`] connect.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Topology {
+    /// The map ends at its edges.
+    #[default]
+    Bounded,
+    /// Leaving the east edge arrives at the west edge and leaving the north
+    /// edge arrives at the south edge, like a Pac-Man level.
+    Toroidal,
+}
+
+impl<G: TileGenerator> Map<G> {
+    /// Like [`Map::new`], but the edges wrap around, see
+    /// [`Topology::Toroidal`]. Exits across the seams line up like any other.
+    pub fn new_toroidal(size: usize, generator: G) -> Self {
+        let mut map = Self {
+            size,
+            x: size,
+            y: size,
+            tiles: TileGrid::with_size(UVec2::splat(size as u32)),
+            generator,
+            topology: Topology::Toroidal,
+            movement_costs: MovementCosts::default(),
+            exploration: Exploration::default(),
+            occupancy: Occupancy::default(),
+            subscribers: MapSubscribers::default(),
+        };
+        map.regenerate();
+        map
+    }
+
+    /// Maps `position` back onto the grid on a toroidal map; bounded maps
+    /// return it unchanged.
+    pub fn wrap(&self, position: IVec2) -> IVec2 {
+        match self.topology {
+            Topology::Bounded => position,
+            Topology::Toroidal => position.rem_euclid(IVec2::new(self.x as i32, self.y as i32)),
+        }
+    }
+
+    /// The position one step from `position` towards `direction`, wrapped
+    /// on toroidal maps. It may be off a bounded map.
+    pub fn adjacent(&self, position: IVec2, direction: Direction) -> IVec2 {
+        self.wrap(position + direction.offset())
+    }
+
+    /// The fewest steps between `from` and `to` on an open grid, taking the
+    /// shorter way around on toroidal maps.
+    pub fn distance(&self, from: IVec2, to: IVec2) -> u32 {
+        let difference = (to - from).abs();
+        match self.topology {
+            Topology::Bounded => (difference.x + difference.y) as u32,
+            Topology::Toroidal => {
+                let x = difference.x.min(self.x as i32 - difference.x);
+                let y = difference.y.min(self.y as i32 - difference.y);
+                (x + y) as u32
+            }
+        }
+    }
+
+    /// Copies `tile` just past the opposite edge when `position` lies on an
+    /// edge of a toroidal map, so a generator looking at direct neighbours
+    /// sees the tile across the seam.
+    pub(crate) fn mirror_across_seams(&mut self, position: IVec2, tile: Tile) {
+        if self.topology != Topology::Toroidal {
+            return;
+        }
+        let size = IVec2::new(self.x as i32, self.y as i32);
+        for axis in [IVec2::X, IVec2::Y] {
+            if position.dot(axis) == 0 {
+                self.tiles.insert(position + size * axis, tile);
+            }
+            if position.dot(axis) == size.dot(axis) - 1 {
+                self.tiles.insert(position - size * axis, tile);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::open_field::OpenFieldGenerator;
+    use crate::testing::GridBuilder;
+    use crate::tile_generator::TileGeneratorDefault;
+
+    /// A single corridor row with exits east and west on every tile.
+    fn ring() -> GridBuilder {
+        GridBuilder::new("    \n    \n    \n────")
+    }
+
+    #[test]
+    fn moving_off_the_east_edge_arrives_at_the_west_edge() {
+        let map = ring().toroidal().build(OpenFieldGenerator);
+
+        assert!(map.can_move(IVec2::new(3, 0), IVec2::new(0, 0)));
+        assert_eq!(
+            map.neighbors(IVec2::new(0, 0)).collect::<Vec<_>>(),
+            vec![IVec2::new(1, 0), IVec2::new(3, 0)]
+        );
+        assert_eq!(
+            map.find_path(IVec2::new(0, 0), IVec2::new(3, 0)),
+            Some(vec![IVec2::new(0, 0), IVec2::new(3, 0)])
+        );
+        assert_eq!(map.tiles.len(), 16);
+    }
+
+    #[test]
+    fn bounded_maps_stop_at_the_edge() {
+        let map = ring().build(OpenFieldGenerator);
+
+        assert!(!map.can_move(IVec2::new(3, 0), IVec2::new(0, 0)));
+        assert_eq!(
+            map.find_path(IVec2::new(0, 0), IVec2::new(3, 0))
+                .map(|path| path.len()),
+            Some(4)
+        );
+    }
+
+    #[test]
+    fn distance_takes_the_short_way_around() {
+        let map = Map::new_toroidal(10, OpenFieldGenerator);
+
+        assert_eq!(map.distance(IVec2::new(1, 1), IVec2::new(9, 8)), 2 + 3);
+        assert_eq!(map.wrap(IVec2::new(-1, 10)), IVec2::new(9, 0));
+    }
+
+    #[test]
+    fn generated_seams_line_up() {
+        let mut crossed = false;
+        for seed in 0..20 {
+            let generator = TileGeneratorDefault::with_seed_and_probabilities(seed, 0.6, 0.3);
+            let mut map = Map::new_toroidal(7, generator);
+            assert_eq!(map.validate(), Ok(()), "seed {seed}");
+
+            map.regenerate_region(IRect::new(0, 0, 1, 6));
+            assert_eq!(map.validate(), Ok(()), "seed {seed} after regenerating");
+            assert_eq!(map.tiles.len(), 49);
+            crossed |= (0..7).any(|y| map.can_move(IVec2::new(6, y), IVec2::new(0, y)));
+        }
+        assert!(crossed, "no generated exit crosses the seam");
+    }
+}
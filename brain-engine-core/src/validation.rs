@@ -0,0 +1,223 @@
+use crate::map::Map;
+use crate::map_tile::Direction;
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use std::fmt;
+
+/// A broken map invariant found by [`Map::validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapViolation {
+    /// A grid position has no tile.
+    MissingTile { position: IVec2 },
+    /// A tile is stored outside the grid.
+    TileOutOfBounds { position: IVec2 },
+    /// An exit leads off the edge of the map.
+    ExitOffMap {
+        position: IVec2,
+        direction: Direction,
+    },
+    /// An exit whose neighbor has no matching exit back.
+    OneWayExit {
+        position: IVec2,
+        direction: Direction,
+    },
+}
+
+impl fmt::Display for MapViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapViolation::MissingTile { position } => write!(f, "no tile at {position}"),
+            MapViolation::TileOutOfBounds { position } => {
+                write!(f, "tile at {position} is outside the map")
+            }
+            MapViolation::ExitOffMap {
+                position,
+                direction,
+            } => write!(f, "exit {direction} of {position} leads off the map"),
+            MapViolation::OneWayExit {
+                position,
+                direction,
+            } => write!(f, "exit {direction} of {position} has no exit back"),
+        }
+    }
+}
+
+impl<G: TileGenerator> Map<G> {
+    /// Checks that every grid position has a tile, that no tile lies outside
+    /// the grid, that no exit leads off the map and that every exit is
+    /// matched by an exit back from the neighbor, across the seams on a
+    /// toroidal map.
+    ///
+    /// Violations are listed in x-then-y order of their position, out of
+    /// bounds tiles last.
+    pub fn validate(&self) -> Result<(), Vec<MapViolation>> {
+        let mut violations = Vec::new();
+
+        for position in self.positions() {
+            let Some(tile) = self.tiles.get(&position) else {
+                violations.push(MapViolation::MissingTile { position });
+                continue;
+            };
+            for direction in tile.directions() {
+                let neighbor = self.adjacent(position, direction);
+                if !self.in_bounds(neighbor) {
+                    violations.push(MapViolation::ExitOffMap {
+                        position,
+                        direction,
+                    });
+                } else if self
+                    .tiles
+                    .get(&neighbor)
+                    .is_some_and(|other| !other.map_tile.contains(direction.opposite()))
+                {
+                    violations.push(MapViolation::OneWayExit {
+                        position,
+                        direction,
+                    });
+                }
+            }
+        }
+
+        let mut outside: Vec<IVec2> = self
+            .tiles
+            .keys()
+            .copied()
+            .filter(|position| !self.in_bounds(*position))
+            .collect();
+        outside.sort_by_key(|position| (position.x, position.y));
+        violations.extend(
+            outside
+                .into_iter()
+                .map(|position| MapViolation::TileOutOfBounds { position }),
+        );
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_tile::{MapTile, Tile, TileSet};
+    use crate::tile_generator::{RandomDrawMode, TileGeneratorDefault};
+    use crate::tile_grid::TileGrid;
+    use proptest::prelude::*;
+
+    /// Every tile has a single exit, to the east.
+    struct EastOnly;
+
+    impl TileGenerator for EastOnly {
+        fn tile_at(&self, _tiles: &TileGrid, _location: IVec2) -> Tile {
+            Tile::new(TileSet::Corridor, MapTile::E)
+        }
+    }
+
+    #[test]
+    fn every_violation_is_reported() {
+        let mut map = Map::new(2, EastOnly);
+        map.tiles.remove(&IVec2::new(0, 1));
+        map.tiles
+            .insert(IVec2::new(5, 5), Tile::new(TileSet::Room, MapTile::ZERO));
+
+        assert_eq!(
+            map.validate(),
+            Err(vec![
+                MapViolation::OneWayExit {
+                    position: IVec2::new(0, 0),
+                    direction: Direction::East,
+                },
+                MapViolation::MissingTile {
+                    position: IVec2::new(0, 1),
+                },
+                MapViolation::ExitOffMap {
+                    position: IVec2::new(1, 0),
+                    direction: Direction::East,
+                },
+                MapViolation::ExitOffMap {
+                    position: IVec2::new(1, 1),
+                    direction: Direction::East,
+                },
+                MapViolation::TileOutOfBounds {
+                    position: IVec2::new(5, 5),
+                },
+            ])
+        );
+        assert_eq!(
+            MapViolation::OneWayExit {
+                position: IVec2::new(0, 0),
+                direction: Direction::East,
+            }
+            .to_string(),
+            "exit East of [0, 0] has no exit back"
+        );
+    }
+
+    #[test]
+    fn edge_exits_are_reported_when_allowed() {
+        let generator = TileGeneratorDefault::builder()
+            .seed(1)
+            .tile_exit_probability(1.0)
+            .allow_edge_exits(true)
+            .build();
+
+        let violations = Map::new(4, generator).validate().unwrap_err();
+
+        assert!(
+            violations
+                .iter()
+                .all(|violation| matches!(violation, MapViolation::ExitOffMap { .. }))
+        );
+    }
+
+    #[test]
+    fn closed_maps_are_valid() {
+        let generator = TileGeneratorDefault::builder()
+            .seed(1)
+            .tile_exit_probability(0.0)
+            .build();
+
+        assert_eq!(Map::new(6, generator).validate(), Ok(()));
+    }
+
+    proptest! {
+        #[test]
+        fn generated_maps_keep_their_invariants(
+            seed: u64,
+            size in 1usize..24,
+            tile_exit_probability in 0.0f64..=1.0,
+            room_probability in 0.0f64..=1.0,
+            legacy: bool,
+        ) {
+            let generator = TileGeneratorDefault::builder()
+                .seed(seed)
+                .tile_exit_probability(tile_exit_probability)
+                .room_probability(room_probability)
+                .draw_mode(if legacy { RandomDrawMode::Legacy } else { RandomDrawMode::Fixed })
+                .build();
+            let map = Map::new(size, generator);
+
+            prop_assert_eq!(map.tiles.len(), size * size);
+            prop_assert_eq!(map.validate(), Ok(()));
+        }
+
+        #[test]
+        fn regenerated_regions_keep_their_invariants(
+            seed: u64,
+            min_x in 0i32..12,
+            min_y in 0i32..12,
+            width in 0i32..12,
+            height in 0i32..12,
+        ) {
+            let mut map = Map::new(12, TileGeneratorDefault::with_seed(seed));
+
+            map.regenerate_region(IRect::new(min_x, min_y, min_x + width, min_y + height));
+
+            prop_assert_eq!(map.validate(), Ok(()));
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use crate::map::Map;
+use crate::map_tile::Direction;
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use itertools::iproduct;
+use std::collections::HashSet;
+
+impl<G: TileGenerator> Map<G> {
+    /// Returns true when a straight line from the center of `from` to the
+    /// center of `to` crosses only open edges: every tile boundary on the
+    /// way must allow [`Map::can_move`], so missing exits act as walls. A
+    /// line through a corner gets past when either way around it is open.
+    ///
+    /// Symmetric: `to` can see `from` exactly when `from` can see `to`.
+    pub fn line_of_sight(&self, from: IVec2, to: IVec2) -> bool {
+        self.in_bounds(from) && self.in_bounds(to) && self.ray_is_clear(from, to - from)
+    }
+
+    /// Every tile `origin` can see within `radius` tiles (Euclidean), by
+    /// [`Map::line_of_sight`], including `origin` itself. On toroidal maps
+    /// sight carries across the seams. Empty when `origin` is off the map.
+    pub fn visible_from(&self, origin: IVec2, radius: u32) -> HashSet<IVec2> {
+        if !self.in_bounds(origin) {
+            return HashSet::new();
+        }
+        let radius = radius as i32;
+        let mut visible = HashSet::new();
+        for (x, y) in iproduct!(-radius..=radius, -radius..=radius) {
+            let delta = IVec2::new(x, y);
+            if delta.length_squared() > radius * radius {
+                continue;
+            }
+            let target = self.wrap(origin + delta);
+            if self.in_bounds(target) && self.ray_is_clear(origin, delta) {
+                visible.insert(target);
+            }
+        }
+        visible
+    }
+
+    /// Walks the tiles a line from the center of `from` along `delta`
+    /// passes through, failing at the first closed edge.
+    fn ray_is_clear(&self, from: IVec2, delta: IVec2) -> bool {
+        let steps = delta.abs();
+        let x_step = if delta.x < 0 {
+            Direction::West
+        } else {
+            Direction::East
+        };
+        let y_step = if delta.y < 0 {
+            Direction::South
+        } else {
+            Direction::North
+        };
+
+        let mut current = from;
+        let (mut x_taken, mut y_taken) = (0, 0);
+        while x_taken < steps.x || y_taken < steps.y {
+            // Compare where the line crosses the next vertical and the next
+            // horizontal tile boundary, in units of 1 / (2 * steps.x * steps.y).
+            let next_x = (1 + 2 * x_taken) * steps.y;
+            let next_y = (1 + 2 * y_taken) * steps.x;
+            let (moved, next) = if y_taken == steps.y || (x_taken < steps.x && next_x < next_y) {
+                x_taken += 1;
+                (
+                    self.can_step(current, x_step),
+                    self.adjacent(current, x_step),
+                )
+            } else if x_taken == steps.x || next_y < next_x {
+                y_taken += 1;
+                (
+                    self.can_step(current, y_step),
+                    self.adjacent(current, y_step),
+                )
+            } else {
+                x_taken += 1;
+                y_taken += 1;
+                let via_x = self.adjacent(current, x_step);
+                let via_y = self.adjacent(current, y_step);
+                let open = (self.can_step(current, x_step) && self.can_step(via_x, y_step))
+                    || (self.can_step(current, y_step) && self.can_step(via_y, x_step));
+                (open, self.adjacent(via_x, y_step))
+            };
+            if !moved {
+                return false;
+            }
+            current = next;
+        }
+        true
+    }
+
+    fn can_step(&self, position: IVec2, direction: Direction) -> bool {
+        self.can_move(position, self.adjacent(position, direction))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::open_field::OpenFieldGenerator;
+    use crate::testing::GridBuilder;
+    use crate::tile_generator::TileGeneratorDefault;
+
+    #[test]
+    fn open_rooms_are_visible_up_to_the_radius() {
+        let generator = TileGeneratorDefault::builder()
+            .tile_exit_probability(1.0)
+            .room_probability(1.0)
+            .build();
+        let map = Map::new(9, generator);
+
+        let visible = map.visible_from(IVec2::new(4, 4), 2);
+
+        assert_eq!(visible.len(), 13);
+        assert!(visible.contains(&IVec2::new(4, 6)));
+        assert!(!visible.contains(&IVec2::new(6, 6)));
+    }
+
+    #[test]
+    fn walls_between_tiles_block_sight() {
+        // An open room with a wall between columns 2 and 3 on rows 0 to 2.
+        let map = GridBuilder::new("╋╋╋╋╋╋\n╋╋╋╋╋╋\n╋╋╋╋╋╋\n╋╋┫┣╋╋\n╋╋┫┣╋╋\n╋╋┫┣╋╋")
+            .build(OpenFieldGenerator);
+
+        assert!(!map.line_of_sight(IVec2::new(0, 1), IVec2::new(5, 1)));
+        assert!(map.line_of_sight(IVec2::new(0, 4), IVec2::new(5, 4)));
+        // Over the top of the wall.
+        assert!(map.line_of_sight(IVec2::new(2, 2), IVec2::new(3, 3)));
+        assert!(
+            !map.visible_from(IVec2::new(1, 0), 3)
+                .contains(&IVec2::new(4, 0))
+        );
+    }
+
+    #[test]
+    fn sight_wraps_around_toroidal_maps() {
+        let generator = TileGeneratorDefault::builder()
+            .tile_exit_probability(1.0)
+            .build();
+        let map = Map::new_toroidal(8, generator);
+
+        assert!(
+            map.visible_from(IVec2::new(0, 0), 1)
+                .contains(&IVec2::new(7, 0))
+        );
+    }
+
+    #[test]
+    fn line_of_sight_is_symmetric() {
+        let map = Map::new(
+            10,
+            TileGeneratorDefault::with_seed_and_probabilities(5, 0.8, 0.6),
+        );
+
+        for position in map.positions() {
+            for other in map.positions() {
+                assert_eq!(
+                    map.line_of_sight(position, other),
+                    map.line_of_sight(other, position),
+                    "{position} and {other}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use crate::map::Map;
+use crate::map_tile::Direction;
+use crate::tile_generator::TileGenerator;
+
+use bevy::prelude::*;
+use itertools::iproduct;
+use std::collections::BTreeMap;
+
+/// A straight, axis-aligned wall measured in tile units.
+///
+/// Tile `(x, y)` occupies the unit square from `(x, y)` to `(x + 1, y + 1)`, so
+/// wall end points always land on tile corners.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WallSegment {
+    pub start: Vec2,
+    pub end: Vec2,
+}
+
+impl WallSegment {
+    pub fn length(&self) -> f32 {
+        self.start.distance(self.end)
+    }
+}
+
+/// Wall outline of a whole map, built from each tile's exit mask.
+///
+/// A wall is placed on every tile edge that cannot be crossed, and collinear
+/// neighbouring walls are merged so long straight runs become one segment.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WallGeometry {
+    segments: Vec<WallSegment>,
+}
+
+impl WallGeometry {
+    /// Builds the merged wall outline for `map`.
+    ///
+    /// An edge shared by two tiles is open only when both tiles have an exit
+    /// through it. An edge on the map border is open when the tile has an exit
+    /// leading off the map.
+    pub fn from_map<G: TileGenerator>(map: &Map<G>) -> Self {
+        // Unit walls keyed by the line they sit on, holding the start offset
+        // along that line, so merging is a single sorted sweep per line.
+        let mut horizontal: BTreeMap<i32, Vec<i32>> = BTreeMap::new();
+        let mut vertical: BTreeMap<i32, Vec<i32>> = BTreeMap::new();
+
+        for (x, y) in iproduct!(0..map.x as i32, 0..map.y as i32) {
+            let position = IVec2::new(x, y);
+            // Each tile owns its south and west edges; the north and east
+            // edges are only owned on the map border, so shared edges are
+            // considered exactly once.
+            if is_wall(map, position, Direction::South) {
+                horizontal.entry(y).or_default().push(x);
+            }
+            if is_wall(map, position, Direction::West) {
+                vertical.entry(x).or_default().push(y);
+            }
+            if y == map.y as i32 - 1 && is_wall(map, position, Direction::North) {
+                horizontal.entry(y + 1).or_default().push(x);
+            }
+            if x == map.x as i32 - 1 && is_wall(map, position, Direction::East) {
+                vertical.entry(x + 1).or_default().push(y);
+            }
+        }
+
+        let mut segments = Vec::new();
+        for (y, starts) in horizontal {
+            for (from, to) in merge_runs(starts) {
+                segments.push(WallSegment {
+                    start: Vec2::new(from as f32, y as f32),
+                    end: Vec2::new(to as f32, y as f32),
+                });
+            }
+        }
+        for (x, starts) in vertical {
+            for (from, to) in merge_runs(starts) {
+                segments.push(WallSegment {
+                    start: Vec2::new(x as f32, from as f32),
+                    end: Vec2::new(x as f32, to as f32),
+                });
+            }
+        }
+
+        Self { segments }
+    }
+
+    /// Returns the merged wall segments.
+    pub fn segments(&self) -> &[WallSegment] {
+        &self.segments
+    }
+
+    /// Total length of all walls, in tile units.
+    pub fn total_length(&self) -> f32 {
+        self.segments.iter().map(WallSegment::length).sum()
+    }
+
+    /// Expands every segment into an axis-aligned rectangle of the given
+    /// thickness, suitable for filled rendering or static physics colliders.
+    ///
+    /// Rectangles extend half the thickness past each end so corners close.
+    pub fn to_rects(&self, thickness: f32) -> Vec<Rect> {
+        let half = thickness / 2.0;
+        self.segments
+            .iter()
+            .map(|segment| Rect::from_corners(segment.start - half, segment.end + half))
+            .collect()
+    }
+}
+
+fn is_wall<G: TileGenerator>(map: &Map<G>, position: IVec2, direction: Direction) -> bool {
+    let Some(tile) = map.tiles.get(&position) else {
+        return true;
+    };
+    if !tile.map_tile.directions().contains(&direction) {
+        return true;
+    }
+
+    let neighbor = position + direction.offset();
+    match map.tiles.get(&neighbor) {
+        Some(neighbor_tile) => !neighbor_tile
+            .map_tile
+            .directions()
+            .contains(&direction.opposite()),
+        None => false,
+    }
+}
+
+/// Collapses unit wall offsets into `(start, end)` runs of consecutive units.
+fn merge_runs(mut starts: Vec<i32>) -> Vec<(i32, i32)> {
+    starts.sort_unstable();
+    let mut runs: Vec<(i32, i32)> = Vec::new();
+    for start in starts {
+        match runs.last_mut() {
+            Some((_, end)) if *end == start => *end = start + 1,
+            _ => runs.push((start, start + 1)),
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map_tile::{MapTile, Tile, TileSet};
+    use crate::open_field::OpenFieldGenerator;
+    use crate::testing::GridBuilder;
+
+    #[test]
+    fn closed_tile_is_boxed_in() {
+        let map = GridBuilder::new("▪").build(OpenFieldGenerator);
+        let geometry = WallGeometry::from_map(&map);
+
+        assert_eq!(geometry.segments().len(), 4);
+        assert_eq!(geometry.total_length(), 4.0);
+    }
+
+    #[test]
+    fn open_grid_only_has_an_outer_outline_when_exits_are_sealed() {
+        let map = GridBuilder::new("┏┓\n┗┛").build(OpenFieldGenerator);
+
+        let geometry = WallGeometry::from_map(&map);
+
+        // Four merged border walls, each two tiles long.
+        assert_eq!(geometry.segments().len(), 4);
+        assert!(
+            geometry
+                .segments()
+                .iter()
+                .all(|segment| segment.length() == 2.0)
+        );
+    }
+
+    #[test]
+    fn one_sided_exit_still_produces_a_wall() {
+        // GridBuilder rejects an exit without one back, so set it by hand.
+        let mut map = GridBuilder::new("▪▪\n▪▪").build(OpenFieldGenerator);
+        map.tiles
+            .insert(IVec2::new(0, 0), Tile::new(TileSet::Room, MapTile::E));
+
+        let geometry = WallGeometry::from_map(&map);
+
+        assert!(geometry.segments().contains(&WallSegment {
+            start: Vec2::new(1.0, 0.0),
+            end: Vec2::new(1.0, 2.0),
+        }));
+    }
+
+    #[test]
+    fn exits_off_the_map_leave_the_border_open() {
+        let map = GridBuilder::new("╋").build(OpenFieldGenerator);
+        let geometry = WallGeometry::from_map(&map);
+
+        assert!(geometry.segments().is_empty());
+    }
+
+    #[test]
+    fn rects_close_corners() {
+        let map = GridBuilder::new("▪").build(OpenFieldGenerator);
+        let rects = WallGeometry::from_map(&map).to_rects(0.2);
+
+        assert_eq!(rects.len(), 4);
+        for rect in rects {
+            assert!((rect.width().max(rect.height()) - 1.2).abs() < 1e-6);
+            assert!((rect.width().min(rect.height()) - 0.2).abs() < 1e-6);
+        }
+    }
+}
@@ -0,0 +1,191 @@
+//! Golden map regression harness.
+//!
+//! Every `tests/golden/*.map` fixture is a map file whose leading comments
+//! record how it was produced:
+//!
+//! ```text
+//! # crate-version: 0.1.0
+//! # draw-mode: fixed
+//! # edge-exits: closed
+//! # generator: default
+//! # seed: 42
+//! # size: 16
+//! # tile-exit-probability: 0.35
+//! # room-probability: 0.35
+//! ```
+//!
+//! `draw-mode` is `fixed`, `legacy` or `position-hashed`, see
+//! [`RandomDrawMode`]. `edge-exits` is `closed` or `allow`, see
+//! [`TileGeneratorDefault::allow_edge_exits`].
+//!
+//! The harness regenerates each fixture with the current code and fails with
+//! a per-tile diff when the output differs. After an intentional generator
+//! change, review the diff and re-bless the fixtures:
+//!
+//! ```shell
+//! BLESS_GOLDEN_MAPS=1 cargo test -p brain-engine-core --test golden_maps
+//! ```
+
+use bevy::prelude::*;
+use brain_engine_core::{Map, MapFile, RandomDrawMode, TileExtensions, TileGeneratorDefault};
+use itertools::iproduct;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Maximum number of differing tiles listed per fixture.
+const MAX_REPORTED_TILES: usize = 10;
+
+struct Provenance {
+    fields: BTreeMap<String, String>,
+}
+
+impl Provenance {
+    fn read(text: &str) -> Self {
+        let fields = text
+            .lines()
+            .map_while(|line| line.strip_prefix('#'))
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect();
+        Self { fields }
+    }
+
+    fn field<T: std::str::FromStr>(&self, fixture: &Path, key: &str) -> T {
+        self.fields
+            .get(key)
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_else(|| panic!("{}: missing or invalid '{key}'", fixture.display()))
+    }
+
+    fn generate(&self, fixture: &Path) -> Map<TileGeneratorDefault> {
+        let generator: String = self.field(fixture, "generator");
+        assert_eq!(
+            generator,
+            "default",
+            "{}: unknown generator",
+            fixture.display()
+        );
+        let draw_mode: String = self.field(fixture, "draw-mode");
+        let draw_mode = match draw_mode.as_str() {
+            "fixed" => RandomDrawMode::Fixed,
+            "legacy" => RandomDrawMode::Legacy,
+            "position-hashed" => RandomDrawMode::PositionHashed,
+            _ => panic!("{}: unknown draw mode '{draw_mode}'", fixture.display()),
+        };
+        let edge_exits: String = self.field(fixture, "edge-exits");
+        let allow_edge_exits = match edge_exits.as_str() {
+            "closed" => false,
+            "allow" => true,
+            _ => panic!("{}: unknown edge exits '{edge_exits}'", fixture.display()),
+        };
+        let generator = TileGeneratorDefault::builder()
+            .seed(self.field(fixture, "seed"))
+            .tile_exit_probability(self.field(fixture, "tile-exit-probability"))
+            .room_probability(self.field(fixture, "room-probability"))
+            .draw_mode(draw_mode)
+            .allow_edge_exits(allow_edge_exits)
+            .build();
+        Map::new(self.field(fixture, "size"), generator)
+    }
+
+    /// Writes the provenance comments with the current crate version.
+    fn header(&self) -> String {
+        let mut fields = self.fields.clone();
+        fields.insert(
+            "crate-version".to_string(),
+            env!("CARGO_PKG_VERSION").to_string(),
+        );
+        let mut header = format!("# crate-version: {}\n", fields["crate-version"]);
+        for (key, value) in fields.iter().filter(|(key, _)| *key != "crate-version") {
+            header.push_str(&format!("# {key}: {value}\n"));
+        }
+        header
+    }
+}
+
+fn fixtures() -> Vec<PathBuf> {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let mut fixtures: Vec<PathBuf> = fs::read_dir(&directory)
+        .unwrap_or_else(|error| panic!("cannot read {}: {error}", directory.display()))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "map"))
+        .collect();
+    fixtures.sort();
+    fixtures
+}
+
+/// Describes how `actual` differs from `expected`, or `None` when they match.
+fn diff(expected: &MapFile, actual: &MapFile) -> Option<String> {
+    if (expected.x, expected.y) != (actual.x, actual.y) {
+        return Some(format!(
+            "  size changed from {}x{} to {}x{}",
+            expected.x, expected.y, actual.x, actual.y
+        ));
+    }
+
+    let changed: Vec<IVec2> = iproduct!(0..expected.x as i32, 0..expected.y as i32)
+        .map(|(x, y)| IVec2::new(x, y))
+        .filter(|position| expected.tiles.get(position) != actual.tiles.get(position))
+        .collect();
+    if changed.is_empty() {
+        return None;
+    }
+
+    let mut report = format!(
+        "  {} of {} tiles changed",
+        changed.len(),
+        expected.tiles.len()
+    );
+    for position in changed.iter().take(MAX_REPORTED_TILES) {
+        let expected = expected.tiles[position];
+        let actual = actual.tiles[position];
+        report.push_str(&format!(
+            "\n  {position}: {} {} -> {} {}",
+            expected.tile_set, expected.map_tile, actual.tile_set, actual.map_tile
+        ));
+    }
+    if changed.len() > MAX_REPORTED_TILES {
+        report.push_str(&format!(
+            "\n  ... and {} more",
+            changed.len() - MAX_REPORTED_TILES
+        ));
+    }
+    Some(report)
+}
+
+#[test]
+fn golden_maps_match_current_generator() {
+    let bless = std::env::var_os("BLESS_GOLDEN_MAPS").is_some();
+    let fixtures = fixtures();
+    assert!(!fixtures.is_empty(), "no golden map fixtures found");
+
+    let mut failures = Vec::new();
+    for fixture in &fixtures {
+        let text = fs::read_to_string(fixture).unwrap();
+        let provenance = Provenance::read(&text);
+        let actual = MapFile::from_map(&provenance.generate(fixture), TileExtensions::new());
+
+        if bless {
+            fs::write(fixture, provenance.header() + &actual.write()).unwrap();
+            continue;
+        }
+
+        let expected =
+            MapFile::parse(&text).unwrap_or_else(|error| panic!("{}: {error}", fixture.display()));
+        if let Some(report) = diff(&expected, &actual) {
+            let version: String = provenance.field(fixture, "crate-version");
+            failures.push(format!(
+                "{} (blessed by {version}):\n{report}",
+                fixture.display()
+            ));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "generated maps differ from golden fixtures; re-run with BLESS_GOLDEN_MAPS=1 \
+         if the change is intended\n\n{}",
+        failures.join("\n\n")
+    );
+}
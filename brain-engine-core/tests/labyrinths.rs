@@ -0,0 +1,187 @@
+//! Table-driven pathfinding tests against a corpus of hand-drawn labyrinths.
+//!
+//! Every `tests/labyrinths/*.map` fixture is a map file whose leading
+//! comments describe the labyrinth and list the ground truth for it:
+//!
+//! ```text
+//! # A corridor snaking back and forth across five rows.
+//! # path: 0 4 -> 0 0 = 24
+//! # unreachable: 0 0 -> 3 1
+//! # area: 0 0 = 30
+//! ```
+//!
+//! `path` gives the number of steps on a shortest path, `unreachable` a pair
+//! with no path, and `area` the number of tiles reachable from a tile; the
+//! `area` lines cover every connected area of the labyrinth. Every path
+//! feature is checked against the same table, so a new one only needs a
+//! case in [`check`].
+
+use bevy::prelude::*;
+use brain_engine_core::{
+    IncrementalPathfinder, Map, MapFile, PathfindingStatus, TileGeneratorDefault,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug)]
+enum Expectation {
+    Path {
+        from: IVec2,
+        to: IVec2,
+        steps: usize,
+    },
+    Unreachable {
+        from: IVec2,
+        to: IVec2,
+    },
+    Area {
+        from: IVec2,
+        tiles: usize,
+    },
+}
+
+struct Labyrinth {
+    name: String,
+    map: Map<TileGeneratorDefault>,
+    expectations: Vec<Expectation>,
+}
+
+impl Labyrinth {
+    fn load(fixture: &Path) -> Self {
+        let name = fixture.file_stem().unwrap().to_string_lossy().into_owned();
+        let text = fs::read_to_string(fixture).unwrap();
+        let (map, _) = MapFile::parse(&text)
+            .unwrap_or_else(|error| panic!("{name}: {error}"))
+            .into_map(TileGeneratorDefault::new());
+        let expectations = text
+            .lines()
+            .map_while(|line| line.strip_prefix('#'))
+            .filter_map(|line| line.split_once(':'))
+            .map(|(kind, value)| parse_expectation(&name, kind.trim(), value))
+            .collect();
+        Self {
+            name,
+            map,
+            expectations,
+        }
+    }
+}
+
+fn parse_expectation(name: &str, kind: &str, value: &str) -> Expectation {
+    let numbers: Vec<i32> = value
+        .split(|character: char| !character.is_ascii_digit())
+        .filter(|number| !number.is_empty())
+        .map(|number| number.parse().unwrap())
+        .collect();
+    match (kind, numbers.as_slice()) {
+        ("path", &[x, y, to_x, to_y, steps]) => Expectation::Path {
+            from: IVec2::new(x, y),
+            to: IVec2::new(to_x, to_y),
+            steps: steps as usize,
+        },
+        ("unreachable", &[x, y, to_x, to_y]) => Expectation::Unreachable {
+            from: IVec2::new(x, y),
+            to: IVec2::new(to_x, to_y),
+        },
+        ("area", &[x, y, tiles]) => Expectation::Area {
+            from: IVec2::new(x, y),
+            tiles: tiles as usize,
+        },
+        _ => panic!("{name}: invalid expectation '{kind}:{value}'"),
+    }
+}
+
+fn labyrinths() -> Vec<Labyrinth> {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/labyrinths");
+    let mut fixtures: Vec<PathBuf> = fs::read_dir(&directory)
+        .unwrap_or_else(|error| panic!("cannot read {}: {error}", directory.display()))
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "map"))
+        .collect();
+    fixtures.sort();
+    fixtures
+        .iter()
+        .map(|fixture| Labyrinth::load(fixture))
+        .collect()
+}
+
+/// Runs every path feature on one expectation.
+fn check(labyrinth: &Labyrinth, expectation: &Expectation) {
+    let map = &labyrinth.map;
+    let context = format!("{}: {expectation:?}", labyrinth.name);
+    match *expectation {
+        Expectation::Path { from, to, steps } => {
+            let path = map.find_path(from, to).expect(&context);
+            assert_eq!(path.len(), steps + 1, "{context}");
+            assert_eq!((path[0], path[steps]), (from, to), "{context}");
+            assert!(
+                path.windows(2).all(|pair| map.can_move(pair[0], pair[1])),
+                "{context}"
+            );
+            let back = map.find_path(to, from).expect(&context);
+            assert_eq!(back.len(), path.len(), "{context}");
+            assert_eq!(incremental_path(map, from, to), Some(path), "{context}");
+            assert!(map.reachable_from(from).contains(&to), "{context}");
+            let distances = map.dijkstra_map(&[from]);
+            assert_eq!(distances.get(&to), Some(&(steps as u32)), "{context}");
+        }
+        Expectation::Unreachable { from, to } => {
+            assert_eq!(map.find_path(from, to), None, "{context}");
+            assert_eq!(map.find_path(to, from), None, "{context}");
+            assert_eq!(incremental_path(map, from, to), None, "{context}");
+            assert!(!map.reachable_from(from).contains(&to), "{context}");
+            assert_eq!(map.dijkstra_map(&[from]).get(&to), None, "{context}");
+        }
+        Expectation::Area { from, tiles } => {
+            assert_eq!(map.reachable_from(from).len(), tiles, "{context}");
+            assert_eq!(map.dijkstra_map(&[from]).len(), tiles, "{context}");
+        }
+    }
+}
+
+/// Runs an [`IncrementalPathfinder`] a few tiles per step until it finishes.
+fn incremental_path(map: &Map<TileGeneratorDefault>, from: IVec2, to: IVec2) -> Option<Vec<IVec2>> {
+    let mut pathfinder = IncrementalPathfinder::new(map, from, to);
+    loop {
+        match pathfinder.step(map, 3) {
+            PathfindingStatus::InProgress => continue,
+            PathfindingStatus::Found(path) => return Some(path),
+            PathfindingStatus::NotFound => return None,
+        }
+    }
+}
+
+#[test]
+fn paths_match_the_labyrinth_corpus() {
+    let labyrinths = labyrinths();
+    assert!(!labyrinths.is_empty(), "no labyrinth fixtures found");
+
+    for labyrinth in &labyrinths {
+        assert_eq!(labyrinth.map.validate(), Ok(()), "{}", labyrinth.name);
+        assert!(
+            !labyrinth.expectations.is_empty(),
+            "{}: no expectations",
+            labyrinth.name
+        );
+        for expectation in &labyrinth.expectations {
+            check(labyrinth, expectation);
+        }
+    }
+}
+
+#[test]
+fn area_lines_cover_every_walkable_tile() {
+    for labyrinth in labyrinths() {
+        let stats = labyrinth.map.stats();
+        let walkable = stats.tile_count - stats.closed_tiles;
+        let covered: usize = labyrinth
+            .expectations
+            .iter()
+            .filter_map(|expectation| match expectation {
+                Expectation::Area { tiles, .. } => Some(tiles),
+                _ => None,
+            })
+            .sum();
+        assert_eq!(covered, walkable, "{}", labyrinth.name);
+    }
+}
@@ -0,0 +1,401 @@
+//! A level editor for brain-engine maps.
+//!
+//! Run with `cargo run -p brain-engine-editor -- [map file] [size]`. The map
+//! file is loaded when it exists and is where the map is saved; otherwise a
+//! new map of `size` tiles is generated. A map file that exists but can't be
+//! loaded is an error, so it is never overwritten by a new map.
+//!
+//! * Left click - apply the current tool to the tile under the cursor.
+//! * `1` - exits tool: toggle the exit on the side of the tile nearest the
+//!   cursor, and the matching exit of the neighbor.
+//! * `2` - tile set tool: cycle the tile between corridor, room and field.
+//! * `3` - prefab tool: stamp a prefab with its bottom-left cell on the tile.
+//!   Press again to pick the next prefab.
+//! * `R` - regenerate the map.
+//! * Ctrl+Z / Ctrl+Y - undo and redo.
+//! * Ctrl+S / Ctrl+O - save the map file and load it back.
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use brain_engine_core::{
+    Direction, FileNameResolver, Map, MapFile, MapHistory, MapTile, Prefab, Screen,
+    TextureResolver, Tile, TileExtensions, TileGeneratorDefault, TileSet,
+};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+const TILE_SIZE: f32 = 64.0;
+const WINDOW_SIZE: f32 = 768.0;
+const DEFAULT_GRID_SIZE: usize = 12;
+const DEFAULT_MAP_FILE: &str = "level.map";
+
+/// Prefabs offered by the prefab tool, written with the glyphs of
+/// `Map::render_ascii`.
+const PREFABS: [(&str, &str); 3] = [
+    ("vault", "┏┳┓\n┣╋┫\n┗┻┛"),
+    ("crossroads", "?│?\n─┼─\n?│?"),
+    ("clearing", "...\n.#.\n..."),
+];
+
+/// Field tiles have no textures of their own, so they are drawn as rooms in
+/// these colors.
+const FIELD_COLOR: Color = Color::srgb(0.55, 0.85, 0.45);
+const OBSTACLE_COLOR: Color = Color::srgb(0.3, 0.3, 0.3);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tool {
+    Exits,
+    TileSets,
+    /// Stamps the prefab at this index of [`Editor::prefabs`].
+    Prefab(usize),
+}
+
+#[derive(Resource)]
+struct Editor {
+    history: MapHistory<TileGeneratorDefault>,
+    path: PathBuf,
+    tool: Tool,
+    prefabs: Vec<(&'static str, Prefab)>,
+    /// Whether the map changed since it was last saved or loaded.
+    unsaved: bool,
+}
+
+impl fmt::Display for Editor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let tool = match self.tool {
+            Tool::Exits => "exits",
+            Tool::TileSets => "tile sets",
+            Tool::Prefab(index) => self.prefabs[index].0,
+        };
+        let unsaved = if self.unsaved { "*" } else { "" };
+        write!(f, "{tool} - {}{unsaved}", self.path.display())
+    }
+}
+
+/// Marks a sprite that draws one map tile.
+#[derive(Component)]
+struct EditorTile;
+
+fn main() -> AppExit {
+    let mut arguments = std::env::args().skip(1);
+    let path = PathBuf::from(arguments.next().unwrap_or(DEFAULT_MAP_FILE.to_string()));
+    let grid_size = arguments
+        .next()
+        .and_then(|size| size.parse().ok())
+        .unwrap_or(DEFAULT_GRID_SIZE);
+
+    let map = if path.exists() {
+        match load(&path) {
+            Ok(map) => map,
+            Err(error) => {
+                eprintln!("could not load {error}");
+                return AppExit::error();
+            }
+        }
+    } else {
+        info!("starting a new map: {} does not exist", path.display());
+        Map::new(grid_size, TileGeneratorDefault::new())
+    };
+    let prefabs = PREFABS
+        .into_iter()
+        .map(|(name, glyphs)| (name, glyphs.parse().expect("built-in prefabs are valid")))
+        .collect();
+    let screen = Screen::new(UVec2::new(map.x as u32, map.y as u32), TILE_SIZE);
+
+    App::new()
+        .add_plugins(
+            DefaultPlugins
+                .set(WindowPlugin {
+                    primary_window: Some(Window {
+                        resolution: (WINDOW_SIZE, WINDOW_SIZE).into(),
+                        resizable: false,
+                        ..default()
+                    }),
+                    ..default()
+                })
+                .set(AssetPlugin {
+                    file_path: "../brain-engine-bin/assets".to_string(),
+                    ..default()
+                }),
+        )
+        .insert_resource(Editor {
+            history: MapHistory::new(map),
+            path,
+            tool: Tool::Exits,
+            prefabs,
+            unsaved: false,
+        })
+        .insert_resource(screen)
+        .add_systems(Startup, setup_camera)
+        .add_systems(
+            Update,
+            (
+                (switch_tool, edit_history, save_and_load, paint),
+                (redraw_tiles, update_title).run_if(resource_changed::<Editor>),
+            )
+                .chain(),
+        )
+        .run()
+}
+
+fn setup_camera(mut commands: Commands) {
+    commands.spawn(Camera2d);
+}
+
+fn load(path: &PathBuf) -> Result<Map<TileGeneratorDefault>, String> {
+    let text = fs::read_to_string(path).map_err(|error| format!("{}: {error}", path.display()))?;
+    let file = MapFile::parse(&text).map_err(|error| format!("{}: {error}", path.display()))?;
+    Ok(file.into_map(TileGeneratorDefault::new()).0)
+}
+
+fn control_pressed(keys: &ButtonInput<KeyCode>) -> bool {
+    keys.any_pressed([
+        KeyCode::ControlLeft,
+        KeyCode::ControlRight,
+        KeyCode::SuperLeft,
+        KeyCode::SuperRight,
+    ])
+}
+
+fn switch_tool(keys: Res<ButtonInput<KeyCode>>, mut editor: ResMut<Editor>) {
+    let tool = if keys.just_pressed(KeyCode::Digit1) {
+        Tool::Exits
+    } else if keys.just_pressed(KeyCode::Digit2) {
+        Tool::TileSets
+    } else if keys.just_pressed(KeyCode::Digit3) {
+        match editor.tool {
+            Tool::Prefab(index) => Tool::Prefab((index + 1) % editor.prefabs.len()),
+            _ => Tool::Prefab(0),
+        }
+    } else {
+        return;
+    };
+    editor.tool = tool;
+}
+
+fn edit_history(keys: Res<ButtonInput<KeyCode>>, mut editor: ResMut<Editor>) {
+    let changed = if control_pressed(&keys) && keys.just_pressed(KeyCode::KeyZ) {
+        editor.history.undo()
+    } else if control_pressed(&keys) && keys.just_pressed(KeyCode::KeyY) {
+        editor.history.redo()
+    } else if !control_pressed(&keys) && keys.just_pressed(KeyCode::KeyR) {
+        editor.history.edit(|map| map.regenerate());
+        true
+    } else {
+        return;
+    };
+    if changed {
+        editor.unsaved = true;
+    }
+}
+
+fn save_and_load(keys: Res<ButtonInput<KeyCode>>, mut editor: ResMut<Editor>) {
+    if !control_pressed(&keys) {
+        return;
+    }
+    if keys.just_pressed(KeyCode::KeyS) {
+        let file = MapFile::from_map(editor.history.map(), TileExtensions::default());
+        match fs::write(&editor.path, file.write()) {
+            Ok(()) => editor.unsaved = false,
+            Err(error) => warn!("could not save {}: {error}", editor.path.display()),
+        }
+    } else if keys.just_pressed(KeyCode::KeyO) {
+        match load(&editor.path) {
+            // Loading is an edit too, so it can be undone.
+            Ok(loaded) => {
+                editor.history.edit(|map| *map = loaded);
+                editor.unsaved = false;
+            }
+            Err(error) => warn!("could not load {error}"),
+        }
+    }
+}
+
+/// Applies the current tool to the tile under the cursor.
+fn paint(
+    mouse: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    screen: Res<Screen>,
+    mut editor: ResMut<Editor>,
+) {
+    if !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let (Ok(window), Ok((camera, camera_transform))) = (windows.single(), cameras.single()) else {
+        return;
+    };
+    let Some(world_position) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor).ok())
+    else {
+        return;
+    };
+    let Some(position) = screen.tile_position(world_position) else {
+        return;
+    };
+
+    let editor = editor.as_mut();
+    let changed = match editor.tool {
+        Tool::Exits => {
+            let offset = world_position - screen.pixel_position(position).truncate();
+            let direction = nearest_side(offset);
+            editor
+                .history
+                .edit(|map| toggle_exit(map, position, direction))
+        }
+        Tool::TileSets => editor.history.edit(|map| {
+            let Some(tile) = map.tiles.get_mut(&position) else {
+                return false;
+            };
+            tile.tile_set = match tile.tile_set {
+                TileSet::Corridor => TileSet::Room,
+                TileSet::Room => TileSet::Field,
+                TileSet::Field => TileSet::Corridor,
+            };
+            true
+        }),
+        Tool::Prefab(index) => {
+            let prefab = &editor.prefabs[index].1;
+            !editor
+                .history
+                .edit(|map| map.stamp(prefab, position))
+                .is_empty()
+        }
+    };
+    if changed {
+        editor.unsaved = true;
+    }
+}
+
+/// The side of a tile closest to `offset`, measured from the tile center.
+fn nearest_side(offset: Vec2) -> Direction {
+    match (
+        offset.x.abs() > offset.y.abs(),
+        offset.x > 0.0,
+        offset.y > 0.0,
+    ) {
+        (true, true, _) => Direction::East,
+        (true, false, _) => Direction::West,
+        (false, _, true) => Direction::North,
+        (false, _, false) => Direction::South,
+    }
+}
+
+/// Opens or closes the exit from `position` towards `direction`, and the exit
+/// back from the neighbor, so the map stays consistent. Returns false,
+/// changing nothing, when the exit would lead off the map.
+fn toggle_exit(map: &mut Map<TileGeneratorDefault>, position: IVec2, direction: Direction) -> bool {
+    let neighbor = position + direction.offset();
+    if !map.in_bounds(neighbor) {
+        return false;
+    }
+    let open = !map.tiles[&position].map_tile.contains(direction);
+    for (position, direction) in [(position, direction), (neighbor, direction.opposite())] {
+        if let Some(tile) = map.tiles.get_mut(&position) {
+            tile.map_tile = if open {
+                tile.map_tile.with(direction)
+            } else {
+                tile.map_tile.without(direction)
+            };
+        }
+    }
+    true
+}
+
+/// Respawns the tile sprites and fits the screen to the map after every
+/// change, including loads that resize it.
+fn redraw_tiles(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    editor: Res<Editor>,
+    mut screen: ResMut<Screen>,
+    tiles: Query<Entity, With<EditorTile>>,
+) {
+    for entity in &tiles {
+        commands.entity(entity).despawn();
+    }
+
+    let map = editor.history.map();
+    let dimensions = UVec2::new(map.x as u32, map.y as u32);
+    if screen.dimensions() != dimensions {
+        *screen = Screen::new(dimensions, TILE_SIZE);
+    }
+    let fit = WINDOW_SIZE / (dimensions.max_element() as f32 * TILE_SIZE);
+    screen.set_zoom(fit.min(1.0));
+
+    let resolver = |position: IVec2, tile: Tile| {
+        let color = match (tile.tile_set, tile.map_tile) {
+            (TileSet::Field, MapTile::ZERO) => OBSTACLE_COLOR,
+            (TileSet::Field, _) => FIELD_COLOR,
+            _ => return (FileNameResolver.texture(position, tile), Color::WHITE),
+        };
+        let room = Tile::new(TileSet::Room, tile.map_tile);
+        (FileNameResolver.texture(position, room), color)
+    };
+    for (position, (texture, color)) in map.iterate_tiles_with(resolver) {
+        let mut sprite = Sprite::from_image(asset_server.load(texture));
+        sprite.color = color;
+        commands.spawn((
+            EditorTile,
+            sprite,
+            Transform::from_translation(screen.pixel_position(position))
+                .with_scale(Vec3::splat(screen.zoom())),
+        ));
+    }
+}
+
+fn update_title(editor: Res<Editor>, mut windows: Query<&mut Window, With<PrimaryWindow>>) {
+    if let Ok(mut window) = windows.single_mut() {
+        window.title = format!("brain-engine-editor - {}", *editor);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_nearest_side_is_along_the_larger_offset() {
+        assert_eq!(nearest_side(Vec2::new(10.0, 3.0)), Direction::East);
+        assert_eq!(nearest_side(Vec2::new(-10.0, -3.0)), Direction::West);
+        assert_eq!(nearest_side(Vec2::new(2.0, 5.0)), Direction::North);
+        assert_eq!(nearest_side(Vec2::new(2.0, -5.0)), Direction::South);
+    }
+
+    #[test]
+    fn toggling_an_exit_changes_both_tiles() {
+        let mut map = Map::new(3, TileGeneratorDefault::with_seed(1));
+        let (position, neighbor) = (IVec2::new(1, 1), IVec2::new(2, 1));
+        let open = map.tiles[&position].map_tile.contains(Direction::East);
+
+        assert!(toggle_exit(&mut map, position, Direction::East));
+
+        assert_eq!(
+            map.tiles[&position].map_tile.contains(Direction::East),
+            !open
+        );
+        assert_eq!(
+            map.tiles[&neighbor].map_tile.contains(Direction::West),
+            !open
+        );
+        assert_eq!(map.validate(), Ok(()));
+        assert!(toggle_exit(&mut map, position, Direction::East));
+        assert_eq!(
+            map.tiles[&position].map_tile.contains(Direction::East),
+            open
+        );
+    }
+
+    #[test]
+    fn exits_off_the_map_are_not_toggled() {
+        let mut map = Map::new(3, TileGeneratorDefault::with_seed(1));
+        let before = map.tiles.clone();
+
+        assert!(!toggle_exit(&mut map, IVec2::new(2, 0), Direction::East));
+        assert!(!toggle_exit(&mut map, IVec2::new(0, 0), Direction::South));
+
+        assert_eq!(map.tiles, before);
+    }
+}
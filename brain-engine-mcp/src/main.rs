@@ -1,7 +1,30 @@
+mod map_store;
+mod playtest;
+mod session_log;
+
 use anyhow::Result;
+use bevy::math::IVec2;
+use brain_engine_core::{
+    Direction, Map, MapTile, ParseTileError, Tile, TileGeneratorDefault, TileSet,
+};
+use map_store::MapStore;
+use playtest::Playtest;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use std::io::{self, BufRead, Write};
+use session_log::{LogEntry, SessionLog};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::time::{Instant, SystemTime};
+
+/// Largest map side length `generate_map` accepts.
+const MAX_MAP_SIZE: usize = 256;
+
+/// MCP protocol versions this server speaks, newest first.
+const PROTOCOL_VERSIONS: [&str; 3] = ["2025-06-18", "2025-03-26", "2024-11-05"];
 
 #[derive(Debug, Deserialize)]
 struct JsonRpcRequest {
@@ -27,15 +50,185 @@ struct JsonRpcError {
     message: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 struct SumParams {
+    /// First number
     a: i64,
+    /// Second number
     b: i64,
 }
 
+#[derive(Debug, Deserialize, JsonSchema)]
+struct GenerateMapParams {
+    /// Side length in tiles
+    #[schemars(range(min = 1, max = 256))]
+    size: usize,
+    /// Seed for a reproducible map
+    seed: Option<u64>,
+    /// Chance of each free exit being open
+    #[schemars(range(min = 0.0, max = 1.0))]
+    tile_exit_probability: Option<f64>,
+    /// Chance of a tile being a room
+    #[schemars(range(min = 0.0, max = 1.0))]
+    room_probability: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct EditMapParams {
+    /// The map's resource URI, e.g. map://1
+    uri: String,
+    /// Tile column
+    x: i32,
+    /// Tile row, counting up from the bottom
+    y: i32,
+    /// room, corridor or field
+    tile_set: String,
+    /// Open exits such as NES, or ZERO for none
+    exits: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct MapStatisticsParams {
+    /// The map's resource URI, e.g. map://1
+    uri: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct RenderMapParams {
+    /// A stored map's resource URI, e.g. map://1
+    uri: Option<String>,
+    /// Side length of a new map to draw instead of a stored one
+    #[schemars(range(min = 1, max = 256))]
+    size: Option<usize>,
+    /// Seed for a reproducible new map
+    seed: Option<u64>,
+    /// Chance of each free exit being open in a new map
+    #[schemars(range(min = 0.0, max = 1.0))]
+    tile_exit_probability: Option<f64>,
+    /// Chance of a tile being a room in a new map
+    #[schemars(range(min = 0.0, max = 1.0))]
+    room_probability: Option<f64>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct StartPlaytestParams {
+    /// The map's resource URI, e.g. map://1
+    uri: String,
+    /// Start column; defaults to the first tile with an exit
+    x: Option<i32>,
+    /// Start row; defaults to the first tile with an exit
+    y: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ActParams {
+    /// The id start_playtest returned
+    playtest: u64,
+    /// move, interact (finishes the playtest on the goal) or report (records
+    /// a blocker)
+    action: String,
+    /// For move: north, east, south or west, or N, E, S or W
+    direction: Option<String>,
+    /// For report: what blocks progress
+    note: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct ObserveParams {
+    /// The id start_playtest returned
+    playtest: u64,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+struct FindPathParams {
+    /// The map's resource URI, e.g. map://1
+    uri: String,
+    /// Start column
+    from_x: i32,
+    /// Start row, counting up from the bottom
+    from_y: i32,
+    /// Goal column
+    to_x: i32,
+    /// Goal row, counting up from the bottom
+    to_y: i32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InitializeParams {
+    protocol_version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResourceParams {
+    uri: String,
+}
+
+/// State shared by every request of a session.
+#[derive(Default)]
+struct Server {
+    /// Version agreed on by `initialize`; `None` until the client has
+    /// initialized the session.
+    protocol_version: Option<String>,
+    maps: MapStore,
+    /// URIs of the resources the client subscribed to.
+    subscriptions: HashSet<String>,
+    /// Notifications to send after the response to the current request.
+    notifications: Vec<Value>,
+    /// Playtests started with `start_playtest`, by id.
+    playtests: BTreeMap<u64, Playtest>,
+}
+
+/// Command line options: `--log <path>` appends every request and response
+/// to a JSONL log, `--replay <path>` re-runs a logged session against this
+/// build and reports the responses that changed.
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    log: Option<PathBuf>,
+    replay: Option<PathBuf>,
+}
+
+impl Options {
+    fn parse(mut arguments: impl Iterator<Item = String>) -> Result<Self> {
+        let mut options = Options::default();
+        while let Some(argument) = arguments.next() {
+            let slot = match argument.as_str() {
+                "--log" => &mut options.log,
+                "--replay" => &mut options.replay,
+                _ => anyhow::bail!("unknown argument: {}", argument),
+            };
+            let path = arguments
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("{} needs a path", argument))?;
+            *slot = Some(PathBuf::from(path));
+        }
+        Ok(options)
+    }
+}
+
 fn main() -> Result<()> {
+    let options = Options::parse(std::env::args().skip(1))?;
+    if let Some(path) = &options.replay {
+        let entries = session_log::read_log(BufReader::new(File::open(path)?))?;
+        let differences = replay(&entries);
+        for difference in &differences {
+            println!("{}", difference);
+        }
+        println!(
+            "Replayed {} messages, {} differ",
+            entries.len(),
+            differences.len()
+        );
+        if !differences.is_empty() {
+            anyhow::bail!("replay found {} differences", differences.len());
+        }
+        return Ok(());
+    }
+
+    let mut log = options.log.as_deref().map(SessionLog::open).transpose()?;
     let stdin = io::stdin();
     let mut stdout = io::stdout();
+    let mut server = Server::default();
 
     for line in stdin.lock().lines() {
         let line = line?;
@@ -43,128 +236,1098 @@ fn main() -> Result<()> {
             continue;
         }
 
-        let request: JsonRpcRequest = match serde_json::from_str(&line) {
-            Ok(req) => req,
+        let received = SystemTime::now();
+        let started = Instant::now();
+        let response = server.handle_line(&line);
+        let notifications: Vec<Value> = server.notifications.drain(..).collect();
+        let duration = started.elapsed();
+
+        if let Some(response) = &response {
+            writeln!(stdout, "{}", response)?;
+        }
+        for notification in &notifications {
+            writeln!(stdout, "{}", notification)?;
+        }
+        stdout.flush()?;
+
+        if let Some(log) = &mut log {
+            let request = serde_json::from_str(&line).unwrap_or(Value::String(line));
+            log.record(&LogEntry::new(
+                received,
+                duration,
+                request,
+                response,
+                notifications,
+            ))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Feeds every logged request to a fresh server and describes each one
+/// whose response or notifications differ from the log.
+fn replay(entries: &[LogEntry]) -> Vec<String> {
+    let mut server = Server::default();
+    let mut differences = Vec::new();
+    for (index, entry) in entries.iter().enumerate() {
+        let line = match &entry.request {
+            Value::String(line) => line.clone(),
+            request => request.to_string(),
+        };
+        let response = server.handle_line(&line);
+        let notifications: Vec<Value> = server.notifications.drain(..).collect();
+        if response != entry.response || notifications != entry.notifications {
+            differences.push(format!(
+                "message {}: {}\n  logged:   {} {:?}\n  replayed: {} {:?}",
+                index + 1,
+                line,
+                json!(entry.response),
+                entry.notifications,
+                json!(response),
+                notifications
+            ));
+        }
+    }
+    differences
+}
+
+impl Server {
+    /// Handles one raw line from the client, answering JSON that doesn't
+    /// parse with a parse error.
+    fn handle_line(&mut self, line: &str) -> Option<Value> {
+        match serde_json::from_str(line) {
+            Ok(message) => self.handle_message(message),
             Err(e) => {
                 eprintln!("Failed to parse request: {}", e);
-                continue;
+                Some(error_value(JsonRpcError {
+                    code: -32700,
+                    message: format!("Parse error: {}", e),
+                }))
+            }
+        }
+    }
+
+    /// Handles one line from the client: a request, a notification or a
+    /// batch of them. Returns what to write back, if anything; notifications
+    /// get no response, and neither does a batch of only notifications.
+    fn handle_message(&mut self, message: Value) -> Option<Value> {
+        match message {
+            Value::Array(batch) if batch.is_empty() => Some(error_value(JsonRpcError {
+                code: -32600,
+                message: "Empty batch".to_string(),
+            })),
+            Value::Array(batch) => {
+                let responses: Vec<Value> = batch
+                    .into_iter()
+                    .filter_map(|message| self.handle_single(message))
+                    .collect();
+                (!responses.is_empty()).then_some(Value::Array(responses))
+            }
+            message => self.handle_single(message),
+        }
+    }
+
+    fn handle_single(&mut self, message: Value) -> Option<Value> {
+        match serde_json::from_value::<JsonRpcRequest>(message) {
+            Ok(request) => self.handle_request(request).map(|response| {
+                serde_json::to_value(response).expect("responses always serialize")
+            }),
+            Err(e) => Some(error_value(JsonRpcError {
+                code: -32600,
+                message: format!("Invalid request: {}", e),
+            })),
+        }
+    }
+
+    /// Returns `None` for notifications, which have no id.
+    fn handle_request(&mut self, request: JsonRpcRequest) -> Option<JsonRpcResponse> {
+        if request.id.is_none() {
+            self.handle_notification(&request.method);
+            return None;
+        }
+
+        let result = if request.jsonrpc != "2.0" {
+            Err(JsonRpcError {
+                code: -32600,
+                message: format!("Unsupported JSON-RPC version: {}", request.jsonrpc),
+            })
+        } else if self.protocol_version.is_none()
+            && !matches!(request.method.as_str(), "initialize" | "ping")
+        {
+            Err(JsonRpcError {
+                code: -32002,
+                message: "Server not initialized".to_string(),
+            })
+        } else {
+            match request.method.as_str() {
+                "initialize" => self.handle_initialize(request.params),
+                "ping" => Ok(json!({})),
+                "tools/list" => handle_tools_list(),
+                "tools/call" => self.handle_tool_call(request.params),
+                "resources/list" => self.handle_resources_list(),
+                "resources/read" => self.handle_resources_read(request.params),
+                "resources/subscribe" => self.handle_subscribe(request.params),
+                "resources/unsubscribe" => self.handle_unsubscribe(request.params),
+                _ => Err(JsonRpcError {
+                    code: -32601,
+                    message: format!("Method not found: {}", request.method),
+                }),
             }
         };
 
-        let response = handle_request(request);
-        let response_json = serde_json::to_string(&response)?;
-        writeln!(stdout, "{}", response_json)?;
-        stdout.flush()?;
+        Some(JsonRpcResponse::new(request.id, result))
     }
 
-    Ok(())
-}
+    /// Requests are handled one at a time, so there is nothing in flight to
+    /// cancel, and `initialized` needs no action: the session is usable as
+    /// soon as `initialize` has been answered.
+    fn handle_notification(&mut self, method: &str) {
+        match method {
+            "notifications/initialized" | "notifications/cancelled" => {}
+            _ => eprintln!("Ignoring notification: {}", method),
+        }
+    }
 
-fn handle_request(request: JsonRpcRequest) -> JsonRpcResponse {
-    let result = match request.method.as_str() {
-        "initialize" => handle_initialize(),
-        "tools/list" => handle_tools_list(),
-        "tools/call" => handle_tool_call(request.params),
-        _ => Err(JsonRpcError {
-            code: -32601,
-            message: format!("Method not found: {}", request.method),
-        }),
-    };
+    /// Agrees on the client's protocol version if this server speaks it, and
+    /// otherwise offers the newest one it does.
+    fn handle_initialize(&mut self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        if self.protocol_version.is_some() {
+            return Err(JsonRpcError {
+                code: -32600,
+                message: "Session already initialized".to_string(),
+            });
+        }
+        let params: InitializeParams = parse_params(params)?;
+        let version = PROTOCOL_VERSIONS
+            .into_iter()
+            .find(|version| *version == params.protocol_version)
+            .unwrap_or(PROTOCOL_VERSIONS[0]);
+        self.protocol_version = Some(version.to_string());
 
-    match result {
-        Ok(value) => JsonRpcResponse {
-            jsonrpc: "2.0".to_string(),
-            id: request.id,
-            result: Some(value),
-            error: None,
-        },
-        Err(error) => JsonRpcResponse {
-            jsonrpc: "2.0".to_string(),
-            id: request.id,
-            result: None,
-            error: Some(error),
-        },
+        Ok(json!({
+            "protocolVersion": version,
+            "serverInfo": {
+                "name": "brain-engine-mcp",
+                "version": "0.1.0"
+            },
+            "capabilities": {
+                "tools": {},
+                "resources": {
+                    "subscribe": true
+                }
+            }
+        }))
     }
-}
 
-fn handle_initialize() -> Result<Value, JsonRpcError> {
-    Ok(json!({
-        "protocolVersion": "2024-11-05",
-        "serverInfo": {
-            "name": "brain-engine-mcp",
-            "version": "0.1.0"
-        },
-        "capabilities": {
-            "tools": {}
+    fn handle_tool_call(&mut self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let params = params.ok_or_else(|| JsonRpcError {
+            code: -32602,
+            message: "Missing params".to_string(),
+        })?;
+
+        let tool_name =
+            params
+                .get("name")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| JsonRpcError {
+                    code: -32602,
+                    message: "Missing tool name".to_string(),
+                })?;
+
+        match tool_name {
+            "sum" => {
+                let sum_params: SumParams = parse_arguments(&params)?;
+
+                let result = sum_params.a + sum_params.b;
+
+                Ok(text_content(format!(
+                    "The sum of {} and {} is {}",
+                    sum_params.a, sum_params.b, result
+                )))
+            }
+            "generate_map" => {
+                let generate_params: GenerateMapParams = parse_arguments(&params)?;
+                let size = generate_params.size;
+                let uri = self.maps.insert(new_map(&generate_params)?);
+
+                Ok(text_content(format!(
+                    "Generated a {size}x{size} map, available as {uri}"
+                )))
+            }
+            "edit_map" => {
+                let edit_params: EditMapParams = parse_arguments(&params)?;
+                let tile_set: TileSet = edit_params.tile_set.parse().map_err(invalid_tile)?;
+                let map_tile: MapTile = edit_params.exits.parse().map_err(invalid_tile)?;
+                let map = self
+                    .maps
+                    .get_mut(&edit_params.uri)
+                    .ok_or_else(|| unknown_resource(&edit_params.uri))?;
+                let position = IVec2::new(edit_params.x, edit_params.y);
+                if !map.in_bounds(position) {
+                    return Err(JsonRpcError {
+                        code: -32602,
+                        message: format!("{} is outside {}", position, edit_params.uri),
+                    });
+                }
+
+                map.tiles.insert(position, Tile::new(tile_set, map_tile));
+                self.resource_updated(&edit_params.uri);
+
+                Ok(text_content(format!(
+                    "Set {} on {} to {} {}",
+                    position, edit_params.uri, tile_set, map_tile
+                )))
+            }
+            "map_statistics" => {
+                let statistics_params: MapStatisticsParams = parse_arguments(&params)?;
+                let map = self
+                    .maps
+                    .get(&statistics_params.uri)
+                    .ok_or_else(|| unknown_resource(&statistics_params.uri))?;
+                let stats = map.stats();
+                let mut report = serde_json::to_value(&stats).expect("stats always serialize");
+                report["connectivity"] = json!(stats.connectivity());
+
+                Ok(text_content(report.to_string()))
+            }
+            "render_map" => {
+                let render_params: RenderMapParams = parse_arguments(&params)?;
+                let (label, text) = match (&render_params.uri, render_params.size) {
+                    (Some(uri), None) => {
+                        let map = self.maps.get(uri).ok_or_else(|| unknown_resource(uri))?;
+                        (uri.clone(), map.render_ascii())
+                    }
+                    (None, Some(size)) => {
+                        let map = new_map(&GenerateMapParams {
+                            size,
+                            seed: render_params.seed,
+                            tile_exit_probability: render_params.tile_exit_probability,
+                            room_probability: render_params.room_probability,
+                        })?;
+                        ("a new map".to_string(), map.render_ascii())
+                    }
+                    _ => {
+                        return Err(JsonRpcError {
+                            code: -32602,
+                            message: "Give either uri or size".to_string(),
+                        })
+                    }
+                };
+
+                Ok(text_content(format!(
+                    "{}, north up, rooms in heavy lines, corridors in light lines:\n{}",
+                    label, text
+                )))
+            }
+            "start_playtest" => {
+                let start_params: StartPlaytestParams = parse_arguments(&params)?;
+                let map = self
+                    .maps
+                    .get(&start_params.uri)
+                    .ok_or_else(|| unknown_resource(&start_params.uri))?;
+                let start = match (start_params.x, start_params.y) {
+                    (Some(x), Some(y)) => Some(IVec2::new(x, y)),
+                    (None, None) => None,
+                    _ => {
+                        return Err(JsonRpcError {
+                            code: -32602,
+                            message: "Give both x and y, or neither".to_string(),
+                        })
+                    }
+                };
+                let playtest =
+                    Playtest::start(&start_params.uri, map, start).ok_or_else(|| JsonRpcError {
+                        code: -32602,
+                        message: format!("Cannot start a playtest on {}", start_params.uri),
+                    })?;
+                let observation = playtest.observe(map);
+                let id = self.playtests.len() as u64 + 1;
+                self.playtests.insert(id, playtest);
+
+                Ok(text_content(
+                    json!({ "playtest": id, "observation": observation }).to_string(),
+                ))
+            }
+            "act" => {
+                let act_params: ActParams = parse_arguments(&params)?;
+                let playtest = self
+                    .playtests
+                    .get_mut(&act_params.playtest)
+                    .ok_or_else(|| unknown_playtest(act_params.playtest))?;
+                let map = self
+                    .maps
+                    .get(&playtest.uri)
+                    .ok_or_else(|| unknown_resource(&playtest.uri))?;
+                if playtest.finished {
+                    return Err(JsonRpcError {
+                        code: -32602,
+                        message: format!("Playtest {} is finished", act_params.playtest),
+                    });
+                }
+                let outcome = match act_params.action.as_str() {
+                    "move" => {
+                        let direction = act_params
+                            .direction
+                            .as_deref()
+                            .and_then(parse_direction)
+                            .ok_or_else(|| JsonRpcError {
+                            code: -32602,
+                            message: "move needs a direction: north, east, south or west"
+                                .to_string(),
+                        })?;
+                        playtest.walk(map, direction)
+                    }
+                    "interact" => playtest.interact(),
+                    "report" => {
+                        let note = act_params.note.as_deref().unwrap_or("blocked");
+                        playtest.report(note)
+                    }
+                    action => {
+                        return Err(JsonRpcError {
+                            code: -32602,
+                            message: format!("Unknown action: {}", action),
+                        })
+                    }
+                };
+
+                Ok(text_content(
+                    json!({ "outcome": outcome, "observation": playtest.observe(map) }).to_string(),
+                ))
+            }
+            "observe" => {
+                let observe_params: ObserveParams = parse_arguments(&params)?;
+                let playtest = self
+                    .playtests
+                    .get(&observe_params.playtest)
+                    .ok_or_else(|| unknown_playtest(observe_params.playtest))?;
+                let map = self
+                    .maps
+                    .get(&playtest.uri)
+                    .ok_or_else(|| unknown_resource(&playtest.uri))?;
+
+                Ok(text_content(playtest.observe(map).to_string()))
+            }
+            "find_path" => {
+                let path_params: FindPathParams = parse_arguments(&params)?;
+                let map = self
+                    .maps
+                    .get(&path_params.uri)
+                    .ok_or_else(|| unknown_resource(&path_params.uri))?;
+                let from = IVec2::new(path_params.from_x, path_params.from_y);
+                let to = IVec2::new(path_params.to_x, path_params.to_y);
+                let outside = [from, to]
+                    .into_iter()
+                    .find(|position| !map.in_bounds(*position));
+                if let Some(position) = outside {
+                    return Err(JsonRpcError {
+                        code: -32602,
+                        message: format!("{} is outside {}", position, path_params.uri),
+                    });
+                }
+
+                let result = map.path_report(from, to).unwrap_or_else(|| {
+                    json!({
+                        "reachable": false,
+                        "reason": format!("No path from {} to {} on {}", from, to, path_params.uri),
+                    })
+                });
+
+                Ok(text_content(result.to_string()))
+            }
+            _ => Err(JsonRpcError {
+                code: -32602,
+                message: format!("Unknown tool: {}", tool_name),
+            }),
         }
-    }))
+    }
+
+    fn handle_resources_list(&self) -> Result<Value, JsonRpcError> {
+        let resources: Vec<Value> = self
+            .maps
+            .iter()
+            .map(|(uri, map)| {
+                json!({
+                    "uri": uri,
+                    "name": uri,
+                    "description": format!("{}x{} map", map.x, map.y),
+                    "mimeType": "application/json"
+                })
+            })
+            .collect();
+        Ok(json!({ "resources": resources }))
+    }
+
+    /// Returns a stored map twice: as JSON for programs and as the ASCII
+    /// drawing `render_map` makes for reading.
+    fn handle_resources_read(&self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let resource: ResourceParams = parse_params(params)?;
+        let map = self
+            .maps
+            .get(&resource.uri)
+            .ok_or_else(|| unknown_resource(&resource.uri))?;
+
+        Ok(json!({
+            "contents": [
+                {
+                    "uri": resource.uri,
+                    "mimeType": "application/json",
+                    "text": map.to_json().to_string()
+                },
+                {
+                    "uri": resource.uri,
+                    "mimeType": "text/plain",
+                    "text": map.render_ascii()
+                }
+            ]
+        }))
+    }
+
+    fn handle_subscribe(&mut self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let resource: ResourceParams = parse_params(params)?;
+        if !self.maps.contains(&resource.uri) {
+            return Err(unknown_resource(&resource.uri));
+        }
+        self.subscriptions.insert(resource.uri);
+        Ok(json!({}))
+    }
+
+    fn handle_unsubscribe(&mut self, params: Option<Value>) -> Result<Value, JsonRpcError> {
+        let resource: ResourceParams = parse_params(params)?;
+        self.subscriptions.remove(&resource.uri);
+        Ok(json!({}))
+    }
+
+    /// Tells the client a resource changed, if it subscribed to it. Every
+    /// tool that modifies a stored map must call this.
+    fn resource_updated(&mut self, uri: &str) {
+        if self.subscriptions.contains(uri) {
+            self.notifications.push(json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/resources/updated",
+                "params": { "uri": uri }
+            }));
+        }
+    }
+}
+
+/// Generates a map from `generate_map`-style parameters, checking their
+/// ranges.
+fn new_map(params: &GenerateMapParams) -> Result<Map<TileGeneratorDefault>, JsonRpcError> {
+    if params.size == 0 || params.size > MAX_MAP_SIZE {
+        return Err(JsonRpcError {
+            code: -32602,
+            message: format!("Map size must be between 1 and {}", MAX_MAP_SIZE),
+        });
+    }
+    let probabilities = [params.tile_exit_probability, params.room_probability];
+    if probabilities
+        .iter()
+        .flatten()
+        .any(|probability| !(0.0..=1.0).contains(probability))
+    {
+        return Err(JsonRpcError {
+            code: -32602,
+            message: "Probabilities must be between 0 and 1".to_string(),
+        });
+    }
+
+    let mut builder = TileGeneratorDefault::builder();
+    if let Some(seed) = params.seed {
+        builder = builder.seed(seed);
+    }
+    if let Some(probability) = params.tile_exit_probability {
+        builder = builder.tile_exit_probability(probability);
+    }
+    if let Some(probability) = params.room_probability {
+        builder = builder.room_probability(probability);
+    }
+    Ok(Map::new(params.size, builder.build()))
 }
 
 fn handle_tools_list() -> Result<Value, JsonRpcError> {
     Ok(json!({
         "tools": [
-            {
-                "name": "sum",
-                "description": "Add two integers together",
-                "inputSchema": {
-                    "type": "object",
-                    "properties": {
-                        "a": {
-                            "type": "number",
-                            "description": "First number"
-                        },
-                        "b": {
-                            "type": "number",
-                            "description": "Second number"
-                        }
-                    },
-                    "required": ["a", "b"]
-                }
-            }
+            tool::<SumParams>("sum", "Add two integers together"),
+            tool::<GenerateMapParams>(
+                "generate_map",
+                "Generate a square map and store it as a map:// resource",
+            ),
+            tool::<EditMapParams>(
+                "edit_map",
+                "Replace one tile of a stored map. Subscribers of the map are notified.",
+            ),
+            tool::<MapStatisticsParams>(
+                "map_statistics",
+                "Measure a stored map: tile counts, connectivity, an exit-count histogram, \
+                 per-row and per-column openness and room sizes, as JSON",
+            ),
+            tool::<RenderMapParams>(
+                "render_map",
+                "Draw a stored map, or a new one generated from size and seed, as ASCII art \
+                 with one box-drawing character per tile",
+            ),
+            tool::<StartPlaytestParams>(
+                "start_playtest",
+                "Start playing a stored map turn by turn. The goal is the reachable tile \
+                 farthest from the start. Returns the playtest id and a first observation.",
+            ),
+            tool::<ActParams>(
+                "act",
+                "Take one turn in a playtest: move a tile, interact to finish on the goal, \
+                 or report a blocker. Moves into walls are recorded as blockers.",
+            ),
+            tool::<ObserveParams>(
+                "observe",
+                "Describe a playtest: position, exits, the goal once in sight, turns taken, \
+                 blockers and an ASCII view of the tiles in sight",
+            ),
+            tool::<FindPathParams>(
+                "find_path",
+                "Find the cheapest path between two tiles of a stored map. Returns JSON with \
+                 reachable, and for reachable goals the steps, the movement cost and the path \
+                 as [x, y] pairs from start to goal",
+            ),
         ]
     }))
 }
 
-fn handle_tool_call(params: Option<Value>) -> Result<Value, JsonRpcError> {
+/// Describes a tool, deriving its input schema from the parameter struct
+/// the tool parses its arguments into.
+fn tool<T: JsonSchema>(name: &str, description: &str) -> Value {
+    let mut input_schema = schemars::schema_for!(T).to_value();
+    if let Some(schema) = input_schema.as_object_mut() {
+        schema.remove("$schema");
+        schema.remove("title");
+    }
+    json!({
+        "name": name,
+        "description": description,
+        "inputSchema": input_schema
+    })
+}
+
+fn parse_params<T: DeserializeOwned>(params: Option<Value>) -> Result<T, JsonRpcError> {
     let params = params.ok_or_else(|| JsonRpcError {
         code: -32602,
         message: "Missing params".to_string(),
     })?;
+    serde_json::from_value(params).map_err(|e| JsonRpcError {
+        code: -32602,
+        message: format!("Invalid params: {}", e),
+    })
+}
 
-    let tool_name = params.get("name")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| JsonRpcError {
-            code: -32602,
-            message: "Missing tool name".to_string(),
-        })?;
+fn parse_arguments<T: DeserializeOwned>(params: &Value) -> Result<T, JsonRpcError> {
+    let arguments = params.get("arguments").ok_or_else(|| JsonRpcError {
+        code: -32602,
+        message: "Missing arguments".to_string(),
+    })?;
 
-    match tool_name {
-        "sum" => {
-            let arguments = params.get("arguments").ok_or_else(|| JsonRpcError {
-                code: -32602,
-                message: "Missing arguments".to_string(),
-            })?;
+    serde_json::from_value(arguments.clone()).map_err(|e| JsonRpcError {
+        code: -32602,
+        message: format!("Invalid arguments: {}", e),
+    })
+}
 
-            let sum_params: SumParams = serde_json::from_value(arguments.clone())
-                .map_err(|e| JsonRpcError {
-                    code: -32602,
-                    message: format!("Invalid arguments: {}", e),
-                })?;
+impl JsonRpcResponse {
+    fn new(id: Option<Value>, result: Result<Value, JsonRpcError>) -> Self {
+        let (result, error) = match result {
+            Ok(value) => (Some(value), None),
+            Err(error) => (None, Some(error)),
+        };
+        JsonRpcResponse {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result,
+            error,
+        }
+    }
+}
 
-            let result = sum_params.a + sum_params.b;
+/// An error response to a message whose id could not be read.
+fn error_value(error: JsonRpcError) -> Value {
+    serde_json::to_value(JsonRpcResponse::new(None, Err(error)))
+        .expect("responses always serialize")
+}
 
-            Ok(json!({
-                "content": [
-                    {
-                        "type": "text",
-                        "text": format!("The sum of {} and {} is {}", sum_params.a, sum_params.b, result)
-                    }
-                ]
-            }))
+fn text_content(text: String) -> Value {
+    json!({
+        "content": [
+            {
+                "type": "text",
+                "text": text
+            }
+        ]
+    })
+}
+
+fn invalid_tile(error: ParseTileError) -> JsonRpcError {
+    JsonRpcError {
+        code: -32602,
+        message: error.to_string(),
+    }
+}
+
+fn unknown_playtest(id: u64) -> JsonRpcError {
+    JsonRpcError {
+        code: -32602,
+        message: format!("Unknown playtest: {}", id),
+    }
+}
+
+/// Parses a direction name, e.g. `north` or `N`, in any case.
+fn parse_direction(name: &str) -> Option<Direction> {
+    match name.to_ascii_lowercase().as_str() {
+        "n" | "north" => Some(Direction::North),
+        "e" | "east" => Some(Direction::East),
+        "s" | "south" => Some(Direction::South),
+        "w" | "west" => Some(Direction::West),
+        _ => None,
+    }
+}
+
+fn unknown_resource(uri: &str) -> JsonRpcError {
+    JsonRpcError {
+        code: -32602,
+        message: format!("Unknown resource: {}", uri),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(server: &mut Server, method: &str, params: Value) -> JsonRpcResponse {
+        server
+            .handle_request(JsonRpcRequest {
+                jsonrpc: "2.0".to_string(),
+                id: Some(json!(1)),
+                method: method.to_string(),
+                params: Some(params),
+            })
+            .unwrap()
+    }
+
+    fn initialized_server() -> Server {
+        let mut server = Server::default();
+        call(
+            &mut server,
+            "initialize",
+            json!({ "protocolVersion": "2025-06-18" }),
+        );
+        server
+    }
+
+    fn edit(server: &mut Server, uri: &str) -> JsonRpcResponse {
+        call(
+            server,
+            "tools/call",
+            json!({
+                "name": "edit_map",
+                "arguments": { "uri": uri, "x": 1, "y": 0, "tile_set": "room", "exits": "NE" }
+            }),
+        )
+    }
+
+    fn generate(server: &mut Server) -> String {
+        let response = call(
+            server,
+            "tools/call",
+            json!({ "name": "generate_map", "arguments": { "size": 4, "seed": 3 } }),
+        );
+        let text = response.result.unwrap()["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        text.rsplit(' ').next().unwrap().to_string()
+    }
+
+    #[test]
+    fn subscribers_are_notified_when_a_map_is_edited() {
+        let mut server = initialized_server();
+        let uri = generate(&mut server);
+        assert_eq!(uri, "map://1");
+
+        assert!(edit(&mut server, &uri).error.is_none());
+        assert!(server.notifications.is_empty());
+
+        assert!(
+            call(&mut server, "resources/subscribe", json!({ "uri": uri }))
+                .error
+                .is_none()
+        );
+        edit(&mut server, &uri);
+        assert_eq!(
+            server.notifications,
+            vec![json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/resources/updated",
+                "params": { "uri": "map://1" }
+            })]
+        );
+
+        server.notifications.clear();
+        call(&mut server, "resources/unsubscribe", json!({ "uri": uri }));
+        edit(&mut server, &uri);
+        assert!(server.notifications.is_empty());
+    }
+
+    #[test]
+    fn only_changed_maps_notify_their_subscribers() {
+        let mut server = initialized_server();
+        let watched = generate(&mut server);
+        let other = generate(&mut server);
+        call(
+            &mut server,
+            "resources/subscribe",
+            json!({ "uri": watched }),
+        );
+
+        edit(&mut server, &other);
+
+        assert!(server.notifications.is_empty());
+    }
+
+    #[test]
+    fn stored_maps_are_listed_and_read_as_resources() {
+        let mut server = initialized_server();
+        assert_eq!(
+            call(&mut server, "resources/list", json!({}))
+                .result
+                .unwrap(),
+            json!({ "resources": [] })
+        );
+        let uri = generate(&mut server);
+
+        let listed = call(&mut server, "resources/list", json!({}))
+            .result
+            .unwrap();
+        assert_eq!(listed["resources"][0]["uri"], uri);
+        assert_eq!(listed["resources"][0]["description"], "4x4 map");
+
+        let read = call(&mut server, "resources/read", json!({ "uri": uri }))
+            .result
+            .unwrap();
+        let contents = &read["contents"];
+        let map: Value = serde_json::from_str(contents[0]["text"].as_str().unwrap()).unwrap();
+        assert_eq!(map["width"], 4);
+        assert_eq!(map["height"], 4);
+        assert_eq!(map["topology"], "bounded");
+        let stored = server.maps.get(&uri).unwrap();
+        let tile = &stored.tiles[&IVec2::new(1, 2)];
+        assert_eq!(map["tiles"][6]["exits"], tile.map_tile.to_string());
+        assert_eq!(map["tiles"][6]["tile_set"], tile.tile_set.to_string());
+        assert_eq!(contents[1]["mimeType"], "text/plain");
+        assert_eq!(contents[1]["text"], stored.render_ascii());
+    }
+
+    #[test]
+    fn unknown_resources_cannot_be_subscribed_or_edited() {
+        let mut server = initialized_server();
+
+        let response = call(
+            &mut server,
+            "resources/subscribe",
+            json!({ "uri": "map://9" }),
+        );
+        assert_eq!(response.error.unwrap().message, "Unknown resource: map://9");
+        assert!(edit(&mut server, "map://9").error.is_some());
+        let response = call(&mut server, "resources/read", json!({ "uri": "map://9" }));
+        assert_eq!(response.error.unwrap().message, "Unknown resource: map://9");
+    }
+
+    #[test]
+    fn initialize_negotiates_the_protocol_version() {
+        let mut server = Server::default();
+        let response = call(
+            &mut server,
+            "initialize",
+            json!({ "protocolVersion": "2025-03-26" }),
+        );
+        assert_eq!(response.result.unwrap()["protocolVersion"], "2025-03-26");
+        assert!(call(
+            &mut server,
+            "initialize",
+            json!({ "protocolVersion": "2025-03-26" })
+        )
+        .error
+        .is_some());
+
+        let mut server = Server::default();
+        let response = call(
+            &mut server,
+            "initialize",
+            json!({ "protocolVersion": "1999-01-01" }),
+        );
+        assert_eq!(
+            response.result.unwrap()["protocolVersion"],
+            PROTOCOL_VERSIONS[0]
+        );
+    }
+
+    #[test]
+    fn only_pings_are_answered_before_initialize() {
+        let mut server = Server::default();
+
+        assert!(call(&mut server, "ping", json!({})).error.is_none());
+        assert_eq!(
+            call(&mut server, "tools/list", json!({}))
+                .error
+                .unwrap()
+                .code,
+            -32002
+        );
+    }
+
+    #[test]
+    fn notifications_get_no_response() {
+        let mut server = initialized_server();
+
+        let initialized = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        assert_eq!(server.handle_message(initialized), None);
+        let unknown = json!({ "jsonrpc": "2.0", "method": "notifications/unknown" });
+        assert_eq!(server.handle_message(unknown), None);
+    }
+
+    #[test]
+    fn batches_answer_every_request_in_one_array() {
+        let mut server = initialized_server();
+
+        let response = server.handle_message(json!([
+            { "jsonrpc": "2.0", "id": 1, "method": "ping" },
+            { "jsonrpc": "2.0", "method": "notifications/initialized" },
+            { "jsonrpc": "2.0", "id": 2, "method": "tools/list" },
+            { "id": 3 }
+        ]));
+
+        let responses = response.unwrap();
+        let ids: Vec<&Value> = responses
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| &r["id"])
+            .collect();
+        assert_eq!(ids, vec![&json!(1), &json!(2), &Value::Null]);
+        assert_eq!(responses[2]["error"]["code"], -32600);
+        assert_eq!(
+            server.handle_message(json!([])).unwrap()["error"]["code"],
+            -32600
+        );
+    }
+
+    #[test]
+    fn replaying_a_session_reports_only_changed_responses() {
+        let mut server = Server::default();
+        let mut entries = Vec::new();
+        for line in [
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"protocolVersion":"2025-06-18"}}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"tools/call","params":{"name":"sum","arguments":{"a":2,"b":3}}}"#,
+            "not json",
+        ] {
+            let response = server.handle_line(line);
+            let request = serde_json::from_str(line).unwrap_or(Value::String(line.to_string()));
+            entries.push(LogEntry::new(
+                SystemTime::now(),
+                std::time::Duration::ZERO,
+                request,
+                response,
+                server.notifications.drain(..).collect(),
+            ));
         }
-        _ => Err(JsonRpcError {
-            code: -32602,
-            message: format!("Unknown tool: {}", tool_name),
-        }),
+        assert_eq!(replay(&entries), Vec::<String>::new());
+
+        entries[1].response = Some(json!({ "jsonrpc": "2.0", "id": 2, "result": 6 }));
+        let differences = replay(&entries);
+        assert_eq!(differences.len(), 1);
+        assert!(differences[0].starts_with("message 2:"));
+    }
+
+    #[test]
+    fn options_take_log_and_replay_paths() {
+        let arguments = ["--log", "session.jsonl"].map(String::from);
+        assert_eq!(
+            Options::parse(arguments.into_iter()).unwrap(),
+            Options {
+                log: Some(PathBuf::from("session.jsonl")),
+                replay: None,
+            }
+        );
+        assert!(Options::parse(["--replay".to_string()].into_iter()).is_err());
+        assert!(Options::parse(["--verbose".to_string()].into_iter()).is_err());
+    }
+
+    #[test]
+    fn statistics_report_histograms_and_densities() {
+        let mut server = initialized_server();
+        let uri = generate(&mut server);
+
+        let response = call(
+            &mut server,
+            "tools/call",
+            json!({ "name": "map_statistics", "arguments": { "uri": uri } }),
+        );
+
+        let text = response.result.unwrap()["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .to_string();
+        let report: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(report["tile_count"], 16);
+        assert_eq!(report["exit_histogram"].as_array().unwrap().len(), 5);
+        assert_eq!(report["row_openness"].as_array().unwrap().len(), 4);
+        assert!(report["connectivity"].is_number());
+
+        let response = call(
+            &mut server,
+            "tools/call",
+            json!({ "name": "map_statistics", "arguments": { "uri": "map://9" } }),
+        );
+        assert!(response.error.is_some());
+    }
+
+    fn tool_text(server: &mut Server, name: &str, arguments: Value) -> String {
+        let response = call(
+            server,
+            "tools/call",
+            json!({ "name": name, "arguments": arguments }),
+        );
+        response.result.unwrap()["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn render_map_draws_stored_and_new_maps() {
+        let mut server = initialized_server();
+        let uri = generate(&mut server);
+        let expected = server.maps.get(&uri).unwrap().render_ascii();
+
+        let stored = tool_text(&mut server, "render_map", json!({ "uri": uri }));
+        let new = tool_text(&mut server, "render_map", json!({ "size": 4, "seed": 3 }));
+
+        assert!(stored.starts_with("map://1, north up"));
+        assert!(stored.ends_with(&expected));
+        assert!(new.ends_with(&expected));
+        let response = call(
+            &mut server,
+            "tools/call",
+            json!({ "name": "render_map", "arguments": { "uri": uri, "size": 4 } }),
+        );
+        assert_eq!(response.error.unwrap().message, "Give either uri or size");
+    }
+
+    fn tool_json(server: &mut Server, name: &str, arguments: Value) -> Value {
+        serde_json::from_str(&tool_text(server, name, arguments)).unwrap()
+    }
+
+    #[test]
+    fn playtests_walk_to_the_goal_and_record_blockers() {
+        let mut server = initialized_server();
+        let uri = generate(&mut server);
+        let started = tool_json(&mut server, "start_playtest", json!({ "uri": uri }));
+        assert_eq!(started["playtest"], 1);
+        let playtest = &server.playtests[&1];
+        let path = server
+            .maps
+            .get(&uri)
+            .unwrap()
+            .find_path(playtest.agent.position(), playtest.goal)
+            .unwrap();
+
+        let wall = Direction::all()
+            .into_iter()
+            .find(|direction| {
+                !started["observation"]["exits"]
+                    .as_array()
+                    .unwrap()
+                    .contains(&json!(direction.to_string()))
+            })
+            .unwrap();
+        let bump = tool_json(
+            &mut server,
+            "act",
+            json!({ "playtest": 1, "action": "move", "direction": wall.to_string() }),
+        );
+        assert!(bump["outcome"].as_str().unwrap().starts_with("Blocked"));
+        for pair in path.windows(2) {
+            let direction = Direction::all()
+                .into_iter()
+                .find(|direction| pair[0] + direction.offset() == pair[1])
+                .unwrap();
+            tool_json(
+                &mut server,
+                "act",
+                json!({ "playtest": 1, "action": "move", "direction": direction.to_string() }),
+            );
+        }
+        let finished = tool_json(
+            &mut server,
+            "act",
+            json!({ "playtest": 1, "action": "interact" }),
+        );
+
+        let observation = &finished["observation"];
+        assert_eq!(observation["finished"], true);
+        assert_eq!(observation["turns"], path.len() + 1);
+        assert_eq!(observation["blockers"].as_array().unwrap().len(), 1);
+        assert!(observation["view"].as_str().unwrap().contains('@'));
+        assert_eq!(
+            tool_json(&mut server, "observe", json!({ "playtest": 1 })),
+            *observation
+        );
+        let response = call(
+            &mut server,
+            "tools/call",
+            json!({ "name": "act", "arguments": { "playtest": 1, "action": "interact" } }),
+        );
+        assert_eq!(response.error.unwrap().message, "Playtest 1 is finished");
+    }
+
+    #[test]
+    fn find_path_returns_the_path_or_unreachable() {
+        let mut server = initialized_server();
+        let uri = generate(&mut server);
+        let map = server.maps.get_mut(&uri).unwrap();
+        for (x, y) in [(0, 0), (1, 0), (2, 0)] {
+            map.tiles
+                .insert(IVec2::new(x, y), Tile::new(TileSet::Corridor, MapTile::EW));
+        }
+        map.tiles
+            .insert(IVec2::new(3, 3), Tile::new(TileSet::Room, MapTile::ZERO));
+        let arguments = |to_x: i32, to_y: i32| json!({ "uri": uri, "from_x": 0, "from_y": 0, "to_x": to_x, "to_y": to_y });
+
+        let found = tool_json(&mut server, "find_path", arguments(2, 0));
+        assert_eq!(found["reachable"], true);
+        assert_eq!(found["steps"], 2);
+        assert_eq!(found["cost"], 2);
+        assert_eq!(found["path"], json!([[0, 0], [1, 0], [2, 0]]));
+
+        let walled_in = tool_json(&mut server, "find_path", arguments(3, 3));
+        assert_eq!(walled_in["reachable"], false);
+
+        let response = call(
+            &mut server,
+            "tools/call",
+            json!({ "name": "find_path", "arguments": arguments(9, 0) }),
+        );
+        assert!(response.error.unwrap().message.contains("is outside"));
+    }
+
+    #[test]
+    fn tool_schemas_are_derived_from_parameter_structs() {
+        let tools = handle_tools_list().unwrap();
+        let generate_map = &tools["tools"][1];
+
+        assert_eq!(generate_map["name"], "generate_map");
+        let schema = &generate_map["inputSchema"];
+        assert_eq!(schema["type"], "object");
+        assert_eq!(schema["required"], json!(["size"]));
+        assert_eq!(
+            schema["properties"]["size"]["description"],
+            "Side length in tiles"
+        );
+        assert_eq!(schema["properties"]["size"]["maximum"], MAX_MAP_SIZE);
     }
 }
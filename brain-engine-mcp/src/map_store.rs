@@ -0,0 +1,45 @@
+use brain_engine_core::{Map, TileGeneratorDefault};
+use std::collections::BTreeMap;
+
+const URI_SCHEME: &str = "map://";
+
+/// Maps created through MCP tools, each addressable as `map://{id}`.
+#[derive(Default)]
+pub struct MapStore {
+    maps: BTreeMap<u64, Map<TileGeneratorDefault>>,
+    next_id: u64,
+}
+
+impl MapStore {
+    /// Stores a map and returns its URI.
+    pub fn insert(&mut self, map: Map<TileGeneratorDefault>) -> String {
+        self.next_id += 1;
+        self.maps.insert(self.next_id, map);
+        map_uri(self.next_id)
+    }
+
+    pub fn get(&self, uri: &str) -> Option<&Map<TileGeneratorDefault>> {
+        self.maps.get(&map_id(uri)?)
+    }
+
+    pub fn get_mut(&mut self, uri: &str) -> Option<&mut Map<TileGeneratorDefault>> {
+        self.maps.get_mut(&map_id(uri)?)
+    }
+
+    /// Every stored map with its URI, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = (String, &Map<TileGeneratorDefault>)> {
+        self.maps.iter().map(|(id, map)| (map_uri(*id), map))
+    }
+
+    pub fn contains(&self, uri: &str) -> bool {
+        map_id(uri).is_some_and(|id| self.maps.contains_key(&id))
+    }
+}
+
+pub fn map_uri(id: u64) -> String {
+    format!("{URI_SCHEME}{id}")
+}
+
+fn map_id(uri: &str) -> Option<u64> {
+    uri.strip_prefix(URI_SCHEME)?.parse().ok()
+}
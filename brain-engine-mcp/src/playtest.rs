@@ -0,0 +1,142 @@
+use bevy::math::IVec2;
+use brain_engine_core::{glyph, Agent, Direction, Map, MapTile, TileGeneratorDefault};
+use serde_json::{json, Value};
+
+/// How far a player sees in an observation, in tiles.
+pub const SIGHT_RADIUS: u32 = 4;
+
+const PLAYER_GLYPH: char = '@';
+const GOAL_GLYPH: char = '*';
+const UNSEEN_GLYPH: char = ' ';
+
+/// One player walking a stored map turn by turn, from a start tile to the
+/// reachable tile farthest from it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Playtest {
+    pub uri: String,
+    pub agent: Agent,
+    pub goal: IVec2,
+    pub turns: u32,
+    pub finished: bool,
+    /// Moves into walls and problems the player reported, in turn order.
+    pub blockers: Vec<String>,
+}
+
+impl Playtest {
+    /// Starts at `start`, or at the first tile with an exit in x-then-y
+    /// order. Returns `None` when there is no such tile.
+    pub fn start(uri: &str, map: &Map<TileGeneratorDefault>, start: Option<IVec2>) -> Option<Self> {
+        let start = match start {
+            Some(start) => start,
+            None => map.positions().find(|position| {
+                map.tiles
+                    .get(position)
+                    .is_some_and(|tile| tile.map_tile != MapTile::ZERO)
+            })?,
+        };
+        if !map.in_bounds(start) {
+            return None;
+        }
+        Some(Playtest {
+            uri: uri.to_string(),
+            agent: Agent::new(start),
+            goal: farthest_from(map, start),
+            turns: 0,
+            finished: false,
+            blockers: Vec::new(),
+        })
+    }
+
+    /// Moves one tile towards `direction`. A move into a wall costs a turn
+    /// and is recorded as a blocker.
+    pub fn walk(&mut self, map: &Map<TileGeneratorDefault>, direction: Direction) -> String {
+        self.turns += 1;
+        let position = self.agent.position();
+        if self.agent.try_move(map, direction) {
+            format!("Moved {} to {}", direction, self.agent.position())
+        } else {
+            self.blockers.push(format!(
+                "turn {}: no way {} from {}",
+                self.turns, direction, position
+            ));
+            format!("Blocked: no way {} from {}", direction, position)
+        }
+    }
+
+    /// Finishes the playtest when the player stands on the goal.
+    pub fn interact(&mut self) -> String {
+        self.turns += 1;
+        if self.agent.position() == self.goal {
+            self.finished = true;
+            format!("Reached the goal in {} turns", self.turns)
+        } else {
+            format!("Nothing to interact with at {}", self.agent.position())
+        }
+    }
+
+    /// Records a problem the player noticed, such as a goal it cannot find
+    /// a way to.
+    pub fn report(&mut self, note: &str) -> String {
+        self.blockers.push(format!(
+            "turn {}: {} at {}",
+            self.turns,
+            note,
+            self.agent.position()
+        ));
+        format!("Reported a blocker at {}", self.agent.position())
+    }
+
+    /// What the player knows: its position and exits, the goal once seen,
+    /// and an ASCII view of the tiles in sight, drawn like
+    /// [`Map::render_ascii`] with `@` for the player and `*` for the goal.
+    pub fn observe(&self, map: &Map<TileGeneratorDefault>) -> Value {
+        let player = self.agent.position();
+        let visible = map.visible_from(player, SIGHT_RADIUS);
+        let radius = SIGHT_RADIUS as i32;
+        let mut view = String::new();
+        for y in (-radius..=radius).rev() {
+            for x in -radius..=radius {
+                let position = map.wrap(player + IVec2::new(x, y));
+                let character = if position == player {
+                    PLAYER_GLYPH
+                } else if !visible.contains(&position) {
+                    UNSEEN_GLYPH
+                } else if position == self.goal {
+                    GOAL_GLYPH
+                } else {
+                    map.tiles
+                        .get(&position)
+                        .map_or(UNSEEN_GLYPH, |tile| glyph(*tile))
+                };
+                view.push(character);
+            }
+            view.push('\n');
+        }
+        let exits: Vec<String> = map
+            .tiles
+            .get(&player)
+            .map_or_else(Vec::new, |tile| tile.directions())
+            .into_iter()
+            .map(|direction| direction.to_string())
+            .collect();
+        json!({
+            "position": [player.x, player.y],
+            "facing": self.agent.facing().to_string(),
+            "exits": exits,
+            "goal": visible.contains(&self.goal).then_some([self.goal.x, self.goal.y]),
+            "turns": self.turns,
+            "finished": self.finished,
+            "blockers": self.blockers,
+            "view": view,
+        })
+    }
+}
+
+/// The reachable tile the most steps from `start`, the first in x-then-y
+/// order on a tie.
+fn farthest_from(map: &Map<TileGeneratorDefault>, start: IVec2) -> IVec2 {
+    map.dijkstra_map(&[start])
+        .into_iter()
+        .max_by_key(|(position, distance)| (*distance, -position.x, -position.y))
+        .map_or(start, |(position, _)| position)
+}
@@ -0,0 +1,278 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::File;
+use std::io::{self, BufRead, BufWriter, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Object keys whose values are replaced by [`REDACTED`] before logging,
+/// matched case-insensitively against the key with `-` and `_` removed.
+const SENSITIVE_KEYS: [&str; 7] = [
+    "apikey",
+    "authorization",
+    "credentials",
+    "password",
+    "secret",
+    "token",
+    "accesstoken",
+];
+
+pub const REDACTED: &str = "[redacted]";
+
+/// One line of the log: a message from the client and what the server
+/// wrote back.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// When the message arrived, in milliseconds since the Unix epoch.
+    pub timestamp_ms: u64,
+    /// Time spent handling the message, in microseconds.
+    pub duration_us: u64,
+    /// The message as parsed JSON, or the raw line when it wasn't JSON.
+    /// Either way, sensitive values are redacted.
+    pub request: Value,
+    pub response: Option<Value>,
+    pub notifications: Vec<Value>,
+}
+
+impl LogEntry {
+    pub fn new(
+        received: SystemTime,
+        duration: Duration,
+        request: Value,
+        response: Option<Value>,
+        notifications: Vec<Value>,
+    ) -> Self {
+        let timestamp_ms = received
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_millis() as u64);
+        let mut entry = Self {
+            timestamp_ms,
+            duration_us: duration.as_micros() as u64,
+            request,
+            response,
+            notifications,
+        };
+        match &mut entry.request {
+            Value::String(line) => *line = redact_line(line),
+            request => redact(request),
+        }
+        entry
+    }
+}
+
+/// An opt-in JSONL request/response log, one [`LogEntry`] per line.
+pub struct SessionLog<W: Write> {
+    writer: W,
+}
+
+impl SessionLog<BufWriter<File>> {
+    /// Appends to the log at `path`, creating it if needed.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::options().create(true).append(true).open(path)?;
+        Ok(Self::new(BufWriter::new(file)))
+    }
+}
+
+impl<W: Write> SessionLog<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Writes and flushes one entry, so the log survives a crash.
+    pub fn record(&mut self, entry: &LogEntry) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, entry)?;
+        writeln!(self.writer)?;
+        self.writer.flush()
+    }
+}
+
+/// Reads every entry of a log written by [`SessionLog`].
+pub fn read_log(reader: impl BufRead) -> anyhow::Result<Vec<LogEntry>> {
+    let mut entries = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line)
+            .map_err(|error| anyhow::anyhow!("log line {}: {}", index + 1, error))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// Replaces the values of sensitive-looking keys anywhere in `value`.
+pub fn redact(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                if is_sensitive(key) {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// Replaces the values of sensitive-looking keys in a line that isn't valid
+/// JSON, so a truncated or malformed request can't leak them either. Any
+/// quoted key followed by a `:` is checked, and its value is cut at the
+/// end of its string or brackets, or at the next `,`, `}` or `]`.
+pub fn redact_line(line: &str) -> String {
+    let mut redacted = String::with_capacity(line.len());
+    let mut rest = line;
+    while let Some(start) = rest.find('"') {
+        let end = start + string_length(&rest[start..]);
+        let literal = &rest[start + 1..end];
+        let key = literal.strip_suffix('"').unwrap_or(literal);
+        let after_key = &rest[end..];
+        let value = after_key
+            .trim_start()
+            .strip_prefix(':')
+            .map(str::trim_start);
+        match value {
+            Some(value) if is_sensitive(key) => {
+                redacted.push_str(&rest[..rest.len() - value.len()]);
+                redacted.push_str(&Value::String(REDACTED.to_string()).to_string());
+                rest = &value[value_length(value)..];
+            }
+            _ => {
+                redacted.push_str(&rest[..end]);
+                rest = after_key;
+            }
+        }
+    }
+    redacted.push_str(rest);
+    redacted
+}
+
+/// The length of the string literal `text` starts with, quotes included,
+/// or all of `text` if it is never closed.
+fn string_length(text: &str) -> usize {
+    let mut escaped = false;
+    for (index, character) in text.char_indices().skip(1) {
+        match character {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return index + 1,
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+/// The length of the JSON value `text` starts with, as far as it can be
+/// told without parsing it.
+fn value_length(text: &str) -> usize {
+    if text.starts_with('"') {
+        return string_length(text);
+    }
+    let mut depth = 0usize;
+    let mut index = 0;
+    while index < text.len() {
+        let character = text[index..].chars().next().unwrap();
+        match character {
+            '"' => {
+                index += string_length(&text[index..]);
+                continue;
+            }
+            '{' | '[' => depth += 1,
+            '}' | ']' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    return index + 1;
+                }
+            }
+            ',' | '}' | ']' if depth == 0 => return index,
+            _ => {}
+        }
+        index += character.len_utf8();
+    }
+    text.len()
+}
+
+fn is_sensitive(key: &str) -> bool {
+    let key: String = key
+        .chars()
+        .filter(|character| !matches!(character, '-' | '_'))
+        .flat_map(char::to_lowercase)
+        .collect();
+    SENSITIVE_KEYS.contains(&key.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn sensitive_values_are_redacted_at_any_depth() {
+        let mut value = json!({
+            "name": "generate_map",
+            "arguments": { "size": 4, "API-Key": "abc", "nested": [{ "access_token": "def" }] }
+        });
+
+        redact(&mut value);
+
+        assert_eq!(
+            value,
+            json!({
+                "name": "generate_map",
+                "arguments": { "size": 4, "API-Key": REDACTED, "nested": [{ "access_token": REDACTED }] }
+            })
+        );
+    }
+
+    #[test]
+    fn sensitive_values_are_redacted_in_lines_that_are_not_json() {
+        assert_eq!(
+            redact_line(r#"{"params": {"token": "abc\"d", "Api-Key":12, "size": 4"#),
+            r#"{"params": {"token": "[redacted]", "Api-Key":"[redacted]", "size": 4"#
+        );
+        assert_eq!(
+            redact_line(r#"{"credentials": {"user": "a", "password": "b"}, "id": 1,"#),
+            r#"{"credentials": "[redacted]", "id": 1,"#
+        );
+        assert_eq!(
+            redact_line(r#"{"secret": "cut off here"#),
+            r#"{"secret": "[redacted]""#
+        );
+        assert_eq!(redact_line("not json"), "not json");
+
+        let entry = LogEntry::new(
+            UNIX_EPOCH,
+            Duration::ZERO,
+            Value::String(r#"{"password": "hunter2""#.to_string()),
+            None,
+            Vec::new(),
+        );
+        assert_eq!(
+            entry.request,
+            Value::String(r#"{"password": "[redacted]""#.to_string())
+        );
+    }
+
+    #[test]
+    fn entries_round_trip_through_the_log() {
+        let entry = LogEntry::new(
+            UNIX_EPOCH + Duration::from_millis(1_700),
+            Duration::from_micros(250),
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "ping", "params": { "token": "x" } }),
+            Some(json!({ "jsonrpc": "2.0", "id": 1, "result": {} })),
+            Vec::new(),
+        );
+        assert_eq!(entry.timestamp_ms, 1_700);
+        assert_eq!(entry.request["params"]["token"], REDACTED);
+
+        let mut log = SessionLog::new(Vec::new());
+        log.record(&entry).unwrap();
+        log.record(&entry).unwrap();
+
+        let entries = read_log(log.writer.as_slice()).unwrap();
+        assert_eq!(entries, vec![entry.clone(), entry]);
+    }
+}
@@ -0,0 +1,293 @@
+//! Python bindings for brain-engine-core.
+//!
+//! Built as the `brain_engine` Python module with maturin. The bindings only
+//! copy data in and out of the core map types; Python never holds references
+//! into a map.
+
+use bevy::math::IVec2;
+use brain_engine_core::{Map, MapFile, RenderStyle, TileExtensions, TileGeneratorDefault};
+#[cfg(feature = "image")]
+use pyo3::exceptions::PyRuntimeError;
+use pyo3::exceptions::{PyIndexError, PyValueError};
+use pyo3::prelude::*;
+#[cfg(feature = "image")]
+use pyo3::types::PyBytes;
+use pyo3::types::PyDict;
+
+/// A position as Python sees it: an `(x, y)` tuple.
+type Position = (i32, i32);
+
+/// The largest map the bindings generate, the same limit as the CLI.
+const MAX_MAP_SIZE: usize = 1024;
+
+/// A generated map. North is +y, so `(0, 0)` is the bottom-left tile.
+#[pyclass(name = "Map", module = "brain_engine")]
+pub struct PyMap {
+    map: Map<TileGeneratorDefault>,
+}
+
+#[pymethods]
+impl PyMap {
+    /// Generates a `size` by `size` map, at most 1024 tiles wide.
+    /// Probabilities must lie between 0 and 1; a seed makes the map
+    /// reproducible.
+    #[new]
+    #[pyo3(signature = (size, *, seed = None, tile_exit_probability = None, room_probability = None))]
+    fn new(
+        size: usize,
+        seed: Option<u64>,
+        tile_exit_probability: Option<f64>,
+        room_probability: Option<f64>,
+    ) -> PyResult<Self> {
+        if size == 0 {
+            return Err(PyValueError::new_err("size must be at least 1"));
+        }
+        if size > MAX_MAP_SIZE {
+            return Err(PyValueError::new_err(format!(
+                "size must be at most {MAX_MAP_SIZE}, got {size}"
+            )));
+        }
+        let mut builder = TileGeneratorDefault::builder();
+        if let Some(seed) = seed {
+            builder = builder.seed(seed);
+        }
+        if let Some(probability) = tile_exit_probability {
+            builder = builder
+                .tile_exit_probability(check_probability("tile_exit_probability", probability)?);
+        }
+        if let Some(probability) = room_probability {
+            builder = builder.room_probability(check_probability("room_probability", probability)?);
+        }
+        Ok(Self {
+            map: Map::new(size, builder.build()),
+        })
+    }
+
+    #[getter]
+    fn width(&self) -> usize {
+        self.map.x
+    }
+
+    #[getter]
+    fn height(&self) -> usize {
+        self.map.y
+    }
+
+    /// The tile set and exit code of a tile, e.g. `("room", "NE")`.
+    fn tile(&self, x: i32, y: i32) -> PyResult<(String, String)> {
+        let tile = self
+            .map
+            .tiles
+            .get(&IVec2::new(x, y))
+            .ok_or_else(|| PyIndexError::new_err(format!("({x}, {y}) is outside the map")))?;
+        Ok((tile.tile_set.to_string(), tile.map_tile.to_string()))
+    }
+
+    /// A shortest path from `start` to `goal`, both included, or `None` when
+    /// `goal` can't be reached.
+    fn find_path(&self, start: Position, goal: Position) -> Option<Vec<Position>> {
+        let path = self.map.find_path(to_ivec2(start), to_ivec2(goal))?;
+        Some(path.into_iter().map(to_position).collect())
+    }
+
+    /// Every position reachable from `start`, sorted.
+    fn reachable_from(&self, start: Position) -> Vec<Position> {
+        let mut reached: Vec<Position> = self
+            .map
+            .reachable_from(to_ivec2(start))
+            .into_iter()
+            .map(to_position)
+            .collect();
+        reached.sort_unstable();
+        reached
+    }
+
+    fn dead_ends(&self) -> Vec<Position> {
+        self.map.dead_ends().into_iter().map(to_position).collect()
+    }
+
+    /// The tiles of each detected room.
+    fn rooms(&self) -> Vec<Vec<Position>> {
+        self.map
+            .detect_rooms()
+            .into_iter()
+            .map(|room| room.tiles.into_iter().map(to_position).collect())
+            .collect()
+    }
+
+    /// The numbers from `Map::stats` as a dict, ready for a DataFrame row.
+    fn stats<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let stats = self.map.stats();
+        let dict = PyDict::new(py);
+        dict.set_item("tile_count", stats.tile_count)?;
+        dict.set_item("room_tiles", stats.room_tiles)?;
+        dict.set_item("corridor_tiles", stats.corridor_tiles)?;
+        dict.set_item("closed_tiles", stats.closed_tiles)?;
+        dict.set_item("connections", stats.connections)?;
+        dict.set_item("dead_ends", stats.dead_ends)?;
+        dict.set_item("rooms", stats.rooms)?;
+        dict.set_item("connected_areas", stats.connected_areas)?;
+        dict.set_item("largest_area", stats.largest_area)?;
+        dict.set_item("connectivity", stats.connectivity())?;
+        Ok(dict)
+    }
+
+    /// Problems found by `Map::validate`, as messages; empty when valid.
+    fn validate(&self) -> Vec<String> {
+        self.map
+            .validate()
+            .err()
+            .unwrap_or_default()
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    fn render_ascii(&self) -> String {
+        self.map.render_ascii()
+    }
+
+    fn render_svg(&self) -> String {
+        self.map.render_svg(&RenderStyle::default())
+    }
+
+    /// The map as PNG `bytes`, drawn like `render_svg`.
+    #[cfg(feature = "image")]
+    fn render_png<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        let png = self
+            .map
+            .render_png(&RenderStyle::default())
+            .map_err(|error| PyRuntimeError::new_err(error.to_string()))?;
+        Ok(PyBytes::new(py, &png))
+    }
+
+    /// The region graph as JSON, see `Map::regions_to_json`.
+    fn regions_json(&self) -> String {
+        self.map.regions_to_json()
+    }
+
+    /// The map in the text map file format.
+    fn to_map_file(&self) -> String {
+        MapFile::from_map(&self.map, TileExtensions::new()).write()
+    }
+
+    fn __str__(&self) -> String {
+        self.map.render_ascii()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<Map {}x{}>", self.map.x, self.map.y)
+    }
+}
+
+fn check_probability(name: &str, probability: f64) -> PyResult<f64> {
+    if (0.0..=1.0).contains(&probability) {
+        Ok(probability)
+    } else {
+        Err(PyValueError::new_err(format!(
+            "{name} must be between 0 and 1, got {probability}"
+        )))
+    }
+}
+
+fn to_ivec2((x, y): Position) -> IVec2 {
+    IVec2::new(x, y)
+}
+
+fn to_position(position: IVec2) -> Position {
+    (position.x, position.y)
+}
+
+#[pymodule]
+fn brain_engine(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyMap>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::ffi::c_str;
+
+    fn with_module(test: impl FnOnce(Python<'_>, &Bound<'_, PyModule>)) {
+        Python::with_gil(|py| {
+            let module = PyModule::new(py, "brain_engine").unwrap();
+            brain_engine(&module).unwrap();
+            test(py, &module);
+        });
+    }
+
+    #[test]
+    fn maps_are_generated_and_inspected_from_python() {
+        with_module(|py, module| {
+            let locals = PyDict::new(py);
+            locals.set_item("brain_engine", module).unwrap();
+            py.run(
+                c_str!(
+                    r#"
+map = brain_engine.Map(8, seed=3, tile_exit_probability=0.8)
+assert (map.width, map.height) == (8, 8)
+assert repr(map) == "<Map 8x8>"
+assert map.tile(0, 0)[0] in ("room", "corridor")
+stats = map.stats()
+assert stats["tile_count"] == 64
+assert 0.0 <= stats["connectivity"] <= 1.0
+reached = map.reachable_from((0, 0))
+path = map.find_path((0, 0), reached[-1])
+assert path[0] == (0, 0) and path[-1] == reached[-1]
+assert str(map).count("\n") == 8
+assert map.render_svg().startswith("<svg")
+assert '"regions"' in map.regions_json()
+"#
+                ),
+                None,
+                Some(&locals),
+            )
+            .unwrap();
+        });
+    }
+
+    #[test]
+    fn bad_arguments_raise_python_errors() {
+        with_module(|py, module| {
+            let map_class = module.getattr("Map").unwrap();
+
+            let error = map_class.call1((0,)).unwrap_err();
+            assert!(error.is_instance_of::<PyValueError>(py));
+
+            let error = map_class.call1((MAX_MAP_SIZE + 1,)).unwrap_err();
+            assert!(error.is_instance_of::<PyValueError>(py));
+
+            let kwargs = PyDict::new(py);
+            kwargs.set_item("room_probability", 1.5).unwrap();
+            let error = map_class.call((4,), Some(&kwargs)).unwrap_err();
+            assert!(error.is_instance_of::<PyValueError>(py));
+
+            let map = map_class.call1((4,)).unwrap();
+            let error = map.call_method1("tile", (4, 0)).unwrap_err();
+            assert!(error.is_instance_of::<PyIndexError>(py));
+        });
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn maps_render_to_png_bytes() {
+        with_module(|_, module| {
+            let map = module.getattr("Map").unwrap().call1((4,)).unwrap();
+
+            let png = map.call_method0("render_png").unwrap();
+
+            let png = png.downcast::<PyBytes>().unwrap().as_bytes();
+            assert!(png.starts_with(b"\x89PNG"));
+        });
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_map() {
+        let first = PyMap::new(10, Some(7), None, None).unwrap();
+        let second = PyMap::new(10, Some(7), None, None).unwrap();
+
+        assert_eq!(first.to_map_file(), second.to_map_file());
+        assert_eq!(first.validate(), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,21 @@
+mod map_store;
+mod routes;
+
+use routes::AppState;
+
+/// Where the server listens unless an address is passed as the first
+/// argument.
+const DEFAULT_ADDRESS: &str = "127.0.0.1:3000";
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let address = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| DEFAULT_ADDRESS.to_string());
+    let listener = tokio::net::TcpListener::bind(&address).await?;
+    eprintln!(
+        "brain-engine-server listening on http://{}",
+        listener.local_addr()?
+    );
+    axum::serve(listener, routes::router(AppState::default())).await
+}
@@ -0,0 +1,65 @@
+use brain_engine_core::{DynMap, MapEvent};
+use std::collections::BTreeMap;
+use std::sync::mpsc::Receiver;
+use tokio::sync::broadcast;
+
+/// How many events a WebSocket client may fall behind before it misses some.
+const EVENT_CAPACITY: usize = 256;
+
+/// A map created through the API, with the channel its change events are
+/// forwarded on to WebSocket clients.
+pub struct StoredMap {
+    pub map: DynMap,
+    events: Receiver<MapEvent>,
+    broadcaster: broadcast::Sender<MapEvent>,
+}
+
+impl StoredMap {
+    fn new(mut map: DynMap) -> Self {
+        let events = map.subscribe();
+        let (broadcaster, _) = broadcast::channel(EVENT_CAPACITY);
+        Self {
+            map,
+            events,
+            broadcaster,
+        }
+    }
+
+    /// Forwards the events of every change since the last call to the
+    /// WebSocket clients. Call it after each edit.
+    pub fn publish_events(&self) {
+        for event in self.events.try_iter() {
+            // Without clients the event has nowhere to go, which is fine.
+            let _ = self.broadcaster.send(event);
+        }
+    }
+
+    /// A receiver for the events [`StoredMap::publish_events`] forwards.
+    pub fn subscribe(&self) -> broadcast::Receiver<MapEvent> {
+        self.broadcaster.subscribe()
+    }
+}
+
+/// Maps created through `POST /maps`, by id.
+#[derive(Default)]
+pub struct MapStore {
+    maps: BTreeMap<u64, StoredMap>,
+    next_id: u64,
+}
+
+impl MapStore {
+    /// Stores a map and returns its id.
+    pub fn insert(&mut self, map: DynMap) -> u64 {
+        self.next_id += 1;
+        self.maps.insert(self.next_id, StoredMap::new(map));
+        self.next_id
+    }
+
+    pub fn get(&self, id: u64) -> Option<&StoredMap> {
+        self.maps.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: u64) -> Option<&mut StoredMap> {
+        self.maps.get_mut(&id)
+    }
+}
@@ -0,0 +1,383 @@
+use crate::map_store::MapStore;
+
+use axum::extract::rejection::JsonRejection;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post, put};
+use axum::{Json, Router};
+use bevy::math::IVec2;
+use brain_engine_core::{
+    DynMap, GeneratorConfig, MapDiff, MapEvent, MapTile, ParseTileError, Tile, TileSet,
+    tile_to_json,
+};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+/// Largest map side length `POST /maps` accepts.
+const MAX_MAP_SIZE: usize = 256;
+
+pub type AppState = Arc<Mutex<MapStore>>;
+
+/// The API:
+///
+/// * `POST /maps` generates a map from a [`GeneratorConfig`] in JSON.
+/// * `GET /maps/{id}` returns every tile of a map.
+/// * `GET /maps/{id}/path?from=x,y&to=x,y` finds the cheapest path.
+/// * `PUT /maps/{id}/tiles/{x}/{y}` replaces a tile.
+/// * `GET /maps/{id}/events` upgrades to a WebSocket that sends the
+///   [`MapEvent`]s of later edits as JSON.
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/maps", post(create_map))
+        .route("/maps/{id}", get(get_map))
+        .route("/maps/{id}/path", get(find_path))
+        .route("/maps/{id}/tiles/{x}/{y}", put(edit_tile))
+        .route("/maps/{id}/events", get(events))
+        .with_state(state)
+}
+
+/// An error response with a JSON body of the form `{"error": message}`.
+#[derive(Debug)]
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn bad_request(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: message.into(),
+        }
+    }
+
+    fn unknown_map(id: u64) -> Self {
+        Self {
+            status: StatusCode::NOT_FOUND,
+            message: format!("No map {id}"),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(json!({ "error": self.message }))).into_response()
+    }
+}
+
+impl From<ParseTileError> for ApiError {
+    fn from(error: ParseTileError) -> Self {
+        Self::bad_request(error.0)
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::bad_request(rejection.body_text())
+    }
+}
+
+async fn create_map(
+    State(state): State<AppState>,
+    config: Result<Json<GeneratorConfig>, JsonRejection>,
+) -> Result<(StatusCode, Json<Value>), ApiError> {
+    let Json(config) = config?;
+    if config.size > MAX_MAP_SIZE {
+        return Err(ApiError::bad_request(format!(
+            "map size must be at most {MAX_MAP_SIZE}, got {}",
+            config.size
+        )));
+    }
+    let map =
+        DynMap::from_config(&config).map_err(|error| ApiError::bad_request(error.to_string()))?;
+    let body = map.to_json();
+    let id = state.lock().unwrap().insert(map);
+    Ok((StatusCode::CREATED, Json(with_id(id, body))))
+}
+
+async fn get_map(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+) -> Result<Json<Value>, ApiError> {
+    let store = state.lock().unwrap();
+    let stored = store.get(id).ok_or(ApiError::unknown_map(id))?;
+    Ok(Json(with_id(id, stored.map.to_json())))
+}
+
+#[derive(Debug, Deserialize)]
+struct PathQuery {
+    /// The start tile as `x,y`.
+    from: String,
+    /// The goal tile as `x,y`.
+    to: String,
+}
+
+async fn find_path(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+    Query(query): Query<PathQuery>,
+) -> Result<Json<Value>, ApiError> {
+    let (from, to) = (parse_position(&query.from)?, parse_position(&query.to)?);
+    let store = state.lock().unwrap();
+    let map = &store.get(id).ok_or(ApiError::unknown_map(id))?.map;
+    if let Some(position) = [from, to]
+        .into_iter()
+        .find(|position| !map.in_bounds(*position))
+    {
+        return Err(ApiError::bad_request(format!(
+            "{position} is outside map {id}"
+        )));
+    }
+
+    let result = map.path_report(from, to).unwrap_or_else(|| {
+        json!({
+            "reachable": false,
+            "reason": format!("No path from {from} to {to} on map {id}"),
+        })
+    });
+    Ok(Json(result))
+}
+
+#[derive(Debug, Deserialize)]
+struct TileEdit {
+    /// `room`, `corridor` or `field`.
+    tile_set: String,
+    /// The exits as compass letters, e.g. `NE`.
+    exits: String,
+}
+
+/// Replaces one tile as given, without touching its neighbors, like the MCP
+/// `edit_map` tool. WebSocket clients of the map receive the change.
+async fn edit_tile(
+    State(state): State<AppState>,
+    Path((id, x, y)): Path<(u64, i32, i32)>,
+    edit: Result<Json<TileEdit>, JsonRejection>,
+) -> Result<Json<Value>, ApiError> {
+    let Json(edit) = edit?;
+    let tile_set: TileSet = edit.tile_set.parse()?;
+    let map_tile: MapTile = edit.exits.parse()?;
+
+    let mut store = state.lock().unwrap();
+    let stored = store.get_mut(id).ok_or(ApiError::unknown_map(id))?;
+    let position = IVec2::new(x, y);
+    if !stored.map.in_bounds(position) {
+        return Err(ApiError::bad_request(format!(
+            "{position} is outside map {id}"
+        )));
+    }
+    let tile = Tile::new(tile_set, map_tile);
+    stored.map.apply(&MapDiff {
+        x: stored.map.x,
+        y: stored.map.y,
+        changes: vec![(position, Some(tile))],
+    });
+    stored.publish_events();
+    Ok(Json(tile_to_json(position, &tile)))
+}
+
+async fn events(
+    State(state): State<AppState>,
+    Path(id): Path<u64>,
+    upgrade: WebSocketUpgrade,
+) -> Result<Response, ApiError> {
+    let receiver = state
+        .lock()
+        .unwrap()
+        .get(id)
+        .ok_or(ApiError::unknown_map(id))?
+        .subscribe();
+    Ok(upgrade.on_upgrade(move |socket| stream_events(socket, receiver)))
+}
+
+/// Sends every event as a JSON text message until the client disconnects.
+/// A client too slow to keep up is told how many events it missed.
+async fn stream_events(mut socket: WebSocket, mut receiver: broadcast::Receiver<MapEvent>) {
+    loop {
+        tokio::select! {
+            event = receiver.recv() => {
+                let message = match event {
+                    Ok(event) => event_json(&event),
+                    Err(RecvError::Lagged(missed)) => json!({ "type": "lagged", "missed": missed }),
+                    Err(RecvError::Closed) => break,
+                };
+                if socket.send(Message::Text(message.to_string().into())).await.is_err() {
+                    break;
+                }
+            }
+            message = socket.recv() => {
+                // Clients only listen, so anything but a close is ignored.
+                if matches!(message, None | Some(Err(_)) | Some(Ok(Message::Close(_)))) {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Parses a position written as `x,y`.
+fn parse_position(text: &str) -> Result<IVec2, ApiError> {
+    let invalid = || ApiError::bad_request(format!("Expected a position as x,y, got {text:?}"));
+    let (x, y) = text.split_once(',').ok_or_else(invalid)?;
+    let x = x.trim().parse().map_err(|_| invalid())?;
+    let y = y.trim().parse().map_err(|_| invalid())?;
+    Ok(IVec2::new(x, y))
+}
+
+fn with_id(id: u64, mut body: Value) -> Value {
+    body["id"] = json!(id);
+    body
+}
+
+fn event_json(event: &MapEvent) -> Value {
+    let optional_tile = |position: IVec2, tile: &Option<Tile>| {
+        tile.as_ref().map(|tile| tile_to_json(position, tile))
+    };
+    match event {
+        MapEvent::TileChanged {
+            position,
+            before,
+            after,
+        } => json!({
+            "type": "tile_changed",
+            "x": position.x,
+            "y": position.y,
+            "before": optional_tile(*position, before),
+            "after": optional_tile(*position, after),
+        }),
+        MapEvent::DoorOpened {
+            position,
+            direction,
+        } => json!({
+            "type": "door_opened",
+            "x": position.x,
+            "y": position.y,
+            "direction": direction,
+        }),
+        MapEvent::RegionRevealed { positions } => json!({
+            "type": "region_revealed",
+            "positions": positions.iter().map(|position| [position.x, position.y]).collect::<Vec<_>>(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    async fn send(state: &AppState, method: &str, uri: &str, body: Value) -> (StatusCode, Value) {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        let response = router(state.clone()).oneshot(request).await.unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, serde_json::from_slice(&bytes).unwrap())
+    }
+
+    async fn create(state: &AppState, generator: Value) -> u64 {
+        let (status, body) = send(
+            state,
+            "POST",
+            "/maps",
+            json!({ "size": 4, "generator": generator }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        body["id"].as_u64().unwrap()
+    }
+
+    #[tokio::test]
+    async fn created_maps_can_be_fetched() {
+        let state = AppState::default();
+        let id = create(&state, json!({ "default": { "seed": 3 } })).await;
+
+        let (status, body) = send(&state, "GET", &format!("/maps/{id}"), Value::Null).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["width"], 4);
+        assert_eq!(body["tiles"].as_array().unwrap().len(), 16);
+
+        let (status, body) = send(&state, "GET", "/maps/9", Value::Null).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"], "No map 9");
+    }
+
+    #[tokio::test]
+    async fn invalid_configs_are_rejected() {
+        let state = AppState::default();
+        for config in [
+            json!({ "size": 0, "generator": "open_field" }),
+            json!({ "size": 1000, "generator": "open_field" }),
+            json!({ "size": 4, "generator": "unknown" }),
+        ] {
+            let (status, body) = send(&state, "POST", "/maps", config).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST);
+            assert!(body["error"].is_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn paths_cross_the_map() {
+        let state = AppState::default();
+        let id = create(&state, json!("open_field")).await;
+
+        let (status, body) = send(
+            &state,
+            "GET",
+            &format!("/maps/{id}/path?from=0,0&to=3,3"),
+            Value::Null,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["reachable"], true);
+        assert_eq!(body["steps"], 6);
+        assert_eq!(body["path"][0], json!([0, 0]));
+
+        let (status, _) = send(
+            &state,
+            "GET",
+            &format!("/maps/{id}/path?from=0,0&to=4,4"),
+            Value::Null,
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let (status, _) = send(
+            &state,
+            "GET",
+            &format!("/maps/{id}/path?from=0&to=3,3"),
+            Value::Null,
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn edits_are_broadcast_to_subscribers() {
+        let state = AppState::default();
+        let id = create(&state, json!("open_field")).await;
+        let mut receiver = state.lock().unwrap().get(id).unwrap().subscribe();
+
+        let edit = json!({ "tile_set": "room", "exits": "NE" });
+        let (status, body) = send(&state, "PUT", &format!("/maps/{id}/tiles/1/0"), edit).await;
+
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["exits"], "NE");
+        let event = receiver.try_recv().unwrap();
+        assert!(
+            matches!(event, MapEvent::TileChanged { position, .. } if position == IVec2::new(1, 0))
+        );
+        assert_eq!(event_json(&event)["after"]["tile_set"], "room");
+    }
+}